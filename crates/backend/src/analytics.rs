@@ -0,0 +1,279 @@
+//! Statistics on the peer ratings of a project.
+//!
+//! The aggregates are computed by the database, see [`crate::db::analytics`].
+//! On top of them, raters are flagged as outliers if they give everyone the
+//! best or the worst grade, or rate much more lenient or harsh than the rest
+//! of their group. The cohesion of a group tells how much its ratings agree:
+//! `1.0` means every rating in the group was the same, `0.0` is the largest
+//! possible spread. The gap between self-assessment and received ratings is
+//! positive for students who rate themselves worse than their group mates do.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::analytics::{ProjectStats, RatingCount, RatingStats, StudentStats};
+
+/// Difference between the average rating of a rater and the average of the
+/// other raters of their group from which the rater counts as outlier
+pub const OUTLIER_DEVIATION: f64 = 1.5;
+
+const BEST_RATING: i16 = 1;
+const WORST_RATING: i16 = 6;
+/// Largest possible standard deviation of ratings between 1 and 6
+const MAX_STD_DEV: f64 = (WORST_RATING - BEST_RATING) as f64 / 2.0;
+
+#[derive(Serialize, ToSchema)]
+pub struct ProjectAnalytics {
+    /// Statistics of every question of the feedback form
+    questions: Vec<QuestionAnalytics>,
+    /// Members of all groups, ordered by group and name
+    students: Vec<StudentAnalytics>,
+    outlier_raters: Vec<OutlierRater>,
+    /// Ordered by name
+    groups: Vec<GroupAnalytics>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct QuestionAnalytics {
+    /// Key of the question, `peer_rating` or `self_assessment`
+    question: String,
+    responses: i64,
+    average: Option<f64>,
+    /// Population standard deviation of the ratings
+    std_dev: Option<f64>,
+    min: Option<i16>,
+    max: Option<i16>,
+    /// Number of responses per rating, starting with 1
+    distribution: Vec<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StudentAnalytics {
+    user_id: Uuid,
+    name: String,
+    group_id: Uuid,
+    group_name: String,
+    ratings_received: i64,
+    average_received: Option<f64>,
+    ratings_given: i64,
+    average_given: Option<f64>,
+    self_rating: Option<i16>,
+    /// Self-assessment minus the average rating received
+    self_peer_gap: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierReason {
+    /// Rated every group mate with the best grade
+    AllBest,
+    /// Rated every group mate with the worst grade
+    AllWorst,
+    /// Rates much better than the rest of the group
+    Lenient,
+    /// Rates much worse than the rest of the group
+    Harsh,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct OutlierRater {
+    user_id: Uuid,
+    name: String,
+    group_id: Uuid,
+    group_name: String,
+    reason: OutlierReason,
+    ratings_given: i64,
+    average_given: Option<f64>,
+    /// Average rating given by the other members of the group
+    group_average_given: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GroupAnalytics {
+    group_id: Uuid,
+    name: String,
+    members: i64,
+    /// Members who submitted their feedback
+    completed: i64,
+    ratings: i64,
+    average: Option<f64>,
+    std_dev: Option<f64>,
+    /// Agreement of the ratings in the group from 0 to 1, missing without ratings
+    cohesion: Option<f64>,
+}
+
+impl From<ProjectStats> for ProjectAnalytics {
+    fn from(stats: ProjectStats) -> Self {
+        let mut questions = vec![question("peer_rating", stats.ratings, &stats.distribution)];
+        if stats.self_ratings.responses > 0 {
+            questions.push(question(
+                "self_assessment",
+                stats.self_ratings,
+                &stats.self_distribution,
+            ));
+        }
+
+        let outlier_raters = stats
+            .students
+            .iter()
+            .filter_map(|student| {
+                Some(OutlierRater {
+                    reason: outlier_reason(student)?,
+                    user_id: student.user_id,
+                    name: student.name.clone(),
+                    group_id: student.group_id,
+                    group_name: student.group_name.clone(),
+                    ratings_given: student.ratings_given,
+                    average_given: student.average_given,
+                    group_average_given: student.others_average_given,
+                })
+            })
+            .collect();
+
+        let students = stats
+            .students
+            .into_iter()
+            .map(|student| StudentAnalytics {
+                user_id: student.user_id,
+                name: student.name,
+                group_id: student.group_id,
+                group_name: student.group_name,
+                ratings_received: student.ratings_received,
+                average_received: student.average_received,
+                ratings_given: student.ratings_given,
+                average_given: student.average_given,
+                self_rating: student.self_rating,
+                self_peer_gap: student
+                    .self_rating
+                    .zip(student.average_received)
+                    .map(|(own, received)| f64::from(own) - received),
+            })
+            .collect();
+
+        let groups = stats
+            .groups
+            .into_iter()
+            .map(|group| GroupAnalytics {
+                cohesion: group.std_dev.map(cohesion),
+                group_id: group.group_id,
+                name: group.name,
+                members: group.members,
+                completed: group.completed,
+                ratings: group.ratings,
+                average: group.average,
+                std_dev: group.std_dev,
+            })
+            .collect();
+
+        ProjectAnalytics {
+            questions,
+            students,
+            outlier_raters,
+            groups,
+        }
+    }
+}
+
+fn question(key: &str, stats: RatingStats, distribution: &[RatingCount]) -> QuestionAnalytics {
+    QuestionAnalytics {
+        question: key.to_owned(),
+        responses: stats.responses,
+        average: stats.average,
+        std_dev: stats.std_dev,
+        min: stats.min,
+        max: stats.max,
+        distribution: (BEST_RATING..=WORST_RATING)
+            .map(|rating| {
+                distribution
+                    .iter()
+                    .find(|count| count.rating == rating)
+                    .map_or(0, |count| count.count)
+            })
+            .collect(),
+    }
+}
+
+/// Why the ratings the student gave stand out, if they do. Rating the only
+/// group mate with the best grade is nothing unusual, so giving everyone the
+/// same extreme grade needs at least two ratings.
+fn outlier_reason(student: &StudentStats) -> Option<OutlierReason> {
+    if student.ratings_given >= 2 && student.min_given == student.max_given {
+        match student.min_given {
+            Some(BEST_RATING) => return Some(OutlierReason::AllBest),
+            Some(WORST_RATING) => return Some(OutlierReason::AllWorst),
+            _ => {}
+        }
+    }
+
+    let deviation = student.average_given? - student.others_average_given?;
+    if deviation <= -OUTLIER_DEVIATION {
+        Some(OutlierReason::Lenient)
+    } else if deviation >= OUTLIER_DEVIATION {
+        Some(OutlierReason::Harsh)
+    } else {
+        None
+    }
+}
+
+fn cohesion(std_dev: f64) -> f64 {
+    (1.0 - std_dev / MAX_STD_DEV).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rater(given: &[i16], others_average: Option<f64>) -> StudentStats {
+        StudentStats {
+            user_id: Uuid::nil(),
+            name: "Max".to_owned(),
+            group_id: Uuid::nil(),
+            group_name: "Gruppe 1".to_owned(),
+            ratings_received: 0,
+            average_received: None,
+            ratings_given: given.len() as i64,
+            average_given: (!given.is_empty())
+                .then(|| given.iter().map(|r| f64::from(*r)).sum::<f64>() / given.len() as f64),
+            min_given: given.iter().min().copied(),
+            max_given: given.iter().max().copied(),
+            others_average_given: others_average,
+            self_rating: None,
+        }
+    }
+
+    #[test]
+    fn same_extreme_grade_for_everyone_is_an_outlier() {
+        assert_eq!(
+            outlier_reason(&rater(&[1, 1, 1], Some(1.5))),
+            Some(OutlierReason::AllBest)
+        );
+        assert_eq!(
+            outlier_reason(&rater(&[6, 6], None)),
+            Some(OutlierReason::AllWorst)
+        );
+        assert_eq!(outlier_reason(&rater(&[1], Some(2.0))), None);
+        assert_eq!(outlier_reason(&rater(&[3, 3], Some(3.0))), None);
+    }
+
+    #[test]
+    fn deviating_from_the_group_is_an_outlier() {
+        assert_eq!(
+            outlier_reason(&rater(&[4, 5], Some(2.0))),
+            Some(OutlierReason::Harsh)
+        );
+        assert_eq!(
+            outlier_reason(&rater(&[1, 2], Some(3.5))),
+            Some(OutlierReason::Lenient)
+        );
+        assert_eq!(outlier_reason(&rater(&[2, 3], Some(3.5))), None);
+        assert_eq!(outlier_reason(&rater(&[], Some(3.5))), None);
+    }
+
+    #[test]
+    fn cohesion_of_ratings() {
+        assert_eq!(cohesion(0.0), 1.0);
+        assert_eq!(cohesion(1.25), 0.5);
+        assert_eq!(cohesion(2.5), 0.0);
+    }
+}
@@ -0,0 +1,280 @@
+use actix_session::{Session, SessionExt};
+use actix_web::{FromRequest, HttpRequest, dev::Payload, http::Method, http::header, web};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::{future::Future, pin::Pin};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::entity::sea_orm_active_enums::{TokenAccess, UserRole},
+    error::ApiError,
+};
+
+const USER_KEY: &str = "user";
+const FEEDBACK_KEY: &str = "feedback";
+const EXPIRES_AT_KEY: &str = "expires_at";
+
+/// How long a student session opened with a feedback link lasts
+pub const STUDENT_SESSION_LIFETIME: TimeDelta = TimeDelta::hours(2);
+
+/// The user making the request, authenticated either by the session cookie or
+/// by a personal access token sent as `Authorization: Bearer <token>`.
+///
+/// Requests with a read-only token are rejected unless they use a safe method.
+/// Sessions opened with a feedback link are restricted to the student's
+/// project and end after [`STUDENT_SESSION_LIFETIME`] or once the link stops
+/// working, e.g. because the tokens were regenerated.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub id: Uuid,
+    /// Effective role, for tokens this is the role the token was scoped to
+    pub role: UserRole,
+    /// Set if the request was authenticated with an API token
+    pub token_id: Option<Uuid>,
+    /// Set for sessions opened with a feedback link, the only project they can access
+    pub project_id: Option<Uuid>,
+}
+
+impl AuthenticatedUser {
+    /// Fails with `Forbidden` if the user's role is below `required`
+    pub fn require_role(&self, required: UserRole) -> Result<(), ApiError> {
+        if role_rank(self.role) >= role_rank(required) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden)
+        }
+    }
+
+    /// Fails with `Forbidden` unless the user logged in, requests with an API
+    /// token or from a session opened with a feedback link are rejected
+    pub fn require_session(&self) -> Result<(), ApiError> {
+        match (self.token_id, self.project_id) {
+            (None, None) => Ok(()),
+            _ => Err(ApiError::Forbidden),
+        }
+    }
+
+    /// Project of a session opened with a feedback link, fails with
+    /// `Forbidden` for every other user
+    pub fn feedback_project(&self) -> Result<Uuid, ApiError> {
+        self.project_id.ok_or(ApiError::Forbidden)
+    }
+}
+
+/// Whether a user logged in, a session opened with a feedback link doesn't count
+pub fn is_logged_in(session: &Session) -> Result<bool, ApiError> {
+    Ok(session.get::<Uuid>(USER_KEY)?.is_some() && session.get::<Uuid>(FEEDBACK_KEY)?.is_none())
+}
+
+/// Starts the session of a user who logged in, replacing a session opened
+/// with a feedback link
+pub fn start_session(session: &Session, user_id: Uuid) -> Result<(), ApiError> {
+    session.renew();
+    session.remove(FEEDBACK_KEY);
+    session.remove(EXPIRES_AT_KEY);
+    session.insert(USER_KEY, user_id)?;
+    Ok(())
+}
+
+/// Starts a session restricted to the project of the feedback link, returns
+/// when it ends
+pub fn start_feedback_session(
+    session: &Session,
+    user_id: Uuid,
+    feedback_id: Uuid,
+) -> Result<DateTime<Utc>, ApiError> {
+    let expires_at = Utc::now() + STUDENT_SESSION_LIFETIME;
+
+    session.renew();
+    session.insert(USER_KEY, user_id)?;
+    session.insert(FEEDBACK_KEY, feedback_id)?;
+    session.insert(EXPIRES_AT_KEY, expires_at)?;
+    Ok(expires_at)
+}
+
+/// Project of a session opened with a feedback link. Expired sessions are
+/// ended, as are sessions whose link no longer belongs to the user or whose
+/// user was deactivated.
+async fn feedback_session_project(
+    db: &Database,
+    session: &Session,
+    user_id: Uuid,
+    feedback_id: Uuid,
+) -> Result<Uuid, ApiError> {
+    let expires_at = session.get::<DateTime<Utc>>(EXPIRES_AT_KEY)?;
+    if expires_at.is_none_or(|expires_at| expires_at <= Utc::now()) {
+        session.purge();
+        return Err(ApiError::Unauthorized);
+    }
+
+    match db.get_feedback_member(feedback_id).await {
+        Ok((member, project)) if member.user_id == user_id => Ok(project.id),
+        Ok(_) | Err(ApiError::NotFound | ApiError::Unauthorized) => {
+            session.purge();
+            Err(ApiError::Unauthorized)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn role_rank(role: UserRole) -> u8 {
+    match role {
+        UserRole::Student => 0,
+        UserRole::Teacher => 1,
+        UserRole::Admin => 2,
+    }
+}
+
+/// Returns `true` if `role` grants at least the permissions of `other`
+pub fn role_includes(role: UserRole, other: UserRole) -> bool {
+    role_rank(role) >= role_rank(other)
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let db = req
+                .app_data::<web::Data<Database>>()
+                .ok_or_else(|| ApiError::InternalServerError("Database not configured".into()))?;
+
+            if let Some(token) = bearer_token(&req) {
+                let (user, api_token) = db.authenticate_api_token(token).await?;
+
+                let is_safe_method = matches!(*req.method(), Method::GET | Method::HEAD);
+                if api_token.access == TokenAccess::Read && !is_safe_method {
+                    return Err(ApiError::Forbidden);
+                }
+
+                // A token never grants more than its owner currently has
+                let role = if role_includes(user.role, api_token.role) {
+                    api_token.role
+                } else {
+                    user.role
+                };
+
+                return Ok(AuthenticatedUser {
+                    id: user.id,
+                    role,
+                    token_id: Some(api_token.id),
+                    project_id: None,
+                });
+            }
+
+            let session = req.get_session();
+            let user_id = session
+                .get::<Uuid>(USER_KEY)?
+                .ok_or(ApiError::Unauthorized)?;
+
+            let user = db.get_user(user_id).await.map_err(|e| match e {
+                ApiError::NotFound => ApiError::Unauthorized,
+                e => e,
+            })?;
+            let user = user.ok_or(ApiError::Unauthorized)?;
+
+            // Deactivation also ends sessions that were opened before
+            if user.deactivated_at.is_some() {
+                return Err(ApiError::Unauthorized);
+            }
+
+            if let Some(feedback_id) = session.get::<Uuid>(FEEDBACK_KEY)? {
+                let project_id =
+                    feedback_session_project(db, &session, user.id, feedback_id).await?;
+                return Ok(AuthenticatedUser {
+                    id: user.id,
+                    role: UserRole::Student,
+                    token_id: None,
+                    project_id: Some(project_id),
+                });
+            }
+
+            Ok(AuthenticatedUser {
+                id: user.id,
+                role: user.role,
+                token_id: None,
+                project_id: None,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: UserRole, token_id: Option<Uuid>) -> AuthenticatedUser {
+        AuthenticatedUser {
+            id: Uuid::nil(),
+            role,
+            token_id,
+            project_id: None,
+        }
+    }
+
+    #[test]
+    fn require_role_respects_hierarchy() {
+        assert!(
+            user(UserRole::Admin, None)
+                .require_role(UserRole::Teacher)
+                .is_ok()
+        );
+        assert!(
+            user(UserRole::Teacher, None)
+                .require_role(UserRole::Teacher)
+                .is_ok()
+        );
+        assert!(
+            user(UserRole::Teacher, None)
+                .require_role(UserRole::Admin)
+                .is_err()
+        );
+        assert!(
+            user(UserRole::Student, None)
+                .require_role(UserRole::Teacher)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn require_session_rejects_tokens() {
+        assert!(user(UserRole::Admin, None).require_session().is_ok());
+        assert!(
+            user(UserRole::Admin, Some(Uuid::nil()))
+                .require_session()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn feedback_sessions_are_restricted_to_their_project() {
+        let student = AuthenticatedUser {
+            project_id: Some(Uuid::nil()),
+            ..user(UserRole::Student, None)
+        };
+        assert!(student.require_session().is_err());
+        assert!(student.require_role(UserRole::Teacher).is_err());
+        assert_eq!(student.feedback_project().unwrap(), Uuid::nil());
+        assert!(user(UserRole::Student, None).feedback_project().is_err());
+    }
+
+    #[test]
+    fn role_includes_is_reflexive() {
+        for role in [UserRole::Student, UserRole::Teacher, UserRole::Admin] {
+            assert!(role_includes(role, role));
+        }
+        assert!(!role_includes(UserRole::Teacher, UserRole::Admin));
+    }
+}
@@ -1,15 +1,35 @@
 use actix_web::web::{self, ServiceConfig};
 
+use crate::error::ApiError;
+
 // TODO: Refactor to use re-exports instead of making module public
+pub mod audit;
 pub mod auth;
 pub mod class;
 pub mod feedback;
 pub mod group;
+pub mod job;
 pub mod project;
+pub mod retention;
+pub mod school_year;
 pub mod template;
 pub mod user;
 
 pub fn register_controllers(cfg: &mut ServiceConfig) {
+    // Report malformed requests the same way as every other error
+    cfg.app_data(
+        web::JsonConfig::default()
+            .error_handler(|err, _| ApiError::BadRequest(err.to_string()).into()),
+    )
+    .app_data(
+        web::PathConfig::default()
+            .error_handler(|err, _| ApiError::BadRequest(err.to_string()).into()),
+    )
+    .app_data(
+        web::QueryConfig::default()
+            .error_handler(|err, _| ApiError::BadRequest(err.to_string()).into()),
+    );
+
     cfg.service(web::scope("/project").configure(project::setup))
         .service(web::scope("/group").configure(group::setup))
         .service(web::scope("/user").configure(user::setup))
@@ -17,6 +37,10 @@ pub fn register_controllers(cfg: &mut ServiceConfig) {
         .service(web::scope("/template").configure(template::setup))
         .service(web::scope("/auth").configure(auth::setup))
         .service(web::scope("/feedback").configure(feedback::setup))
+        .service(web::scope("/job").configure(job::setup))
+        .service(web::scope("/audit").configure(audit::setup))
+        .service(web::scope("/school-year").configure(school_year::setup))
+        .service(web::scope("/retention").configure(retention::setup))
         .service(
             web::resource("/ok").to(|| async { actix_web::HttpResponse::Ok().body("available") }),
         );
@@ -0,0 +1,72 @@
+use actix_web::{get, web};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::db::audit::AuditFilter;
+use crate::db::entity;
+use crate::db::entity::sea_orm_active_enums::{AuditAction, UserRole};
+use crate::error::{ApiError, ProblemDetails};
+use crate::pagination::{ListQuery, Page, Pagination};
+
+pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(get_audit_events);
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// Only changes made by this user
+    actor_id: Option<Uuid>,
+    /// Only changes of this kind
+    action: Option<AuditAction>,
+    /// Only changes of this user, project, class, group, feedback or token
+    entity_id: Option<Uuid>,
+    /// Only changes within this project
+    project_id: Option<Uuid>,
+    /// Only changes at or after this time
+    from: Option<DateTime<Utc>>,
+    /// Only changes before this time
+    until: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit",
+    tag = "audit",
+    summary = "List audit events",
+    description = "Retrieve a page of the changes teachers and admins made, with the state before and after each change. \
+        Oldest first, sortable by `created_at`",
+    params(ListQuery, AuditQuery),
+    responses(
+        (status = 200, description = "Page of audit events", body = Page<entity::audit_event::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid filter, paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("")]
+async fn get_audit_events(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    pagination: Pagination,
+    query: web::Query<AuditQuery>,
+) -> Result<web::Json<Page<entity::audit_event::Model>>, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    let query = query.into_inner();
+    let filter = AuditFilter {
+        actor_id: query.actor_id,
+        action: query.action,
+        entity_id: query.entity_id,
+        project_id: query.project_id,
+        from: query.from,
+        until: query.until,
+    };
+
+    let (events, total) = db.get_audit_events(&filter, &pagination).await?;
+    Ok(web::Json(pagination.page(events, total)))
+}
@@ -1,15 +1,18 @@
 use actix_session::Session;
 use actix_web::{
-    HttpRequest, HttpResponse, Responder, post,
+    HttpRequest, HttpResponse, Responder, get,
+    http::header,
+    post,
     web::{self, ServiceConfig},
 };
-use log::debug;
+use log::{debug, warn};
 use serde::Deserialize;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    Database,
-    error::{ApiError, MessageResponse},
+    Database, auth,
+    error::{ApiError, MessageResponse, ProblemDetails},
+    oidc::{OidcClient, OidcLoginState},
 };
 
 #[derive(Deserialize, ToSchema)]
@@ -20,8 +23,25 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct OidcCallback {
+    /// Authorization code issued by the identity provider
+    code: Option<String>,
+    /// State value sent with the authorization request
+    state: Option<String>,
+    /// Error code if the identity provider rejected the login
+    error: Option<String>,
+    /// Human readable error description from the identity provider
+    error_description: Option<String>,
+}
+
+const OIDC_LOGIN_STATE_KEY: &str = "oidc_login";
+
 pub fn setup(cfg: &mut ServiceConfig) {
-    cfg.service(login).service(logout);
+    cfg.service(login)
+        .service(logout)
+        .service(oidc_login)
+        .service(oidc_callback);
 }
 
 #[utoipa::path(
@@ -33,9 +53,9 @@ pub fn setup(cfg: &mut ServiceConfig) {
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = MessageResponse, content_type = "application/json"),
-        (status = 400, description = "Invalid credentials"),
-        (status = 409, description = "User already logged in"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Invalid credentials", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "User already logged in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[post("/login")]
@@ -50,11 +70,11 @@ pub async fn login(
         .verify_local_user(&login_request.username, &login_request.password)
         .await?;
 
-    if session.get::<String>("user").is_ok() {
+    if auth::is_logged_in(&session)? {
         return Err(ApiError::AlreadyLoggedIn);
     }
 
-    session.insert("user", user_id)?;
+    auth::start_session(&session, user_id)?;
 
     Ok(HttpResponse::Ok().json(MessageResponse::new("Login successful")))
 }
@@ -67,7 +87,7 @@ pub async fn login(
     description = "Log out the currently authenticated user and clear session",
     responses(
         (status = 200, description = "Logout successful", body = MessageResponse, content_type = "application/json"),
-        (status = 500, description = "Internal server error")
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[post("/logout")]
@@ -78,3 +98,89 @@ pub async fn logout(session: Session, request: HttpRequest) -> Result<impl Respo
     debug!("Session entries after purge: {:?}", session.entries());
     Ok(HttpResponse::Ok().json(MessageResponse::new("Logged out successfully")))
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/oidc/login",
+    tag = "auth",
+    summary = "Start OpenID Connect login",
+    description = "Redirect to the configured identity provider using the authorization code flow with PKCE",
+    responses(
+        (status = 302, description = "Redirect to the identity provider"),
+        (status = 404, description = "OpenID Connect is not configured", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/oidc/login")]
+pub async fn oidc_login(
+    oidc: Option<web::Data<OidcClient>>,
+    session: Session,
+) -> Result<impl Responder, ApiError> {
+    let oidc = oidc.ok_or(ApiError::NotFound)?;
+    let request = oidc.authorization_request();
+
+    session.insert(OIDC_LOGIN_STATE_KEY, request.login_state)?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, request.url))
+        .finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/oidc/callback",
+    tag = "auth",
+    summary = "OpenID Connect callback",
+    description = "Complete the login started with /auth/oidc/login, creating the user on first login",
+    params(OidcCallback),
+    responses(
+        (status = 302, description = "Login successful, redirect to the frontend"),
+        (status = 400, description = "Missing or invalid callback parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Identity provider rejected the login or the ID token is invalid", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "OpenID Connect is not configured", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "A user with the same username already exists", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 502, description = "Identity provider could not be reached", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/oidc/callback")]
+pub async fn oidc_callback(
+    db: web::Data<Database>,
+    oidc: Option<web::Data<OidcClient>>,
+    callback: web::Query<OidcCallback>,
+    session: Session,
+) -> Result<impl Responder, ApiError> {
+    let oidc = oidc.ok_or(ApiError::NotFound)?;
+    let callback = callback.into_inner();
+
+    let login_state = session
+        .remove_as::<OidcLoginState>(OIDC_LOGIN_STATE_KEY)
+        .and_then(Result::ok)
+        .ok_or_else(|| ApiError::BadRequest("No OIDC login in progress".to_owned()))?;
+
+    if let Some(error) = callback.error {
+        warn!(
+            "OIDC login rejected by identity provider: {} ({})",
+            error,
+            callback.error_description.unwrap_or_default()
+        );
+        return Err(ApiError::Unauthorized);
+    }
+
+    if callback.state.as_deref() != Some(login_state.state.as_str()) {
+        return Err(ApiError::BadRequest("OIDC state mismatch".to_owned()));
+    }
+
+    let code = callback
+        .code
+        .ok_or_else(|| ApiError::BadRequest("Missing authorization code".to_owned()))?;
+
+    let identity = oidc.exchange_code(&code, &login_state).await?;
+    let user = db.upsert_oidc_user(identity).await?;
+
+    auth::start_session(&session, user.id)?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, oidc.config().post_login_redirect.as_str()))
+        .finish())
+}
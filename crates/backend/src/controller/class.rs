@@ -1,90 +1,572 @@
-use actix_web::{Responder, delete, get, post, put};
+use actix_web::{delete, get, post, put, web};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::AuthenticatedUser;
+use crate::csv;
+use crate::db::Database;
+use crate::db::enrolment::RosterStudent;
+use crate::db::entity;
+use crate::db::entity::sea_orm_active_enums::{GradeRounding, GradeScale, UserRole};
+use crate::error::{ApiError, ProblemDetails};
+use crate::pagination::{ListQuery, Page, Pagination};
+use crate::scale::Scale;
 
 pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
     cfg.service(get_classes)
         .service(get_class)
         .service(create_class)
         .service(update_class)
-        .service(delete_class);
+        .service(delete_class)
+        .service(get_roster)
+        .service(enrol_student)
+        .service(import_roster)
+        .service(update_enrolment)
+        .service(delete_enrolment);
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClassQuery {
+    /// Case-insensitive search in the class name
+    search: Option<String>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct ClassSettings {
+    /// Class name, e.g. `FIAE 23a`
+    #[validate(length(min = 1, max = 255))]
+    name: String,
+    /// Scale the grades of the projects are given in, German grades if omitted
+    #[serde(default)]
+    grade_scale: GradeScale,
+    /// How grades are rounded when they are shown and exported, to a tenth if omitted.
+    /// `tendency` is only available for German grades.
+    #[serde(default)]
+    grade_rounding: GradeRounding,
+    /// School year or term the class is taught in. Projects of classes without one are never deleted by the retention policy.
+    school_year_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RosterQuery {
+    /// Students enrolled on this day, today if omitted
+    on: Option<NaiveDate>,
+    /// All enrolments, including past and future ones
+    #[serde(default)]
+    all: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RosterEntry {
+    #[serde(flatten)]
+    enrolment: entity::class_enrolment::Model,
+    student: entity::user::Model,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Enrolment {
+    user_id: Uuid,
+    /// First day in the class, the start of the class's school year or today if omitted
+    #[schema(value_type = Option<String>, format = Date)]
+    starts_on: Option<NaiveDate>,
+    /// Last day in the class, open-ended if omitted
+    #[schema(value_type = Option<String>, format = Date)]
+    ends_on: Option<NaiveDate>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct EnrolmentPeriod {
+    #[schema(value_type = String, format = Date)]
+    starts_on: NaiveDate,
+    /// Last day in the class, open-ended if omitted
+    #[schema(value_type = Option<String>, format = Date)]
+    ends_on: Option<NaiveDate>,
+}
+
+/// A row of a roster import, new students are validated like [`CreateUser`](super::user::CreateUser)
+#[derive(Validate)]
+struct RosterRow {
+    #[validate(length(min = 4, max = 255))]
+    username: String,
+    #[validate(length(min = 3))]
+    name: Option<String>,
+    #[validate(email)]
+    email: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RosterRowStatus {
+    /// An existing student was enrolled
+    Enrolled,
+    /// The student was created and enrolled
+    Created,
+    Failed,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RosterRowResult {
+    /// Line in the CSV file, the header is line 1
+    row: usize,
+    username: String,
+    status: RosterRowStatus,
+    user_id: Option<Uuid>,
+    /// Error code if the row failed, as in problem details
+    code: Option<String>,
+    /// Reason the row failed
+    error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RosterImportResult {
+    enrolled: usize,
+    created: usize,
+    failed: usize,
+    rows: Vec<RosterRowResult>,
+}
+
+/// Dates as `2025-08-01` or as German dates like `01.08.2025`
+fn parse_date(column: &str, value: Option<String>) -> Result<Option<NaiveDate>, ApiError> {
+    let Some(value) = value.filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value.trim(), "%d.%m.%Y"))
+        .map(Some)
+        .map_err(|_| ApiError::BadRequest(format!("Invalid date in {}: {}", column, value)))
+}
+
+impl ClassSettings {
+    fn scale(&self) -> Result<Scale, ApiError> {
+        Scale {
+            kind: self.grade_scale,
+            rounding: self.grade_rounding,
+        }
+        .validate()
+    }
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/class",
     tag = "classes",
-    summary = "Get all classes (Not Implemented)",
-    description = "Retrieve a list of all classes - currently not implemented",
+    summary = "Get all classes",
+    description = "Retrieve a page of classes, sortable by `name`",
+    params(ListQuery, ClassQuery),
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "List of classes retrieved successfully", body = Page<entity::class::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("")]
-async fn get_classes() -> impl Responder {
-    ""
+async fn get_classes(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    pagination: Pagination,
+    query: web::Query<ClassQuery>,
+) -> Result<web::Json<Page<entity::class::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (classes, total) = db.get_classes(query.search.as_deref(), &pagination).await?;
+
+    Ok(web::Json(pagination.page(classes, total)))
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/class/{id}",
     tag = "classes",
-    summary = "Get class by ID (Not Implemented)",
-    description = "Retrieve a specific class by its ID - currently not implemented",
+    summary = "Get class by ID",
+    description = "Retrieve a specific class by its ID",
     params(
         ("id" = String, Path, description = "Class ID")
     ),
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "Class retrieved successfully", body = entity::class::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Class not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("/{id}")]
-async fn get_class() -> impl Responder {
-    ""
+async fn get_class(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<entity::class::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let class = db.get_class(path.into_inner()).await?;
+
+    Ok(web::Json(class))
 }
 
 #[utoipa::path(
     post,
     path = "/api/v1/class",
     tag = "classes",
-    summary = "Create class (Not Implemented)",
-    description = "Create a new class - currently not implemented",
+    summary = "Create class",
+    description = "Create a new class with the grade scale its projects are graded in",
+    request_body = ClassSettings,
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "Class created successfully", body = entity::class::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[post("")]
-async fn create_class() -> impl Responder {
-    ""
+async fn create_class(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    settings: web::Json<ClassSettings>,
+) -> Result<web::Json<entity::class::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    settings.validate()?;
+    let scale = settings.scale()?;
+
+    let settings = settings.into_inner();
+    let class = db
+        .create_class(settings.name, scale, settings.school_year_id, Some(user.id))
+        .await?;
+    Ok(web::Json(class))
 }
 
 #[utoipa::path(
     put,
-    path = "/api/v1/class",
+    path = "/api/v1/class/{id}",
     tag = "classes",
-    summary = "Update class (Not Implemented)",
-    description = "Update an existing class - currently not implemented",
+    summary = "Update class",
+    description = "Rename the class or change its grade scale. Group and individual grades of its projects are converted \
+        to the new scale, which isn't possible while a survey of one of them is finalised.",
+    params(
+        ("id" = String, Path, description = "Class ID to update")
+    ),
+    request_body = ClassSettings,
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "Class updated successfully", body = entity::class::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Class not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "A survey of the class is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
-#[put("")]
-async fn update_class() -> impl Responder {
-    ""
+#[put("/{id}")]
+async fn update_class(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    settings: web::Json<ClassSettings>,
+) -> Result<web::Json<entity::class::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    settings.validate()?;
+    let scale = settings.scale()?;
+
+    let settings = settings.into_inner();
+    let class = db
+        .update_class(
+            path.into_inner(),
+            settings.name,
+            scale,
+            settings.school_year_id,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(class))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/v1/class/{id}",
     tag = "classes",
-    summary = "Delete class (Not Implemented)",
-    description = "Delete a class by its ID - currently not implemented",
+    summary = "Delete class",
+    description = "Delete a class by its ID. Its projects are kept without class, their grades are converted to German grades.",
     params(
         ("id" = String, Path, description = "Class ID to delete")
     ),
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "Class deleted successfully", body = String, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Class not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "A survey of the class is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[delete("/{id}")]
-async fn delete_class() -> impl Responder {
-    ""
+async fn delete_class(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<String>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    db.delete_class(id, Some(user.id)).await?;
+
+    Ok(web::Json(format!("Class {} deleted", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/class/{id}/students",
+    tag = "classes",
+    summary = "Get the roster of the class",
+    description = "Retrieve a page of the students enrolled in the class with their enrolments, sortable by `name` and `username`. \
+        Groups of the projects of the class can only be formed from these students.",
+    params(
+        ("id" = String, Path, description = "Class ID"),
+        ListQuery,
+        RosterQuery
+    ),
+    responses(
+        (status = 200, description = "Enrolled students", body = Page<RosterEntry>, content_type = "application/json"),
+        (status = 400, description = "Invalid date, paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Class not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/students")]
+async fn get_roster(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    pagination: Pagination,
+    query: web::Query<RosterQuery>,
+) -> Result<web::Json<Page<RosterEntry>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+
+    let day = (!query.all).then(|| query.on.unwrap_or_else(|| Utc::now().date_naive()));
+    let (roster, total) = db.get_roster(path.into_inner(), day, &pagination).await?;
+    let roster = roster
+        .into_iter()
+        .map(|(enrolment, student)| RosterEntry { enrolment, student })
+        .collect();
+    Ok(web::Json(pagination.page(roster, total)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/class/{id}/students",
+    tag = "classes",
+    summary = "Enrol a student",
+    description = "Enrol a student in the class for a period. A student can be enrolled several times, e.g. after a break, but the periods may not overlap.",
+    params(
+        ("id" = String, Path, description = "Class ID")
+    ),
+    request_body = Enrolment,
+    responses(
+        (status = 200, description = "Student enrolled", body = entity::class_enrolment::Model, content_type = "application/json"),
+        (status = 400, description = "Not a student, invalid or overlapping period", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Class not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/students")]
+async fn enrol_student(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<Enrolment>,
+) -> Result<web::Json<entity::class_enrolment::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+
+    let enrolment = db
+        .enrol_student(
+            path.into_inner(),
+            request.user_id,
+            request.starts_on,
+            request.ends_on,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(enrolment))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/class/{id}/students/import",
+    tag = "classes",
+    summary = "Import the roster from a CSV file",
+    description = "Enrol a student for every row of a CSV file with the column `username` and optionally `name`, `email`, `starts_on` and `ends_on`. \
+        Students with an unknown username are created without password if a name is given. \
+        Dates are written like `2025-08-01` or `01.08.2025`. Rows are imported independently, the result reports success or failure per row.",
+    params(
+        ("id" = String, Path, description = "Class ID")
+    ),
+    request_body(content = String, content_type = "text/csv", example = "username,name,email,starts_on\nmmuster,Max Mustermann,max@example.org,2025-08-01\n"),
+    responses(
+        (status = 200, description = "Result per row", body = RosterImportResult, content_type = "application/json"),
+        (status = 400, description = "CSV is empty or lacks the username column", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Class not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/students/import")]
+async fn import_roster(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    body: String,
+) -> Result<web::Json<RosterImportResult>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let class = db.get_class(path.into_inner()).await?;
+
+    let header = csv::parse(&body).into_iter().next().unwrap_or_default();
+    if !header.iter().any(|h| h.trim().to_lowercase() == "username") {
+        return Err(ApiError::BadRequest(
+            "CSV must have the column username".to_owned(),
+        ));
+    }
+
+    let mut rows = Vec::new();
+    for (index, mut record) in csv::parse_with_header(&body).into_iter().enumerate() {
+        let row = RosterRow {
+            username: record.remove("username").unwrap_or_default(),
+            name: record.remove("name").filter(|n| !n.is_empty()),
+            email: record.remove("email").filter(|e| !e.is_empty()),
+        };
+        let username = row.username.clone();
+
+        let result = match (
+            row.validate(),
+            parse_date("starts_on", record.remove("starts_on")),
+            parse_date("ends_on", record.remove("ends_on")),
+        ) {
+            (Ok(()), Ok(starts_on), Ok(ends_on)) => {
+                let student = RosterStudent {
+                    username: row.username,
+                    name: row.name,
+                    email: row.email,
+                    starts_on,
+                    ends_on,
+                };
+                db.import_roster_student(class.id, student, Some(user.id))
+                    .await
+            }
+            (Err(e), _, _) => Err(ApiError::ValidationError(e)),
+            (_, Err(e), _) | (_, _, Err(e)) => Err(e),
+        };
+
+        rows.push(match result {
+            Ok((enrolment, created)) => RosterRowResult {
+                row: index + 2,
+                username,
+                status: if created {
+                    RosterRowStatus::Created
+                } else {
+                    RosterRowStatus::Enrolled
+                },
+                user_id: Some(enrolment.user_id),
+                code: None,
+                error: None,
+            },
+            Err(e) => RosterRowResult {
+                row: index + 2,
+                username,
+                status: RosterRowStatus::Failed,
+                user_id: None,
+                code: Some(e.code().to_owned()),
+                error: Some(super::user::bulk_row_error(&e)),
+            },
+        });
+    }
+
+    let count = |status: RosterRowStatus| rows.iter().filter(|row| row.status == status).count();
+    Ok(web::Json(RosterImportResult {
+        enrolled: count(RosterRowStatus::Enrolled),
+        created: count(RosterRowStatus::Created),
+        failed: count(RosterRowStatus::Failed),
+        rows,
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/class/{id}/students/{enrolment_id}",
+    tag = "classes",
+    summary = "Change an enrolment",
+    description = "Change the period of an enrolment, e.g. set the last day when a student leaves the class. \
+        Students who left stay in the groups they were in.",
+    params(
+        ("id" = String, Path, description = "Class ID"),
+        ("enrolment_id" = String, Path, description = "Enrolment ID")
+    ),
+    request_body = EnrolmentPeriod,
+    responses(
+        (status = 200, description = "Enrolment changed", body = entity::class_enrolment::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid or overlapping period", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Enrolment not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[put("/{id}/students/{enrolment_id}")]
+async fn update_enrolment(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    request: web::Json<EnrolmentPeriod>,
+) -> Result<web::Json<entity::class_enrolment::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (id, enrolment_id) = path.into_inner();
+
+    let enrolment = db
+        .update_enrolment(
+            id,
+            enrolment_id,
+            request.starts_on,
+            request.ends_on,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(enrolment))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/class/{id}/students/{enrolment_id}",
+    tag = "classes",
+    summary = "Delete an enrolment",
+    description = "Delete an enrolment made by mistake. Students who leave the class should get an end date instead, so the roster of earlier days stays correct.",
+    params(
+        ("id" = String, Path, description = "Class ID"),
+        ("enrolment_id" = String, Path, description = "Enrolment ID")
+    ),
+    responses(
+        (status = 200, description = "Enrolment deleted", body = String, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Enrolment not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[delete("/{id}/students/{enrolment_id}")]
+async fn delete_enrolment(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<web::Json<String>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (id, enrolment_id) = path.into_inner();
+    db.delete_enrolment(id, enrolment_id, Some(user.id)).await?;
+
+    Ok(web::Json(format!("Enrolment {} deleted", enrolment_id)))
 }
@@ -1,59 +1,549 @@
-use actix_web::{HttpResponse, Responder, delete, get, post, web::ServiceConfig};
+use actix_session::Session;
+use actix_web::{HttpResponse, Responder, delete, get, post, web, web::ServiceConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
 
-use crate::error::{ApiError, MessageResponse};
+use crate::auth::{self, AuthenticatedUser};
+use crate::db::Database;
+use crate::db::entity::sea_orm_active_enums::{GradeScale, SurveyStatus, UserRole};
+use crate::db::entity::{project, user_group_project};
+use crate::db::feedback::{NewFeedbackResponse, NewSelfAssessment};
+use crate::error::{ApiError, MessageResponse, ProblemDetails};
+use crate::grading;
+use crate::survey;
 
 pub fn setup(cfg: &mut ServiceConfig) {
-    cfg.service(get_feedback_form)
+    // The session routes come first, `/status` would match `/{token}` as well
+    cfg.service(get_session_feedback_form)
+        .service(submit_session_feedback)
+        .service(get_session_feedback_status)
+        .service(open_feedback_session)
+        .service(get_feedback_form)
         .service(submit_feedback)
         .service(get_feedback_status)
         .service(reset_feedback);
 }
 
-#[utoipa::path(get, path = "/api/v1/feedback/{token}", tag = "feedback")]
-#[get("/feedback/{token}")]
-pub async fn get_feedback_form() -> Result<impl Responder, ApiError> {
-    // TODO: Implement feedback form retrieval
-    // 1. Validate token exists and is not completed
-    // 2. Get project template and group information
-    // 3. Return form structure
+#[derive(Serialize, ToSchema)]
+pub struct FeedbackForm {
+    project_id: Uuid,
+    project_name: String,
+    survey_status: SurveyStatus,
+    /// Start of the feedback period, if the project has one
+    #[schema(value_type = Option<String>, format = DateTime)]
+    opens_at: Option<DateTime<Utc>>,
+    /// End of the feedback period, if the project has one
+    #[schema(value_type = Option<String>, format = DateTime)]
+    deadline: Option<DateTime<Utc>>,
+    /// Whether the form can be submitted right now
+    submission_open: bool,
+    completed: bool,
+    /// Whether the form asks the student to rate themselves as well
+    self_assessment_enabled: bool,
+    /// Self-assessment submitted so far
+    self_assessment: Option<SelfAssessment>,
+    /// Group mates to rate, with the ratings submitted so far
+    members: Vec<FeedbackFormMember>,
+}
 
-    Ok(HttpResponse::Ok().json("{}"))
+/// A session restricted to the project of a feedback link
+#[derive(Serialize, ToSchema)]
+pub struct StudentSession {
+    user_id: Uuid,
+    project_id: Uuid,
+    /// End of the session, the student has to open their link again afterwards
+    #[schema(value_type = String, format = DateTime)]
+    expires_at: DateTime<Utc>,
 }
 
-#[utoipa::path(post, path = "/api/v1/feedback/{token}", tag = "feedback")]
-#[post("/feedback/{token}")]
-pub async fn submit_feedback() -> Result<impl Responder, ApiError> {
-    // TODO: Implement feedback submission
-    // 1. Validate token and ensure not already completed
-    // 2. Store feedback responses
-    // 3. Mark as completed with timestamp
+#[derive(Serialize, ToSchema)]
+pub struct FeedbackFormMember {
+    user_id: Uuid,
+    name: String,
+    rating: Option<i16>,
+    comment: Option<String>,
+}
 
-    Ok(HttpResponse::Ok().json(MessageResponse {
-        message: "Feedback submitted successfully".to_string(),
-    }))
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct SubmitFeedback {
+    /// One rating for each group mate
+    #[validate(nested)]
+    ratings: Vec<PeerRating>,
+    /// Required if the project asks for a self-assessment, not allowed otherwise
+    #[validate(nested)]
+    self_assessment: Option<SelfAssessment>,
+}
+
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct SelfAssessment {
+    /// German school grade from 1 (very good) to 6 (insufficient)
+    #[validate(range(min = 1, max = 6))]
+    rating: i16,
+    #[validate(length(max = 2000))]
+    comment: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FeedbackStatus {
+    project_name: String,
+    survey_status: SurveyStatus,
+    /// Whether the student submitted their feedback
+    completed: bool,
+    /// Whether every member of the group submitted their feedback
+    group_completed: bool,
+    /// The student's own self-assessment
+    self_assessment: Option<SelfAssessment>,
+    /// Scale of the grade, set by the class of the project
+    grade_scale: GradeScale,
+    /// Final grade rounded as configured for the class, once the survey is finalised
+    grade: Option<f64>,
+    /// Final grade as written on a report, e.g. `2+` for German grades with tendency
+    grade_text: Option<String>,
+    /// Average rating received from group mates, once the survey is finalised
+    peer_average: Option<f64>,
+    /// Self-assessment minus the average peer rating, once the survey is finalised.
+    /// Positive if the student rated themselves worse than their group mates did.
+    self_peer_gap: Option<f64>,
+    /// Ratings and comments received from group mates without their names, once the survey is finalised
+    received: Vec<ReceivedFeedback>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReceivedFeedback {
+    rating: i16,
+    comment: Option<String>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct PeerRating {
+    user_id: Uuid,
+    /// German school grade from 1 (very good) to 6 (insufficient)
+    #[validate(range(min = 1, max = 6))]
+    rating: i16,
+    #[validate(length(max = 2000))]
+    comment: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/feedback/{token}",
+    tag = "feedback",
+    summary = "Get the feedback form",
+    description = "Retrieve the form behind a student's feedback link: the group mates to rate, the ratings submitted so far \
+        and whether the survey currently accepts submissions.",
+    params(
+        ("token" = String, Path, description = "Feedback token of the student")
+    ),
+    responses(
+        (status = 200, description = "Feedback form", body = FeedbackForm, content_type = "application/json"),
+        (status = 401, description = "The student is deactivated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown feedback token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{token}")]
+pub async fn get_feedback_form(
+    db: web::Data<Database>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<FeedbackForm>, ApiError> {
+    let (member, project) = db.get_feedback_member(path.into_inner()).await?;
+    Ok(web::Json(feedback_form(&db, member, project).await?))
+}
+
+async fn feedback_form(
+    db: &Database,
+    member: user_group_project::Model,
+    project: project::Model,
+) -> Result<FeedbackForm, ApiError> {
+    let given = db.get_given_feedback(project.id, member.user_id).await?;
+    let self_assessment = db
+        .get_self_assessment(project.id, member.user_id)
+        .await?
+        .map(|a| SelfAssessment {
+            rating: a.rating,
+            comment: a.comment,
+        });
+
+    let members = db
+        .get_group_members(project.id, member.group_id)
+        .await?
+        .into_iter()
+        .filter(|(mate, _)| mate.user_id != member.user_id)
+        .map(|(_, user)| {
+            let response = given.iter().find(|r| r.to_user_id == user.id);
+            FeedbackFormMember {
+                user_id: user.id,
+                name: user.name,
+                rating: response.map(|r| r.rating),
+                comment: response.and_then(|r| r.comment.clone()),
+            }
+        })
+        .collect();
+
+    Ok(FeedbackForm {
+        project_id: project.id,
+        submission_open: survey::check_submission_window(&project, Utc::now()).is_ok(),
+        project_name: project.name,
+        survey_status: project.survey_status,
+        opens_at: project.feedback_opens_at.map(|d| d.to_utc()),
+        deadline: project.feedback_deadline.map(|d| d.to_utc()),
+        completed: member.feedback_completed,
+        self_assessment_enabled: project.self_assessment,
+        self_assessment,
+        members,
+    })
 }
 
-#[utoipa::path(get, path = "/api/v1/feedback/{token}/status", tag = "feedback")]
-#[get("/feedback/{token}/status")]
-pub async fn get_feedback_status() -> Result<impl Responder, ApiError> {
-    // TODO: Implement status checking
-    // 1. Get completion status for this token
-    // 2. Check if all group members completed feedback
-    // 3. Calculate final grade if group grade exists
-    // 4. Get anonymized feedback received
+#[utoipa::path(
+    post,
+    path = "/api/v1/feedback/{token}",
+    tag = "feedback",
+    summary = "Submit feedback",
+    description = "Submit a rating for every group mate, and a self-assessment if the project asks for one. \
+        The form can only be submitted once, unless a teacher resets it.",
+    params(
+        ("token" = String, Path, description = "Feedback token of the student")
+    ),
+    request_body = SubmitFeedback,
+    responses(
+        (status = 200, description = "Feedback stored", body = MessageResponse, content_type = "application/json"),
+        (status = 400, description = "Invalid ratings, not every group mate rated or self-assessment missing or not allowed", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "The student is deactivated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown feedback token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Feedback already submitted, the survey is not open or outside the feedback period", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{token}")]
+pub async fn submit_feedback(
+    db: web::Data<Database>,
+    path: web::Path<Uuid>,
+    request: web::Json<SubmitFeedback>,
+) -> Result<impl Responder, ApiError> {
+    request.validate()?;
+
+    let (member, project) = db.get_feedback_member(path.into_inner()).await?;
+    store_feedback(&db, member, project, request.into_inner()).await?;
 
-    Ok(HttpResponse::Ok().json("{}"))
+    Ok(HttpResponse::Ok().json(MessageResponse::new("Feedback submitted successfully")))
 }
 
-#[utoipa::path(delete, path = "/api/v1/feedback/{token}/reset", tag = "feedback")]
-#[delete("/feedback/{token}/reset")]
-pub async fn reset_feedback() -> Result<impl Responder, ApiError> {
-    // TODO: Implement feedback reset
-    // 1. Verify teacher authorization
-    // 2. Reset completion status and clear responses
-    // 3. Optionally regenerate token
+async fn store_feedback(
+    db: &Database,
+    member: user_group_project::Model,
+    project: project::Model,
+    request: SubmitFeedback,
+) -> Result<(), ApiError> {
+    let now = Utc::now();
+    survey::check_submission_window(&project, now)?;
+    if member.feedback_completed {
+        return Err(ApiError::FeedbackAlreadySubmitted);
+    }
+
+    let mates: HashSet<Uuid> = db
+        .get_group_members(project.id, member.group_id)
+        .await?
+        .into_iter()
+        .map(|(mate, _)| mate.user_id)
+        .filter(|id| *id != member.user_id)
+        .collect();
+
+    let rated: HashSet<Uuid> = request.ratings.iter().map(|r| r.user_id).collect();
+    if rated.len() != request.ratings.len() {
+        return Err(ApiError::BadRequest(
+            "Each group mate can only be rated once".to_owned(),
+        ));
+    }
+    if rated != mates {
+        return Err(ApiError::BadRequest(
+            "Every group mate, and only them, must be rated".to_owned(),
+        ));
+    }
+
+    match (&request.self_assessment, project.self_assessment) {
+        (None, true) => {
+            return Err(ApiError::BadRequest(
+                "The self-assessment is missing".to_owned(),
+            ));
+        }
+        (Some(_), false) => {
+            return Err(ApiError::BadRequest(
+                "The project doesn't ask for a self-assessment".to_owned(),
+            ));
+        }
+        _ => {}
+    }
+
+    let self_assessment = request.self_assessment.map(|a| NewSelfAssessment {
+        rating: a.rating,
+        comment: a.comment.filter(|c| !c.trim().is_empty()),
+    });
+    let responses = request
+        .ratings
+        .into_iter()
+        .map(|r| NewFeedbackResponse {
+            to_user_id: r.user_id,
+            rating: r.rating,
+            comment: r.comment.filter(|c| !c.trim().is_empty()),
+        })
+        .collect();
+
+    db.submit_feedback(&member, responses, self_assessment, now)
+        .await
+}
 
-    Ok(HttpResponse::Ok().json(MessageResponse {
-        message: "Feedback reset successfully".to_string(),
+#[utoipa::path(
+    get,
+    path = "/api/v1/feedback/{token}/status",
+    tag = "feedback",
+    summary = "Get the feedback status",
+    description = "The page students see after submitting their feedback. Shows whether the group completed the survey and the \
+        student's self-assessment. Once the survey is finalised it also shows the grade, the ratings and comments received \
+        without the names of the group mates, and how the self-assessment compares to them.",
+    params(
+        ("token" = String, Path, description = "Feedback token of the student")
+    ),
+    responses(
+        (status = 200, description = "Feedback status", body = FeedbackStatus, content_type = "application/json"),
+        (status = 401, description = "The student is deactivated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown feedback token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{token}/status")]
+pub async fn get_feedback_status(
+    db: web::Data<Database>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<FeedbackStatus>, ApiError> {
+    let (member, project) = db.get_feedback_member(path.into_inner()).await?;
+    Ok(web::Json(feedback_status(&db, member, project).await?))
+}
+
+async fn feedback_status(
+    db: &Database,
+    member: user_group_project::Model,
+    project: project::Model,
+) -> Result<FeedbackStatus, ApiError> {
+    let grading = db.get_group_grading(project.id, member.group_id).await?;
+
+    let self_assessment = grading
+        .self_assessments
+        .iter()
+        .find(|a| a.user_id == member.user_id)
+        .map(|a| SelfAssessment {
+            rating: a.rating,
+            comment: a.comment.clone(),
+        });
+
+    let scale = db.get_project_scale(&project).await?;
+    let mut status = FeedbackStatus {
+        project_name: project.name.clone(),
+        survey_status: project.survey_status,
+        grade_scale: scale.kind,
+        completed: member.feedback_completed,
+        group_completed: grading.members.iter().all(|(m, _)| m.feedback_completed),
+        self_assessment,
+        grade: None,
+        grade_text: None,
+        peer_average: None,
+        self_peer_gap: None,
+        received: Vec::new(),
+    };
+    if project.survey_status != SurveyStatus::Finalised {
+        return Ok(status);
+    }
+
+    let grades = grading::student_grades(&project, &scale, std::slice::from_ref(&grading));
+    let grade = grades
+        .iter()
+        .find(|grade| grade.user.id == member.user_id)
+        .and_then(|grade| grade.final_grade());
+    status.grade = grade.map(|grade| scale.round(grade));
+    status.grade_text = grade.map(|grade| scale.display(grade, project.language));
+
+    // Ratings of students who left the group don't count, as in the grade
+    let mut received: Vec<ReceivedFeedback> = grading
+        .responses
+        .iter()
+        .filter(|r| r.to_user_id == member.user_id)
+        .filter(|r| {
+            grading
+                .members
+                .iter()
+                .any(|(mate, _)| mate.user_id == r.from_user_id)
+        })
+        .map(|r| ReceivedFeedback {
+            rating: r.rating,
+            comment: r.comment.clone(),
+        })
+        .collect();
+    // Sorted so the order doesn't reveal who gave which rating
+    received.sort_by(|a, b| (a.rating, &a.comment).cmp(&(b.rating, &b.comment)));
+
+    if !received.is_empty() {
+        let sum: f64 = received.iter().map(|r| f64::from(r.rating)).sum();
+        let average = sum / received.len() as f64;
+        status.peer_average = Some(average);
+        status.self_peer_gap = status
+            .self_assessment
+            .as_ref()
+            .map(|a| f64::from(a.rating) - average);
+    }
+    status.received = received;
+
+    Ok(status)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/feedback/{token}/reset",
+    tag = "feedback",
+    summary = "Reset feedback",
+    description = "Delete the ratings a student submitted so they can fill in the form again. Not possible once the survey is finalised.",
+    params(
+        ("token" = String, Path, description = "Feedback token of the student")
+    ),
+    responses(
+        (status = 200, description = "Feedback reset", body = MessageResponse, content_type = "application/json"),
+        (status = 401, description = "Not authenticated or the student is deactivated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown feedback token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[delete("/{token}/reset")]
+pub async fn reset_feedback(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    db.reset_feedback(path.into_inner(), Some(user.id)).await?;
+
+    Ok(HttpResponse::Ok().json(MessageResponse::new("Feedback reset successfully")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/feedback/{token}/session",
+    tag = "feedback",
+    summary = "Open a student session",
+    description = "Start a short-lived session with the student's feedback link, so the link doesn't have to stay in the address bar. \
+        The session only gives access to the feedback form of this project and ends after two hours or once the link stops working. \
+        Students don't need a password for this.",
+    params(
+        ("token" = String, Path, description = "Feedback token of the student")
+    ),
+    responses(
+        (status = 200, description = "Session started", body = StudentSession, content_type = "application/json"),
+        (status = 401, description = "The student is deactivated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown feedback token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "A user is logged in", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{token}/session")]
+pub async fn open_feedback_session(
+    db: web::Data<Database>,
+    path: web::Path<Uuid>,
+    session: Session,
+) -> Result<web::Json<StudentSession>, ApiError> {
+    let feedback_id = path.into_inner();
+    let (member, project) = db.get_feedback_member(feedback_id).await?;
+
+    if auth::is_logged_in(&session)? {
+        return Err(ApiError::AlreadyLoggedIn);
+    }
+    let expires_at = auth::start_feedback_session(&session, member.user_id, feedback_id)?;
+
+    Ok(web::Json(StudentSession {
+        user_id: member.user_id,
+        project_id: project.id,
+        expires_at,
     }))
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/feedback",
+    tag = "feedback",
+    summary = "Get the feedback form of the student session",
+    description = "Same as the form behind the feedback link, for a session opened with the link.",
+    responses(
+        (status = 200, description = "Feedback form", body = FeedbackForm, content_type = "application/json"),
+        (status = 401, description = "No session or the session expired", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not a session opened with a feedback link", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("")]
+pub async fn get_session_feedback_form(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<FeedbackForm>, ApiError> {
+    let (member, project) = db
+        .get_project_member(user.feedback_project()?, user.id)
+        .await?;
+    Ok(web::Json(feedback_form(&db, member, project).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/feedback",
+    tag = "feedback",
+    summary = "Submit feedback in the student session",
+    description = "Same as submitting the form behind the feedback link, for a session opened with the link.",
+    request_body = SubmitFeedback,
+    responses(
+        (status = 200, description = "Feedback stored", body = MessageResponse, content_type = "application/json"),
+        (status = 400, description = "Invalid ratings, not every group mate rated or self-assessment missing or not allowed", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "No session or the session expired", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not a session opened with a feedback link", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Feedback already submitted, the survey is not open or outside the feedback period", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("")]
+pub async fn submit_session_feedback(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    request: web::Json<SubmitFeedback>,
+) -> Result<impl Responder, ApiError> {
+    request.validate()?;
+
+    let (member, project) = db
+        .get_project_member(user.feedback_project()?, user.id)
+        .await?;
+    store_feedback(&db, member, project, request.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(MessageResponse::new("Feedback submitted successfully")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/feedback/status",
+    tag = "feedback",
+    summary = "Get the feedback status of the student session",
+    description = "Same as the status behind the feedback link, for a session opened with the link.",
+    responses(
+        (status = 200, description = "Feedback status", body = FeedbackStatus, content_type = "application/json"),
+        (status = 401, description = "No session or the session expired", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not a session opened with a feedback link", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/status")]
+pub async fn get_session_feedback_status(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<FeedbackStatus>, ApiError> {
+    let (member, project) = db
+        .get_project_member(user.feedback_project()?, user.id)
+        .await?;
+    Ok(web::Json(feedback_status(&db, member, project).await?))
+}
@@ -1,4 +1,16 @@
-use actix_web::{Responder, delete, get, post, put};
+use actix_web::{Responder, delete, get, post, put, web};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::db::entity;
+use crate::db::entity::sea_orm_active_enums::UserRole;
+use crate::db::group::{MembershipChange, NewGradeOverride};
+use crate::error::{ApiError, ProblemDetails};
+use crate::pagination::{ListQuery, Page, Pagination};
 
 pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
     cfg.service(get_groups)
@@ -9,40 +21,70 @@ pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
         .service(generate_group_feedback_tokens)
         .service(get_group_feedback_tokens)
         .service(set_group_grade)
-        .service(set_individual_grades);
+        .service(set_individual_grades)
+        .service(move_group_member)
+        .service(remove_group_member);
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/group",
     tag = "groups",
-    summary = "Get all groups (Not Implemented)",
-    description = "Retrieve a list of all groups - currently not implemented",
+    summary = "Get all groups",
+    description = "Retrieve a page of the groups of all projects, sortable by `name`.",
+    params(ListQuery),
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "List of groups retrieved successfully", body = Page<entity::group::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("")]
-async fn get_groups() -> impl Responder {
-    ""
+async fn get_groups(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    pagination: Pagination,
+) -> Result<web::Json<Page<entity::group::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (groups, total) = db.get_groups(None, &pagination).await?;
+    Ok(web::Json(pagination.page(groups, total)))
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/group/{project}",
     tag = "groups",
-    summary = "Get groups for project (Not Implemented)",
-    description = "Retrieve groups for a specific project - currently not implemented",
+    summary = "Get groups for project",
+    description = "Retrieve a page of the groups of a project, sortable by `name`.",
     params(
-        ("project" = String, Path, description = "Project ID")
+        ("project" = String, Path, description = "Project ID"),
+        ListQuery
     ),
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "Groups of the project", body = Page<entity::group::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("/{project}")]
-async fn get_groups_for_project() -> impl Responder {
-    ""
+async fn get_groups_for_project(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    pagination: Pagination,
+) -> Result<web::Json<Page<entity::group::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let project = db
+        .get_project(&path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let (groups, total) = db.get_groups(Some(project.id), &pagination).await?;
+    Ok(web::Json(pagination.page(groups, total)))
 }
 
 #[utoipa::path(
@@ -96,15 +138,32 @@ async fn delete_group() -> impl Responder {
 #[utoipa::path(
     post,
     path = "/api/v1/group/{id}/generate-feedback-tokens",
-    tag = "groups"
+    tag = "groups",
+    summary = "Regenerate feedback tokens",
+    description = "Give every member of the group a new feedback token, links sent before stop working. Submitted feedback is kept.",
+    params(
+        ("id" = String, Path, description = "Group ID")
+    ),
+    responses(
+        (status = 200, description = "Members with their new feedback tokens", body = Vec<entity::user_group_project::Model>, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
 )]
 #[post("/{id}/generate-feedback-tokens")]
-async fn generate_group_feedback_tokens() -> impl Responder {
-    // TODO: Generate feedback tokens for all students in this group
-    // 1. Get all UserGroupProject entries for this group
-    // 2. Generate UUID tokens for each student
-    // 3. Update database with tokens
-    ""
+async fn generate_group_feedback_tokens(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<Vec<entity::user_group_project::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+
+    let members = db
+        .regenerate_feedback_tokens(path.into_inner(), Some(user.id))
+        .await?;
+    Ok(web::Json(members))
 }
 
 #[utoipa::path(get, path = "/api/v1/group/{id}/feedback-tokens", tag = "groups")]
@@ -116,23 +175,215 @@ async fn get_group_feedback_tokens() -> impl Responder {
     ""
 }
 
-#[utoipa::path(post, path = "/api/v1/group/{id}/grade", tag = "groups")]
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct GroupGrade {
+    /// Grade in the scale of the project's class, German school grade from 1.0 (very good) to 6.0 (insufficient) without class
+    #[validate(range(min = 0.0, max = 100.0))]
+    grade: f64,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct IndividualGrades {
+    #[validate(nested)]
+    grades: Vec<IndividualGrade>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct IndividualGrade {
+    user_id: Uuid,
+    /// Grade in the scale of the project's class, German school grade from 1.0 (very good) to 6.0 (insufficient) without class.
+    /// `null` removes the override, so the computed grade applies again.
+    #[validate(range(min = 0.0, max = 100.0))]
+    grade: Option<f64>,
+    /// Why the computed grade is overridden or the override removed, kept in the grade history
+    #[validate(length(min = 1, max = 2000))]
+    justification: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/group/{id}/grade",
+    tag = "groups",
+    summary = "Set the group grade",
+    description = "Set the grade of the group's work. Not possible once the survey of the project is finalised.",
+    params(
+        ("id" = String, Path, description = "Group ID")
+    ),
+    request_body = GroupGrade,
+    responses(
+        (status = 200, description = "Group with the new grade", body = entity::group::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid grade", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 #[post("/{id}/grade")]
-async fn set_group_grade() -> impl Responder {
-    // TODO: Set the group grade (same grade for all students)
-    // 1. Validate teacher authorization
-    // 2. Store group grade for all students in group
-    // 3. Apply same grade to all UserGroupProject entries
-    ""
+async fn set_group_grade(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<GroupGrade>,
+) -> Result<web::Json<entity::group::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    request.validate()?;
+
+    let group = db
+        .set_group_grade(path.into_inner(), request.grade, Some(user.id))
+        .await?;
+    Ok(web::Json(group))
 }
 
-#[utoipa::path(post, path = "/api/v1/group/{id}/individual-grades", tag = "groups")]
+#[utoipa::path(
+    post,
+    path = "/api/v1/group/{id}/individual-grades",
+    tag = "groups",
+    summary = "Override grades",
+    description = "Override the computed grades of single group members, either all of them are stored or none. \
+        Every override needs a justification and is recorded in the grade history along with the computed grade. \
+        Students only see the effective grade. Not possible once the survey of the project is finalised.",
+    params(
+        ("id" = String, Path, description = "Group ID")
+    ),
+    request_body = IndividualGrades,
+    responses(
+        (status = 200, description = "Updated group memberships", body = Vec<entity::user_group_project::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid grade, missing justification or user not in the group", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 #[post("/{id}/individual-grades")]
-async fn set_individual_grades() -> impl Responder {
-    // TODO: Set individual grades for each student in group
-    // 1. Validate teacher authorization
-    // 2. Accept array of student_id -> grade mappings
-    // 3. Store individual grades in UserGroupProject entries
-    // 4. Calculate final grades with peer feedback if available
-    ""
+async fn set_individual_grades(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<IndividualGrades>,
+) -> Result<web::Json<Vec<entity::user_group_project::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    request.validate()?;
+
+    let overrides = request
+        .into_inner()
+        .grades
+        .into_iter()
+        .map(|grade| NewGradeOverride {
+            user_id: grade.user_id,
+            grade: grade.grade,
+            justification: grade.justification,
+        })
+        .collect();
+
+    let members = db
+        .set_individual_grades(path.into_inner(), overrides, user.id)
+        .await?;
+    Ok(web::Json(members))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MoveMember {
+    /// Group of the same project the student moves to
+    group_id: Uuid,
+    /// Reset the affected forms instead of only marking them stale, the students have to submit again
+    #[serde(default)]
+    reset_feedback: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RemoveMemberQuery {
+    /// Reset the affected forms instead of only marking them stale, the students have to submit again
+    #[serde(default)]
+    reset_feedback: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/group/{id}/members/{user_id}/move",
+    tag = "groups",
+    summary = "Move a student to another group",
+    description = "Move a student to another group of the project, their feedback link and grade are kept. \
+        Submitted forms that no longer match the groups are marked stale or reset: the student's own, \
+        those of the new group mates and those of old group mates who rated the student. \
+        Only ratings between current members of a group count toward the grades. \
+        Not possible once the survey of the project is finalised.",
+    params(
+        ("id" = String, Path, description = "Group ID"),
+        ("user_id" = String, Path, description = "User ID of the student")
+    ),
+    request_body = MoveMember,
+    responses(
+        (status = 200, description = "New membership and the affected forms", body = MembershipChange, content_type = "application/json"),
+        (status = 400, description = "User not in the group or target group not in the project", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/members/{user_id}/move")]
+async fn move_group_member(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    request: web::Json<MoveMember>,
+) -> Result<web::Json<MembershipChange>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (id, user_id) = path.into_inner();
+
+    let change = db
+        .move_group_member(
+            id,
+            user_id,
+            request.group_id,
+            request.reset_feedback,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(change))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/group/{id}/members/{user_id}",
+    tag = "groups",
+    summary = "Remove a student from the group",
+    description = "Remove a student from the group along with the feedback they gave, which is kept in the audit log. \
+        Submitted forms of group mates who rated the student are marked stale or reset. \
+        Not possible once the survey of the project is finalised.",
+    params(
+        ("id" = String, Path, description = "Group ID"),
+        ("user_id" = String, Path, description = "User ID of the student"),
+        RemoveMemberQuery
+    ),
+    responses(
+        (status = 200, description = "Affected forms", body = MembershipChange, content_type = "application/json"),
+        (status = 400, description = "User not in the group", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[delete("/{id}/members/{user_id}")]
+async fn remove_group_member(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<RemoveMemberQuery>,
+) -> Result<web::Json<MembershipChange>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (id, user_id) = path.into_inner();
+
+    let change = db
+        .remove_group_member(id, user_id, query.reset_feedback, Some(user.id))
+        .await?;
+    Ok(web::Json(change))
 }
@@ -0,0 +1,91 @@
+use actix_web::{get, web};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::db::entity;
+use crate::db::entity::sea_orm_active_enums::{JobStatus, UserRole};
+use crate::error::{ApiError, ProblemDetails};
+use crate::pagination::{ListQuery, Page, Pagination};
+
+pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(get_jobs).service(get_job);
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobQuery {
+    /// Only jobs with this status
+    status: Option<JobStatus>,
+}
+
+/// Admins see every job, everybody else only their own
+fn visible_to(user: &AuthenticatedUser) -> Option<Uuid> {
+    (user.role != UserRole::Admin).then_some(user.id)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/job",
+    tag = "jobs",
+    summary = "List background jobs",
+    description = "Retrieve a page of the jobs started by the current user, or of all jobs for admins. Sortable by `created_at` and `status`",
+    params(ListQuery, JobQuery),
+    responses(
+        (status = 200, description = "Page of jobs", body = Page<entity::job::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("")]
+async fn get_jobs(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    pagination: Pagination,
+    query: web::Query<JobQuery>,
+) -> Result<web::Json<Page<entity::job::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (jobs, total) = db
+        .get_jobs(visible_to(&user), query.status, &pagination)
+        .await?;
+
+    Ok(web::Json(pagination.page(jobs, total)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/job/{id}",
+    tag = "jobs",
+    summary = "Get job status",
+    description = "Retrieve status, progress and result of a background job. Poll this until the status is `succeeded` or `failed`.",
+    params(
+        ("id" = String, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 200, description = "Job retrieved successfully", body = entity::job::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Job not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}")]
+async fn get_job(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<entity::job::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let job = db.get_job(path.into_inner()).await?;
+
+    // Don't reveal that other users' jobs exist
+    if visible_to(&user).is_some_and(|id| job.created_by != Some(id)) {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(web::Json(job))
+}
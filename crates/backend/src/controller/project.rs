@@ -1,18 +1,220 @@
-use actix_web::{Result, delete, get, post, put, web};
+use actix_web::{
+    HttpResponse, Result, delete, get,
+    http::header::{self, ContentDisposition, DispositionParam, DispositionType},
+    post, put, web,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
+use crate::analytics::ProjectAnalytics;
+use crate::auth::AuthenticatedUser;
 use crate::db::Database;
 use crate::db::entity;
-use crate::db::project::CreateProject;
-use crate::error::ApiError;
+use crate::db::entity::sea_orm_active_enums::{
+    JobKind, Language, PeerAggregation, SurveyStatus, UserRole,
+};
+use crate::db::group::NewGroup;
+use crate::db::project::{CreateProject, SurveySettings};
+use crate::error::{ApiError, ProblemDetails};
+use crate::export::{self, ExportFormat};
+use crate::grading;
+use crate::grouping::{self, BalanceBy, GroupSizing, GroupingMethod, ProposedGroup};
+use crate::jobs;
+use crate::mail::FeedbackLinksJob;
+use crate::pagination::{ListQuery, Page, Pagination};
+use crate::report;
+use crate::survey;
 
 pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
     cfg.service(get_project)
         .service(get_projects)
         .service(create_project)
         .service(update_project)
-        .service(delete_project);
+        .service(delete_project)
+        .service(clone_project)
+        .service(archive_project)
+        .service(unarchive_project)
+        .service(update_feedback_settings)
+        .service(update_grading_settings)
+        .service(send_feedback_links)
+        .service(get_mail_deliveries)
+        .service(set_survey_status)
+        .service(get_survey_events)
+        .service(get_grades)
+        .service(get_grade_history)
+        .service(export_grades)
+        .service(get_student_report)
+        .service(get_group_report)
+        .service(get_project_analytics)
+        .service(generate_groups)
+        .service(create_groups);
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProjectQuery {
+    /// Case-insensitive search in the project name
+    search: Option<String>,
+    /// Also list archived projects
+    #[serde(default)]
+    include_archived: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// `csv`, `xlsx` or `moodle` for the Moodle grade import
+    format: ExportFormat,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GradeHistoryQuery {
+    /// Only changes of this student's grade
+    user_id: Option<Uuid>,
+}
+
+/// Grade of a student as teachers see it, in the scale of the project's class
+#[derive(Serialize, ToSchema)]
+pub struct ProjectGrade {
+    user_id: Uuid,
+    name: String,
+    username: String,
+    group_name: String,
+    group_grade: Option<f64>,
+    peer_factor: Option<f64>,
+    /// Grade computed from the group grade and the peer feedback, rounded as configured for the class
+    computed_grade: Option<f64>,
+    /// Grade the teacher set instead of the computed one
+    individual_grade: Option<f64>,
+    /// Why the teacher overrode the computed grade
+    justification: Option<String>,
+    /// Effective grade, the only one the student sees, rounded as configured for the class
+    final_grade: Option<f64>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct CloneProject {
+    /// Name of the new project (minimum 3 characters and maximum 255 characters)
+    #[validate(length(min = 3, max = 255))]
+    name: String,
+    /// Class of the new project, e.g. the class of the next school year, the class of the original if omitted
+    class_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SendFeedbackLinks {
+    /// Only send to the members of this group, all groups of the project if omitted
+    group_id: Option<Uuid>,
+    /// Language of the mail, German if omitted
+    #[serde(default)]
+    language: Language,
+}
+
+/// Longest cadence that makes sense for a school term
+const MAX_REMINDER_DAYS: u16 = 90;
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FeedbackSettings {
+    /// Students can submit their feedback from then on, as soon as the survey is opened if omitted
+    #[schema(value_type = Option<String>, format = DateTime)]
+    opens_at: Option<DateTime<Utc>>,
+    /// Students should have submitted their feedback by then, no reminders are sent without
+    #[schema(value_type = Option<String>, format = DateTime)]
+    deadline: Option<DateTime<Utc>>,
+    /// Days before the deadline at which students without feedback get a reminder, e.g. `[7, 1]`
+    #[validate(length(max = 10), custom(function = "validate_reminder_days"))]
+    #[serde(default)]
+    reminder_days: Vec<u16>,
+    /// Set to `false` to stop reminders, e.g. once grading is closed
+    #[serde(default = "default_true")]
+    reminders_enabled: bool,
+    /// Language of the reminder mails, German if omitted
+    #[serde(default)]
+    language: Language,
+    /// Ask students to rate themselves as well
+    #[serde(default)]
+    self_assessment: bool,
+    /// Count self-assessments towards the peer factor like a rating from a group mate, requires `self_assessment`
+    #[serde(default)]
+    self_assessment_in_grade: bool,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct GradingSettings {
+    /// How the ratings a student received are turned into their peer factor, `mean` if omitted
+    #[serde(default)]
+    peer_aggregation: PeerAggregation,
+    /// Largest difference between computed grade and group grade, e.g. `1.0`, unbounded if omitted
+    #[validate(range(min = 0.0, max = 5.0))]
+    max_grade_deviation: Option<f64>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct SurveyTransition {
+    /// New status of the survey
+    status: SurveyStatus,
+    /// Why the status is changed, required when reopening a finalised survey
+    #[validate(length(max = 1000))]
+    reason: Option<String>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct GenerateGroups {
+    /// Students to divide into groups, none of them may be in a group of the project yet.
+    /// All students enrolled in the class of the project who aren't in a group yet if omitted.
+    #[validate(length(min = 1, max = 1000))]
+    user_ids: Option<Vec<Uuid>>,
+    /// Largest number of members per group, either this or `group_count` is required
+    group_size: Option<usize>,
+    /// Number of groups, either this or `group_size` is required
+    group_count: Option<usize>,
+    /// `random` if omitted
+    #[serde(default)]
+    method: GroupingMethod,
+    /// What `balanced` groups are balanced by, `previous_grade` if omitted
+    #[serde(default)]
+    balance_by: BalanceBy,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct CreateGroups {
+    #[validate(nested)]
+    groups: Vec<CreateGroup>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct CreateGroup {
+    #[validate(length(min = 1, max = 255))]
+    name: String,
+    /// Students of the group, none of them may be in another group of the project
+    user_ids: Vec<Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedGroup {
+    #[serde(flatten)]
+    group: entity::group::Model,
+    members: Vec<entity::user_group_project::Model>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn validate_reminder_days(days: &[u16]) -> Result<(), ValidationError> {
+    if days
+        .iter()
+        .all(|days| (1..=MAX_REMINDER_DAYS).contains(days))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("range")
+            .with_message(format!("must be between 1 and {MAX_REMINDER_DAYS}").into()))
+    }
 }
 
 #[utoipa::path(
@@ -20,19 +222,29 @@ pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
     path = "/api/v1/project",
     tag = "projects",
     summary = "Get all projects",
-    description = "Retrieve a list of all projects",
+    description = "Retrieve a page of projects, sortable by `name`. Archived projects are left out unless `include_archived` is set.",
+    params(ListQuery, ProjectQuery),
     responses(
-        (status = 200, description = "List of projects retrieved successfully", body = Vec<entity::project::Model>, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 200, description = "List of projects retrieved successfully", body = Page<entity::project::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("")]
 async fn get_projects(
     db: web::Data<Database>,
-) -> Result<web::Json<Vec<entity::project::Model>>, ApiError> {
-    let projects = db.get_projects().await?;
+    user: AuthenticatedUser,
+    pagination: Pagination,
+    query: web::Query<ProjectQuery>,
+) -> Result<web::Json<Page<entity::project::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (projects, total) = db
+        .get_projects(query.search.as_deref(), query.include_archived, &pagination)
+        .await?;
 
-    Ok(web::Json(projects))
+    Ok(web::Json(pagination.page(projects, total)))
 }
 
 #[utoipa::path(
@@ -46,15 +258,19 @@ async fn get_projects(
     ),
     responses(
         (status = 200, description = "Project retrieved successfully", body = entity::project::Model, content_type = "application/json"),
-        (status = 404, description = "Project not found", body = String, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("/{id}")]
 async fn get_project(
     db: web::Data<Database>,
+    user: AuthenticatedUser,
     path: web::Path<Uuid>,
 ) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
     let id = path.into_inner();
 
     let project = db.get_project(&id).await?;
@@ -71,17 +287,23 @@ async fn get_project(
     request_body = CreateProject,
     responses(
         (status = 200, description = "Project created successfully", body = entity::project::Model, content_type = "application/json"),
-        (status = 400, description = "Invalid request data or validation error", body = String, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[post("")]
 async fn create_project(
     db: web::Data<Database>,
+    user: AuthenticatedUser,
     create_project: web::Json<CreateProject>,
 ) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
     create_project.validate()?;
-    let result = db.create_project(create_project.into_inner()).await?;
+    let result = db
+        .create_project(create_project.into_inner(), Some(user.id))
+        .await?;
 
     Ok(web::Json(result))
 }
@@ -98,19 +320,23 @@ async fn create_project(
     request_body = CreateProject,
     responses(
         (status = 200, description = "Project updated successfully", body = entity::project::Model, content_type = "application/json"),
-        (status = 400, description = "Invalid request data or validation error", body = String, content_type = "application/json"),
-        (status = 404, description = "Project not found", body = String, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[put("/{id}")]
 async fn update_project(
     db: web::Data<Database>,
+    user: AuthenticatedUser,
     path: web::Path<Uuid>,
     update_project: web::Json<CreateProject>,
 ) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
     let updated_project = db
-        .update_project(&path, update_project.into_inner())
+        .update_project(&path, update_project.into_inner(), Some(user.id))
         .await?;
 
     Ok(web::Json(updated_project))
@@ -127,20 +353,783 @@ async fn update_project(
     ),
     responses(
         (status = 200, description = "Project deleted successfully", body = String, content_type = "application/json"),
-        (status = 404, description = "Project not found", body = String, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[delete("/{id}")]
 async fn delete_project(
     db: web::Data<Database>,
+    user: AuthenticatedUser,
     path: web::Path<Uuid>,
 ) -> Result<web::Json<String>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
     let id = path.into_inner();
-    let result = db.delete_project(&id).await?;
+    let result = db.delete_project(&id, Some(user.id)).await?;
 
     Ok(web::Json(format!(
         "Successfully deleted {} project/s with the id: {}",
         result.rows_affected, id
     )))
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/clone",
+    tag = "projects",
+    summary = "Clone project",
+    description = "Create a new project, e.g. for the next school year, with the feedback and grading settings of this one. \
+        Groups, students, feedback and grades are not copied, neither are the feedback period and the deadline.",
+    params(
+        ("id" = String, Path, description = "ID of the project to copy")
+    ),
+    request_body = CloneProject,
+    responses(
+        (status = 200, description = "Project created", body = entity::project::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or unknown class", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/clone")]
+async fn clone_project(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<CloneProject>,
+) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    request.validate()?;
+
+    let request = request.into_inner();
+    let project = db
+        .clone_project(
+            path.into_inner(),
+            request.name,
+            request.class_id,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(project))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/archive",
+    tag = "projects",
+    summary = "Archive project",
+    description = "Hide a finished project from the project list. Its groups, feedback and grades are kept and can still be viewed and exported.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    responses(
+        (status = 200, description = "Project archived", body = entity::project::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/archive")]
+async fn archive_project(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let project = db
+        .set_project_archived(path.into_inner(), true, Some(user.id))
+        .await?;
+
+    Ok(web::Json(project))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/unarchive",
+    tag = "projects",
+    summary = "Unarchive project",
+    description = "List an archived project again. Projects archived by the retention policy are archived again on its next run.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    responses(
+        (status = 200, description = "Project unarchived", body = entity::project::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/unarchive")]
+async fn unarchive_project(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let project = db
+        .set_project_archived(path.into_inner(), false, Some(user.id))
+        .await?;
+
+    Ok(web::Json(project))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/project/{id}/feedback-settings",
+    tag = "projects",
+    summary = "Set feedback period and reminders",
+    description = "Set the feedback period of the project and when students who haven't submitted their feedback get reminder mails. \
+        Missed reminders, e.g. because the deadline was set late, are merged into a single mail.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    request_body = FeedbackSettings,
+    responses(
+        (status = 200, description = "Project with the new settings", body = entity::project::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[put("/{id}/feedback-settings")]
+async fn update_feedback_settings(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    settings: web::Json<FeedbackSettings>,
+) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    settings.validate()?;
+    let settings = settings.into_inner();
+
+    if let (Some(opens_at), Some(deadline)) = (settings.opens_at, settings.deadline)
+        && opens_at >= deadline
+    {
+        return Err(ApiError::BadRequest(
+            "opens_at must be before the deadline".to_owned(),
+        ));
+    }
+
+    if settings.self_assessment_in_grade && !settings.self_assessment {
+        return Err(ApiError::BadRequest(
+            "self_assessment_in_grade requires self_assessment".to_owned(),
+        ));
+    }
+
+    // Counting self-assessments or not changes the grades
+    let id = path.into_inner();
+    let current = db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+    if current.self_assessment_in_grade != settings.self_assessment_in_grade {
+        survey::ensure_not_finalised(&current)?;
+    }
+
+    let mut reminder_days: Vec<i32> = settings.reminder_days.into_iter().map(i32::from).collect();
+    reminder_days.sort_unstable_by(|a, b| b.cmp(a));
+    reminder_days.dedup();
+
+    let project = db
+        .update_feedback_settings(
+            id,
+            SurveySettings {
+                opens_at: settings.opens_at,
+                deadline: settings.deadline,
+                reminder_days,
+                reminders_enabled: settings.reminders_enabled,
+                language: settings.language,
+                self_assessment: settings.self_assessment,
+                self_assessment_in_grade: settings.self_assessment_in_grade,
+            },
+            Some(user.id),
+        )
+        .await?;
+
+    Ok(web::Json(project))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/project/{id}/grading-settings",
+    tag = "projects",
+    summary = "Set how grades are computed",
+    description = "Choose how the peer ratings a student received are aggregated into their peer factor: `mean`, \
+        `trimmed_mean` dropping the best and the worst rating, `median` or `webpa` normalising the ratings per rater. \
+        Optionally bound how far the computed grade may move away from the group grade. \
+        The settings can't be changed while the survey is finalised.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    request_body = GradingSettings,
+    responses(
+        (status = 200, description = "Project with the new settings", body = entity::project::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[put("/{id}/grading-settings")]
+async fn update_grading_settings(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    settings: web::Json<GradingSettings>,
+) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    settings.validate()?;
+
+    let id = path.into_inner();
+    let project = db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+    survey::ensure_not_finalised(&project)?;
+
+    let project = db
+        .update_grading_settings(
+            id,
+            settings.peer_aggregation,
+            settings.max_grade_deviation,
+            Some(user.id),
+        )
+        .await?;
+
+    Ok(web::Json(project))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/feedback-links/send",
+    tag = "projects",
+    summary = "Email feedback links",
+    description = "Queue a job that sends every student of the project, or of a single group, the personal link to their feedback form. \
+        Students without email address or deactivated accounts are skipped. The outcome is recorded per recipient, \
+        the progress can be polled at the returned job.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    request_body = SendFeedbackLinks,
+    responses(
+        (status = 202, description = "Job queued, its URL is in the `Location` header", body = entity::job::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project or group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/feedback-links/send")]
+async fn send_feedback_links(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<SendFeedbackLinks>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let project_id = path.into_inner();
+
+    // Fail now instead of in the background for unknown ids
+    db.get_project(&project_id).await?;
+    if let Some(group_id) = request.group_id {
+        db.get_group(project_id, group_id).await?;
+    }
+
+    let job = jobs::enqueue(
+        &db,
+        JobKind::SendFeedbackLinks,
+        &FeedbackLinksJob {
+            project_id,
+            group_id: request.group_id,
+            language: request.language,
+        },
+        Some(user.id),
+    )
+    .await?;
+
+    Ok(HttpResponse::Accepted()
+        .insert_header((header::LOCATION, format!("/api/v1/job/{}", job.id)))
+        .json(job))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/mail-deliveries",
+    tag = "projects",
+    summary = "List sent mails",
+    description = "Retrieve the delivery status of the mails sent for a project, sortable by `created_at`, `status` and `recipient`",
+    params(
+        ("id" = String, Path, description = "Project ID"),
+        ListQuery
+    ),
+    responses(
+        (status = 200, description = "Page of mail deliveries", body = Page<entity::mail_delivery::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/mail-deliveries")]
+async fn get_mail_deliveries(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    pagination: Pagination,
+) -> Result<web::Json<Page<entity::mail_delivery::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    db.get_project(&id).await?;
+
+    let (deliveries, total) = db.get_mail_deliveries(id, &pagination).await?;
+    Ok(web::Json(pagination.page(deliveries, total)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/survey/status",
+    tag = "projects",
+    summary = "Change the survey status",
+    description = "Move the feedback survey of the project through its lifecycle: `draft` → `open` ⇄ `closed` → `finalised`. \
+        Students can only submit while the survey is open. Finalising locks grades and responses, \
+        a finalised survey can only be reopened to `closed` with a reason. Every change is logged.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    request_body = SurveyTransition,
+    responses(
+        (status = 200, description = "Project with the new survey status", body = entity::project::Model, content_type = "application/json"),
+        (status = 400, description = "Transition not allowed or reason missing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/survey/status")]
+async fn set_survey_status(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<SurveyTransition>,
+) -> Result<web::Json<entity::project::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    request.validate()?;
+    let request = request.into_inner();
+
+    let project = db
+        .set_survey_status(path.into_inner(), request.status, user.id, request.reason)
+        .await?;
+
+    Ok(web::Json(project))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/survey/events",
+    tag = "projects",
+    summary = "Survey status history",
+    description = "Retrieve every status change of the project's survey with who made it and why, oldest first",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    responses(
+        (status = 200, description = "Status changes of the survey", body = Vec<entity::survey_event::Model>, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/survey/events")]
+async fn get_survey_events(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<Vec<entity::survey_event::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    db.get_project(&id).await?;
+
+    Ok(web::Json(db.get_survey_events(id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/grades",
+    tag = "projects",
+    summary = "Get grades",
+    description = "Grades of all students of the project ordered by group and name, with the computed grade next to the grade \
+        a teacher overrode it with and why.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    responses(
+        (status = 200, description = "Grades of the students", body = Vec<ProjectGrade>, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/grades")]
+async fn get_grades(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<Vec<ProjectGrade>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    let project = db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+
+    let groups = db.get_project_grading(id).await?;
+    let scale = db.get_project_scale(&project).await?;
+    let grades = grading::student_grades(&project, &scale, &groups)
+        .into_iter()
+        .map(|grade| ProjectGrade {
+            user_id: grade.user.id,
+            name: grade.user.name.clone(),
+            username: grade.user.username.clone(),
+            group_name: grade.group_name.to_owned(),
+            group_grade: grade.group_grade,
+            peer_factor: grade.peer_factor,
+            computed_grade: grade.computed_grade.map(|g| scale.round(g)),
+            individual_grade: grade.individual_grade,
+            justification: grade.justification.map(str::to_owned),
+            final_grade: grade.final_grade().map(|g| scale.round(g)),
+        })
+        .collect();
+
+    Ok(web::Json(grades))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/grades/history",
+    tag = "projects",
+    summary = "Get the grade history",
+    description = "Every override of a computed grade and every removal of one, with the computed grade at that time, who made \
+        the change and why, oldest first",
+    params(
+        ("id" = String, Path, description = "Project ID"),
+        GradeHistoryQuery
+    ),
+    responses(
+        (status = 200, description = "Changes of the grades", body = Vec<entity::grade_override::Model>, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/grades/history")]
+async fn get_grade_history(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    query: web::Query<GradeHistoryQuery>,
+) -> Result<web::Json<Vec<entity::grade_override::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+
+    Ok(web::Json(db.get_grade_overrides(id, query.user_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/grades/export",
+    tag = "projects",
+    summary = "Export grades",
+    description = "Download the grades of all students of the project with their group, group grade, peer factor, computed grade, \
+        overridden grade with its justification and final grade as CSV or XLSX. The `moodle` format is a CSV for the Moodle \
+        grade import, keyed by email address like the Moodle group export and only containing students with a final grade. Column headers follow the language of the project.",
+    params(
+        ("id" = String, Path, description = "Project ID"),
+        ExportQuery
+    ),
+    responses(
+        (status = 200, description = "Export file", content(
+            (String = "text/csv"),
+            (Vec<u8> = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+        )),
+        (status = 400, description = "Unknown format", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/grades/export")]
+async fn export_grades(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    let project = db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+
+    let groups = db.get_project_grading(id).await?;
+    let scale = db.get_project_scale(&project).await?;
+    let grades = grading::student_grades(&project, &scale, &groups);
+    let export = export::grades(&project, &scale, &grades, query.format)?;
+
+    Ok(attachment(
+        export.content_type,
+        export.filename,
+        export.body,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/reports/students/{user_id}",
+    tag = "projects",
+    summary = "Get the evaluation sheet of a student",
+    description = "Download the evaluation sheet of a student as PDF: the peer feedback question, the anonymised ratings and \
+        comments the student received and how their grade was calculated.",
+    params(
+        ("id" = String, Path, description = "Project ID"),
+        ("user_id" = String, Path, description = "User ID of the student")
+    ),
+    responses(
+        (status = 200, description = "Evaluation sheet", content((Vec<u8> = "application/pdf"))),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found or the student is not in a group of the project", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/reports/students/{user_id}")]
+async fn get_student_report(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (id, user_id) = path.into_inner();
+    let project = db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+    let group_id = db
+        .get_student_group(id, user_id)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let grading = db.get_group_grading(id, group_id).await?;
+    let scale = db.get_project_scale(&project).await?;
+    let report = report::student_sheet(&project, &scale, &grading, user_id, Utc::now())?;
+
+    Ok(attachment("application/pdf", report.filename, report.body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/reports/groups/{group_id}",
+    tag = "projects",
+    summary = "Get the report of a group",
+    description = "Download the report of a group as PDF: an overview of who rated whom with their comments, followed by the \
+        evaluation sheets of all members.",
+    params(
+        ("id" = String, Path, description = "Project ID"),
+        ("group_id" = String, Path, description = "Group ID")
+    ),
+    responses(
+        (status = 200, description = "Group report", content((Vec<u8> = "application/pdf"))),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project or group not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/reports/groups/{group_id}")]
+async fn get_group_report(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (id, group_id) = path.into_inner();
+    let project = db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+
+    let grading = db.get_group_grading(id, group_id).await?;
+    let scale = db.get_project_scale(&project).await?;
+    let report = report::group_report(&project, &scale, &grading, Utc::now());
+
+    Ok(attachment("application/pdf", report.filename, report.body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/project/{id}/analytics",
+    tag = "projects",
+    summary = "Get peer rating analytics",
+    description = "Statistics on the peer ratings of the project: average, spread and distribution per question, the ratings \
+        each student received and gave, raters who give everyone the best or worst grade or deviate strongly from their group, \
+        and how much the ratings within each group agree. Only ratings between current group members are counted.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    responses(
+        (status = 200, description = "Peer rating analytics", body = ProjectAnalytics, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}/analytics")]
+async fn get_project_analytics(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<ProjectAnalytics>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let id = path.into_inner();
+    db.get_project(&id).await?.ok_or(ApiError::NotFound)?;
+
+    let stats = db.get_project_stats(id).await?;
+    Ok(web::Json(stats.into()))
+}
+
+/// Response that browsers download as a file
+pub(crate) fn attachment(content_type: &str, filename: String, body: Vec<u8>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .body(body)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/groups/generate",
+    tag = "projects",
+    summary = "Preview generated groups",
+    description = "Divide the students into groups of about the same size, either randomly or balanced by their results in earlier projects. \
+        Nothing is stored, the groups can be adjusted and then created with `POST /api/v1/project/{id}/groups`. \
+        Every call shuffles anew.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    request_body = GenerateGroups,
+    responses(
+        (status = 200, description = "Proposed groups", body = Vec<ProposedGroup>, content_type = "application/json"),
+        (status = 400, description = "Invalid group size or count, unknown users, students not enrolled in the class or already in a group", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/groups/generate")]
+async fn generate_groups(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<GenerateGroups>,
+) -> Result<web::Json<Vec<ProposedGroup>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    request.validate()?;
+
+    let sizing = match (request.group_size, request.group_count) {
+        (Some(size), None) => GroupSizing::Size(size),
+        (None, Some(count)) => GroupSizing::Count(count),
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Either group_size or group_count is required".to_owned(),
+            ));
+        }
+    };
+
+    let project = db.get_project(&path.into_inner()).await?.unwrap();
+    let students = db
+        .get_ungrouped_students(&project, request.user_ids.as_deref())
+        .await?;
+    let count = grouping::group_count(students.len(), sizing)?;
+
+    let scores = if request.method == GroupingMethod::Balanced {
+        let user_ids: Vec<Uuid> = students.iter().map(|student| student.id).collect();
+        db.get_previous_results(&user_ids, project.id)
+            .await?
+            .into_iter()
+            .filter_map(|(id, results)| {
+                let score = match request.balance_by {
+                    BalanceBy::PreviousGrade => results.grade,
+                    BalanceBy::PeerFactor => results.peer_factor,
+                };
+                Some((id, score?))
+            })
+            .collect()
+    } else {
+        Default::default()
+    };
+
+    Ok(web::Json(grouping::propose(
+        &students,
+        &scores,
+        request.method,
+        count,
+        project.language,
+        &mut rand::thread_rng(),
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/project/{id}/groups",
+    tag = "projects",
+    summary = "Create groups",
+    description = "Create groups with their members, e.g. a preview of generated groups, either all of them are created or none. \
+        A student can only be in one group of the project and, if the project belongs to a class, has to be enrolled in it. \
+        Not possible once the survey of the project is finalised.",
+    params(
+        ("id" = String, Path, description = "Project ID")
+    ),
+    request_body = CreateGroups,
+    responses(
+        (status = 200, description = "Created groups with their members", body = Vec<CreatedGroup>, content_type = "application/json"),
+        (status = 400, description = "Invalid request data, unknown users, students not enrolled in the class or already in a group", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Project not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "The survey is finalised", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/groups")]
+async fn create_groups(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    request: web::Json<CreateGroups>,
+) -> Result<web::Json<Vec<CreatedGroup>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    request.validate()?;
+
+    let groups = request
+        .into_inner()
+        .groups
+        .into_iter()
+        .map(|group| NewGroup {
+            name: group.name,
+            user_ids: group.user_ids,
+        })
+        .collect();
+
+    let created = db
+        .create_groups(path.into_inner(), groups, Some(user.id))
+        .await?
+        .into_iter()
+        .map(|(group, members)| CreatedGroup { group, members })
+        .collect();
+    Ok(web::Json(created))
+}
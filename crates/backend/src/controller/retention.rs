@@ -0,0 +1,91 @@
+use actix_web::{HttpResponse, get, http::header, post, web};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::db::entity;
+use crate::db::entity::sea_orm_active_enums::{JobKind, UserRole};
+use crate::error::{ApiError, ProblemDetails};
+use crate::jobs;
+use crate::retention::{self, RetentionJob, RetentionPolicy, RetentionReport};
+
+pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(get_retention_report).service(apply_retention);
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RetentionQuery {
+    /// Day to apply the policy for, defaults to today
+    #[param(value_type = Option<String>, format = Date)]
+    as_of: Option<NaiveDate>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/retention/report",
+    tag = "retention",
+    summary = "Preview the retention policy",
+    description = "List the projects the retention policy archives and deletes on the given day, and the students deleted with them. \
+        Nothing is changed, also when the policy is disabled.",
+    params(RetentionQuery),
+    responses(
+        (status = 200, description = "Retention report", body = RetentionReport, content_type = "application/json"),
+        (status = 400, description = "Invalid date", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/report")]
+async fn get_retention_report(
+    db: web::Data<Database>,
+    policy: Option<web::Data<RetentionPolicy>>,
+    user: AuthenticatedUser,
+    query: web::Query<RetentionQuery>,
+) -> Result<web::Json<RetentionReport>, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    let policy = policy.map(|p| p.get_ref().clone()).unwrap_or_default();
+    let as_of = query.as_of.unwrap_or_else(|| Utc::now().date_naive());
+
+    let report = retention::report(&db, &policy, as_of).await?;
+    Ok(web::Json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/retention/apply",
+    tag = "retention",
+    summary = "Apply the retention policy",
+    description = "Queue a job that archives and deletes the projects due today, without waiting for the daily run. \
+        Works while the automatic runs are disabled, the progress can be polled at the returned job.",
+    responses(
+        (status = 202, description = "Job queued, its URL is in the `Location` header", body = entity::job::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/apply")]
+async fn apply_retention(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_role(UserRole::Admin)?;
+
+    let job = jobs::enqueue(
+        &db,
+        JobKind::ApplyRetention,
+        &RetentionJob {
+            as_of: Utc::now().date_naive(),
+        },
+        Some(user.id),
+    )
+    .await?;
+
+    Ok(HttpResponse::Accepted()
+        .insert_header((header::LOCATION, format!("/api/v1/job/{}", job.id)))
+        .json(job))
+}
@@ -0,0 +1,212 @@
+use actix_web::{delete, get, post, put, web};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::db::entity;
+use crate::db::entity::sea_orm_active_enums::UserRole;
+use crate::error::{ApiError, ProblemDetails};
+use crate::pagination::{ListQuery, Page, Pagination};
+
+pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(get_school_years)
+        .service(get_school_year)
+        .service(create_school_year)
+        .service(update_school_year)
+        .service(delete_school_year);
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct SchoolYearSettings {
+    /// School year or term, e.g. `2025/26` or `2025/26 2. Halbjahr`
+    #[validate(length(min = 1, max = 255))]
+    name: String,
+    #[schema(value_type = String, format = Date)]
+    starts_on: NaiveDate,
+    /// Last day of the school year, the retention periods of its projects count from this day
+    #[schema(value_type = String, format = Date)]
+    ends_on: NaiveDate,
+}
+
+impl SchoolYearSettings {
+    fn validate_dates(&self) -> Result<(), ApiError> {
+        if self.ends_on < self.starts_on {
+            return Err(ApiError::BadRequest(
+                "A school year can't end before it starts".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/school-year",
+    tag = "school years",
+    summary = "Get all school years",
+    description = "Retrieve a page of school years and terms, sortable by `starts_on` and `name`",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "List of school years retrieved successfully", body = Page<entity::school_year::Model>, content_type = "application/json"),
+        (status = 400, description = "Invalid paging or sort parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("")]
+async fn get_school_years(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    pagination: Pagination,
+) -> Result<web::Json<Page<entity::school_year::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (school_years, total) = db.get_school_years(&pagination).await?;
+
+    Ok(web::Json(pagination.page(school_years, total)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/school-year/{id}",
+    tag = "school years",
+    summary = "Get school year by ID",
+    description = "Retrieve a specific school year by its ID",
+    params(
+        ("id" = String, Path, description = "School year ID")
+    ),
+    responses(
+        (status = 200, description = "School year retrieved successfully", body = entity::school_year::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "School year not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/{id}")]
+async fn get_school_year(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<entity::school_year::Model>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let school_year = db.get_school_year(path.into_inner()).await?;
+
+    Ok(web::Json(school_year))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/school-year",
+    tag = "school years",
+    summary = "Create school year",
+    description = "Create a school year or term classes can be assigned to",
+    request_body = SchoolYearSettings,
+    responses(
+        (status = 200, description = "School year created successfully", body = entity::school_year::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "A school year with this name already exists", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("")]
+async fn create_school_year(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    settings: web::Json<SchoolYearSettings>,
+) -> Result<web::Json<entity::school_year::Model>, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    settings.validate()?;
+    settings.validate_dates()?;
+
+    let settings = settings.into_inner();
+    let school_year = db
+        .create_school_year(
+            settings.name,
+            settings.starts_on,
+            settings.ends_on,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(school_year))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/school-year/{id}",
+    tag = "school years",
+    summary = "Update school year",
+    description = "Rename a school year or change its dates. Changing the end moves the retention deadlines of the projects of its classes.",
+    params(
+        ("id" = String, Path, description = "School year ID to update")
+    ),
+    request_body = SchoolYearSettings,
+    responses(
+        (status = 200, description = "School year updated successfully", body = entity::school_year::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "School year not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "A school year with this name already exists", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[put("/{id}")]
+async fn update_school_year(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    settings: web::Json<SchoolYearSettings>,
+) -> Result<web::Json<entity::school_year::Model>, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    settings.validate()?;
+    settings.validate_dates()?;
+
+    let settings = settings.into_inner();
+    let school_year = db
+        .update_school_year(
+            path.into_inner(),
+            settings.name,
+            settings.starts_on,
+            settings.ends_on,
+            Some(user.id),
+        )
+        .await?;
+    Ok(web::Json(school_year))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/school-year/{id}",
+    tag = "school years",
+    summary = "Delete school year",
+    description = "Delete a school year by its ID. Its classes are kept without school year, so their projects are no longer deleted by the retention policy.",
+    params(
+        ("id" = String, Path, description = "School year ID to delete")
+    ),
+    responses(
+        (status = 200, description = "School year deleted successfully", body = String, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "School year not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[delete("/{id}")]
+async fn delete_school_year(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<String>, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    let id = path.into_inner();
+    db.delete_school_year(id, Some(user.id)).await?;
+
+    Ok(web::Json(format!("School year {} deleted", id)))
+}
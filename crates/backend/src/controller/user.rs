@@ -1,17 +1,57 @@
-use crate::{Database, db::entity, error::ApiError};
-use actix_web::error::ErrorInternalServerError;
-use actix_web::{Responder, delete, get, post, put, web};
-use serde::Deserialize;
+use super::project::attachment;
+use crate::{
+    Database,
+    auth::{AuthenticatedUser, role_includes},
+    csv,
+    db::entity::{
+        self,
+        sea_orm_active_enums::{TokenAccess, UserRole},
+    },
+    error::{ApiError, ProblemDetails},
+    pagination::{ListQuery, Page, Pagination},
+    privacy::{DataExportFormat, UserDataExport},
+};
+use actix_web::{HttpResponse, delete, get, post, put, web};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use validator::Validate;
 
 pub fn setup(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.service(get_users)
+    cfg.service(get_me)
+        .service(get_tokens)
+        .service(create_token)
+        .service(delete_token)
+        .service(get_users)
         .service(get_user)
         .service(create_user)
+        .service(bulk_create_users)
+        .service(update_user)
+        .service(deactivate_user)
+        .service(reactivate_user)
+        .service(export_user_data)
+        .service(erase_user)
         .service(delete_user);
 }
 
+#[derive(Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DataExportQuery {
+    /// `json` for a single document, `zip` for one file per section
+    #[serde(default)]
+    format: DataExportFormat,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserQuery {
+    /// Case-insensitive search in name and username
+    search: Option<String>,
+    /// Also list deactivated users
+    #[serde(default)]
+    include_deactivated: bool,
+}
+
 #[derive(Deserialize, Validate, ToSchema)]
 pub struct CreateUser {
     #[validate(length(min = 4, max = 255))]
@@ -21,40 +61,277 @@ pub struct CreateUser {
     #[validate(length(min = 3))]
     /// Full name of the user (minimum 3 characters)
     name: String,
+    #[validate(email)]
+    /// Email address, used to send feedback links
+    email: Option<String>,
     #[validate(length(min = 8, max = 255))]
     /// Password (minimum 8 characters, maximum 255 characters)
     password: String,
 }
 
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct UpdateUser {
+    #[validate(length(min = 4, max = 255))]
+    /// New username (minimum 4 characters, maximum 255 characters)
+    username: Option<String>,
+    #[validate(length(min = 3))]
+    /// New full name (minimum 3 characters)
+    name: Option<String>,
+    #[validate(email)]
+    /// New email address
+    email: Option<String>,
+    /// New role, can only be changed by admins
+    role: Option<UserRole>,
+}
+
+/// A student row of a bulk import, validated like [`CreateUser`]
+#[derive(Validate)]
+struct BulkUserRow {
+    #[validate(length(min = 4, max = 255))]
+    username: String,
+    #[validate(length(min = 3))]
+    name: String,
+    #[validate(email)]
+    email: Option<String>,
+    #[validate(length(min = 8, max = 255))]
+    password: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkRowStatus {
+    Created,
+    Failed,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkUserResult {
+    /// Line in the CSV file, the header is line 1
+    row: usize,
+    username: String,
+    status: BulkRowStatus,
+    /// ID of the created user
+    user_id: Option<uuid::Uuid>,
+    /// Error code if the row failed, as in problem details
+    code: Option<String>,
+    /// Reason the row failed
+    error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkCreateResult {
+    created: usize,
+    failed: usize,
+    rows: Vec<BulkUserResult>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct CreateApiToken {
+    #[validate(length(min = 1, max = 255))]
+    /// Name to recognise the token by, e.g. the script using it
+    name: String,
+    /// Role the token acts as, defaults to the role of the user and may not exceed it
+    role: Option<UserRole>,
+    /// Whether the token may only read or also modify data
+    access: TokenAccess,
+    /// Point in time after which the token is rejected, never expires if omitted
+    expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiToken {
+    /// Plaintext token, only returned once
+    token: String,
+    #[serde(flatten)]
+    api_token: entity::api_token::Model,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/user/me",
+    tag = "users",
+    summary = "Get the current user",
+    description = "Retrieve the user authenticated by the session or API token",
+    responses(
+        (status = 200, description = "Current user", body = entity::user::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/me")]
+async fn get_me(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<entity::user::Model>, ApiError> {
+    let user = db.get_user(user.id).await?;
+
+    Ok(web::Json(user.unwrap()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/user/me/tokens",
+    tag = "users",
+    summary = "List personal access tokens",
+    description = "Retrieve the API tokens of the current user, the token values themselves are never returned",
+    responses(
+        (status = 200, description = "List of tokens", body = Vec<entity::api_token::Model>, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Tokens can only be managed with a session", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[get("/me/tokens")]
+async fn get_tokens(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<Vec<entity::api_token::Model>>, ApiError> {
+    user.require_session()?;
+
+    let tokens = db.get_api_tokens(user.id).await?;
+    Ok(web::Json(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/me/tokens",
+    tag = "users",
+    summary = "Create a personal access token",
+    description = "Create an API token for scripted access, send it as `Authorization: Bearer <token>`",
+    request_body = CreateApiToken,
+    responses(
+        (status = 200, description = "Token created, the plaintext token is only shown once", body = CreatedApiToken, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Requested role exceeds the user's role or request used a token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/me/tokens")]
+async fn create_token(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    create_token: web::Json<CreateApiToken>,
+) -> Result<web::Json<CreatedApiToken>, ApiError> {
+    user.require_session()?;
+    create_token.validate()?;
+    let create_token = create_token.into_inner();
+
+    let role = create_token.role.unwrap_or(user.role);
+    if !role_includes(user.role, role) {
+        return Err(ApiError::Forbidden);
+    }
+
+    if create_token
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(ApiError::BadRequest(
+            "expires_at must be in the future".to_owned(),
+        ));
+    }
+
+    let (api_token, token) = db
+        .create_api_token(
+            user.id,
+            create_token.name,
+            role,
+            create_token.access,
+            create_token.expires_at,
+        )
+        .await?;
+
+    Ok(web::Json(CreatedApiToken { token, api_token }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/user/me/tokens/{id}",
+    tag = "users",
+    summary = "Revoke a personal access token",
+    description = "Delete an API token of the current user",
+    params(
+        ("id" = String, Path, description = "Token ID to revoke")
+    ),
+    responses(
+        (status = 200, description = "Token revoked", body = String, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Tokens can only be managed with a session", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Token not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[delete("/me/tokens/{id}")]
+async fn delete_token(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    id: web::Path<uuid::Uuid>,
+) -> Result<web::Json<String>, ApiError> {
+    user.require_session()?;
+
+    let id = id.into_inner();
+    db.delete_api_token(user.id, id).await?;
+    Ok(web::Json(format!("Token {} revoked", id)))
+}
+
 #[utoipa::path(
     get,
     path = "/api/v1/user",
     tag = "users",
     summary = "Get all users",
-    description = "Retrieve a list of all users",
+    description = "Retrieve a page of users, optionally filtered by name or username and sortable by `username`, `name` and `role`. Deactivated users are omitted unless requested.",
+    params(ListQuery, UserQuery),
     responses(
-        (status = 200, description = "List of users retrieved successfully", body = Vec<entity::user::Model>, content_type = "application/json",
-        example = json!([
-            {
-                "id": "831195d1-01c4-4029-8284-349f5c41e398",
-                "username": "MyAwesomeUsername",
-                "name": "My Awesome Name",
-            },
-            {
-                "id": "0024870c-ea5c-4927-802f-8e44fc57b098",
-                "username": "AnotherUser",
-                "name": "Another User",
+        (status = 200, description = "List of users retrieved successfully", body = Page<entity::user::Model>, content_type = "application/json",
+        example = json!({
+            "items": [
+                {
+                    "id": "0024870c-ea5c-4927-802f-8e44fc57b098",
+                    "username": "AnotherUser",
+                    "name": "Another User",
+                    "role": "student",
+                },
+                {
+                    "id": "831195d1-01c4-4029-8284-349f5c41e398",
+                    "username": "MyAwesomeUsername",
+                    "name": "My Awesome Name",
+                    "role": "teacher",
+                }
+            ],
+            "total": 2,
+            "page": 1,
+            "per_page": 25,
+            "total_pages": 1,
+            "links": {
+                "self": "/api/v1/user?page=1&per_page=25",
+                "first": "/api/v1/user?page=1&per_page=25",
+                "prev": null,
+                "next": null,
+                "last": "/api/v1/user?page=1&per_page=25"
             }
-        ])),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        })),
+        (status = 400, description = "Invalid query parameters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("")]
 async fn get_users(
     db: web::Data<Database>,
-) -> Result<web::Json<Vec<entity::user::Model>>, ApiError> {
-    let users = db.get_users().await?;
-    Ok(web::Json(users))
+    user: AuthenticatedUser,
+    pagination: Pagination,
+    query: web::Query<UserQuery>,
+) -> Result<web::Json<Page<entity::user::Model>>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+    let (users, total) = db
+        .get_users(
+            query.search.as_deref(),
+            query.include_deactivated,
+            &pagination,
+        )
+        .await?;
+    Ok(web::Json(pagination.page(users, total)))
 }
 
 #[utoipa::path(
@@ -73,18 +350,22 @@ async fn get_users(
             "username": "MyAwesomeUsername",
             "name": "My Awesome Name",
         })),
-        (status = 404, description = "User not found", body = String, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[get("/{id}")]
 async fn get_user(
     db: web::Data<Database>,
+    actor: AuthenticatedUser,
     id: web::Path<uuid::Uuid>,
 ) -> Result<web::Json<entity::user::Model>, ApiError> {
+    actor.require_role(UserRole::Teacher)?;
     let user = db.get_user(id.into_inner()).await?;
 
-    Ok(web::Json(user.unwrap()))
+    Ok(web::Json(user.ok_or(ApiError::NotFound)?))
 }
 
 #[utoipa::path(
@@ -92,7 +373,7 @@ async fn get_user(
     path = "/api/v1/user",
     tag = "users",
     summary = "Create a new user",
-    description = "Create a new user with username, name, and password",
+    description = "Create a new student with username, name, and password. Requires the teacher role.",
     request_body = CreateUser,
     responses(
         (status = 200, description = "User created successfully", body = entity::user::Model, content_type = "application/json", 
@@ -101,52 +382,360 @@ async fn get_user(
             "username": "MyAwesomeUsername",
             "name": "My Awesome Name",
         })),
-        (status = 400, description = "Invalid request data or validation error", body = String, content_type = "application/json"),
-        (status = 409, description = "User already exists", body = String, content_type = "application/json", example = "User with username - MyAwesomeUsername - already exists"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "User already exists", body = ProblemDetails, content_type = "application/problem+json",
+        example = json!({
+            "type": "urn:pgg:problem:user_already_exists",
+            "title": "Conflict",
+            "status": 409,
+            "detail": "User with username - MyAwesomeUsername - already exists",
+            "code": "user_already_exists",
+            "correlation_id": "5c5e3c1c-8f2a-4c47-9a4d-1f0c1d6b8e21"
+        })),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Teacher role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[post("")]
 async fn create_user(
     db: web::Data<Database>,
+    actor: AuthenticatedUser,
     user: web::Json<CreateUser>,
 ) -> Result<web::Json<entity::user::Model>, ApiError> {
+    actor.require_role(UserRole::Teacher)?;
     let user = user.into_inner();
-    user.validate()
-        .map_err(|e| ApiError::BadRequest(format!("\nValidation error: {}", e)))?;
+    user.validate()?;
 
-    let username = user.username.clone();
     let result = db
-        .create_user(user.name, user.username, user.password)
-        .await;
-
-    match result {
-        Ok(result) => Ok(web::Json(result)),
-        Err(e) => {
-            if e.to_string().contains("user_username_key") {
-                Err(ApiError::UserAlreadyExists(username))
-            } else {
-                Err(ApiError::InternalServerError(
-                    "/user/ - create_user - Error: {e}".to_owned(),
-                ))
+        .create_user(
+            user.name,
+            user.username,
+            user.email,
+            Some(user.password),
+            UserRole::Student,
+            Some(actor.id),
+        )
+        .await?;
+
+    Ok(web::Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/bulk",
+    tag = "users",
+    summary = "Create students from a CSV file",
+    description = "Create a student for every row of a CSV file with the columns `username`, `name` and optionally `email` and `password`. \
+        Students without password can't log in, they open their feedback form through their link or use single sign-on. Rows are created independently, the result reports success or failure per row.",
+    request_body(content = String, content_type = "text/csv", example = "username,name,email,password\nmmuster,Max Mustermann,max@example.org,geheim123\n"),
+    responses(
+        (status = 200, description = "Result per row", body = BulkCreateResult, content_type = "application/json"),
+        (status = 400, description = "CSV is empty or lacks required columns", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not a teacher", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/bulk")]
+async fn bulk_create_users(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    body: String,
+) -> Result<web::Json<BulkCreateResult>, ApiError> {
+    user.require_role(UserRole::Teacher)?;
+
+    let header = csv::parse(&body).into_iter().next().unwrap_or_default();
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+    if !["username", "name"]
+        .iter()
+        .all(|column| header.iter().any(|h| h == column))
+    {
+        return Err(ApiError::BadRequest(
+            "CSV must have the columns username and name".to_owned(),
+        ));
+    }
+
+    let mut rows = Vec::new();
+    for (index, mut record) in csv::parse_with_header(&body).into_iter().enumerate() {
+        let row = BulkUserRow {
+            username: record.remove("username").unwrap_or_default(),
+            name: record.remove("name").unwrap_or_default(),
+            email: record.remove("email").filter(|e| !e.is_empty()),
+            password: record.remove("password").filter(|p| !p.is_empty()),
+        };
+        let username = row.username.clone();
+
+        let result = match row.validate() {
+            Ok(()) => {
+                db.create_user(
+                    row.name,
+                    row.username,
+                    row.email,
+                    row.password,
+                    UserRole::Student,
+                    Some(user.id),
+                )
+                .await
             }
+            Err(e) => Err(ApiError::ValidationError(e)),
+        };
+
+        rows.push(match result {
+            Ok(created) => BulkUserResult {
+                row: index + 2,
+                username,
+                status: BulkRowStatus::Created,
+                user_id: Some(created.id),
+                code: None,
+                error: None,
+            },
+            Err(e) => BulkUserResult {
+                row: index + 2,
+                username,
+                status: BulkRowStatus::Failed,
+                user_id: None,
+                code: Some(e.code().to_owned()),
+                error: Some(bulk_row_error(&e)),
+            },
+        });
+    }
+
+    let created = rows
+        .iter()
+        .filter(|row| row.status == BulkRowStatus::Created)
+        .count();
+
+    Ok(web::Json(BulkCreateResult {
+        created,
+        failed: rows.len() - created,
+        rows,
+    }))
+}
+
+pub(super) fn bulk_row_error(error: &ApiError) -> String {
+    match error {
+        ApiError::ValidationError(errors) => {
+            let mut fields: Vec<_> = errors.field_errors().into_keys().collect();
+            fields.sort();
+            format!("Invalid {}", fields.join(", "))
         }
+        e => e
+            .public_detail()
+            .unwrap_or_else(|| "Internal server error".to_owned()),
+    }
+}
+
+/// Teachers manage students and themselves, everyone else needs an admin
+fn require_manage(actor: &AuthenticatedUser, target: &entity::user::Model) -> Result<(), ApiError> {
+    actor.require_role(UserRole::Teacher)?;
+
+    if target.id == actor.id || target.role == UserRole::Student {
+        Ok(())
+    } else {
+        actor.require_role(UserRole::Admin)
     }
 }
 
 #[utoipa::path(
     put,
-    path = "/api/v1/user",
+    path = "/api/v1/user/{id}",
+    tag = "users",
+    summary = "Update user",
+    description = "Change name, username or role of a user. Teachers can update students and themselves, changing roles requires an admin.",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    request_body = UpdateUser,
+    responses(
+        (status = 200, description = "User updated successfully", body = entity::user::Model, content_type = "application/json"),
+        (status = 400, description = "Invalid request data or validation error", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not allowed to update this user", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Username already taken", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[put("/{id}")]
+async fn update_user(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    id: web::Path<uuid::Uuid>,
+    update: web::Json<UpdateUser>,
+) -> Result<web::Json<entity::user::Model>, ApiError> {
+    let update = update.into_inner();
+    update.validate()?;
+
+    let target = db.get_user(id.into_inner()).await?.unwrap();
+    require_manage(&user, &target)?;
+    if update.role.is_some_and(|role| role != target.role) {
+        user.require_role(UserRole::Admin)?;
+    }
+
+    let updated = db
+        .update_user(
+            target.id,
+            update.name,
+            update.username,
+            update.email,
+            update.role,
+            Some(user.id),
+        )
+        .await?;
+
+    Ok(web::Json(updated))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/{id}/deactivate",
+    tag = "users",
+    summary = "Deactivate user",
+    description = "Block a user from logging in while keeping their projects and grades. Sessions and API tokens of the user stop working.",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User deactivated", body = entity::user::Model, content_type = "application/json"),
+        (status = 400, description = "Users cannot deactivate themselves", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not allowed to manage this user", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/deactivate")]
+async fn deactivate_user(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    id: web::Path<uuid::Uuid>,
+) -> Result<web::Json<entity::user::Model>, ApiError> {
+    let target = db.get_user(id.into_inner()).await?.unwrap();
+    if target.id == user.id {
+        return Err(ApiError::BadRequest(
+            "You cannot deactivate your own account".to_owned(),
+        ));
+    }
+    require_manage(&user, &target)?;
+
+    let target = db
+        .set_user_deactivated(target.id, true, Some(user.id))
+        .await?;
+    Ok(web::Json(target))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/{id}/reactivate",
+    tag = "users",
+    summary = "Reactivate user",
+    description = "Allow a deactivated user to log in again",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User reactivated", body = entity::user::Model, content_type = "application/json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Not allowed to manage this user", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/reactivate")]
+async fn reactivate_user(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    id: web::Path<uuid::Uuid>,
+) -> Result<web::Json<entity::user::Model>, ApiError> {
+    let target = db.get_user(id.into_inner()).await?.unwrap();
+    require_manage(&user, &target)?;
+
+    let target = db
+        .set_user_deactivated(target.id, false, Some(user.id))
+        .await?;
+    Ok(web::Json(target))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/user/{id}/data-export",
     tag = "users",
-    summary = "Update user (Not Implemented)",
-    description = "Update user information - currently not implemented",
+    summary = "Export the data of a user",
+    description = "Download everything stored about a user for a GDPR access request: profile, group memberships with grades, \
+        submitted and received feedback, self-assessments, grade overrides, mails and API tokens. Received ratings don't name \
+        the group mate who gave them.",
+    params(
+        ("id" = String, Path, description = "User ID"),
+        DataExportQuery
+    ),
     responses(
-        (status = 501, description = "Not implemented", body = String, content_type = "application/json")
+        (status = 200, description = "Data of the user", content(
+            (UserDataExport = "application/json"),
+            (Vec<u8> = "application/zip")
+        )),
+        (status = 400, description = "Unknown format", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
-#[put("")]
-async fn update_user() -> impl Responder {
-    ""
+#[get("/{id}/data-export")]
+async fn export_user_data(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    id: web::Path<uuid::Uuid>,
+    query: web::Query<DataExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    let data = db.get_user_data(id.into_inner()).await?;
+    let export = UserDataExport::new(data).render(query.format)?;
+
+    Ok(attachment(
+        export.content_type,
+        export.filename,
+        export.body,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/{id}/erase",
+    tag = "users",
+    summary = "Erase the personal data of a user",
+    description = "Pseudonymise a user for a GDPR erasure request. Name and username are replaced, email address, logins, \
+        API tokens, feedback links, comments, grade justifications, mail addresses and the audit log snapshots about the user \
+        are removed. \
+        Ratings, group memberships and grades are kept, so the grades of their group mates don't change. This can't be undone.",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User erased", body = entity::user::Model, content_type = "application/json"),
+        (status = 400, description = "Users cannot erase themselves", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+#[post("/{id}/erase")]
+async fn erase_user(
+    db: web::Data<Database>,
+    user: AuthenticatedUser,
+    id: web::Path<uuid::Uuid>,
+) -> Result<web::Json<entity::user::Model>, ApiError> {
+    user.require_role(UserRole::Admin)?;
+    let id = id.into_inner();
+    if id == user.id {
+        return Err(ApiError::BadRequest(
+            "You cannot erase your own account".to_owned(),
+        ));
+    }
+
+    let erased = db.erase_user(id, Some(user.id)).await?;
+    Ok(web::Json(erased))
 }
 
 #[utoipa::path(
@@ -154,23 +743,34 @@ async fn update_user() -> impl Responder {
     path = "/api/v1/user/{id}",
     tag = "users",
     summary = "Delete user",
-    description = "Delete a user by their ID",
+    description = "Delete a user by their ID. Requires the admin role, the audit log keeps only the ID.",
     params(
         ("id" = String, Path, description = "User ID to delete")
     ),
     responses(
         (status = 200, description = "User deleted successfully", body = String, content_type = "application/json", example = "User 123e4567-e89b-12d3-a456-426614174000 deleted"),
-        (status = 404, description = "User not found", body = String, content_type = "application/json"),
-        (status = 500, description = "Internal server error", body = String, content_type = "application/json")
+        (status = 400, description = "Users cannot delete themselves", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Not authenticated", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "Admin role required", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = ProblemDetails, content_type = "application/problem+json")
     )
 )]
 #[delete("/{id}")]
 async fn delete_user(
     db: web::Data<Database>,
+    user: AuthenticatedUser,
     id: web::Path<uuid::Uuid>,
 ) -> Result<web::Json<String>, ApiError> {
+    user.require_role(UserRole::Admin)?;
     let id = id.into_inner();
-    db.delete_user(id).await?;
+    if id == user.id {
+        return Err(ApiError::BadRequest(
+            "You cannot delete your own account".to_owned(),
+        ));
+    }
+
+    db.delete_user(id, Some(user.id)).await?;
     Ok(web::Json(format!("User {} deleted", id)))
 }
 
@@ -183,6 +783,7 @@ mod tests {
         let user = CreateUser {
             username: "testuser".to_string(),
             name: "Test User".to_string(),
+            email: None,
             password: "password123".to_string(),
         };
         let validation_result = user.validate();
@@ -194,6 +795,7 @@ mod tests {
         let user = CreateUser {
             username: "usr".to_string(), // too short
             name: "Test User".to_string(),
+            email: None,
             password: "password".to_string(),
         };
         let validation_result = user.validate();
@@ -205,6 +807,7 @@ mod tests {
         let user = CreateUser {
             username: "a".repeat(256), // too long
             name: "Test User".to_string(),
+            email: None,
             password: "password123".to_string(),
         };
         let validation_result = user.validate();
@@ -216,6 +819,7 @@ mod tests {
         let user = CreateUser {
             username: "testuser".to_string(),
             name: "".to_string(), // empty name
+            email: None,
             password: "password123".to_string(),
         };
         let validation_result = user.validate();
@@ -227,17 +831,77 @@ mod tests {
         let user = CreateUser {
             username: "testuser".to_string(),
             name: "Test User".to_string(),
+            email: None,
             password: "pass".to_string(), // too short
         };
         let validation_result = user.validate();
         assert!(validation_result.is_err());
     }
 
+    #[actix_web::test]
+    async fn test_validation_create_user_struct_email_invalid() {
+        let user = CreateUser {
+            username: "testuser".to_string(),
+            name: "Test User".to_string(),
+            email: Some("not-an-email".to_string()),
+            password: "password123".to_string(),
+        };
+        let validation_result = user.validate();
+        assert!(validation_result.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_validation_update_user_struct_partial() {
+        let update = UpdateUser {
+            username: None,
+            name: Some("New Name".to_string()),
+            email: None,
+            role: None,
+        };
+        assert!(update.validate().is_ok());
+
+        let update = UpdateUser {
+            username: Some("usr".to_string()), // too short
+            name: None,
+            email: None,
+            role: None,
+        };
+        assert!(update.validate().is_err());
+    }
+
+    #[test]
+    fn test_require_manage_limits_teachers_to_students() {
+        let teacher = AuthenticatedUser {
+            id: uuid::Uuid::new_v4(),
+            role: UserRole::Teacher,
+            token_id: None,
+            project_id: None,
+        };
+        let target = |role| entity::user::Model {
+            id: uuid::Uuid::new_v4(),
+            username: "target".to_string(),
+            name: "Target".to_string(),
+            email: None,
+            role,
+            deactivated_at: None,
+        };
+
+        assert!(require_manage(&teacher, &target(UserRole::Student)).is_ok());
+        assert!(require_manage(&teacher, &target(UserRole::Teacher)).is_err());
+
+        let admin = AuthenticatedUser {
+            role: UserRole::Admin,
+            ..teacher
+        };
+        assert!(require_manage(&admin, &target(UserRole::Teacher)).is_ok());
+    }
+
     #[actix_web::test]
     async fn test_validation_create_user_struct_password_too_long() {
         let user = CreateUser {
             username: "testuser".to_string(),
             name: "Test User".to_string(),
+            email: None,
             password: "a".repeat(256), // too long
         };
         let validation_result = user.validate();
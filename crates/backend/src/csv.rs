@@ -0,0 +1,181 @@
+//! Minimal RFC 4180 CSV reading and writing.
+//!
+//! Spreadsheet exports from German locales use `;` as separator, so the
+//! reader detects the delimiter from the header line. The writer always uses `,`.
+
+/// Parses CSV text into records, skipping empty lines
+pub fn parse(input: &str) -> Vec<Vec<String>> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let delimiter = detect_delimiter(input);
+
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_quotes = true,
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                push_record(&mut records, std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        push_record(&mut records, record);
+    }
+
+    records
+}
+
+fn push_record(records: &mut Vec<Vec<String>>, record: Vec<String>) {
+    if record.iter().any(|field| !field.trim().is_empty()) {
+        records.push(record);
+    }
+}
+
+fn detect_delimiter(input: &str) -> char {
+    let header = input.lines().next().unwrap_or_default();
+    if header.matches(';').count() > header.matches(',').count() {
+        ';'
+    } else {
+        ','
+    }
+}
+
+/// Parses CSV text with a header line into maps from lower-cased column name to value
+pub fn parse_with_header(input: &str) -> Vec<std::collections::HashMap<String, String>> {
+    let mut records = parse(input).into_iter();
+    let Some(header) = records.next() else {
+        return Vec::new();
+    };
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_lowercase()).collect();
+
+    records
+        .map(|record| {
+            header
+                .iter()
+                .cloned()
+                .zip(record.into_iter().map(|value| value.trim().to_owned()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Writes records as CSV with `,` as separator and CRLF line endings. Fields
+/// a spreadsheet would evaluate as formula are prefixed with `'`.
+pub fn write<I, R, F>(records: I) -> String
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator<Item = F>,
+    F: AsRef<str>,
+{
+    let mut output = String::new();
+
+    for record in records {
+        let fields: Vec<String> = record.into_iter().map(|f| escape(f.as_ref())).collect();
+        output.push_str(&fields.join(","));
+        output.push_str("\r\n");
+    }
+
+    output
+}
+
+fn escape(field: &str) -> String {
+    // The apostrophe makes spreadsheets show the field as text
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_owned()
+    };
+
+    if field.contains([',', ';', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_simple() {
+        let records = parse("username,name\nmax,Max Mustermann\n");
+        assert_eq!(
+            records,
+            vec![vec!["username", "name"], vec!["max", "Max Mustermann"]]
+        );
+    }
+
+    #[test]
+    fn parse_quoted_fields() {
+        let records = parse("a,b\r\n\"Mustermann, Max\",\"sagt \"\"Hallo\"\"\"\r\n");
+        assert_eq!(records[1], vec!["Mustermann, Max", "sagt \"Hallo\""]);
+    }
+
+    #[test]
+    fn parse_detects_semicolon() {
+        let records = parse("\u{feff}Nachname;Vorname\nMustermann;Max");
+        assert_eq!(
+            records,
+            vec![vec!["Nachname", "Vorname"], vec!["Mustermann", "Max"]]
+        );
+    }
+
+    #[test]
+    fn parse_skips_empty_lines() {
+        assert_eq!(parse("a\n\n,\nb\n").len(), 2);
+    }
+
+    #[test]
+    fn parse_with_header_maps_columns() {
+        let rows = parse_with_header("Username, Name\n max , Max\nerika");
+        assert_eq!(rows[0]["username"], "max");
+        assert_eq!(rows[0]["name"], "Max");
+        assert!(!rows[1].contains_key("name"));
+    }
+
+    #[test]
+    fn write_round_trips() {
+        let records = vec![vec!["name", "comment"], vec!["Max", "gut, \"sehr\" gut"]];
+        let output = write(records.clone());
+        assert_eq!(output, "name,comment\r\nMax,\"gut, \"\"sehr\"\" gut\"\r\n");
+        assert_eq!(parse(&output), records);
+    }
+
+    #[test]
+    fn write_neutralises_formulas() {
+        let output = write([[
+            "=HYPERLINK(\"http://example.org\")",
+            "@SUM(A1)",
+            "-2+3",
+            "Max",
+        ]]);
+        assert_eq!(
+            output,
+            "\"'=HYPERLINK(\"\"http://example.org\"\")\",'@SUM(A1),'-2+3,Max\r\n"
+        );
+        assert_eq!(write([["\tcmd", "2.3"]]), "'\tcmd,2.3\r\n");
+    }
+}
@@ -1,8 +1,22 @@
 use sea_orm::{ConnectOptions, DatabaseConnection};
 
+pub mod analytics;
+pub mod api_token;
+pub mod audit;
+pub mod class;
+pub mod enrolment;
 pub mod entity;
-mod group;
+pub mod feedback;
+pub mod grading;
+pub mod group;
+pub mod job;
+pub mod mail;
+pub mod privacy;
 pub mod project;
+pub mod reminder;
+pub mod retention;
+pub mod school_year;
+pub mod survey;
 mod user;
 
 #[derive(Clone)]
@@ -0,0 +1,196 @@
+use sea_orm::{DbBackend, FromQueryResult, Statement};
+use uuid::Uuid;
+
+use crate::{Database, error::ApiError};
+
+/// Ratings between current members of the same group and their
+/// self-assessments. Ratings of students who left the group don't count, as in
+/// the grade calculation.
+const RATINGS: &str = r#"
+WITH "ratings" AS (
+    SELECT "r"."group_id", "r"."from_user_id", "r"."to_user_id", "r"."rating"
+    FROM "feedback_response" "r"
+    JOIN "user_group_project" "f" ON "f"."project_id" = "r"."project_id"
+        AND "f"."group_id" = "r"."group_id" AND "f"."user_id" = "r"."from_user_id"
+    JOIN "user_group_project" "t" ON "t"."project_id" = "r"."project_id"
+        AND "t"."group_id" = "r"."group_id" AND "t"."user_id" = "r"."to_user_id"
+    WHERE "r"."project_id" = $1
+), "self_ratings" AS (
+    SELECT "s"."group_id", "s"."user_id", "s"."rating"
+    FROM "self_assessment" "s"
+    JOIN "user_group_project" "m" ON "m"."project_id" = "s"."project_id"
+        AND "m"."group_id" = "s"."group_id" AND "m"."user_id" = "s"."user_id"
+    WHERE "s"."project_id" = $1
+)
+"#;
+
+/// Statistics of the ratings in `source`, either `ratings` or `self_ratings`
+fn rating_stats(source: &str) -> String {
+    format!(
+        r#"
+SELECT count(*) AS "responses", avg("rating")::float8 AS "average",
+    stddev_pop("rating")::float8 AS "std_dev", min("rating") AS "min", max("rating") AS "max"
+FROM "{source}"
+"#
+    )
+}
+
+fn rating_distribution(source: &str) -> String {
+    format!(
+        r#"
+SELECT "rating", count(*) AS "count" FROM "{source}" GROUP BY "rating" ORDER BY "rating"
+"#
+    )
+}
+
+const STUDENT_STATS: &str = r#"
+SELECT "m"."user_id", "u"."name", "m"."group_id", "g"."name" AS "group_name",
+    coalesce("received"."count", 0) AS "ratings_received", "received"."average" AS "average_received",
+    coalesce("given"."count", 0) AS "ratings_given", "given"."average" AS "average_given",
+    "given"."min" AS "min_given", "given"."max" AS "max_given", "self"."rating" AS "self_rating",
+    (
+        SELECT avg("o"."rating")::float8 FROM "ratings" "o"
+        WHERE "o"."group_id" = "m"."group_id" AND "o"."from_user_id" <> "m"."user_id"
+    ) AS "others_average_given"
+FROM "user_group_project" "m"
+JOIN "user" "u" ON "u"."id" = "m"."user_id"
+JOIN "group" "g" ON "g"."id" = "m"."group_id" AND "g"."project_id" = "m"."project_id"
+LEFT JOIN (
+    SELECT "to_user_id", count(*) AS "count", avg("rating")::float8 AS "average"
+    FROM "ratings" GROUP BY "to_user_id"
+) "received" ON "received"."to_user_id" = "m"."user_id"
+LEFT JOIN (
+    SELECT "from_user_id", count(*) AS "count", avg("rating")::float8 AS "average",
+        min("rating") AS "min", max("rating") AS "max"
+    FROM "ratings" GROUP BY "from_user_id"
+) "given" ON "given"."from_user_id" = "m"."user_id"
+LEFT JOIN "self_ratings" "self" ON "self"."user_id" = "m"."user_id"
+WHERE "m"."project_id" = $1
+ORDER BY "g"."name", "u"."name"
+"#;
+
+const GROUP_STATS: &str = r#"
+SELECT "g"."id" AS "group_id", "g"."name",
+    (
+        SELECT count(*) FROM "user_group_project" "m"
+        WHERE "m"."project_id" = "g"."project_id" AND "m"."group_id" = "g"."id"
+    ) AS "members",
+    (
+        SELECT count(*) FROM "user_group_project" "m"
+        WHERE "m"."project_id" = "g"."project_id" AND "m"."group_id" = "g"."id"
+            AND "m"."feedback_completed"
+    ) AS "completed",
+    count("r"."rating") AS "ratings", avg("r"."rating")::float8 AS "average",
+    stddev_pop("r"."rating")::float8 AS "std_dev"
+FROM "group" "g"
+LEFT JOIN "ratings" "r" ON "r"."group_id" = "g"."id"
+WHERE "g"."project_id" = $1
+GROUP BY "g"."id", "g"."project_id", "g"."name"
+ORDER BY "g"."name"
+"#;
+
+#[derive(Debug, FromQueryResult)]
+pub struct RatingStats {
+    pub responses: i64,
+    pub average: Option<f64>,
+    pub std_dev: Option<f64>,
+    pub min: Option<i16>,
+    pub max: Option<i16>,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct RatingCount {
+    pub rating: i16,
+    pub count: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct StudentStats {
+    pub user_id: Uuid,
+    pub name: String,
+    pub group_id: Uuid,
+    pub group_name: String,
+    pub ratings_received: i64,
+    pub average_received: Option<f64>,
+    pub ratings_given: i64,
+    pub average_given: Option<f64>,
+    pub min_given: Option<i16>,
+    pub max_given: Option<i16>,
+    /// Average rating the other members of the group gave
+    pub others_average_given: Option<f64>,
+    pub self_rating: Option<i16>,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct GroupStats {
+    pub group_id: Uuid,
+    pub name: String,
+    pub members: i64,
+    pub completed: i64,
+    pub ratings: i64,
+    pub average: Option<f64>,
+    pub std_dev: Option<f64>,
+}
+
+/// Aggregated peer ratings of a project
+pub struct ProjectStats {
+    pub ratings: RatingStats,
+    pub distribution: Vec<RatingCount>,
+    pub self_ratings: RatingStats,
+    pub self_distribution: Vec<RatingCount>,
+    /// Ordered by group and name
+    pub students: Vec<StudentStats>,
+    /// Ordered by name
+    pub groups: Vec<GroupStats>,
+}
+
+fn statement(query: &str, project_id: Uuid) -> Statement {
+    Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!("{RATINGS}{query}"),
+        [project_id.into()],
+    )
+}
+
+impl Database {
+    pub async fn get_project_stats(&self, project_id: Uuid) -> Result<ProjectStats, ApiError> {
+        let (ratings, distribution) = self.get_rating_stats("ratings", project_id).await?;
+        let (self_ratings, self_distribution) =
+            self.get_rating_stats("self_ratings", project_id).await?;
+
+        let students = StudentStats::find_by_statement(statement(STUDENT_STATS, project_id))
+            .all(&self.conn)
+            .await?;
+
+        let groups = GroupStats::find_by_statement(statement(GROUP_STATS, project_id))
+            .all(&self.conn)
+            .await?;
+
+        Ok(ProjectStats {
+            ratings,
+            distribution,
+            self_ratings,
+            self_distribution,
+            students,
+            groups,
+        })
+    }
+
+    async fn get_rating_stats(
+        &self,
+        source: &str,
+        project_id: Uuid,
+    ) -> Result<(RatingStats, Vec<RatingCount>), ApiError> {
+        let stats = RatingStats::find_by_statement(statement(&rating_stats(source), project_id))
+            .one(&self.conn)
+            .await?
+            .ok_or_else(|| ApiError::InternalServerError("Missing rating statistics".into()))?;
+
+        let distribution =
+            RatingCount::find_by_statement(statement(&rating_distribution(source), project_id))
+                .all(&self.conn)
+                .await?;
+
+        Ok((stats, distribution))
+    }
+}
@@ -0,0 +1,170 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use log::debug;
+use rand::RngCore;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set, Unchanged},
+    ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::audit::NewAuditEvent,
+    db::entity::{
+        api_token, sea_orm_active_enums::AuditAction, sea_orm_active_enums::TokenAccess,
+        sea_orm_active_enums::UserRole, user,
+    },
+    error::ApiError,
+};
+
+/// Prefix of every personal access token, makes leaked tokens easy to find
+pub const TOKEN_PREFIX: &str = "pgg_";
+
+/// Generates a new plaintext token, only ever shown to the user once
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Tokens carry 256 bits of entropy, so a plain SHA-256 is sufficient for storage
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+impl Database {
+    pub async fn get_api_tokens(&self, user_id: Uuid) -> Result<Vec<api_token::Model>, ApiError> {
+        let tokens = api_token::Entity::find()
+            .filter(api_token::Column::UserId.eq(user_id))
+            .order_by_desc(api_token::Column::CreatedAt)
+            .all(&self.conn)
+            .await?;
+
+        Ok(tokens)
+    }
+
+    /// Returns the stored token together with its plaintext value
+    pub async fn create_api_token(
+        &self,
+        user_id: Uuid,
+        name: String,
+        role: UserRole,
+        access: TokenAccess,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(api_token::Model, String), ApiError> {
+        debug!("Creating API token '{}' for user {}", name, user_id);
+
+        let token = generate_token();
+
+        let api_token = api_token::ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            name: Set(name),
+            token_hash: Set(hash_token(&token)),
+            role: Set(role),
+            access: Set(access),
+            expires_at: Set(expires_at.map(Into::into)),
+            last_used_at: Set(None),
+            created_at: NotSet,
+        };
+
+        let txn = self.conn.begin().await?;
+        let api_token = api_token.insert(&txn).await?;
+        NewAuditEvent::new(Some(user_id), AuditAction::ApiTokenCreated, api_token.id)
+            .after(&api_token)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok((api_token, token))
+    }
+
+    pub async fn delete_api_token(&self, user_id: Uuid, id: Uuid) -> Result<(), ApiError> {
+        debug!("Deleting API token {} of user {}", id, user_id);
+
+        let txn = self.conn.begin().await?;
+        let token = api_token::Entity::find_by_id(id)
+            .filter(api_token::Column::UserId.eq(user_id))
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        token.clone().delete(&txn).await?;
+        NewAuditEvent::new(Some(user_id), AuditAction::ApiTokenDeleted, id)
+            .before(&token)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Resolves a bearer token to its owner and records the usage.
+    /// Unknown and expired tokens and tokens of deactivated users are rejected as unauthorized.
+    pub async fn authenticate_api_token(
+        &self,
+        token: &str,
+    ) -> Result<(user::Model, api_token::Model), ApiError> {
+        let api_token = api_token::Entity::find()
+            .filter(api_token::Column::TokenHash.eq(hash_token(token)))
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::Unauthorized)?;
+
+        if api_token
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            debug!("API token {} is expired", api_token.id);
+            return Err(ApiError::Unauthorized);
+        }
+
+        let user = api_token
+            .find_related(user::Entity)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::Unauthorized)?;
+
+        if user.deactivated_at.is_some() {
+            debug!("Owner of API token {} is deactivated", api_token.id);
+            return Err(ApiError::Unauthorized);
+        }
+
+        let api_token = api_token::ActiveModel {
+            id: Unchanged(api_token.id),
+            last_used_at: Set(Some(Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&self.conn)
+        .await?;
+
+        Ok((user, api_token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_tokens_are_prefixed_and_unique() {
+        let token = generate_token();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 43);
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn hash_token_is_stable_hex() {
+        let hash = hash_token("pgg_example");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_token("pgg_example"));
+        assert_ne!(hash, hash_token("pgg_other"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Append-only log of the changes teachers and admins make.
+//!
+//! Events are written by the `Database` methods making the change, in the same
+//! transaction, so a change is never stored without its event. They are never
+//! updated or deleted through the API.
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use sea_orm::ActiveValue::{NotSet, Set, Unchanged};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryTrait,
+};
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use super::Database;
+use crate::db::entity::{audit_event, sea_orm_active_enums::AuditAction};
+use crate::error::ApiError;
+use crate::pagination::Pagination;
+
+/// A change about to be recorded
+pub(crate) struct NewAuditEvent {
+    actor_id: Option<Uuid>,
+    action: AuditAction,
+    entity_id: Option<Uuid>,
+    project_id: Option<Uuid>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl NewAuditEvent {
+    pub fn new(actor_id: Option<Uuid>, action: AuditAction, entity_id: Uuid) -> Self {
+        NewAuditEvent {
+            actor_id,
+            action,
+            entity_id: Some(entity_id),
+            project_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn project(mut self, project_id: Uuid) -> Self {
+        self.project_id = Some(project_id);
+        self
+    }
+
+    /// Snapshot of the state before the change
+    pub fn before(mut self, before: &impl Serialize) -> Self {
+        self.before = serde_json::to_value(before).ok();
+        self
+    }
+
+    /// Snapshot of the state after the change
+    pub fn after(mut self, after: &impl Serialize) -> Self {
+        self.after = serde_json::to_value(after).ok();
+        self
+    }
+
+    pub async fn insert<C: ConnectionTrait>(self, conn: &C) -> Result<(), DbErr> {
+        debug!("Recording {:?} of {:?}", self.action, self.entity_id);
+
+        audit_event::ActiveModel {
+            id: NotSet,
+            actor_id: Set(self.actor_id),
+            action: Set(self.action),
+            entity_id: Set(self.entity_id),
+            project_id: Set(self.project_id),
+            before: Set(self.before),
+            after: Set(self.after),
+            created_at: Set(Utc::now().into()),
+        }
+        .insert(conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Removes the snapshots of the events about the entity, for personal data
+/// that must not outlive the entity
+pub(crate) async fn clear_snapshots<C: ConnectionTrait>(
+    conn: &C,
+    entity_id: Uuid,
+) -> Result<(), DbErr> {
+    audit_event::Entity::update_many()
+        .col_expr(
+            audit_event::Column::Before,
+            Expr::value(Option::<serde_json::Value>::None),
+        )
+        .col_expr(
+            audit_event::Column::After,
+            Expr::value(Option::<serde_json::Value>::None),
+        )
+        .filter(audit_event::Column::EntityId.eq(entity_id))
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Removes the comments about the user from the feedback other students
+/// submitted before it was reset, the ratings are kept
+pub(crate) async fn clear_feedback_comments<C: ConnectionTrait>(
+    conn: &C,
+    user_id: Uuid,
+) -> Result<(), DbErr> {
+    let events = audit_event::Entity::find()
+        .filter(audit_event::Column::Action.eq(AuditAction::FeedbackReset))
+        .filter(Expr::cust_with_values(
+            r#""before" @> $1"#,
+            [json!({ "responses": [{ "to_user_id": user_id }] })],
+        ))
+        .all(conn)
+        .await?;
+
+    for event in events {
+        let Some(mut before) = event.before else {
+            continue;
+        };
+        if let Some(responses) = before["responses"].as_array_mut() {
+            for response in responses {
+                if response["to_user_id"] == user_id.to_string() {
+                    response["comment"] = serde_json::Value::Null;
+                }
+            }
+        }
+
+        audit_event::ActiveModel {
+            id: Unchanged(event.id),
+            before: Set(Some(before)),
+            ..Default::default()
+        }
+        .update(conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Filters of the audit log, all of them have to match
+#[derive(Default)]
+pub struct AuditFilter {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub entity_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Database {
+    /// Lists a page of audit events, oldest first unless sorted otherwise
+    pub async fn get_audit_events(
+        &self,
+        filter: &AuditFilter,
+        pagination: &Pagination,
+    ) -> Result<(Vec<audit_event::Model>, u64), ApiError> {
+        let query = audit_event::Entity::find()
+            .apply_if(filter.actor_id, |query, id| {
+                query.filter(audit_event::Column::ActorId.eq(id))
+            })
+            .apply_if(filter.action, |query, action| {
+                query.filter(audit_event::Column::Action.eq(action))
+            })
+            .apply_if(filter.entity_id, |query, id| {
+                query.filter(audit_event::Column::EntityId.eq(id))
+            })
+            .apply_if(filter.project_id, |query, id| {
+                query.filter(audit_event::Column::ProjectId.eq(id))
+            })
+            .apply_if(filter.from, |query, from| {
+                query.filter(audit_event::Column::CreatedAt.gte(from))
+            })
+            .apply_if(filter.until, |query, until| {
+                query.filter(audit_event::Column::CreatedAt.lt(until))
+            });
+
+        let query = pagination.sort(
+            query,
+            &[("created_at", audit_event::Column::CreatedAt)],
+            audit_event::Column::CreatedAt,
+        )?;
+
+        pagination.fetch(&self.conn, query).await
+    }
+}
@@ -0,0 +1,252 @@
+use super::Database;
+use crate::db::audit::NewAuditEvent;
+use crate::db::entity::sea_orm_active_enums::AuditAction;
+use crate::db::entity::{class, grade_override, group, project, user_group_project};
+use crate::db::school_year::ensure_school_year;
+use crate::db::user::escape_like;
+use crate::error::ApiError;
+use crate::pagination::Pagination;
+use crate::scale::Scale;
+use crate::survey;
+use log::debug;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::sea_query::{Expr, extension::postgres::PgExpr};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QuerySelect, TransactionTrait,
+};
+use uuid::Uuid;
+
+/// Scale of the project's class, German grades if it has none
+pub(crate) async fn project_scale<C: ConnectionTrait>(
+    conn: &C,
+    project: &project::Model,
+) -> Result<Scale, ApiError> {
+    let Some(class_id) = project.class_id else {
+        return Ok(Scale::default());
+    };
+
+    let class = class::Entity::find_by_id(class_id).one(conn).await?;
+    Ok(Scale::of(class.as_ref()))
+}
+
+/// Scale of the class a project is moved into, fails if there is no such class
+pub(crate) async fn class_scale<C: ConnectionTrait>(
+    conn: &C,
+    class_id: Option<Uuid>,
+) -> Result<Scale, ApiError> {
+    let Some(class_id) = class_id else {
+        return Ok(Scale::default());
+    };
+
+    let class = class::Entity::find_by_id(class_id)
+        .one(conn)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest(format!("Class {} not found", class_id)))?;
+    Ok(Scale::of(Some(&class)))
+}
+
+/// Converts the group and individual grades of the projects and their history
+/// from one scale to another. Grades of finalised surveys are locked, so their
+/// scale can't change.
+pub(crate) async fn convert_grades<C: ConnectionTrait>(
+    conn: &C,
+    projects: &[project::Model],
+    from: &Scale,
+    to: &Scale,
+) -> Result<(), ApiError> {
+    if from.kind == to.kind || projects.is_empty() {
+        return Ok(());
+    }
+    for project in projects {
+        survey::ensure_not_finalised(project)?;
+    }
+    let ids: Vec<Uuid> = projects.iter().map(|project| project.id).collect();
+    debug!(
+        "Converting grades of {} projects to {:?}",
+        ids.len(),
+        to.kind
+    );
+
+    let groups = group::Entity::find()
+        .filter(group::Column::ProjectId.is_in(ids.clone()))
+        .filter(group::Column::Grade.is_not_null())
+        .all(conn)
+        .await?;
+    for group in groups {
+        let grade = group.grade.map(|grade| from.convert(grade, to));
+        let mut active = group.into_active_model();
+        active.grade = Set(grade);
+        active.update(conn).await?;
+    }
+
+    let members = user_group_project::Entity::find()
+        .filter(user_group_project::Column::ProjectId.is_in(ids.clone()))
+        .filter(user_group_project::Column::Grade.is_not_null())
+        .all(conn)
+        .await?;
+    for member in members {
+        let grade = member.grade.map(|grade| from.convert(grade, to));
+        let mut active = member.into_active_model();
+        active.grade = Set(grade);
+        active.update(conn).await?;
+    }
+
+    let overrides = grade_override::Entity::find()
+        .filter(grade_override::Column::ProjectId.is_in(ids))
+        .all(conn)
+        .await?;
+    for change in overrides {
+        let convert = |grade: Option<f64>| grade.map(|grade| from.convert(grade, to));
+        let (computed, previous, grade) = (
+            convert(change.computed_grade),
+            convert(change.previous_grade),
+            convert(change.grade),
+        );
+        let mut active = change.into_active_model();
+        active.computed_grade = Set(computed);
+        active.previous_grade = Set(previous);
+        active.grade = Set(grade);
+        active.update(conn).await?;
+    }
+
+    Ok(())
+}
+
+impl Database {
+    pub async fn get_classes(
+        &self,
+        search: Option<&str>,
+        pagination: &Pagination,
+    ) -> Result<(Vec<class::Model>, u64), ApiError> {
+        debug!("Fetching classes, page {}", pagination.page);
+
+        let mut query = class::Entity::find();
+
+        if let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) {
+            query = query.filter(
+                Expr::col((class::Entity, class::Column::Name))
+                    .ilike(format!("%{}%", escape_like(search))),
+            );
+        }
+
+        let query =
+            pagination.sort(query, &[("name", class::Column::Name)], class::Column::Name)?;
+
+        pagination.fetch(&self.conn, query).await
+    }
+
+    pub async fn get_class(&self, id: Uuid) -> Result<class::Model, ApiError> {
+        class::Entity::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)
+    }
+
+    pub async fn create_class(
+        &self,
+        name: String,
+        scale: Scale,
+        school_year_id: Option<Uuid>,
+        actor_id: Option<Uuid>,
+    ) -> Result<class::Model, ApiError> {
+        debug!("Creating class with name: {}", name);
+
+        let class = class::ActiveModel {
+            id: NotSet,
+            name: Set(name),
+            grade_scale: Set(scale.kind),
+            grade_rounding: Set(scale.rounding),
+            school_year_id: Set(school_year_id),
+        };
+
+        let txn = self.conn.begin().await?;
+        ensure_school_year(&txn, school_year_id).await?;
+        let class = class.insert(&txn).await?;
+        NewAuditEvent::new(actor_id, AuditAction::ClassCreated, class.id)
+            .after(&class)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(class)
+    }
+
+    /// Renames the class and changes its scale or school year, the grades of its
+    /// projects are converted to the new scale
+    pub async fn update_class(
+        &self,
+        id: Uuid,
+        name: String,
+        scale: Scale,
+        school_year_id: Option<Uuid>,
+        actor_id: Option<Uuid>,
+    ) -> Result<class::Model, ApiError> {
+        debug!("Updating class with id: {}", id);
+
+        let txn = self.conn.begin().await?;
+        ensure_school_year(&txn, school_year_id).await?;
+        let class = class::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let projects = project::Entity::find()
+            .filter(project::Column::ClassId.eq(id))
+            .lock_shared()
+            .all(&txn)
+            .await?;
+        convert_grades(&txn, &projects, &Scale::of(Some(&class)), &scale).await?;
+
+        let before = class.clone();
+        let mut active = class.into_active_model();
+        active.name = Set(name);
+        active.grade_scale = Set(scale.kind);
+        active.grade_rounding = Set(scale.rounding);
+        active.school_year_id = Set(school_year_id);
+        let class = active.update(&txn).await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::ClassUpdated, id)
+            .before(&before)
+            .after(&class)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(class)
+    }
+
+    /// Deletes the class, its projects are kept with their grades converted to
+    /// German grades
+    pub async fn delete_class(&self, id: Uuid, actor_id: Option<Uuid>) -> Result<(), ApiError> {
+        debug!("Deleting class with id: {}", id);
+
+        let txn = self.conn.begin().await?;
+        let class = class::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let projects = project::Entity::find()
+            .filter(project::Column::ClassId.eq(id))
+            .lock_shared()
+            .all(&txn)
+            .await?;
+        convert_grades(&txn, &projects, &Scale::of(Some(&class)), &Scale::default()).await?;
+
+        class::Entity::delete_by_id(id).exec(&txn).await?;
+        NewAuditEvent::new(actor_id, AuditAction::ClassDeleted, id)
+            .before(&class)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_project_scale(&self, project: &project::Model) -> Result<Scale, ApiError> {
+        project_scale(&self.conn, project).await
+    }
+}
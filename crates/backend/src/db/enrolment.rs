@@ -0,0 +1,306 @@
+use chrono::{NaiveDate, Utc};
+use log::debug;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, QueryTrait, TransactionTrait,
+};
+use uuid::Uuid;
+
+use super::Database;
+use crate::db::audit::NewAuditEvent;
+use crate::db::entity::sea_orm_active_enums::{AuditAction, UserRole};
+use crate::db::entity::{class, class_enrolment, school_year, user};
+use crate::error::ApiError;
+use crate::pagination::Pagination;
+
+/// A student of a roster import, created if their username is unknown
+pub struct RosterStudent {
+    pub username: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+}
+
+/// Enrolments that cover the day
+fn enrolled_on(day: NaiveDate) -> Condition {
+    Condition::all()
+        .add(class_enrolment::Column::StartsOn.lte(day))
+        .add(
+            Condition::any()
+                .add(class_enrolment::Column::EndsOn.is_null())
+                .add(class_enrolment::Column::EndsOn.gte(day)),
+        )
+}
+
+/// Students of the class who are enrolled on the day
+pub(crate) async fn enrolled_students<C: ConnectionTrait>(
+    conn: &C,
+    class_id: Uuid,
+    day: NaiveDate,
+) -> Result<Vec<Uuid>, ApiError> {
+    Ok(class_enrolment::Entity::find()
+        .filter(class_enrolment::Column::ClassId.eq(class_id))
+        .filter(enrolled_on(day))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|enrolment| enrolment.user_id)
+        .collect())
+}
+
+/// Fails if the period is empty or overlaps another enrolment of the student
+/// in the class
+async fn check_period<C: ConnectionTrait>(
+    conn: &C,
+    enrolment: &class_enrolment::Model,
+) -> Result<(), ApiError> {
+    if enrolment
+        .ends_on
+        .is_some_and(|ends_on| ends_on < enrolment.starts_on)
+    {
+        return Err(ApiError::BadRequest(
+            "An enrolment can't end before it starts".to_owned(),
+        ));
+    }
+
+    let overlapping = class_enrolment::Entity::find()
+        .filter(class_enrolment::Column::ClassId.eq(enrolment.class_id))
+        .filter(class_enrolment::Column::UserId.eq(enrolment.user_id))
+        .filter(class_enrolment::Column::Id.ne(enrolment.id))
+        .filter(
+            Condition::any()
+                .add(class_enrolment::Column::EndsOn.is_null())
+                .add(class_enrolment::Column::EndsOn.gte(enrolment.starts_on)),
+        )
+        .apply_if(enrolment.ends_on, |query, ends_on| {
+            query.filter(class_enrolment::Column::StartsOn.lte(ends_on))
+        })
+        .one(conn)
+        .await?;
+    if overlapping.is_some() {
+        return Err(ApiError::BadRequest(format!(
+            "User {} is already enrolled in the class during that time",
+            enrolment.user_id
+        )));
+    }
+    Ok(())
+}
+
+impl Database {
+    /// A page of the enrolments of the class with their students, sortable by
+    /// `name` and `username`. Only those covering `day` if given.
+    pub async fn get_roster(
+        &self,
+        class_id: Uuid,
+        day: Option<NaiveDate>,
+        pagination: &Pagination,
+    ) -> Result<(Vec<(class_enrolment::Model, user::Model)>, u64), ApiError> {
+        self.get_class(class_id).await?;
+
+        let query = class_enrolment::Entity::find()
+            .filter(class_enrolment::Column::ClassId.eq(class_id))
+            .apply_if(day, |query, day| query.filter(enrolled_on(day)))
+            .find_also_related(user::Entity);
+        let query = pagination
+            .sort(
+                query,
+                &[
+                    ("name", user::Column::Name),
+                    ("username", user::Column::Username),
+                ],
+                user::Column::Name,
+            )?
+            .order_by_asc(class_enrolment::Column::StartsOn);
+
+        let (enrolments, total) = pagination.fetch(&self.conn, query).await?;
+        let enrolments = enrolments
+            .into_iter()
+            .filter_map(|(enrolment, user)| Some((enrolment, user?)))
+            .collect();
+        Ok((enrolments, total))
+    }
+
+    /// Enrols a student in the class, from the start of the class's school year
+    /// or today if `starts_on` isn't given
+    pub async fn enrol_student(
+        &self,
+        class_id: Uuid,
+        user_id: Uuid,
+        starts_on: Option<NaiveDate>,
+        ends_on: Option<NaiveDate>,
+        actor_id: Option<Uuid>,
+    ) -> Result<class_enrolment::Model, ApiError> {
+        debug!("Enrolling user {} in class {}", user_id, class_id);
+
+        let txn = self.conn.begin().await?;
+        let (class, school_year) = class::Entity::find_by_id(class_id)
+            .find_also_related(school_year::Entity)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        let student = user::Entity::find_by_id(user_id).one(&txn).await?;
+        if student.is_none_or(|student| student.role != UserRole::Student) {
+            return Err(ApiError::BadRequest(format!(
+                "User {} is not a student",
+                user_id
+            )));
+        }
+
+        let starts_on = starts_on
+            .or(school_year.map(|year| year.starts_on))
+            .unwrap_or_else(|| Utc::now().date_naive());
+        let enrolment = class_enrolment::Model {
+            id: Uuid::nil(),
+            class_id: class.id,
+            user_id,
+            starts_on,
+            ends_on,
+        };
+        check_period(&txn, &enrolment).await?;
+
+        let enrolment = class_enrolment::ActiveModel {
+            id: NotSet,
+            class_id: Set(class.id),
+            user_id: Set(user_id),
+            starts_on: Set(starts_on),
+            ends_on: Set(ends_on),
+        }
+        .insert(&txn)
+        .await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::StudentEnrolled, user_id)
+            .after(&enrolment)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(enrolment)
+    }
+
+    /// Changes the period of an enrolment, e.g. when a student leaves the class
+    pub async fn update_enrolment(
+        &self,
+        class_id: Uuid,
+        id: Uuid,
+        starts_on: NaiveDate,
+        ends_on: Option<NaiveDate>,
+        actor_id: Option<Uuid>,
+    ) -> Result<class_enrolment::Model, ApiError> {
+        debug!("Updating enrolment {} of class {}", id, class_id);
+
+        let txn = self.conn.begin().await?;
+        let before = class_enrolment::Entity::find_by_id(id)
+            .filter(class_enrolment::Column::ClassId.eq(class_id))
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        check_period(
+            &txn,
+            &class_enrolment::Model {
+                starts_on,
+                ends_on,
+                ..before.clone()
+            },
+        )
+        .await?;
+
+        let mut active = before.clone().into_active_model();
+        active.starts_on = Set(starts_on);
+        active.ends_on = Set(ends_on);
+        let enrolment = active.update(&txn).await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::EnrolmentUpdated, enrolment.user_id)
+            .before(&before)
+            .after(&enrolment)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(enrolment)
+    }
+
+    /// Deletes an enrolment made by mistake, students who leave the class
+    /// should get an end date instead
+    pub async fn delete_enrolment(
+        &self,
+        class_id: Uuid,
+        id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<(), ApiError> {
+        debug!("Deleting enrolment {} of class {}", id, class_id);
+
+        let txn = self.conn.begin().await?;
+        let enrolment = class_enrolment::Entity::find_by_id(id)
+            .filter(class_enrolment::Column::ClassId.eq(class_id))
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        class_enrolment::Entity::delete_by_id(id).exec(&txn).await?;
+        NewAuditEvent::new(actor_id, AuditAction::EnrolmentDeleted, enrolment.user_id)
+            .before(&enrolment)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Enrols the student with the username in the class, creating them
+    /// without password first if they don't exist. Returns whether the
+    /// student was created.
+    pub async fn import_roster_student(
+        &self,
+        class_id: Uuid,
+        student: RosterStudent,
+        actor_id: Option<Uuid>,
+    ) -> Result<(class_enrolment::Model, bool), ApiError> {
+        if let (Some(starts_on), Some(ends_on)) = (student.starts_on, student.ends_on)
+            && ends_on < starts_on
+        {
+            return Err(ApiError::BadRequest(
+                "An enrolment can't end before it starts".to_owned(),
+            ));
+        }
+
+        let existing = user::Entity::find()
+            .filter(user::Column::Username.eq(&student.username))
+            .one(&self.conn)
+            .await?;
+        let (user_id, created) = match existing {
+            Some(user) => (user.id, false),
+            None => {
+                let name = student.name.ok_or_else(|| {
+                    ApiError::BadRequest(format!(
+                        "User {} does not exist, a name is needed to create them",
+                        student.username
+                    ))
+                })?;
+                let user = self
+                    .create_user(
+                        name,
+                        student.username,
+                        student.email,
+                        None,
+                        UserRole::Student,
+                        actor_id,
+                    )
+                    .await?;
+                (user.id, true)
+            }
+        };
+
+        let enrolment = self
+            .enrol_student(
+                class_id,
+                user_id,
+                student.starts_on,
+                student.ends_on,
+                actor_id,
+            )
+            .await?;
+        Ok((enrolment, created))
+    }
+}
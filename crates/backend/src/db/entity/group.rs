@@ -2,8 +2,10 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = Group)]
 #[sea_orm(table_name = "group")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -11,6 +13,9 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub project_id: Uuid,
     pub name: String,
+    /// Grade of the group's work, the starting point of the individual grades
+    #[sea_orm(column_type = "Double", nullable)]
+    pub grade: Option<f64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -23,16 +28,32 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Project,
+    #[sea_orm(has_many = "super::self_assessment::Entity")]
+    SelfAssessment,
+    #[sea_orm(has_many = "super::feedback_response::Entity")]
+    FeedbackResponse,
     #[sea_orm(has_many = "super::user_group_project::Entity")]
     UserGroupProject,
 }
 
+impl Related<super::feedback_response::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FeedbackResponse.def()
+    }
+}
+
 impl Related<super::project::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Project.def()
     }
 }
 
+impl Related<super::self_assessment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SelfAssessment.def()
+    }
+}
+
 impl Related<super::user_group_project::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserGroupProject.def()
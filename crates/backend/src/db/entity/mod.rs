@@ -2,8 +2,22 @@
 
 pub mod prelude;
 
+pub mod api_token;
+pub mod audit_event;
+pub mod class;
+pub mod class_enrolment;
+pub mod feedback_response;
+pub mod grade_override;
 pub mod group;
+pub mod job;
 pub mod local_auth;
+pub mod mail_delivery;
+pub mod oidc_auth;
 pub mod project;
+pub mod reminder_run;
+pub mod school_year;
+pub mod sea_orm_active_enums;
+pub mod self_assessment;
+pub mod survey_event;
 pub mod user;
 pub mod user_group_project;
@@ -1,7 +1,22 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.7
 
+#![allow(unused_imports)]
+
+pub use super::api_token::Entity as ApiToken;
+pub use super::audit_event::Entity as AuditEvent;
+pub use super::class::Entity as Class;
+pub use super::class_enrolment::Entity as ClassEnrolment;
+pub use super::feedback_response::Entity as FeedbackResponse;
+pub use super::grade_override::Entity as GradeOverride;
 pub use super::group::Entity as Group;
+pub use super::job::Entity as Job;
 pub use super::local_auth::Entity as LocalAuth;
+pub use super::mail_delivery::Entity as MailDelivery;
+pub use super::oidc_auth::Entity as OidcAuth;
 pub use super::project::Entity as Project;
+pub use super::reminder_run::Entity as ReminderRun;
+pub use super::school_year::Entity as SchoolYear;
+pub use super::self_assessment::Entity as SelfAssessment;
+pub use super::survey_event::Entity as SurveyEvent;
 pub use super::user::Entity as User;
 pub use super::user_group_project::Entity as UserGroupProject;
@@ -1,21 +1,70 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.7
 
+use super::sea_orm_active_enums::{Language, PeerAggregation, SurveyStatus};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "project")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub name: String,
+    /// Students should have submitted their feedback by then
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub feedback_deadline: Option<DateTimeWithTimeZone>,
+    /// Days before the deadline at which students without feedback get a reminder
+    pub reminder_days: Vec<i32>,
+    pub reminders_enabled: bool,
+    /// Language of the mails sent automatically
+    pub language: Language,
+    pub survey_status: SurveyStatus,
+    /// Students can submit their feedback from then on, right away if not set
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub feedback_opens_at: Option<DateTimeWithTimeZone>,
+    /// The feedback form asks students to rate themselves as well
+    pub self_assessment: bool,
+    /// Self-assessments count towards the peer factor like a rating from a group mate
+    pub self_assessment_in_grade: bool,
+    /// How the ratings a student received are turned into their peer factor
+    pub peer_aggregation: PeerAggregation,
+    /// Largest difference between computed grade and group grade in German grade
+    /// steps whatever the scale, unbounded if not set
+    #[sea_orm(column_type = "Double", nullable)]
+    pub max_grade_deviation: Option<f64>,
+    /// Class the project belongs to, its grade scale applies to the project
+    pub class_id: Option<Uuid>,
+    /// When a teacher or the retention policy archived the project, archived
+    /// projects are left out of the project list
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub archived_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::class::Entity",
+        from = "Column::ClassId",
+        to = "super::class::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Class,
     #[sea_orm(has_many = "super::group::Entity")]
     Group,
+    #[sea_orm(has_many = "super::mail_delivery::Entity")]
+    MailDelivery,
+    #[sea_orm(has_many = "super::reminder_run::Entity")]
+    ReminderRun,
+    #[sea_orm(has_many = "super::survey_event::Entity")]
+    SurveyEvent,
+}
+
+impl Related<super::class::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Class.def()
+    }
 }
 
 impl Related<super::group::Entity> for Entity {
@@ -24,6 +73,24 @@ impl Related<super::group::Entity> for Entity {
     }
 }
 
+impl Related<super::mail_delivery::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MailDelivery.def()
+    }
+}
+
+impl Related<super::reminder_run::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReminderRun.def()
+    }
+}
+
+impl Related<super::survey_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SurveyEvent.def()
+    }
+}
+
 impl Related<super::user_group_project::Entity> for Entity {
     fn to() -> RelationDef {
         super::group::Relation::UserGroupProject.def()
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.7
 
+use super::sea_orm_active_enums::UserRole;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -12,22 +13,66 @@ pub struct Model {
     #[sea_orm(unique)]
     pub username: String,
     pub name: String,
+    pub email: Option<String>,
+    pub role: UserRole,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub deactivated_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::api_token::Entity")]
+    ApiToken,
+    #[sea_orm(has_many = "super::job::Entity")]
+    Job,
     #[sea_orm(has_one = "super::local_auth::Entity")]
     LocalAuth,
+    #[sea_orm(has_many = "super::mail_delivery::Entity")]
+    MailDelivery,
+    #[sea_orm(has_one = "super::oidc_auth::Entity")]
+    OidcAuth,
+    #[sea_orm(has_many = "super::survey_event::Entity")]
+    SurveyEvent,
     #[sea_orm(has_many = "super::user_group_project::Entity")]
     UserGroupProject,
 }
 
+impl Related<super::api_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiToken.def()
+    }
+}
+
+impl Related<super::job::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Job.def()
+    }
+}
+
 impl Related<super::local_auth::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::LocalAuth.def()
     }
 }
 
+impl Related<super::mail_delivery::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MailDelivery.def()
+    }
+}
+
+impl Related<super::oidc_auth::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OidcAuth.def()
+    }
+}
+
+impl Related<super::survey_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SurveyEvent.def()
+    }
+}
+
 impl Related<super::user_group_project::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserGroupProject.def()
@@ -2,8 +2,10 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, ToSchema)]
+#[schema(as = UserGroupProject)]
 #[sea_orm(table_name = "user_group_project")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -12,6 +14,20 @@ pub struct Model {
     pub group_id: Uuid,
     #[sea_orm(primary_key, auto_increment = false)]
     pub project_id: Uuid,
+    #[sea_orm(unique)]
+    pub feedback_id: Option<Uuid>,
+    pub feedback_completed: bool,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub feedback_completed_at: Option<DateTime>,
+    /// The group changed after the feedback was submitted, so it may lack
+    /// ratings of new group mates or rate students who left
+    pub feedback_stale: bool,
+    /// Grade set by the teacher, overrides the grade computed from the peer feedback
+    #[sea_orm(column_type = "Double", nullable)]
+    pub grade: Option<f64>,
+    /// Why the teacher overrode the computed grade, set along with `grade`
+    #[sea_orm(column_type = "Text", nullable)]
+    pub grade_justification: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,284 @@
+use chrono::{DateTime, Utc};
+use log::debug;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set, Unchanged},
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+    sea_query::Expr,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::{
+        audit::NewAuditEvent,
+        entity::{
+            feedback_response, project, sea_orm_active_enums::AuditAction, self_assessment, user,
+            user_group_project,
+        },
+        survey::lock_project,
+    },
+    error::ApiError,
+    survey,
+};
+
+/// Rating of one group mate, as submitted by a student
+pub struct NewFeedbackResponse {
+    pub to_user_id: Uuid,
+    pub rating: i16,
+    pub comment: Option<String>,
+}
+
+/// A student's rating of their own contribution
+pub struct NewSelfAssessment {
+    pub rating: i16,
+    pub comment: Option<String>,
+}
+
+impl Database {
+    /// Group membership and project belonging to a feedback link, the links of
+    /// deactivated students stop working
+    pub async fn get_feedback_member(
+        &self,
+        feedback_id: Uuid,
+    ) -> Result<(user_group_project::Model, project::Model), ApiError> {
+        let (member, student) = user_group_project::Entity::find()
+            .filter(user_group_project::Column::FeedbackId.eq(feedback_id))
+            .find_also_related(user::Entity)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        if student.is_none_or(|student| student.deactivated_at.is_some()) {
+            return Err(ApiError::Unauthorized);
+        }
+
+        let project = project::Entity::find_by_id(member.project_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        Ok((member, project))
+    }
+
+    /// Group membership of the student in the project, for sessions opened
+    /// with a feedback link
+    pub async fn get_project_member(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(user_group_project::Model, project::Model), ApiError> {
+        let member = user_group_project::Entity::find()
+            .filter(user_group_project::Column::ProjectId.eq(project_id))
+            .filter(user_group_project::Column::UserId.eq(user_id))
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let project = project::Entity::find_by_id(member.project_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        Ok((member, project))
+    }
+
+    /// Members of the group, ordered by name
+    pub async fn get_group_members(
+        &self,
+        project_id: Uuid,
+        group_id: Uuid,
+    ) -> Result<Vec<(user_group_project::Model, user::Model)>, ApiError> {
+        let members = user_group_project::Entity::find()
+            .filter(user_group_project::Column::ProjectId.eq(project_id))
+            .filter(user_group_project::Column::GroupId.eq(group_id))
+            .find_also_related(user::Entity)
+            .order_by_asc(user::Column::Name)
+            .all(&self.conn)
+            .await?;
+
+        Ok(members
+            .into_iter()
+            .filter_map(|(member, user)| Some((member, user?)))
+            .collect())
+    }
+
+    /// Ratings the student gave in the project
+    pub async fn get_given_feedback(
+        &self,
+        project_id: Uuid,
+        from_user_id: Uuid,
+    ) -> Result<Vec<feedback_response::Model>, ApiError> {
+        Ok(feedback_response::Entity::find()
+            .filter(feedback_response::Column::ProjectId.eq(project_id))
+            .filter(feedback_response::Column::FromUserId.eq(from_user_id))
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Self-assessment of the student in the project
+    pub async fn get_self_assessment(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<self_assessment::Model>, ApiError> {
+        Ok(self_assessment::Entity::find()
+            .filter(self_assessment::Column::ProjectId.eq(project_id))
+            .filter(self_assessment::Column::UserId.eq(user_id))
+            .one(&self.conn)
+            .await?)
+    }
+
+    /// Stores the student's ratings and self-assessment and marks their feedback
+    /// as completed. Fails if the survey isn't open at `now` or the feedback was
+    /// already submitted.
+    pub async fn submit_feedback(
+        &self,
+        member: &user_group_project::Model,
+        responses: Vec<NewFeedbackResponse>,
+        self_assessment: Option<NewSelfAssessment>,
+        now: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        debug!(
+            "Storing {} ratings of user {} in project {}",
+            responses.len(),
+            member.user_id,
+            member.project_id
+        );
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, member.project_id).await?;
+        survey::check_submission_window(&project, now)?;
+
+        // Guards against two submissions racing each other
+        let completed = user_group_project::Entity::update_many()
+            .col_expr(
+                user_group_project::Column::FeedbackCompleted,
+                Expr::value(true),
+            )
+            .col_expr(
+                user_group_project::Column::FeedbackCompletedAt,
+                Expr::value(now.naive_utc()),
+            )
+            .filter(user_group_project::Column::UserId.eq(member.user_id))
+            .filter(user_group_project::Column::GroupId.eq(member.group_id))
+            .filter(user_group_project::Column::ProjectId.eq(member.project_id))
+            .filter(user_group_project::Column::FeedbackCompleted.eq(false))
+            .exec(&txn)
+            .await?;
+        if completed.rows_affected == 0 {
+            return Err(ApiError::FeedbackAlreadySubmitted);
+        }
+
+        let responses = responses
+            .into_iter()
+            .map(|response| feedback_response::ActiveModel {
+                id: NotSet,
+                project_id: Set(member.project_id),
+                group_id: Set(member.group_id),
+                from_user_id: Set(member.user_id),
+                to_user_id: Set(response.to_user_id),
+                rating: Set(response.rating),
+                comment: Set(response.comment),
+                submitted_at: Set(now.into()),
+            })
+            .collect::<Vec<_>>();
+        if !responses.is_empty() {
+            feedback_response::Entity::insert_many(responses)
+                .exec(&txn)
+                .await?;
+        }
+
+        if let Some(assessment) = self_assessment {
+            self_assessment::ActiveModel {
+                id: NotSet,
+                project_id: Set(member.project_id),
+                group_id: Set(member.group_id),
+                user_id: Set(member.user_id),
+                rating: Set(assessment.rating),
+                comment: Set(assessment.comment),
+                submitted_at: Set(now.into()),
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Deletes the student's ratings and self-assessment so they can fill in the form again
+    pub async fn reset_feedback(
+        &self,
+        feedback_id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<user_group_project::Model, ApiError> {
+        let (member, _) = self.get_feedback_member(feedback_id).await?;
+        debug!(
+            "Resetting feedback of user {} in project {}",
+            member.user_id, member.project_id
+        );
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, member.project_id).await?;
+        survey::ensure_not_finalised(&project)?;
+
+        let member = reset_member_feedback(&txn, member, actor_id).await?;
+
+        txn.commit().await?;
+        Ok(member)
+    }
+}
+
+/// Deletes the student's ratings and self-assessment in the project and
+/// reopens their form, the deleted feedback is kept in the audit log
+pub(crate) async fn reset_member_feedback<C: ConnectionTrait>(
+    conn: &C,
+    member: user_group_project::Model,
+    actor_id: Option<Uuid>,
+) -> Result<user_group_project::Model, ApiError> {
+    let responses = feedback_response::Entity::find()
+        .filter(feedback_response::Column::ProjectId.eq(member.project_id))
+        .filter(feedback_response::Column::FromUserId.eq(member.user_id))
+        .all(conn)
+        .await?;
+    let assessment = self_assessment::Entity::find()
+        .filter(self_assessment::Column::ProjectId.eq(member.project_id))
+        .filter(self_assessment::Column::UserId.eq(member.user_id))
+        .one(conn)
+        .await?;
+
+    feedback_response::Entity::delete_many()
+        .filter(feedback_response::Column::ProjectId.eq(member.project_id))
+        .filter(feedback_response::Column::FromUserId.eq(member.user_id))
+        .exec(conn)
+        .await?;
+
+    self_assessment::Entity::delete_many()
+        .filter(self_assessment::Column::ProjectId.eq(member.project_id))
+        .filter(self_assessment::Column::UserId.eq(member.user_id))
+        .exec(conn)
+        .await?;
+
+    NewAuditEvent::new(actor_id, AuditAction::FeedbackReset, member.user_id)
+        .project(member.project_id)
+        .before(&json!({
+            "feedback_completed_at": member.feedback_completed_at,
+            "responses": responses,
+            "self_assessment": assessment,
+        }))
+        .insert(conn)
+        .await?;
+
+    Ok(user_group_project::ActiveModel {
+        user_id: Unchanged(member.user_id),
+        group_id: Unchanged(member.group_id),
+        project_id: Unchanged(member.project_id),
+        feedback_completed: Set(false),
+        feedback_completed_at: Set(None),
+        feedback_stale: Set(false),
+        ..Default::default()
+    }
+    .update(conn)
+    .await?)
+}
@@ -0,0 +1,123 @@
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QueryTrait};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::entity::{feedback_response, group, self_assessment, user, user_group_project},
+    error::ApiError,
+};
+
+/// Everything needed to compute the grades of one group
+pub struct GroupGrading {
+    pub group: group::Model,
+    /// Members ordered by name
+    pub members: Vec<(user_group_project::Model, user::Model)>,
+    pub responses: Vec<feedback_response::Model>,
+    pub self_assessments: Vec<self_assessment::Model>,
+}
+
+impl Database {
+    /// Groups of the project with their members and peer ratings, ordered by group name
+    pub async fn get_project_grading(
+        &self,
+        project_id: Uuid,
+    ) -> Result<Vec<GroupGrading>, ApiError> {
+        load_grading(&self.conn, project_id, None).await
+    }
+
+    /// Members and peer ratings of a single group of the project
+    pub async fn get_group_grading(
+        &self,
+        project_id: Uuid,
+        group_id: Uuid,
+    ) -> Result<GroupGrading, ApiError> {
+        group_grading(&self.conn, project_id, group_id).await
+    }
+
+    /// Group of the student in the project
+    pub async fn get_student_group(
+        &self,
+        project_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<Uuid>, ApiError> {
+        Ok(user_group_project::Entity::find()
+            .filter(user_group_project::Column::ProjectId.eq(project_id))
+            .filter(user_group_project::Column::UserId.eq(user_id))
+            .one(&self.conn)
+            .await?
+            .map(|member| member.group_id))
+    }
+}
+
+/// Members and peer ratings of a single group of the project
+pub(crate) async fn group_grading<C: ConnectionTrait>(
+    conn: &C,
+    project_id: Uuid,
+    group_id: Uuid,
+) -> Result<GroupGrading, ApiError> {
+    load_grading(conn, project_id, Some(group_id))
+        .await?
+        .pop()
+        .ok_or(ApiError::NotFound)
+}
+
+async fn load_grading<C: ConnectionTrait>(
+    conn: &C,
+    project_id: Uuid,
+    group_id: Option<Uuid>,
+) -> Result<Vec<GroupGrading>, ApiError> {
+    let groups = group::Entity::find()
+        .filter(group::Column::ProjectId.eq(project_id))
+        .apply_if(group_id, |query, id| query.filter(group::Column::Id.eq(id)))
+        .order_by_asc(group::Column::Name)
+        .all(conn)
+        .await?;
+
+    let members = user_group_project::Entity::find()
+        .filter(user_group_project::Column::ProjectId.eq(project_id))
+        .apply_if(group_id, |query, id| {
+            query.filter(user_group_project::Column::GroupId.eq(id))
+        })
+        .find_also_related(user::Entity)
+        .order_by_asc(user::Column::Name)
+        .all(conn)
+        .await?;
+
+    let responses = feedback_response::Entity::find()
+        .filter(feedback_response::Column::ProjectId.eq(project_id))
+        .apply_if(group_id, |query, id| {
+            query.filter(feedback_response::Column::GroupId.eq(id))
+        })
+        .all(conn)
+        .await?;
+
+    let self_assessments = self_assessment::Entity::find()
+        .filter(self_assessment::Column::ProjectId.eq(project_id))
+        .apply_if(group_id, |query, id| {
+            query.filter(self_assessment::Column::GroupId.eq(id))
+        })
+        .all(conn)
+        .await?;
+
+    Ok(groups
+        .into_iter()
+        .map(|group| GroupGrading {
+            members: members
+                .iter()
+                .filter(|(member, _)| member.group_id == group.id)
+                .filter_map(|(member, user)| Some((member.clone(), user.clone()?)))
+                .collect(),
+            responses: responses
+                .iter()
+                .filter(|response| response.group_id == group.id)
+                .cloned()
+                .collect(),
+            self_assessments: self_assessments
+                .iter()
+                .filter(|assessment| assessment.group_id == group.id)
+                .cloned()
+                .collect(),
+            group,
+        })
+        .collect())
+}
@@ -1,11 +1,662 @@
 use super::Database;
+use crate::db::audit::NewAuditEvent;
+use crate::db::class::project_scale;
+use crate::db::enrolment::enrolled_students;
+use crate::db::entity::sea_orm_active_enums::AuditAction;
+use crate::db::entity::sea_orm_active_enums::UserRole;
+use crate::db::entity::{
+    feedback_response, grade_override, group, project, user, user_group_project,
+};
+use crate::db::feedback::reset_member_feedback;
+use crate::db::grading::group_grading;
+use crate::db::survey::lock_project;
+use crate::error::ApiError;
+use crate::grading;
+use crate::pagination::Pagination;
+use crate::scale::Scale;
+use crate::survey;
+use chrono::Utc;
+use log::debug;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, QueryTrait, TransactionTrait,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Grade a teacher sets instead of the computed one, `None` removes the override
+pub struct NewGradeOverride {
+    pub user_id: Uuid,
+    pub grade: Option<f64>,
+    pub justification: String,
+}
+
+/// A group to create with its members
+pub struct NewGroup {
+    pub name: String,
+    pub user_ids: Vec<Uuid>,
+}
+
+/// Average results of a student in earlier projects
+#[derive(Default)]
+pub struct PreviousResults {
+    /// Final grade as German grade, whatever the scale of the projects
+    pub grade: Option<f64>,
+    pub peer_factor: Option<f64>,
+}
+
+/// Outcome of moving a student to another group or removing them from their group
+#[derive(Serialize, ToSchema)]
+pub struct MembershipChange {
+    /// Membership in the new group, `null` if the student was removed
+    pub member: Option<user_group_project::Model>,
+    /// Students whose submitted feedback no longer matches their group
+    pub stale: Vec<Uuid>,
+    /// Students whose feedback was reset, they have to fill in the form again
+    pub reset: Vec<Uuid>,
+}
 
 impl Database {
-    async fn add_user_to_group(&self) {
-        todo!("Implement add_user_to_group method");
+    pub async fn get_group(&self, project_id: Uuid, id: Uuid) -> Result<group::Model, ApiError> {
+        group::Entity::find_by_id((id, project_id))
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)
+    }
+
+    /// A page of groups, sortable by `name`, only those of `project_id` if given
+    pub async fn get_groups(
+        &self,
+        project_id: Option<Uuid>,
+        pagination: &Pagination,
+    ) -> Result<(Vec<group::Model>, u64), ApiError> {
+        debug!("Fetching groups, page {}", pagination.page);
+
+        let query = group::Entity::find().apply_if(project_id, |query, project_id| {
+            query.filter(group::Column::ProjectId.eq(project_id))
+        });
+        let query =
+            pagination.sort(query, &[("name", group::Column::Name)], group::Column::Name)?;
+
+        pagination.fetch(&self.conn, query).await
+    }
+
+    /// Group by its id alone, for routes that don't name the project
+    pub async fn find_group(&self, id: Uuid) -> Result<group::Model, ApiError> {
+        group::Entity::find()
+            .filter(group::Column::Id.eq(id))
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)
+    }
+
+    /// Sets the grade of the group's work, fails once the survey is finalised
+    pub async fn set_group_grade(
+        &self,
+        id: Uuid,
+        grade: f64,
+        actor_id: Option<Uuid>,
+    ) -> Result<group::Model, ApiError> {
+        let group = self.find_group(id).await?;
+        debug!("Setting grade {} for group {}", grade, id);
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, group.project_id).await?;
+        survey::ensure_not_finalised(&project)?;
+        check_grade(&project_scale(&txn, &project).await?, grade)?;
+
+        let before = group.clone();
+        let mut active = group.into_active_model();
+        active.grade = Set(Some(grade));
+        let group = active.update(&txn).await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::GroupGradeSet, group.id)
+            .project(group.project_id)
+            .before(&before)
+            .after(&group)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(group)
+    }
+
+    /// Overrides the computed grades of group members, all or none are stored.
+    /// Every change is recorded with its justification and the grade computed at
+    /// that time. Fails once the survey is finalised.
+    pub async fn set_individual_grades(
+        &self,
+        id: Uuid,
+        overrides: Vec<NewGradeOverride>,
+        actor_id: Uuid,
+    ) -> Result<Vec<user_group_project::Model>, ApiError> {
+        let group = self.find_group(id).await?;
+        debug!("Overriding {} grades in group {}", overrides.len(), id);
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, group.project_id).await?;
+        survey::ensure_not_finalised(&project)?;
+        let scale = project_scale(&txn, &project).await?;
+
+        let grading = group_grading(&txn, project.id, group.id).await?;
+        let computed = grading::student_grades(&project, &scale, std::slice::from_ref(&grading));
+        let now = Utc::now();
+
+        let mut members = Vec::with_capacity(overrides.len());
+        for NewGradeOverride {
+            user_id,
+            grade,
+            justification,
+        } in overrides
+        {
+            if let Some(grade) = grade {
+                check_grade(&scale, grade)?;
+            }
+            if justification.trim().is_empty() {
+                return Err(ApiError::BadRequest(format!(
+                    "Overriding the grade of user {} needs a justification",
+                    user_id
+                )));
+            }
+            let member =
+                user_group_project::Entity::find_by_id((user_id, group.id, group.project_id))
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| {
+                        ApiError::BadRequest(format!(
+                            "User {} is not a member of the group",
+                            user_id
+                        ))
+                    })?;
+
+            grade_override::ActiveModel {
+                id: NotSet,
+                project_id: Set(group.project_id),
+                group_id: Set(group.id),
+                user_id: Set(user_id),
+                computed_grade: Set(computed
+                    .iter()
+                    .find(|grade| grade.user.id == user_id)
+                    .and_then(|grade| grade.computed_grade)),
+                previous_grade: Set(member.grade),
+                grade: Set(grade),
+                justification: Set(Some(justification.clone())),
+                actor_id: Set(Some(actor_id)),
+                created_at: Set(now.into()),
+            }
+            .insert(&txn)
+            .await?;
+
+            let before = json!({
+                "grade": member.grade,
+                "justification": member.grade_justification,
+            });
+            let mut active = member.into_active_model();
+            active.grade = Set(grade);
+            active.grade_justification = Set(grade.map(|_| justification.clone()));
+            members.push(active.update(&txn).await?);
+
+            NewAuditEvent::new(Some(actor_id), AuditAction::GradeOverridden, user_id)
+                .project(group.project_id)
+                .before(&before)
+                .after(&json!({ "grade": grade, "justification": justification }))
+                .insert(&txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+        Ok(members)
+    }
+
+    /// The students, who must not be in a group of the project yet. Without
+    /// `user_ids`, the students enrolled in the class of the project who aren't
+    /// in a group yet.
+    pub async fn get_ungrouped_students(
+        &self,
+        project: &project::Model,
+        user_ids: Option<&[Uuid]>,
+    ) -> Result<Vec<user::Model>, ApiError> {
+        if let Some(user_ids) = user_ids {
+            return find_ungrouped_students(&self.conn, project, user_ids).await;
+        }
+
+        let Some(class_id) = project.class_id else {
+            return Err(ApiError::BadRequest(
+                "The project has no class, the students have to be listed".to_owned(),
+            ));
+        };
+        let grouped: Vec<Uuid> = user_group_project::Entity::find()
+            .filter(user_group_project::Column::ProjectId.eq(project.id))
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .map(|member| member.user_id)
+            .collect();
+        let enrolled = enrolled_students(&self.conn, class_id, Utc::now().date_naive()).await?;
+
+        Ok(user::Entity::find()
+            .filter(user::Column::Id.is_in(enrolled))
+            .filter(user::Column::Id.is_not_in(grouped))
+            .filter(user::Column::Role.eq(UserRole::Student))
+            .order_by_asc(user::Column::Name)
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Averages of the grades and peer factors the students got in projects
+    /// other than `project_id`, students without any are left out
+    pub async fn get_previous_results(
+        &self,
+        user_ids: &[Uuid],
+        project_id: Uuid,
+    ) -> Result<HashMap<Uuid, PreviousResults>, ApiError> {
+        let project_ids: HashSet<Uuid> = user_group_project::Entity::find()
+            .filter(user_group_project::Column::UserId.is_in(user_ids.to_vec()))
+            .filter(user_group_project::Column::ProjectId.ne(project_id))
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .map(|member| member.project_id)
+            .collect();
+        let projects = project::Entity::find()
+            .filter(project::Column::Id.is_in(project_ids))
+            .all(&self.conn)
+            .await?;
+
+        let mut grades: HashMap<Uuid, Vec<f64>> = HashMap::new();
+        let mut factors: HashMap<Uuid, Vec<f64>> = HashMap::new();
+        for project in projects {
+            let scale = project_scale(&self.conn, &project).await?;
+            let groups = self.get_project_grading(project.id).await?;
+            for grade in grading::student_grades(&project, &scale, &groups)
+                .iter()
+                .filter(|grade| user_ids.contains(&grade.user.id))
+            {
+                if let Some(final_grade) = grade.final_grade() {
+                    grades
+                        .entry(grade.user.id)
+                        .or_default()
+                        .push(scale.german_grade(final_grade));
+                }
+                if let Some(factor) = grade.peer_factor {
+                    factors.entry(grade.user.id).or_default().push(factor);
+                }
+            }
+        }
+
+        let mut results: HashMap<Uuid, PreviousResults> = HashMap::new();
+        for (id, grades) in grades {
+            results.entry(id).or_default().grade = grading::mean(grades.into_iter());
+        }
+        for (id, factors) in factors {
+            results.entry(id).or_default().peer_factor = grading::mean(factors.into_iter());
+        }
+        Ok(results)
+    }
+
+    /// Creates the groups with their members, all or none. Every student may
+    /// only be in one group of the project. Fails once the survey is finalised.
+    pub async fn create_groups(
+        &self,
+        project_id: Uuid,
+        groups: Vec<NewGroup>,
+        actor_id: Option<Uuid>,
+    ) -> Result<Vec<(group::Model, Vec<user_group_project::Model>)>, ApiError> {
+        debug!("Creating {} groups in project {}", groups.len(), project_id);
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, project_id).await?;
+        survey::ensure_not_finalised(&project)?;
+
+        let mut user_ids = HashSet::new();
+        for id in groups.iter().flat_map(|group| &group.user_ids) {
+            if !user_ids.insert(*id) {
+                return Err(ApiError::BadRequest(format!(
+                    "User {} can only be in one group",
+                    id
+                )));
+            }
+        }
+        let user_ids: Vec<Uuid> = user_ids.into_iter().collect();
+        find_ungrouped_students(&txn, &project, &user_ids).await?;
+
+        let mut created = Vec::with_capacity(groups.len());
+        for NewGroup { name, user_ids } in groups {
+            let group = group::ActiveModel {
+                id: NotSet,
+                project_id: Set(project_id),
+                name: Set(name),
+                grade: NotSet,
+            }
+            .insert(&txn)
+            .await?;
+
+            let mut members = Vec::with_capacity(user_ids.len());
+            for user_id in &user_ids {
+                members.push(
+                    user_group_project::ActiveModel {
+                        user_id: Set(*user_id),
+                        group_id: Set(group.id),
+                        project_id: Set(project_id),
+                        ..Default::default()
+                    }
+                    .insert(&txn)
+                    .await?,
+                );
+            }
+
+            NewAuditEvent::new(actor_id, AuditAction::GroupCreated, group.id)
+                .project(project_id)
+                .after(&json!({ "group": group, "user_ids": user_ids }))
+                .insert(&txn)
+                .await?;
+            created.push((group, members));
+        }
+
+        txn.commit().await?;
+        Ok(created)
+    }
+
+    /// Moves a student to another group of the project, keeping their
+    /// feedback link and grade. Submitted forms that no longer match the
+    /// groups, i.e. the student's own, those of their new group mates and those
+    /// of old group mates who rated them, are marked stale or, with `reset`,
+    /// reset. Fails once the survey is finalised.
+    pub async fn move_group_member(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        to_group_id: Uuid,
+        reset: bool,
+        actor_id: Option<Uuid>,
+    ) -> Result<MembershipChange, ApiError> {
+        let group = self.find_group(id).await?;
+        debug!(
+            "Moving user {} from group {} to group {}",
+            user_id, id, to_group_id
+        );
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, group.project_id).await?;
+        survey::ensure_not_finalised(&project)?;
+        let member = find_member(&txn, &group, user_id).await?;
+        let target = group::Entity::find_by_id((to_group_id, group.project_id))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                ApiError::BadRequest(format!("Group {} is not part of the project", to_group_id))
+            })?;
+        if target.id == group.id {
+            return Err(ApiError::BadRequest(format!(
+                "User {} is already a member of the group",
+                user_id
+            )));
+        }
+
+        let mut affected = raters_of(&txn, &group, user_id).await?;
+        user_group_project::Entity::update_many()
+            .col_expr(user_group_project::Column::GroupId, Expr::value(target.id))
+            .filter(user_group_project::Column::UserId.eq(user_id))
+            .filter(user_group_project::Column::GroupId.eq(group.id))
+            .filter(user_group_project::Column::ProjectId.eq(group.project_id))
+            .exec(&txn)
+            .await?;
+        affected.extend(
+            user_group_project::Entity::find()
+                .filter(user_group_project::Column::GroupId.eq(target.id))
+                .filter(user_group_project::Column::ProjectId.eq(target.project_id))
+                .filter(user_group_project::Column::FeedbackCompleted.eq(true))
+                .all(&txn)
+                .await?,
+        );
+
+        let (stale, reset) = invalidate_forms(&txn, affected, reset, actor_id).await?;
+        let moved = user_group_project::Entity::find_by_id((user_id, target.id, target.project_id))
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        NewAuditEvent::new(actor_id, AuditAction::GroupMemberMoved, user_id)
+            .project(group.project_id)
+            .before(&member)
+            .after(&json!({ "member": moved, "stale": stale, "reset": reset }))
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(MembershipChange {
+            member: Some(moved),
+            stale,
+            reset,
+        })
+    }
+
+    /// Removes a student from their group along with the feedback they gave.
+    /// Submitted forms of group mates who rated them are marked stale or, with
+    /// `reset`, reset. Fails once the survey is finalised.
+    pub async fn remove_group_member(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        reset: bool,
+        actor_id: Option<Uuid>,
+    ) -> Result<MembershipChange, ApiError> {
+        let group = self.find_group(id).await?;
+        debug!("Removing user {} from group {}", user_id, id);
+
+        let txn = self.conn.begin().await?;
+        let project = lock_project(&txn, group.project_id).await?;
+        survey::ensure_not_finalised(&project)?;
+        let member = find_member(&txn, &group, user_id).await?;
+
+        let affected = raters_of(&txn, &group, user_id).await?;
+        if member.feedback_completed {
+            // Keeps the feedback of the student in the audit log
+            reset_member_feedback(&txn, member.clone(), actor_id).await?;
+        }
+        user_group_project::Entity::delete_by_id((user_id, group.id, group.project_id))
+            .exec(&txn)
+            .await?;
+
+        let (stale, reset) = invalidate_forms(&txn, affected, reset, actor_id).await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::GroupMemberRemoved, user_id)
+            .project(group.project_id)
+            .before(&member)
+            .after(&json!({ "stale": stale, "reset": reset }))
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(MembershipChange {
+            member: None,
+            stale,
+            reset,
+        })
+    }
+
+    /// Replaces the feedback ids of all group members, so links that were
+    /// sent before stop working. Submitted feedback is kept.
+    pub async fn regenerate_feedback_tokens(
+        &self,
+        id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<Vec<user_group_project::Model>, ApiError> {
+        let group = self.find_group(id).await?;
+        debug!("Regenerating feedback tokens of group {}", id);
+
+        let txn = self.conn.begin().await?;
+        let members = user_group_project::Entity::find()
+            .filter(user_group_project::Column::GroupId.eq(group.id))
+            .filter(user_group_project::Column::ProjectId.eq(group.project_id))
+            .all(&txn)
+            .await?;
+
+        let mut updated = Vec::with_capacity(members.len());
+        for member in members {
+            let mut active = member.into_active_model();
+            active.feedback_id = Set(Some(Uuid::new_v4()));
+            updated.push(active.update(&txn).await?);
+        }
+
+        // The tokens themselves are credentials and stay out of the log
+        let user_ids: Vec<Uuid> = updated.iter().map(|member| member.user_id).collect();
+        NewAuditEvent::new(actor_id, AuditAction::FeedbackTokensRegenerated, group.id)
+            .project(group.project_id)
+            .after(&json!({ "user_ids": user_ids }))
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(updated)
     }
 
-    async fn create_group(&self) {
-        todo!("Implement create_group method");
+    /// Changes of the grades set by teachers, oldest first
+    pub async fn get_grade_overrides(
+        &self,
+        project_id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<Vec<grade_override::Model>, ApiError> {
+        Ok(grade_override::Entity::find()
+            .filter(grade_override::Column::ProjectId.eq(project_id))
+            .apply_if(user_id, |query, id| {
+                query.filter(grade_override::Column::UserId.eq(id))
+            })
+            .order_by_asc(grade_override::Column::CreatedAt)
+            .all(&self.conn)
+            .await?)
+    }
+}
+
+/// Fails unless all users are students who aren't in a group of the project
+/// and, if the project belongs to a class, are enrolled in it today
+async fn find_ungrouped_students<C: ConnectionTrait>(
+    conn: &C,
+    project: &project::Model,
+    user_ids: &[Uuid],
+) -> Result<Vec<user::Model>, ApiError> {
+    let students = user::Entity::find()
+        .filter(user::Column::Id.is_in(user_ids.to_vec()))
+        .order_by_asc(user::Column::Name)
+        .all(conn)
+        .await?;
+    for id in user_ids {
+        match students.iter().find(|student| student.id == *id) {
+            Some(student) if student.role == UserRole::Student => {}
+            Some(_) => {
+                return Err(ApiError::BadRequest(format!(
+                    "User {} is not a student",
+                    id
+                )));
+            }
+            None => {
+                return Err(ApiError::BadRequest(format!("User {} does not exist", id)));
+            }
+        }
+    }
+
+    if let Some(class_id) = project.class_id {
+        let enrolled = enrolled_students(conn, class_id, Utc::now().date_naive()).await?;
+        if let Some(id) = user_ids.iter().find(|id| !enrolled.contains(id)) {
+            return Err(ApiError::BadRequest(format!(
+                "User {} is not enrolled in the class of the project",
+                id
+            )));
+        }
+    }
+
+    if let Some(member) = user_group_project::Entity::find()
+        .filter(user_group_project::Column::ProjectId.eq(project.id))
+        .filter(user_group_project::Column::UserId.is_in(user_ids.to_vec()))
+        .one(conn)
+        .await?
+    {
+        return Err(ApiError::BadRequest(format!(
+            "User {} is already in a group of the project",
+            member.user_id
+        )));
+    }
+
+    Ok(students)
+}
+
+async fn find_member<C: ConnectionTrait>(
+    conn: &C,
+    group: &group::Model,
+    user_id: Uuid,
+) -> Result<user_group_project::Model, ApiError> {
+    user_group_project::Entity::find_by_id((user_id, group.id, group.project_id))
+        .one(conn)
+        .await?
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("User {} is not a member of the group", user_id))
+        })
+}
+
+/// Members of the group who submitted feedback rating the student
+async fn raters_of<C: ConnectionTrait>(
+    conn: &C,
+    group: &group::Model,
+    user_id: Uuid,
+) -> Result<Vec<user_group_project::Model>, ApiError> {
+    let raters: Vec<Uuid> = feedback_response::Entity::find()
+        .select_only()
+        .column(feedback_response::Column::FromUserId)
+        .filter(feedback_response::Column::ProjectId.eq(group.project_id))
+        .filter(feedback_response::Column::GroupId.eq(group.id))
+        .filter(feedback_response::Column::ToUserId.eq(user_id))
+        .into_tuple()
+        .all(conn)
+        .await?;
+
+    Ok(user_group_project::Entity::find()
+        .filter(user_group_project::Column::ProjectId.eq(group.project_id))
+        .filter(user_group_project::Column::GroupId.eq(group.id))
+        .filter(user_group_project::Column::UserId.is_in(raters))
+        .filter(user_group_project::Column::FeedbackCompleted.eq(true))
+        .all(conn)
+        .await?)
+}
+
+/// Marks the submitted forms as stale or resets them, returns the students
+/// whose forms are stale and those whose forms were reset
+async fn invalidate_forms<C: ConnectionTrait>(
+    conn: &C,
+    forms: Vec<user_group_project::Model>,
+    reset: bool,
+    actor_id: Option<Uuid>,
+) -> Result<(Vec<Uuid>, Vec<Uuid>), ApiError> {
+    let mut stale = Vec::new();
+    let mut reopened = Vec::new();
+
+    for form in forms.into_iter().filter(|form| form.feedback_completed) {
+        if reset {
+            reopened.push(form.user_id);
+            reset_member_feedback(conn, form, actor_id).await?;
+        } else {
+            stale.push(form.user_id);
+            let mut active = form.into_active_model();
+            active.feedback_stale = Set(true);
+            active.update(conn).await?;
+        }
+    }
+
+    Ok((stale, reopened))
+}
+
+fn check_grade(scale: &Scale, grade: f64) -> Result<(), ApiError> {
+    if scale.contains(grade) {
+        Ok(())
+    } else {
+        let (best, worst) = scale.bounds();
+        Err(ApiError::BadRequest(format!(
+            "Grade {} is outside the scale of the class, from {} to {}",
+            grade, best, worst
+        )))
     }
 }
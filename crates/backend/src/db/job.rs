@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use log::debug;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set, Unchanged},
+    ColumnTrait, DbBackend, EntityTrait, QueryFilter, Statement, TryInsertResult,
+    sea_query::OnConflict,
+};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::entity::{
+        job,
+        sea_orm_active_enums::{JobKind, JobStatus},
+    },
+    error::ApiError,
+    pagination::Pagination,
+};
+
+/// Takes the oldest due job, or a running job whose worker stopped sending
+/// heartbeats. `SKIP LOCKED` lets any number of workers poll concurrently
+/// without handing out the same job twice.
+const CLAIM_JOB: &str = r#"
+UPDATE "job"
+SET "status" = 'running', "attempts" = "attempts" + 1, "locked_at" = now()
+WHERE "id" = (
+    SELECT "id" FROM "job"
+    WHERE (
+        ("status" = 'queued' AND "run_at" <= now())
+        OR ("status" = 'running' AND "locked_at" < now() - make_interval(secs => $1))
+    )
+    AND ($2::uuid IS NULL OR "id" = $2)
+    ORDER BY "run_at"
+    LIMIT 1
+    FOR UPDATE SKIP LOCKED
+)
+RETURNING *
+"#;
+
+/// A queued job that runs as soon as a worker is free
+pub(crate) fn new_job(
+    kind: JobKind,
+    payload: serde_json::Value,
+    created_by: Option<Uuid>,
+    max_attempts: i32,
+) -> job::ActiveModel {
+    job::ActiveModel {
+        id: NotSet,
+        kind: Set(kind),
+        payload: Set(payload),
+        status: Set(JobStatus::Queued),
+        attempts: Set(0),
+        max_attempts: Set(max_attempts),
+        progress_done: Set(0),
+        progress_total: Set(None),
+        result: Set(None),
+        error: Set(None),
+        created_by: Set(created_by),
+        run_at: NotSet,
+        locked_at: Set(None),
+        created_at: NotSet,
+        finished_at: Set(None),
+        dedup_key: Set(None),
+    }
+}
+
+impl Database {
+    pub async fn enqueue_job(
+        &self,
+        kind: JobKind,
+        payload: serde_json::Value,
+        created_by: Option<Uuid>,
+        max_attempts: i32,
+    ) -> Result<job::Model, ApiError> {
+        debug!("Enqueuing {:?} job", kind);
+
+        let job = new_job(kind, payload, created_by, max_attempts);
+        Ok(job.insert(&self.conn).await?)
+    }
+
+    /// Queues the job unless one of the same kind with the same payload was
+    /// queued before, so recurring work is queued once no matter how many
+    /// instances schedule it. The unique index on the payload settles
+    /// instances scheduling at the same time.
+    pub async fn enqueue_job_once(
+        &self,
+        kind: JobKind,
+        payload: serde_json::Value,
+        max_attempts: i32,
+    ) -> Result<Option<job::Model>, ApiError> {
+        let dedup_key = payload.to_string();
+        let mut job = new_job(kind, payload, None, max_attempts);
+        job.dedup_key = Set(Some(dedup_key));
+        let inserted = job::Entity::insert(job)
+            .on_conflict(
+                OnConflict::columns([job::Column::Kind, job::Column::DedupKey])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec_with_returning_many(&self.conn)
+            .await?;
+
+        // A conflict returns no row rather than an error
+        Ok(match inserted {
+            TryInsertResult::Inserted(jobs) => jobs.into_iter().next(),
+            TryInsertResult::Empty | TryInsertResult::Conflicted => None,
+        })
+    }
+
+    /// Marks the next due job as running and returns it, `only` restricts the
+    /// claim to a single job
+    pub async fn claim_job(
+        &self,
+        stale_after: Duration,
+        only: Option<Uuid>,
+    ) -> Result<Option<job::Model>, ApiError> {
+        let statement = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            CLAIM_JOB,
+            [stale_after.as_secs_f64().into(), only.into()],
+        );
+
+        Ok(job::Entity::find()
+            .from_raw_sql(statement)
+            .one(&self.conn)
+            .await?)
+    }
+
+    /// Records progress, doubles as heartbeat so long jobs are not taken as stale
+    pub async fn set_job_progress(&self, id: Uuid, done: i32, total: i32) -> Result<(), ApiError> {
+        job::ActiveModel {
+            id: Unchanged(id),
+            progress_done: Set(done),
+            progress_total: Set(Some(total)),
+            locked_at: Set(Some(Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&self.conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn complete_job(
+        &self,
+        id: Uuid,
+        result: serde_json::Value,
+    ) -> Result<job::Model, ApiError> {
+        let job = job::ActiveModel {
+            id: Unchanged(id),
+            status: Set(JobStatus::Succeeded),
+            result: Set(Some(result)),
+            error: Set(None),
+            locked_at: Set(None),
+            finished_at: Set(Some(Utc::now().into())),
+            ..Default::default()
+        };
+
+        Ok(job.update(&self.conn).await?)
+    }
+
+    /// Queues the job again at `retry_at`, or fails it for good if there is no retry
+    pub async fn fail_job(
+        &self,
+        id: Uuid,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<job::Model, ApiError> {
+        let job = match retry_at {
+            Some(retry_at) => job::ActiveModel {
+                id: Unchanged(id),
+                status: Set(JobStatus::Queued),
+                error: Set(Some(error)),
+                run_at: Set(retry_at.into()),
+                locked_at: Set(None),
+                ..Default::default()
+            },
+            None => job::ActiveModel {
+                id: Unchanged(id),
+                status: Set(JobStatus::Failed),
+                error: Set(Some(error)),
+                locked_at: Set(None),
+                finished_at: Set(Some(Utc::now().into())),
+                ..Default::default()
+            },
+        };
+
+        Ok(job.update(&self.conn).await?)
+    }
+
+    pub async fn get_job(&self, id: Uuid) -> Result<job::Model, ApiError> {
+        job::Entity::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)
+    }
+
+    /// Jobs sortable by creation time and status, optionally only those created by one user
+    pub async fn get_jobs(
+        &self,
+        created_by: Option<Uuid>,
+        status: Option<JobStatus>,
+        pagination: &Pagination,
+    ) -> Result<(Vec<job::Model>, u64), ApiError> {
+        let mut query = job::Entity::find();
+
+        if let Some(created_by) = created_by {
+            query = query.filter(job::Column::CreatedBy.eq(created_by));
+        }
+        if let Some(status) = status {
+            query = query.filter(job::Column::Status.eq(status));
+        }
+
+        let query = pagination.sort(
+            query,
+            &[
+                ("created_at", job::Column::CreatedAt),
+                ("status", job::Column::Status),
+            ],
+            job::Column::CreatedAt,
+        )?;
+
+        pagination.fetch(&self.conn, query).await
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::Utc;
+use log::debug;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set, Unchanged},
+    ColumnTrait, EntityTrait, QueryFilter, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::entity::{
+        group, mail_delivery,
+        sea_orm_active_enums::{Language, MailKind, MailStatus},
+        user, user_group_project,
+    },
+    error::ApiError,
+    pagination::Pagination,
+};
+
+/// A group member together with their personal feedback link id
+pub struct FeedbackRecipient {
+    pub user: user::Model,
+    pub group: group::Model,
+    pub feedback_id: Uuid,
+    pub feedback_completed: bool,
+}
+
+/// A mail about to be sent
+pub struct NewMailDelivery {
+    pub user_id: Uuid,
+    pub project_id: Uuid,
+    pub group_id: Option<Uuid>,
+    /// Job sending the mail, if it is sent in the background
+    pub job_id: Option<Uuid>,
+    pub kind: MailKind,
+    pub recipient: Option<String>,
+    pub language: Language,
+}
+
+impl Database {
+    /// Members of a project, or only of one of its groups. Members without a
+    /// feedback id get one generated so every recipient has a working link.
+    pub async fn get_feedback_recipients(
+        &self,
+        project_id: Uuid,
+        group_id: Option<Uuid>,
+    ) -> Result<Vec<FeedbackRecipient>, ApiError> {
+        let mut groups = group::Entity::find().filter(group::Column::ProjectId.eq(project_id));
+        if let Some(group_id) = group_id {
+            groups = groups.filter(group::Column::Id.eq(group_id));
+        }
+        let groups = groups.all(&self.conn).await?;
+
+        if group_id.is_some() && groups.is_empty() {
+            return Err(ApiError::NotFound);
+        }
+
+        let members = user_group_project::Entity::find()
+            .filter(user_group_project::Column::ProjectId.eq(project_id))
+            .filter(
+                user_group_project::Column::GroupId
+                    .is_in(groups.iter().map(|group| group.id).collect::<Vec<_>>()),
+            )
+            .find_also_related(user::Entity)
+            .all(&self.conn)
+            .await?;
+
+        let txn = self.conn.begin().await?;
+        let mut recipients = Vec::with_capacity(members.len());
+
+        for (member, user) in members {
+            let Some(user) = user else { continue };
+            let Some(group) = groups.iter().find(|group| group.id == member.group_id) else {
+                continue;
+            };
+
+            let feedback_id = match member.feedback_id {
+                Some(feedback_id) => feedback_id,
+                None => {
+                    debug!("Generating feedback id for user {}", member.user_id);
+                    let feedback_id = Uuid::new_v4();
+                    user_group_project::ActiveModel {
+                        user_id: Unchanged(member.user_id),
+                        group_id: Unchanged(member.group_id),
+                        project_id: Unchanged(member.project_id),
+                        feedback_id: Set(Some(feedback_id)),
+                        ..Default::default()
+                    }
+                    .update(&txn)
+                    .await?;
+                    feedback_id
+                }
+            };
+
+            recipients.push(FeedbackRecipient {
+                user,
+                group: group.clone(),
+                feedback_id,
+                feedback_completed: member.feedback_completed,
+            });
+        }
+
+        txn.commit().await?;
+        Ok(recipients)
+    }
+
+    /// Records a pending mail, its outcome is set with [`Database::set_mail_delivery_status`]
+    pub async fn create_mail_delivery(
+        &self,
+        delivery: NewMailDelivery,
+    ) -> Result<mail_delivery::Model, ApiError> {
+        let delivery = mail_delivery::ActiveModel {
+            id: NotSet,
+            user_id: Set(delivery.user_id),
+            project_id: Set(delivery.project_id),
+            group_id: Set(delivery.group_id),
+            job_id: Set(delivery.job_id),
+            kind: Set(delivery.kind),
+            recipient: Set(delivery.recipient),
+            language: Set(delivery.language),
+            status: Set(MailStatus::Pending),
+            error: Set(None),
+            created_at: NotSet,
+            sent_at: Set(None),
+        };
+
+        Ok(delivery.insert(&self.conn).await?)
+    }
+
+    pub async fn set_mail_delivery_status(
+        &self,
+        id: Uuid,
+        status: MailStatus,
+        error: Option<String>,
+    ) -> Result<mail_delivery::Model, ApiError> {
+        let delivery = mail_delivery::ActiveModel {
+            id: Unchanged(id),
+            status: Set(status),
+            error: Set(error),
+            sent_at: Set((status == MailStatus::Sent).then(|| Utc::now().into())),
+            ..Default::default()
+        };
+
+        Ok(delivery.update(&self.conn).await?)
+    }
+
+    /// Mails sent by a job, a retry uses them to skip recipients that already got theirs
+    pub async fn get_job_mail_deliveries(
+        &self,
+        job_id: Uuid,
+    ) -> Result<Vec<mail_delivery::Model>, ApiError> {
+        Ok(mail_delivery::Entity::find()
+            .filter(mail_delivery::Column::JobId.eq(job_id))
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Mails sent for a project, sortable by creation time, status and recipient
+    pub async fn get_mail_deliveries(
+        &self,
+        project_id: Uuid,
+        pagination: &Pagination,
+    ) -> Result<(Vec<mail_delivery::Model>, u64), ApiError> {
+        let query =
+            mail_delivery::Entity::find().filter(mail_delivery::Column::ProjectId.eq(project_id));
+
+        let query = pagination.sort(
+            query,
+            &[
+                ("created_at", mail_delivery::Column::CreatedAt),
+                ("status", mail_delivery::Column::Status),
+                ("recipient", mail_delivery::Column::Recipient),
+            ],
+            mail_delivery::Column::CreatedAt,
+        )?;
+
+        pagination.fetch(&self.conn, query).await
+    }
+}
@@ -0,0 +1,226 @@
+//! Data subject requests under the GDPR: everything stored about a user, and
+//! its erasure.
+//!
+//! Erasing a user keeps their ratings, grades and group memberships, so the
+//! peer factors and grades of their group mates don't change, but replaces
+//! their name with a pseudonym and removes contact data, logins, comments,
+//! grade justifications and the snapshots of the audit log about them.
+
+use chrono::Utc;
+use log::debug;
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, TransactionTrait,
+};
+use uuid::Uuid;
+
+use super::Database;
+use crate::db::audit::{self, NewAuditEvent};
+use crate::db::entity::{
+    api_token, feedback_response, grade_override, local_auth, mail_delivery, oidc_auth, project,
+    sea_orm_active_enums::AuditAction, self_assessment, user, user_group_project,
+};
+use crate::db::grading::GroupGrading;
+use crate::error::ApiError;
+use crate::scale::Scale;
+
+/// A project the user is a member of, with the grading of their group
+pub struct ProjectData {
+    pub project: project::Model,
+    pub scale: Scale,
+    pub grading: GroupGrading,
+}
+
+/// Everything stored about a user
+pub struct UserData {
+    pub user: user::Model,
+    pub password_login: bool,
+    pub oidc: Option<oidc_auth::Model>,
+    pub projects: Vec<ProjectData>,
+    pub feedback_given: Vec<feedback_response::Model>,
+    pub feedback_received: Vec<feedback_response::Model>,
+    pub self_assessments: Vec<self_assessment::Model>,
+    pub grade_history: Vec<grade_override::Model>,
+    pub mails: Vec<mail_delivery::Model>,
+    pub api_tokens: Vec<api_token::Model>,
+}
+
+/// Name an erased user is shown with, unique so teachers can still tell the
+/// rows of a grade export apart
+pub fn pseudonym(id: Uuid) -> (String, String) {
+    let id = id.simple().to_string();
+    (format!("erased-{id}"), format!("Pseudonym {}", &id[..8]))
+}
+
+impl Database {
+    pub async fn get_user_data(&self, id: Uuid) -> Result<UserData, ApiError> {
+        debug!("Collecting the data of user {}", id);
+
+        let user = self.get_user(id).await?.ok_or(ApiError::NotFound)?;
+        let password_login = local_auth::Entity::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .is_some();
+        let oidc = oidc_auth::Entity::find_by_id(id).one(&self.conn).await?;
+
+        let memberships = user_group_project::Entity::find()
+            .filter(user_group_project::Column::UserId.eq(id))
+            .find_also_related(project::Entity)
+            .all(&self.conn)
+            .await?;
+        let mut projects = Vec::with_capacity(memberships.len());
+        for (member, project) in memberships {
+            let Some(project) = project else { continue };
+            projects.push(ProjectData {
+                scale: self.get_project_scale(&project).await?,
+                grading: self.get_group_grading(project.id, member.group_id).await?,
+                project,
+            });
+        }
+
+        let feedback_given = feedback_response::Entity::find()
+            .filter(feedback_response::Column::FromUserId.eq(id))
+            .order_by_asc(feedback_response::Column::SubmittedAt)
+            .all(&self.conn)
+            .await?;
+        let feedback_received = feedback_response::Entity::find()
+            .filter(feedback_response::Column::ToUserId.eq(id))
+            .order_by_asc(feedback_response::Column::SubmittedAt)
+            .all(&self.conn)
+            .await?;
+        let self_assessments = self_assessment::Entity::find()
+            .filter(self_assessment::Column::UserId.eq(id))
+            .order_by_asc(self_assessment::Column::SubmittedAt)
+            .all(&self.conn)
+            .await?;
+        let grade_history = grade_override::Entity::find()
+            .filter(grade_override::Column::UserId.eq(id))
+            .order_by_asc(grade_override::Column::CreatedAt)
+            .all(&self.conn)
+            .await?;
+        let mails = mail_delivery::Entity::find()
+            .filter(mail_delivery::Column::UserId.eq(id))
+            .order_by_asc(mail_delivery::Column::CreatedAt)
+            .all(&self.conn)
+            .await?;
+        let api_tokens = api_token::Entity::find()
+            .filter(api_token::Column::UserId.eq(id))
+            .order_by_asc(api_token::Column::CreatedAt)
+            .all(&self.conn)
+            .await?;
+
+        Ok(UserData {
+            user,
+            password_login,
+            oidc,
+            projects,
+            feedback_given,
+            feedback_received,
+            self_assessments,
+            grade_history,
+            mails,
+            api_tokens,
+        })
+    }
+
+    /// Replaces the personal data of the user with a pseudonym. The user can't
+    /// log in afterwards, their ratings and grades are kept.
+    pub async fn erase_user(
+        &self,
+        id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<user::Model, ApiError> {
+        debug!("Erasing the personal data of user {}", id);
+
+        let txn = self.conn.begin().await?;
+        let user = user::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let (username, name) = pseudonym(id);
+        let deactivated_at = user.deactivated_at.unwrap_or_else(|| Utc::now().into());
+        let mut active = user.into_active_model();
+        active.username = Set(username);
+        active.name = Set(name);
+        active.email = Set(None);
+        active.deactivated_at = Set(Some(deactivated_at));
+        let user = active.update(&txn).await?;
+
+        local_auth::Entity::delete_by_id(id).exec(&txn).await?;
+        oidc_auth::Entity::delete_by_id(id).exec(&txn).await?;
+        api_token::Entity::delete_many()
+            .filter(api_token::Column::UserId.eq(id))
+            .exec(&txn)
+            .await?;
+
+        // Justifications of grade overrides may describe the user, the grades stay
+        user_group_project::Entity::update_many()
+            .col_expr(
+                user_group_project::Column::FeedbackId,
+                Expr::value(Option::<Uuid>::None),
+            )
+            .col_expr(
+                user_group_project::Column::GradeJustification,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(user_group_project::Column::UserId.eq(id))
+            .exec(&txn)
+            .await?;
+        grade_override::Entity::update_many()
+            .col_expr(
+                grade_override::Column::Justification,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(grade_override::Column::UserId.eq(id))
+            .exec(&txn)
+            .await?;
+
+        // Comments may name the user, the ratings are needed for the grades
+        feedback_response::Entity::update_many()
+            .col_expr(
+                feedback_response::Column::Comment,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(
+                Condition::any()
+                    .add(feedback_response::Column::FromUserId.eq(id))
+                    .add(feedback_response::Column::ToUserId.eq(id)),
+            )
+            .exec(&txn)
+            .await?;
+        self_assessment::Entity::update_many()
+            .col_expr(
+                self_assessment::Column::Comment,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(self_assessment::Column::UserId.eq(id))
+            .exec(&txn)
+            .await?;
+
+        mail_delivery::Entity::update_many()
+            .col_expr(
+                mail_delivery::Column::Recipient,
+                Expr::value(Option::<String>::None),
+            )
+            .col_expr(
+                mail_delivery::Column::Error,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(mail_delivery::Column::UserId.eq(id))
+            .exec(&txn)
+            .await?;
+
+        audit::clear_snapshots(&txn, id).await?;
+        audit::clear_feedback_comments(&txn, id).await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::UserErased, id)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(user)
+    }
+}
@@ -2,9 +2,22 @@ use super::Database;
 use crate::error::ApiError;
 use log::debug;
 
-use crate::db::entity::project;
+use crate::db::audit::NewAuditEvent;
+use crate::db::class::{class_scale, convert_grades, project_scale};
+use crate::db::entity::{
+    project,
+    sea_orm_active_enums::{AuditAction, Language, PeerAggregation},
+};
+use crate::db::survey::lock_project;
+use crate::db::user::escape_like;
+use crate::pagination::Pagination;
+use chrono::{DateTime, Utc};
 use sea_orm::ActiveValue::{NotSet, Set, Unchanged};
-use sea_orm::{ActiveModelTrait, DeleteResult, EntityTrait};
+use sea_orm::sea_query::{Expr, extension::postgres::PgExpr};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DeleteResult, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
 use serde::Deserialize;
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -16,14 +29,55 @@ pub struct CreateProject {
     #[validate(length(min = 3, max = 255))]
     /// Project name (minimum 3 characters and maximum 255 characters)
     pub name: String,
+    /// Class the project belongs to, grades are given in its scale.
+    /// Moving a project to a class with another scale converts its grades.
+    #[serde(default)]
+    pub class_id: Option<Uuid>,
+}
+
+/// Settings of the feedback survey of a project
+#[derive(Default)]
+pub struct SurveySettings {
+    pub opens_at: Option<DateTime<Utc>>,
+    pub deadline: Option<DateTime<Utc>>,
+    /// Days before the deadline, largest first
+    pub reminder_days: Vec<i32>,
+    pub reminders_enabled: bool,
+    pub language: Language,
+    pub self_assessment: bool,
+    pub self_assessment_in_grade: bool,
 }
 
 impl Database {
-    pub async fn get_projects(&self) -> Result<Vec<project::Model>, ApiError> {
-        debug!("Fetching all projects");
+    /// Lists a page of projects, archived ones only if `include_archived` is set
+    pub async fn get_projects(
+        &self,
+        search: Option<&str>,
+        include_archived: bool,
+        pagination: &Pagination,
+    ) -> Result<(Vec<project::Model>, u64), ApiError> {
+        debug!("Fetching projects, page {}", pagination.page);
+
+        let mut query = project::Entity::find();
+
+        if !include_archived {
+            query = query.filter(project::Column::ArchivedAt.is_null());
+        }
+
+        if let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) {
+            query = query.filter(
+                Expr::col((project::Entity, project::Column::Name))
+                    .ilike(format!("%{}%", escape_like(search))),
+            );
+        }
+
+        let query = pagination.sort(
+            query,
+            &[("name", project::Column::Name)],
+            project::Column::Name,
+        )?;
 
-        let projects = project::Entity::find().all(&self.conn).await?;
-        Ok(projects)
+        pagination.fetch(&self.conn, query).await
     }
 
     pub async fn get_project(&self, id: &Uuid) -> Result<Option<project::Model>, ApiError> {
@@ -42,15 +96,114 @@ impl Database {
     pub async fn create_project(
         &self,
         create_project: CreateProject,
+        actor_id: Option<Uuid>,
     ) -> Result<project::Model, ApiError> {
         debug!("Creating project with name: {}", create_project.name);
 
+        let txn = self.conn.begin().await?;
+        class_scale(&txn, create_project.class_id).await?;
         let project = project::ActiveModel {
             id: NotSet,
             name: Set(create_project.name),
+            class_id: Set(create_project.class_id),
+            ..Default::default()
         };
 
-        let project = project.insert(&self.conn).await?;
+        let project = project.insert(&txn).await?;
+        NewAuditEvent::new(actor_id, AuditAction::ProjectCreated, project.id)
+            .project(project.id)
+            .after(&project)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(project)
+    }
+
+    /// Creates a project with the feedback and grading settings of another
+    /// one, in the class of the original if `class_id` is not set. Groups,
+    /// students, responses and grades are not copied, neither are the feedback
+    /// period and the deadline, which belong to the original's school year.
+    pub async fn clone_project(
+        &self,
+        id: Uuid,
+        name: String,
+        class_id: Option<Uuid>,
+        actor_id: Option<Uuid>,
+    ) -> Result<project::Model, ApiError> {
+        debug!("Cloning project {} as {}", id, name);
+
+        let txn = self.conn.begin().await?;
+        let original = project::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        let class_id = class_id.or(original.class_id);
+        class_scale(&txn, class_id).await?;
+
+        let project = project::ActiveModel {
+            id: NotSet,
+            name: Set(name),
+            class_id: Set(class_id),
+            reminder_days: Set(original.reminder_days.clone()),
+            reminders_enabled: Set(original.reminders_enabled),
+            language: Set(original.language),
+            self_assessment: Set(original.self_assessment),
+            self_assessment_in_grade: Set(original.self_assessment_in_grade),
+            peer_aggregation: Set(original.peer_aggregation),
+            max_grade_deviation: Set(original.max_grade_deviation),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::ProjectCloned, project.id)
+            .project(project.id)
+            .before(&original)
+            .after(&project)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(project)
+    }
+
+    /// Archived projects are hidden from the project list but otherwise unchanged
+    pub async fn set_project_archived(
+        &self,
+        id: Uuid,
+        archived: bool,
+        actor_id: Option<Uuid>,
+    ) -> Result<project::Model, ApiError> {
+        debug!("Setting project {} archived: {}", id, archived);
+
+        let txn = self.conn.begin().await?;
+        let before = lock_project(&txn, id).await?;
+        if before.archived_at.is_some() == archived {
+            return Ok(before);
+        }
+
+        let project = project::ActiveModel {
+            id: Unchanged(id),
+            archived_at: Set(archived.then(|| Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        let action = if archived {
+            AuditAction::ProjectArchived
+        } else {
+            AuditAction::ProjectUnarchived
+        };
+        NewAuditEvent::new(actor_id, action, id)
+            .project(id)
+            .before(&before)
+            .after(&project)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
         Ok(project)
     }
 
@@ -58,32 +211,124 @@ impl Database {
         &self,
         id: &Uuid,
         project: CreateProject,
+        actor_id: Option<Uuid>,
     ) -> Result<project::Model, ApiError> {
         debug!("Updating project with id: {}", &id);
 
+        let txn = self.conn.begin().await?;
+        let current = lock_project(&txn, *id).await?;
+        if current.class_id != project.class_id {
+            let from = project_scale(&txn, &current).await?;
+            let to = class_scale(&txn, project.class_id).await?;
+            convert_grades(&txn, std::slice::from_ref(&current), &from, &to).await?;
+        }
+
         let active_model = project::ActiveModel {
             id: Unchanged(*id),
             name: Set(project.name),
+            class_id: Set(project.class_id),
+            ..Default::default()
         };
 
-        let project = active_model.update(&self.conn).await?;
+        let project = active_model.update(&txn).await?;
+        record_update(&txn, actor_id, &current, &project).await?;
 
+        txn.commit().await?;
         Ok(project)
     }
 
-    pub async fn delete_project(&self, id: &Uuid) -> Result<DeleteResult, ApiError> {
-        debug!("Deleting project with id: {}", id);
+    pub async fn update_feedback_settings(
+        &self,
+        id: Uuid,
+        settings: SurveySettings,
+        actor_id: Option<Uuid>,
+    ) -> Result<project::Model, ApiError> {
+        debug!("Updating feedback settings of project {}", id);
 
-        let project = project::Entity::delete_by_id(id.to_owned())
-            .exec(&self.conn)
-            .await?;
+        let txn = self.conn.begin().await?;
+        let current = lock_project(&txn, id).await?;
+        let project = project::ActiveModel {
+            id: Unchanged(id),
+            feedback_opens_at: Set(settings.opens_at.map(Into::into)),
+            feedback_deadline: Set(settings.deadline.map(Into::into)),
+            reminder_days: Set(settings.reminder_days),
+            reminders_enabled: Set(settings.reminders_enabled),
+            language: Set(settings.language),
+            self_assessment: Set(settings.self_assessment),
+            self_assessment_in_grade: Set(settings.self_assessment_in_grade),
+            ..Default::default()
+        };
 
-        if project.rows_affected == 0 {
-            return Err(ApiError::NotFound);
-        }
+        let project = project.update(&txn).await?;
+        record_update(&txn, actor_id, &current, &project).await?;
 
+        txn.commit().await?;
         Ok(project)
     }
+
+    pub async fn update_grading_settings(
+        &self,
+        id: Uuid,
+        peer_aggregation: PeerAggregation,
+        max_grade_deviation: Option<f64>,
+        actor_id: Option<Uuid>,
+    ) -> Result<project::Model, ApiError> {
+        debug!("Updating grading settings of project {}", id);
+
+        let txn = self.conn.begin().await?;
+        let current = lock_project(&txn, id).await?;
+        let project = project::ActiveModel {
+            id: Unchanged(id),
+            peer_aggregation: Set(peer_aggregation),
+            max_grade_deviation: Set(max_grade_deviation),
+            ..Default::default()
+        };
+
+        let project = project.update(&txn).await?;
+        record_update(&txn, actor_id, &current, &project).await?;
+
+        txn.commit().await?;
+        Ok(project)
+    }
+
+    pub async fn delete_project(
+        &self,
+        id: &Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<DeleteResult, ApiError> {
+        debug!("Deleting project with id: {}", id);
+
+        let txn = self.conn.begin().await?;
+        let current = project::Entity::find_by_id(*id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let result = project::Entity::delete_by_id(*id).exec(&txn).await?;
+        NewAuditEvent::new(actor_id, AuditAction::ProjectDeleted, *id)
+            .project(*id)
+            .before(&current)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(result)
+    }
+}
+
+async fn record_update<C: ConnectionTrait>(
+    conn: &C,
+    actor_id: Option<Uuid>,
+    before: &project::Model,
+    after: &project::Model,
+) -> Result<(), ApiError> {
+    NewAuditEvent::new(actor_id, AuditAction::ProjectUpdated, after.id)
+        .project(after.id)
+        .before(before)
+        .after(after)
+        .insert(conn)
+        .await?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -94,6 +339,7 @@ mod tests {
     async fn test_validation_create_project_struct_valid() {
         let project = CreateProject {
             name: "Test Project".to_string(),
+            class_id: None,
         };
         let validation_result = project.validate();
         assert!(validation_result.is_ok());
@@ -103,6 +349,7 @@ mod tests {
     async fn test_validation_create_project_struct_invalid_too_short() {
         let project = CreateProject {
             name: "TP".to_string(), // too short
+            class_id: None,
         };
         let validation_result = project.validate();
         assert!(validation_result.is_err());
@@ -112,6 +359,7 @@ mod tests {
     async fn test_validation_create_project_struct_empty() {
         let project = CreateProject {
             name: "".to_string(), // empty string
+            class_id: None,
         };
         let validation_result = project.validate();
         assert!(validation_result.is_err());
@@ -121,6 +369,7 @@ mod tests {
     async fn test_validation_create_project_struct_min_length() {
         let project = CreateProject {
             name: "abc".to_string(), // exactly at min length
+            class_id: None,
         };
         let validation_result = project.validate();
         assert!(validation_result.is_ok());
@@ -130,7 +379,10 @@ mod tests {
     async fn test_validation_create_project_struct_long_name() {
         // 256 characters long should be invalid because of max length
         let long_name = "a".repeat(256);
-        let project = CreateProject { name: long_name };
+        let project = CreateProject {
+            name: long_name,
+            class_id: None,
+        };
         let validation_result = project.validate();
         assert!(validation_result.is_err());
     }
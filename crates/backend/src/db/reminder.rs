@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use log::debug;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, EntityTrait, QueryFilter, QuerySelect, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::{
+        entity::{
+            job, project, reminder_run,
+            sea_orm_active_enums::{JobKind, SurveyStatus},
+        },
+        job::new_job,
+    },
+    error::ApiError,
+};
+
+impl Database {
+    /// Open projects that want reminders and whose deadline has not passed yet
+    pub async fn get_projects_awaiting_reminders(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<project::Model>, ApiError> {
+        let projects = project::Entity::find()
+            .filter(project::Column::SurveyStatus.eq(SurveyStatus::Open))
+            .filter(project::Column::RemindersEnabled.eq(true))
+            .filter(project::Column::FeedbackDeadline.gt(now))
+            .all(&self.conn)
+            .await?;
+
+        Ok(projects
+            .into_iter()
+            .filter(|project| !project.reminder_days.is_empty())
+            .collect())
+    }
+
+    /// Offsets of the reminders already scheduled for the deadline
+    pub async fn get_scheduled_reminder_days(
+        &self,
+        project_id: Uuid,
+        deadline: DateTime<Utc>,
+    ) -> Result<Vec<i32>, ApiError> {
+        Ok(reminder_run::Entity::find()
+            .select_only()
+            .column(reminder_run::Column::DaysBefore)
+            .filter(reminder_run::Column::ProjectId.eq(project_id))
+            .filter(reminder_run::Column::Deadline.eq(deadline))
+            .into_tuple()
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Records the reminders as scheduled and queues the job sending them in one
+    /// transaction. Returns `None` if another scheduler already took them.
+    pub async fn schedule_reminder(
+        &self,
+        project_id: Uuid,
+        deadline: DateTime<Utc>,
+        days_before: &[i32],
+        payload: serde_json::Value,
+        max_attempts: i32,
+    ) -> Result<Option<job::Model>, ApiError> {
+        debug!(
+            "Scheduling reminders {:?} for project {}",
+            days_before, project_id
+        );
+
+        let txn = self.conn.begin().await?;
+        let job = new_job(JobKind::SendReminders, payload, None, max_attempts)
+            .insert(&txn)
+            .await?;
+
+        let runs = days_before.iter().map(|days| reminder_run::ActiveModel {
+            id: NotSet,
+            project_id: Set(project_id),
+            deadline: Set(deadline.into()),
+            days_before: Set(*days),
+            job_id: Set(Some(job.id)),
+            created_at: NotSet,
+        });
+
+        if let Err(e) = reminder_run::Entity::insert_many(runs).exec(&txn).await {
+            return match ApiError::from(e) {
+                ApiError::Conflict(..) => Ok(None),
+                e => Err(e),
+            };
+        }
+
+        txn.commit().await?;
+        Ok(Some(job))
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{NaiveDate, Utc};
+use log::debug;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use super::Database;
+use crate::db::audit::NewAuditEvent;
+use crate::db::entity::sea_orm_active_enums::{AuditAction, UserRole};
+use crate::db::entity::{
+    audit_event, class, class_enrolment, project, school_year, user, user_group_project,
+};
+use crate::error::ApiError;
+
+/// A project whose school year is over, with its class and school year
+pub struct ExpiredProject {
+    pub project: project::Model,
+    pub class: class::Model,
+    pub school_year: school_year::Model,
+}
+
+/// Students who are only members of the given projects and not enrolled in
+/// a class now or later
+pub(crate) async fn students_only_in<C: ConnectionTrait>(
+    conn: &C,
+    project_ids: &[Uuid],
+) -> Result<Vec<user::Model>, ApiError> {
+    if project_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let members: HashSet<Uuid> = user_group_project::Entity::find()
+        .filter(user_group_project::Column::ProjectId.is_in(project_ids.to_vec()))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|member| member.user_id)
+        .collect();
+    let elsewhere: HashSet<Uuid> = user_group_project::Entity::find()
+        .filter(user_group_project::Column::UserId.is_in(members.clone()))
+        .filter(user_group_project::Column::ProjectId.is_not_in(project_ids.to_vec()))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|member| member.user_id)
+        .collect();
+    let today = Utc::now().date_naive();
+    let enrolled: HashSet<Uuid> = class_enrolment::Entity::find()
+        .filter(class_enrolment::Column::UserId.is_in(members.clone()))
+        .filter(
+            Condition::any()
+                .add(class_enrolment::Column::EndsOn.is_null())
+                .add(class_enrolment::Column::EndsOn.gte(today)),
+        )
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|enrolment| enrolment.user_id)
+        .collect();
+
+    Ok(user::Entity::find()
+        .filter(
+            user::Column::Id.is_in(
+                members
+                    .iter()
+                    .filter(|id| !elsewhere.contains(id) && !enrolled.contains(id))
+                    .copied(),
+            ),
+        )
+        .filter(user::Column::Role.eq(UserRole::Student))
+        .order_by_asc(user::Column::Name)
+        .all(conn)
+        .await?)
+}
+
+impl Database {
+    /// Projects of classes whose school year ended on or before `ended_by`
+    pub async fn get_expired_projects(
+        &self,
+        ended_by: NaiveDate,
+    ) -> Result<Vec<ExpiredProject>, ApiError> {
+        let school_years = school_year::Entity::find()
+            .filter(school_year::Column::EndsOn.lte(ended_by))
+            .all(&self.conn)
+            .await?;
+        let classes = class::Entity::find()
+            .filter(class::Column::SchoolYearId.is_in(school_years.iter().map(|year| year.id)))
+            .all(&self.conn)
+            .await?;
+        let projects = project::Entity::find()
+            .filter(project::Column::ClassId.is_in(classes.iter().map(|class| class.id)))
+            .order_by_asc(project::Column::Name)
+            .all(&self.conn)
+            .await?;
+
+        Ok(projects
+            .into_iter()
+            .filter_map(|project| {
+                let class = classes
+                    .iter()
+                    .find(|class| Some(class.id) == project.class_id)?;
+                let school_year = school_years
+                    .iter()
+                    .find(|year| Some(year.id) == class.school_year_id)?;
+                Some(ExpiredProject {
+                    class: class.clone(),
+                    school_year: school_year.clone(),
+                    project,
+                })
+            })
+            .collect())
+    }
+
+    /// Students who would be deleted along with the projects
+    pub async fn get_students_only_in(
+        &self,
+        project_ids: &[Uuid],
+    ) -> Result<Vec<user::Model>, ApiError> {
+        students_only_in(&self.conn, project_ids).await
+    }
+
+    /// Archives the projects that aren't archived yet, returns how many were
+    pub async fn archive_projects(&self, projects: &[project::Model]) -> Result<usize, ApiError> {
+        let txn = self.conn.begin().await?;
+        let mut archived = 0;
+
+        for project in projects
+            .iter()
+            .filter(|project| project.archived_at.is_none())
+        {
+            debug!("Archiving project {}", project.id);
+            project::Entity::update_many()
+                .col_expr(project::Column::ArchivedAt, Expr::value(Utc::now()))
+                .filter(project::Column::Id.eq(project.id))
+                .filter(project::Column::ArchivedAt.is_null())
+                .exec(&txn)
+                .await?;
+
+            NewAuditEvent::new(None, AuditAction::ProjectArchived, project.id)
+                .project(project.id)
+                .insert(&txn)
+                .await?;
+            archived += 1;
+        }
+
+        txn.commit().await?;
+        Ok(archived)
+    }
+
+    /// Deletes the project with its groups, feedback and grades, and the
+    /// students who aren't in any other project. The audit log of the project
+    /// goes as well, only the purge itself is recorded. Returns the number of
+    /// deleted students.
+    pub async fn purge_project(&self, project: &project::Model) -> Result<usize, ApiError> {
+        debug!("Purging project {}", project.id);
+
+        let txn = self.conn.begin().await?;
+        let students = students_only_in(&txn, &[project.id]).await?;
+        let student_ids: Vec<Uuid> = students.iter().map(|student| student.id).collect();
+
+        audit_event::Entity::delete_many()
+            .filter(audit_event::Column::ProjectId.eq(project.id))
+            .exec(&txn)
+            .await?;
+        project::Entity::delete_by_id(project.id).exec(&txn).await?;
+
+        if !student_ids.is_empty() {
+            audit_event::Entity::update_many()
+                .col_expr(
+                    audit_event::Column::Before,
+                    Expr::value(Option::<serde_json::Value>::None),
+                )
+                .col_expr(
+                    audit_event::Column::After,
+                    Expr::value(Option::<serde_json::Value>::None),
+                )
+                .filter(audit_event::Column::EntityId.is_in(student_ids.clone()))
+                .exec(&txn)
+                .await?;
+            user::Entity::delete_many()
+                .filter(user::Column::Id.is_in(student_ids.clone()))
+                .exec(&txn)
+                .await?;
+        }
+
+        NewAuditEvent::new(None, AuditAction::ProjectPurged, project.id)
+            .before(project)
+            .insert(&txn)
+            .await?;
+        for id in &student_ids {
+            NewAuditEvent::new(None, AuditAction::UserDeleted, *id)
+                .insert(&txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+        Ok(student_ids.len())
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::NaiveDate;
+use log::debug;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, EntityTrait, IntoActiveModel, TransactionTrait};
+use uuid::Uuid;
+
+use super::Database;
+use crate::db::audit::NewAuditEvent;
+use crate::db::entity::school_year;
+use crate::db::entity::sea_orm_active_enums::AuditAction;
+use crate::error::ApiError;
+use crate::pagination::Pagination;
+
+/// Fails if a class is assigned to a school year that doesn't exist
+pub(crate) async fn ensure_school_year<C: ConnectionTrait>(
+    conn: &C,
+    id: Option<Uuid>,
+) -> Result<(), ApiError> {
+    let Some(id) = id else {
+        return Ok(());
+    };
+
+    school_year::Entity::find_by_id(id)
+        .one(conn)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| ApiError::BadRequest(format!("School year {} not found", id)))
+}
+
+impl Database {
+    pub async fn get_school_years(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<(Vec<school_year::Model>, u64), ApiError> {
+        debug!("Fetching school years, page {}", pagination.page);
+
+        let query = pagination.sort(
+            school_year::Entity::find(),
+            &[
+                ("starts_on", school_year::Column::StartsOn),
+                ("name", school_year::Column::Name),
+            ],
+            school_year::Column::StartsOn,
+        )?;
+
+        pagination.fetch(&self.conn, query).await
+    }
+
+    pub async fn get_school_year(&self, id: Uuid) -> Result<school_year::Model, ApiError> {
+        school_year::Entity::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .ok_or(ApiError::NotFound)
+    }
+
+    pub async fn create_school_year(
+        &self,
+        name: String,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        actor_id: Option<Uuid>,
+    ) -> Result<school_year::Model, ApiError> {
+        debug!("Creating school year {}", name);
+
+        let txn = self.conn.begin().await?;
+        let school_year = school_year::ActiveModel {
+            id: NotSet,
+            name: Set(name),
+            starts_on: Set(starts_on),
+            ends_on: Set(ends_on),
+        }
+        .insert(&txn)
+        .await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::SchoolYearCreated, school_year.id)
+            .after(&school_year)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(school_year)
+    }
+
+    /// Changing the end moves the retention deadlines of the projects of its classes
+    pub async fn update_school_year(
+        &self,
+        id: Uuid,
+        name: String,
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        actor_id: Option<Uuid>,
+    ) -> Result<school_year::Model, ApiError> {
+        debug!("Updating school year {}", id);
+
+        let txn = self.conn.begin().await?;
+        let before = school_year::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let mut active = before.clone().into_active_model();
+        active.name = Set(name);
+        active.starts_on = Set(starts_on);
+        active.ends_on = Set(ends_on);
+        let school_year = active.update(&txn).await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::SchoolYearUpdated, id)
+            .before(&before)
+            .after(&school_year)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(school_year)
+    }
+
+    /// Deletes the school year, its classes are kept without school year and so
+    /// are no longer subject to the retention policy
+    pub async fn delete_school_year(
+        &self,
+        id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<(), ApiError> {
+        debug!("Deleting school year {}", id);
+
+        let txn = self.conn.begin().await?;
+        let school_year = school_year::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        school_year::Entity::delete_by_id(id).exec(&txn).await?;
+        NewAuditEvent::new(actor_id, AuditAction::SchoolYearDeleted, id)
+            .before(&school_year)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(())
+    }
+}
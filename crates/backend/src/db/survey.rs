@@ -0,0 +1,103 @@
+use chrono::Utc;
+use log::{debug, info};
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::audit::NewAuditEvent,
+    db::entity::{
+        project,
+        sea_orm_active_enums::{AuditAction, SurveyStatus},
+        survey_event,
+    },
+    error::ApiError,
+    survey,
+};
+
+/// Reads the project and holds a share lock on it until the transaction ends,
+/// so its survey can't be finalised while grades or responses are written
+pub(crate) async fn lock_project<C: ConnectionTrait>(
+    conn: &C,
+    id: Uuid,
+) -> Result<project::Model, ApiError> {
+    project::Entity::find_by_id(id)
+        .lock_shared()
+        .one(conn)
+        .await?
+        .ok_or(ApiError::NotFound)
+}
+
+impl Database {
+    /// Moves the survey of the project to `to` and records who did it and why
+    pub async fn set_survey_status(
+        &self,
+        project_id: Uuid,
+        to: SurveyStatus,
+        actor_id: Uuid,
+        reason: Option<String>,
+    ) -> Result<project::Model, ApiError> {
+        debug!("Changing survey of project {} to {:?}", project_id, to);
+
+        let txn = self.conn.begin().await?;
+        let project = project::Entity::find_by_id(project_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let from = project.survey_status;
+        survey::check_transition(from, to, reason.as_deref())?;
+
+        let mut active = project.into_active_model();
+        active.survey_status = Set(to);
+        let project = active.update(&txn).await?;
+
+        NewAuditEvent::new(Some(actor_id), AuditAction::SurveyStatusChanged, project_id)
+            .project(project_id)
+            .before(&json!({ "survey_status": from }))
+            .after(&json!({ "survey_status": to, "reason": reason }))
+            .insert(&txn)
+            .await?;
+
+        survey_event::ActiveModel {
+            id: NotSet,
+            project_id: Set(project_id),
+            actor_id: Set(Some(actor_id)),
+            from_status: Set(from),
+            to_status: Set(to),
+            reason: Set(reason),
+            created_at: Set(Utc::now().into()),
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        if from == SurveyStatus::Finalised {
+            info!(
+                "Survey of project {} was reopened by user {}",
+                project_id, actor_id
+            );
+        }
+        Ok(project)
+    }
+
+    /// Status changes of the survey, oldest first
+    pub async fn get_survey_events(
+        &self,
+        project_id: Uuid,
+    ) -> Result<Vec<survey_event::Model>, ApiError> {
+        Ok(survey_event::Entity::find()
+            .filter(survey_event::Column::ProjectId.eq(project_id))
+            .order_by_asc(survey_event::Column::CreatedAt)
+            .all(&self.conn)
+            .await?)
+    }
+}
@@ -3,20 +3,68 @@ use argon2::{
     Argon2, PasswordHash, PasswordVerifier,
     password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
 };
+use chrono::Utc;
+use log::debug;
 use sea_orm::{
     ActiveModelTrait,
-    ActiveValue::{NotSet, Set},
-    ColumnTrait, DbErr, DeleteResult, EntityTrait, ModelTrait, QueryFilter, TransactionTrait,
+    ActiveValue::{NotSet, Set, Unchanged},
+    ColumnTrait, Condition, DbErr, DeleteResult, EntityTrait, ModelTrait, QueryFilter,
+    TransactionTrait,
+    sea_query::{Expr, extension::postgres::PgExpr},
 };
 use uuid::Uuid;
 
-use crate::{Database, db::entity};
+use crate::{
+    Database,
+    db::audit::{self, NewAuditEvent},
+    db::entity::{
+        self,
+        sea_orm_active_enums::{AuditAction, UserRole},
+    },
+    oidc::OidcIdentity,
+    pagination::Pagination,
+};
 
 impl Database {
-    pub async fn get_users(&self) -> Result<Vec<entity::user::Model>, ApiError> {
-        let users = entity::user::Entity::find().all(&self.conn).await?;
+    /// Lists a page of users, optionally filtered by a case-insensitive search on name and username
+    pub async fn get_users(
+        &self,
+        search: Option<&str>,
+        include_deactivated: bool,
+        pagination: &Pagination,
+    ) -> Result<(Vec<entity::user::Model>, u64), ApiError> {
+        let mut query = entity::user::Entity::find();
+
+        if let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) {
+            let pattern = format!("%{}%", escape_like(search));
+            query = query.filter(
+                Condition::any()
+                    .add(
+                        Expr::col((entity::user::Entity, entity::user::Column::Name))
+                            .ilike(pattern.clone()),
+                    )
+                    .add(
+                        Expr::col((entity::user::Entity, entity::user::Column::Username))
+                            .ilike(pattern),
+                    ),
+            );
+        }
+
+        if !include_deactivated {
+            query = query.filter(entity::user::Column::DeactivatedAt.is_null());
+        }
 
-        Ok(users)
+        let query = pagination.sort(
+            query,
+            &[
+                ("username", entity::user::Column::Username),
+                ("name", entity::user::Column::Name),
+                ("role", entity::user::Column::Role),
+            ],
+            entity::user::Column::Username,
+        )?;
+
+        pagination.fetch(&self.conn, query).await
     }
 
     pub async fn get_user(&self, id: Uuid) -> Result<Option<entity::user::Model>, ApiError> {
@@ -32,19 +80,18 @@ impl Database {
         Ok(user)
     }
 
+    /// Creates a user, with a local login if a password is given
     pub async fn create_user(
         &self,
         name: String,
         username: String,
-        password: String,
+        email: Option<String>,
+        password: Option<String>,
+        role: UserRole,
+        actor_id: Option<Uuid>,
     ) -> Result<entity::user::Model, ApiError> {
-        let argon2 = Argon2::default();
-
-        let salt = SaltString::generate(&mut OsRng);
-        let hash = argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|err| ApiError::Argon2Error(err.to_string()))?
-            .to_string();
+        let conflict_username = username.clone();
+        let hash = password.as_deref().map(hash_password).transpose()?;
 
         let user = self
             .conn
@@ -54,24 +101,119 @@ impl Database {
                         id: NotSet,
                         name: Set(name),
                         username: Set(username),
+                        email: Set(email),
+                        role: Set(role),
+                        deactivated_at: NotSet,
                     };
 
                     let user: entity::user::Model = user.insert(txn).await?;
 
-                    let local_auth = entity::local_auth::ActiveModel {
-                        id: Set(user.id),
-                        hash: Set(hash),
-                        password_change_required: NotSet,
-                    };
+                    if let Some(hash) = hash {
+                        let local_auth = entity::local_auth::ActiveModel {
+                            id: Set(user.id),
+                            hash: Set(hash),
+                            password_change_required: NotSet,
+                        };
+
+                        local_auth.insert(txn).await?;
+                    }
 
-                    local_auth.insert(txn).await?;
+                    NewAuditEvent::new(actor_id, AuditAction::UserCreated, user.id)
+                        .after(&user)
+                        .insert(txn)
+                        .await?;
                     Ok(user)
                 })
             })
+            .await
+            .map_err(|e| username_conflict(e.into(), &conflict_username))?;
+        Ok(user)
+    }
+
+    /// Updates the given fields of a user, `None` leaves a field unchanged
+    pub async fn update_user(
+        &self,
+        id: Uuid,
+        name: Option<String>,
+        username: Option<String>,
+        email: Option<String>,
+        role: Option<UserRole>,
+        actor_id: Option<Uuid>,
+    ) -> Result<entity::user::Model, ApiError> {
+        debug!("Updating user {}", id);
+
+        let conflict_username = username.clone().unwrap_or_default();
+
+        let txn = self.conn.begin().await?;
+        let before = entity::user::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let user = entity::user::ActiveModel {
+            id: Unchanged(id),
+            name: name.map_or(NotSet, Set),
+            username: username.map_or(NotSet, Set),
+            email: email.map_or(NotSet, |email| Set(Some(email))),
+            role: role.map_or(NotSet, Set),
+            deactivated_at: NotSet,
+        };
+
+        if !user.is_changed() {
+            return Ok(before);
+        }
+
+        let user = user
+            .update(&txn)
+            .await
+            .map_err(|e| username_conflict(e.into(), &conflict_username))?;
+
+        NewAuditEvent::new(actor_id, AuditAction::UserUpdated, id)
+            .before(&before)
+            .after(&user)
+            .insert(&txn)
             .await?;
+
+        txn.commit().await?;
         Ok(user)
     }
 
+    /// Deactivated users keep their data and grading history but can no longer log in
+    pub async fn set_user_deactivated(
+        &self,
+        id: Uuid,
+        deactivated: bool,
+        actor_id: Option<Uuid>,
+    ) -> Result<entity::user::Model, ApiError> {
+        debug!("Setting user {} deactivated: {}", id, deactivated);
+
+        let before = self.get_user(id).await?.unwrap();
+        if before.deactivated_at.is_some() == deactivated {
+            return Ok(before);
+        }
+
+        let txn = self.conn.begin().await?;
+        let user = entity::user::ActiveModel {
+            id: Unchanged(id),
+            deactivated_at: Set(deactivated.then(|| Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        NewAuditEvent::new(actor_id, AuditAction::UserUpdated, id)
+            .before(&before)
+            .after(&user)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(user)
+    }
+
+    /// Checks the password of a user and returns their id. Fails with
+    /// `Unauthorized` for unknown and deactivated users and for users without
+    /// password.
     pub async fn verify_local_user(
         &self,
         username: &str,
@@ -83,11 +225,21 @@ impl Database {
             .await?
             .ok_or(ApiError::Unauthorized)?;
 
-        let local_auth = user
+        if user.deactivated_at.is_some() {
+            debug!("Rejecting login of deactivated user {}", user.id);
+            return Err(ApiError::Unauthorized);
+        }
+
+        // Students usually have no password and only use their feedback link,
+        // just like users who log in through single sign-on
+        let Some(local_auth) = user
             .find_related(entity::local_auth::Entity)
             .one(&self.conn)
             .await?
-            .ok_or(ApiError::Unauthorized)?;
+        else {
+            debug!("Rejecting login of user {} without password", user.id);
+            return Err(ApiError::Unauthorized);
+        };
 
         let argon2 = Argon2::default();
 
@@ -104,19 +256,145 @@ impl Database {
         Ok(user.id)
     }
 
-    pub async fn delete_user(&self, id: Uuid) -> Result<DeleteResult, ApiError> {
-        let user = entity::user::Entity::delete_by_id(id)
-            .exec(&self.conn)
-            .await?;
+    /// Finds the user linked to an OIDC identity, creating the user on first login.
+    /// Name and role are refreshed from the identity provider on every login.
+    pub async fn upsert_oidc_user(
+        &self,
+        identity: OidcIdentity,
+    ) -> Result<entity::user::Model, ApiError> {
+        let username = identity.username.clone();
 
-        if user.rows_affected == 0 {
-            return Err(ApiError::NotFound);
+        let user = self
+            .conn
+            .transaction::<_, entity::user::Model, DbErr>(|txn| {
+                Box::pin(async move {
+                    let oidc_auth = entity::oidc_auth::Entity::find()
+                        .filter(entity::oidc_auth::Column::Issuer.eq(&identity.issuer))
+                        .filter(entity::oidc_auth::Column::Subject.eq(&identity.subject))
+                        .one(txn)
+                        .await?;
+
+                    if let Some(oidc_auth) = oidc_auth {
+                        let user = entity::user::ActiveModel {
+                            id: Unchanged(oidc_auth.id),
+                            name: Set(identity.name),
+                            role: Set(identity.role),
+                            ..Default::default()
+                        };
+
+                        return user.update(txn).await;
+                    }
+
+                    let user = entity::user::ActiveModel {
+                        id: NotSet,
+                        name: Set(identity.name),
+                        username: Set(identity.username),
+                        email: NotSet,
+                        role: Set(identity.role),
+                        deactivated_at: NotSet,
+                    };
+
+                    let user: entity::user::Model = user.insert(txn).await?;
+
+                    let oidc_auth = entity::oidc_auth::ActiveModel {
+                        id: Set(user.id),
+                        issuer: Set(identity.issuer),
+                        subject: Set(identity.subject),
+                    };
+
+                    oidc_auth.insert(txn).await?;
+
+                    // Created on the first login, not by a teacher or admin
+                    NewAuditEvent::new(None, AuditAction::UserCreated, user.id)
+                        .after(&user)
+                        .insert(txn)
+                        .await?;
+                    Ok(user)
+                })
+            })
+            .await
+            .map_err(|e| username_conflict(e.into(), &username))?;
+
+        if user.deactivated_at.is_some() {
+            debug!("Rejecting OIDC login of deactivated user {}", user.id);
+            return Err(ApiError::Unauthorized);
         }
 
         Ok(user)
     }
 
+    /// Deletes the user, the audit log keeps only their id
+    pub async fn delete_user(
+        &self,
+        id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<DeleteResult, ApiError> {
+        let txn = self.conn.begin().await?;
+        entity::user::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        let result = entity::user::Entity::delete_by_id(id).exec(&txn).await?;
+
+        audit::clear_snapshots(&txn, id).await?;
+        NewAuditEvent::new(actor_id, AuditAction::UserDeleted, id)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(result)
+    }
+
+    // TODO: Implement LDAP login
+    #[allow(dead_code)]
     pub async fn verify_ldap_user() {}
 
+    // TODO: Implement password change
+    #[allow(dead_code)]
     pub async fn change_user_password() {}
 }
+
+fn hash_password(password: &str) -> Result<String, ApiError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|err| ApiError::Argon2Error(err.to_string()))?
+        .to_string();
+
+    Ok(hash)
+}
+
+/// Escapes the wildcards of a `LIKE` pattern so user input matches literally,
+/// with the backslash Postgres uses as escape character by default
+pub(crate) fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Reports a duplicate username with the name that was requested
+fn username_conflict(error: ApiError, username: &str) -> ApiError {
+    match error {
+        ApiError::Conflict(constraint) if constraint == "user_username_key" => {
+            ApiError::UserAlreadyExists(username.to_owned())
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_like_escapes_wildcards() {
+        assert_eq!(escape_like("max"), "max");
+        assert_eq!(escape_like("100%_\\"), "100\\%\\_\\\\");
+    }
+}
@@ -1,21 +1,32 @@
 use actix_web::{HttpResponse, ResponseError, http::StatusCode};
-use sea_orm::TransactionError;
+use log::{debug, error, warn};
+use sea_orm::{DbErr, RuntimeErr, SqlErr, TransactionError};
 use serde::Serialize;
-use std::fmt::{Display, Formatter};
+use std::collections::BTreeMap;
 use thiserror::Error;
 use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Media type of RFC 7807 error responses
+pub const PROBLEM_JSON: &str = "application/problem+json";
 
 #[derive(Error, Debug)]
 pub enum ApiError {
     // Database errors
     #[error("Database Error: {0}")]
-    Database(#[from] sea_orm::DbErr),
+    Database(sea_orm::DbErr),
+    #[error("Conflict: {}", describe_constraint(.0))]
+    Conflict(String), // Violated unique constraint
+    #[error("Unprocessable Entity: {}", describe_constraint(.0))]
+    UnprocessableEntity(String), // Violated foreign key, check or not null constraint
 
     // Generic HTTP errors
     #[error("Bad Request: {0}")]
     BadRequest(String), // 400 Bad Request
     #[error("Unauthorized")]
     Unauthorized, // 401 Unauthorized
+    #[error("Forbidden")]
+    Forbidden, // 403 Forbidden
     #[error("Not Found")]
     NotFound, // 404 Not Found
     #[error("Internal Server Error for endpoint: {0}")]
@@ -26,6 +37,8 @@ pub enum ApiError {
     AlreadyLoggedIn,
     #[error("Session insert error: {0}")]
     SessionInsertError(#[from] actix_session::SessionInsertError),
+    #[error("Session get error: {0}")]
+    SessionGetError(#[from] actix_session::SessionGetError),
 
     // Validation errors
     #[error("Validation Error: {0}")]
@@ -38,22 +51,38 @@ pub enum ApiError {
     // User errors
     #[error("User with username - {0} - already exists")]
     UserAlreadyExists(String),
+
+    // OIDC errors
+    #[error("OIDC Error: {0}")]
+    OidcError(String),
+
+    // Survey errors
+    #[error("Survey not open: {0}")]
+    SurveyNotOpen(String),
+    #[error("The survey is finalised, reopen it to make changes")]
+    SurveyLocked,
+    #[error("The feedback was already submitted")]
+    FeedbackAlreadySubmitted,
 }
 impl ResponseError for ApiError {
     fn status_code(&self) -> StatusCode {
         match self {
             // Database errors
             ApiError::Database(..) => StatusCode::INTERNAL_SERVER_ERROR, // 500 Internal Server Error
+            ApiError::Conflict(..) => StatusCode::CONFLICT,              // 409 Conflict
+            ApiError::UnprocessableEntity(..) => StatusCode::UNPROCESSABLE_ENTITY, // 422 Unprocessable Entity
 
             // Generic HTTP errors
             ApiError::BadRequest(..) => StatusCode::BAD_REQUEST, // 400 Bad Request
             ApiError::Unauthorized => StatusCode::UNAUTHORIZED,  // 401 Unauthorized
+            ApiError::Forbidden => StatusCode::FORBIDDEN,        // 403 Forbidden
             ApiError::NotFound => StatusCode::NOT_FOUND,         // 404 Not Found
             ApiError::InternalServerError(..) => StatusCode::INTERNAL_SERVER_ERROR, // 500 Internal Server Error
 
             // Session errors
             ApiError::AlreadyLoggedIn => StatusCode::CONFLICT, // 409 Conflict
             ApiError::SessionInsertError(..) => StatusCode::INTERNAL_SERVER_ERROR, // 500 Internal Server Error
+            ApiError::SessionGetError(..) => StatusCode::INTERNAL_SERVER_ERROR, // 500 Internal Server Error
 
             // Validation errors
             ApiError::ValidationError(..) => StatusCode::BAD_REQUEST, // 400 Bad Request
@@ -63,20 +92,214 @@ impl ResponseError for ApiError {
 
             // User errors
             ApiError::UserAlreadyExists(..) => StatusCode::CONFLICT, // 409 Conflict
+
+            // OIDC errors
+            ApiError::OidcError(..) => StatusCode::BAD_GATEWAY, // 502 Bad Gateway
+
+            // Survey errors
+            ApiError::SurveyNotOpen(..) => StatusCode::CONFLICT, // 409 Conflict
+            ApiError::SurveyLocked => StatusCode::CONFLICT,      // 409 Conflict
+            ApiError::FeedbackAlreadySubmitted => StatusCode::CONFLICT, // 409 Conflict
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).body(self.to_string())
+        let status = self.status_code();
+        let correlation_id = Uuid::new_v4();
+
+        // The internal cause is only logged, never sent to the client
+        if status.is_server_error() {
+            error!("[{}] {}: {:?}", correlation_id, self, self);
+        } else {
+            warn!("[{}] {}", correlation_id, self);
+        }
+
+        let problem = ProblemDetails {
+            problem_type: format!("urn:pgg:problem:{}", self.code()),
+            title: status.canonical_reason().unwrap_or("Error").to_owned(),
+            status: status.as_u16(),
+            detail: self.public_detail(),
+            code: self.code().to_owned(),
+            correlation_id,
+            errors: self.field_errors(),
+        };
+
+        HttpResponse::build(status)
+            .content_type(PROBLEM_JSON)
+            .insert_header(("X-Correlation-Id", correlation_id.to_string()))
+            .json(problem)
+    }
+}
+
+impl ApiError {
+    /// Stable machine-readable error code, safe for clients to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Database(..) => "database_error",
+            ApiError::Conflict(..) => "conflict",
+            ApiError::UnprocessableEntity(..) => "unprocessable_entity",
+            ApiError::BadRequest(..) => "bad_request",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Forbidden => "forbidden",
+            ApiError::NotFound => "not_found",
+            ApiError::InternalServerError(..) => "internal_error",
+            ApiError::AlreadyLoggedIn => "already_logged_in",
+            ApiError::SessionInsertError(..) | ApiError::SessionGetError(..) => "session_error",
+            ApiError::ValidationError(..) => "validation_error",
+            ApiError::Argon2Error(..) => "password_hash_error",
+            ApiError::UserAlreadyExists(..) => "user_already_exists",
+            ApiError::OidcError(..) => "identity_provider_error",
+            ApiError::SurveyNotOpen(..) => "survey_not_open",
+            ApiError::SurveyLocked => "survey_locked",
+            ApiError::FeedbackAlreadySubmitted => "feedback_already_submitted",
+        }
+    }
+
+    /// Human readable detail, `None` for errors whose message would leak internals
+    pub(crate) fn public_detail(&self) -> Option<String> {
+        match self {
+            ApiError::BadRequest(message) | ApiError::SurveyNotOpen(message) => {
+                Some(message.clone())
+            }
+            ApiError::Conflict(constraint) | ApiError::UnprocessableEntity(constraint) => {
+                Some(describe_constraint(constraint).to_owned())
+            }
+            ApiError::ValidationError(..) => Some("Request validation failed".to_owned()),
+            ApiError::AlreadyLoggedIn
+            | ApiError::UserAlreadyExists(..)
+            | ApiError::SurveyLocked => Some(self.to_string()),
+            ApiError::OidcError(..) => {
+                Some("The identity provider could not complete the login".to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    fn field_errors(&self) -> Option<BTreeMap<String, Vec<FieldError>>> {
+        let ApiError::ValidationError(errors) = self else {
+            return None;
+        };
+
+        let fields = errors
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let errors = errors
+                    .iter()
+                    .map(|error| FieldError {
+                        code: error.code.to_string(),
+                        message: error.message.as_ref().map(ToString::to_string),
+                    })
+                    .collect();
+                (field.to_string(), errors)
+            })
+            .collect();
+
+        Some(fields)
+    }
+}
+
+/// RFC 7807 problem details returned for every error
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDetails {
+    /// URI identifying the problem type
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Short summary of the HTTP status
+    pub title: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Explanation specific to this occurrence, omitted for internal errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Stable machine-readable error code
+    pub code: String,
+    /// Identifier to find this error in the server logs
+    pub correlation_id: Uuid,
+    /// Validation errors per request field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BTreeMap<String, Vec<FieldError>>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FieldError {
+    /// Validation rule that failed, e.g. `length`
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Translates constraint violations into client errors, everything else stays a database error
+impl From<DbErr> for ApiError {
+    fn from(value: DbErr) -> Self {
+        let constraint = constraint_name(&value).unwrap_or_default();
+
+        match value.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(message)) => {
+                debug!("Unique constraint violated: {}", message);
+                return ApiError::Conflict(constraint);
+            }
+            Some(SqlErr::ForeignKeyConstraintViolation(message)) => {
+                debug!("Foreign key constraint violated: {}", message);
+                return ApiError::UnprocessableEntity(constraint);
+            }
+            _ => {}
+        }
+
+        match sqlstate(&value).as_deref() {
+            // check_violation, not_null_violation
+            Some("23514") | Some("23502") => {
+                debug!("Constraint violated: {}", value);
+                ApiError::UnprocessableEntity(constraint)
+            }
+            _ => ApiError::Database(value),
+        }
+    }
+}
+
+fn database_error(err: &DbErr) -> Option<&(dyn sea_orm::sqlx::error::DatabaseError + 'static)> {
+    match err {
+        DbErr::Exec(RuntimeErr::SqlxError(sea_orm::sqlx::Error::Database(e)))
+        | DbErr::Query(RuntimeErr::SqlxError(sea_orm::sqlx::Error::Database(e))) => Some(&**e),
+        _ => None,
+    }
+}
+
+fn constraint_name(err: &DbErr) -> Option<String> {
+    database_error(err)?.constraint().map(str::to_owned)
+}
+
+fn sqlstate(err: &DbErr) -> Option<String> {
+    database_error(err)?.code().map(|code| code.into_owned())
+}
+
+/// Client facing description of a violated constraint
+fn describe_constraint(constraint: &str) -> &'static str {
+    match constraint {
+        "user_username_key" => "A user with this username already exists",
+        "oidc_auth_issuer_subject_key" => "This identity is already linked to a user",
+        "user_group_project_pkey" | "user_group_project_user_id_project_id_key" => {
+            "The user is already in a group of this project"
+        }
+        "user_group_project_feedback_id_key" => "The feedback token is already in use",
+        "feedback_response_project_from_to_key" => "This student was already rated",
+        "fk-feedbackresponse-group" => "The referenced group does not exist in this project",
+        "fk-project-id" => "The referenced project does not exist",
+        "fk-project-group-id" => "The referenced group does not exist in this project",
+        "fk-user-id" | "fk-localauth-user" | "fk-oidcauth-user" | "fk-apitoken-user" => {
+            "The referenced user does not exist"
+        }
+        _ => "The request violates a data constraint",
     }
 }
 
 impl From<TransactionError<sea_orm::DbErr>> for ApiError {
     fn from(value: TransactionError<sea_orm::DbErr>) -> Self {
-        Self::Database(match value {
+        match value {
             TransactionError::Connection(e) => e,
             TransactionError::Transaction(e) => e,
-        })
+        }
+        .into()
     }
 }
 
@@ -94,3 +317,123 @@ impl MessageResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::to_bytes, http::header};
+    use serde_json::Value;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Named {
+        #[validate(length(min = 3))]
+        name: String,
+    }
+
+    async fn body(error: ApiError) -> (HttpResponse, Value) {
+        let response = error.error_response();
+        let (response, body) = response.into_parts();
+        let body = to_bytes(body).await.unwrap();
+        (
+            response.map_into_boxed_body(),
+            serde_json::from_slice(&body).unwrap(),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_error_response_is_problem_json() {
+        let (response, body) = body(ApiError::NotFound).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["type"], "urn:pgg:problem:not_found");
+        assert_eq!(
+            response
+                .headers()
+                .get("X-Correlation-Id")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            body["correlation_id"].as_str().unwrap()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_error_response_hides_internal_cause() {
+        let error = ApiError::Database(sea_orm::DbErr::Custom("relation user secret".to_owned()));
+        let (response, body) = body(error).await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "database_error");
+        assert!(body.get("detail").is_none());
+        assert!(!body.to_string().contains("secret"));
+    }
+
+    #[actix_web::test]
+    async fn test_error_response_contains_field_errors() {
+        let errors = Named {
+            name: "ab".to_owned(),
+        }
+        .validate()
+        .unwrap_err();
+        let (response, body) = body(ApiError::ValidationError(errors)).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+        assert_eq!(body["errors"]["name"][0]["code"], "length");
+    }
+
+    #[actix_web::test]
+    async fn test_conflict_describes_constraint() {
+        let (response, body) = body(ApiError::Conflict("user_username_key".to_owned())).await;
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body["code"], "conflict");
+        assert_eq!(body["detail"], "A user with this username already exists");
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_constraint_is_generic() {
+        let (response, body) = body(ApiError::UnprocessableEntity(String::new())).await;
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["detail"], "The request violates a data constraint");
+    }
+
+    #[test]
+    fn test_non_constraint_db_error_stays_database_error() {
+        let error: ApiError = DbErr::RecordNotFound("project".to_owned()).into();
+        assert!(matches!(error, ApiError::Database(..)));
+    }
+
+    #[actix_web::test]
+    async fn test_error_response_keeps_bad_request_detail() {
+        let (_, body) = body(ApiError::BadRequest(
+            "expires_at must be in the future".into(),
+        ))
+        .await;
+
+        assert_eq!(body["detail"], "expires_at must be in the future");
+    }
+
+    #[actix_web::test]
+    async fn test_survey_not_open_explains_why() {
+        let (response, body) = body(ApiError::SurveyNotOpen(
+            "The feedback period ended at 2026-11-10 12:00 UTC".into(),
+        ))
+        .await;
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body["code"], "survey_not_open");
+        assert_eq!(
+            body["detail"],
+            "The feedback period ended at 2026-11-10 12:00 UTC"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Grade exports for entering the results in other systems.
+//!
+//! Teachers can download the grades of a project as CSV or XLSX, or as CSV in
+//! the layout of the Moodle grade import. Moodle matches the rows by email
+//! address, the identifier of the Moodle group export the groups were created
+//! from. Column headers follow the language of the project.
+
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    csv,
+    db::entity::{project, sea_orm_active_enums::Language},
+    error::ApiError,
+    grading::StudentGrade,
+    scale::Scale,
+    xlsx::{self, Cell},
+};
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    /// CSV for the Moodle grade import
+    Moodle,
+}
+
+/// A rendered export file
+pub struct Export {
+    pub content_type: &'static str,
+    pub filename: String,
+    pub body: Vec<u8>,
+}
+
+struct Headers {
+    name: &'static str,
+    username: &'static str,
+    email: &'static str,
+    group: &'static str,
+    group_grade: &'static str,
+    peer_factor: &'static str,
+    computed_grade: &'static str,
+    individual_grade: &'static str,
+    final_grade: &'static str,
+    justification: &'static str,
+    sheet: &'static str,
+}
+
+fn headers(language: Language) -> Headers {
+    match language {
+        Language::De => Headers {
+            name: "Name",
+            username: "Benutzername",
+            email: "E-Mail-Adresse",
+            group: "Gruppe",
+            group_grade: "Gruppennote",
+            peer_factor: "Peer-Faktor",
+            computed_grade: "Berechnete Note",
+            individual_grade: "Festgelegte Note",
+            final_grade: "Endnote",
+            justification: "Begründung",
+            sheet: "Noten",
+        },
+        Language::En => Headers {
+            name: "Name",
+            username: "Username",
+            email: "Email address",
+            group: "Group",
+            group_grade: "Group grade",
+            peer_factor: "Peer factor",
+            computed_grade: "Computed grade",
+            individual_grade: "Overridden grade",
+            final_grade: "Final grade",
+            justification: "Justification",
+            sheet: "Grades",
+        },
+    }
+}
+
+/// Renders the grades of the project in the requested format
+pub fn grades(
+    project: &project::Model,
+    scale: &Scale,
+    grades: &[StudentGrade],
+    format: ExportFormat,
+) -> Result<Export, ApiError> {
+    let headers = headers(project.language);
+    let filename = format!("{}-{}", slug(&project.name), headers.sheet.to_lowercase());
+
+    match format {
+        ExportFormat::Csv => {
+            let mut rows = vec![grade_header(&headers)];
+            rows.extend(grades.iter().map(|grade| {
+                vec![
+                    grade.user.name.clone(),
+                    grade.user.username.clone(),
+                    grade.user.email.clone().unwrap_or_default(),
+                    grade.group_name.to_owned(),
+                    grade_text(grade.group_grade, scale),
+                    number(grade.peer_factor, 2),
+                    grade_text(grade.computed_grade, scale),
+                    grade_text(grade.individual_grade, scale),
+                    grade_text(grade.final_grade(), scale),
+                    grade.justification.unwrap_or_default().to_owned(),
+                ]
+            }));
+
+            // The byte order mark makes Excel detect UTF-8
+            Ok(Export {
+                content_type: "text/csv; charset=utf-8",
+                filename: format!("{filename}.csv"),
+                body: format!("\u{feff}{}", csv::write(rows)).into_bytes(),
+            })
+        }
+        ExportFormat::Xlsx => {
+            let mut rows: Vec<Vec<Cell>> =
+                vec![grade_header(&headers).into_iter().map(Cell::from).collect()];
+            rows.extend(grades.iter().map(|grade| {
+                vec![
+                    Cell::from(grade.user.name.clone()),
+                    Cell::from(grade.user.username.clone()),
+                    Cell::from(grade.user.email.clone().unwrap_or_default()),
+                    Cell::from(grade.group_name),
+                    Cell::from(grade.group_grade.map(|g| scale.round(g))),
+                    Cell::from(grade.peer_factor.map(|f| round(f, 2))),
+                    Cell::from(grade.computed_grade.map(|g| scale.round(g))),
+                    Cell::from(grade.individual_grade.map(|g| scale.round(g))),
+                    Cell::from(grade.final_grade().map(|g| scale.round(g))),
+                    Cell::from(grade.justification.unwrap_or_default()),
+                ]
+            }));
+
+            let body = xlsx::write(headers.sheet, &rows)
+                .map_err(|e| ApiError::InternalServerError(format!("writing xlsx: {e}")))?;
+            Ok(Export {
+                content_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                filename: format!("{filename}.xlsx"),
+                body,
+            })
+        }
+        ExportFormat::Moodle => {
+            // Moodle can't match students without email address, and rows without
+            // grade would clear grades entered in Moodle
+            let mut rows = vec![vec![headers.email.to_owned(), project.name.clone()]];
+            rows.extend(grades.iter().filter_map(|grade| {
+                Some(vec![
+                    grade.user.email.clone()?,
+                    format!("{:.*}", scale.decimals(), scale.round(grade.final_grade()?)),
+                ])
+            }));
+
+            Ok(Export {
+                content_type: "text/csv; charset=utf-8",
+                filename: format!("{filename}-moodle.csv"),
+                body: csv::write(rows).into_bytes(),
+            })
+        }
+    }
+}
+
+fn grade_header(headers: &Headers) -> Vec<String> {
+    [
+        headers.name,
+        headers.username,
+        headers.email,
+        headers.group,
+        headers.group_grade,
+        headers.peer_factor,
+        headers.computed_grade,
+        headers.individual_grade,
+        headers.final_grade,
+        headers.justification,
+    ]
+    .map(str::to_owned)
+    .to_vec()
+}
+
+pub(crate) fn round(value: f64, digits: i32) -> f64 {
+    let factor = 10f64.powi(digits);
+    (value * factor).round() / factor
+}
+
+/// Grade rounded as configured for the class, empty if missing
+fn grade_text(value: Option<f64>, scale: &Scale) -> String {
+    value
+        .map(|value| scale.format(value, '.'))
+        .unwrap_or_default()
+}
+
+/// Number with a decimal point, empty if missing
+fn number(value: Option<f64>, digits: i32) -> String {
+    value
+        .map(|value| format!("{:.*}", digits as usize, round(value, digits)))
+        .unwrap_or_default()
+}
+
+/// File name safe version of the project name
+pub(crate) fn slug(name: &str) -> String {
+    let slug = name
+        .chars()
+        .map(|c| match c {
+            'ä' | 'Ä' => "ae".to_owned(),
+            'ö' | 'Ö' => "oe".to_owned(),
+            'ü' | 'Ü' => "ue".to_owned(),
+            'ß' => "ss".to_owned(),
+            c if c.is_ascii_alphanumeric() => c.to_ascii_lowercase().to_string(),
+            _ => "-".to_owned(),
+        })
+        .collect::<String>();
+
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "project".to_owned()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::entity::{
+        sea_orm_active_enums::{
+            GradeRounding, GradeScale, PeerAggregation, SurveyStatus, UserRole,
+        },
+        user,
+    };
+    use uuid::Uuid;
+
+    fn project(language: Language) -> project::Model {
+        project::Model {
+            id: Uuid::nil(),
+            name: "LF 5: Übung".to_owned(),
+            feedback_deadline: None,
+            reminder_days: Vec::new(),
+            reminders_enabled: true,
+            language,
+            survey_status: SurveyStatus::Finalised,
+            feedback_opens_at: None,
+            self_assessment: false,
+            self_assessment_in_grade: false,
+            peer_aggregation: PeerAggregation::Mean,
+            max_grade_deviation: None,
+            class_id: None,
+            archived_at: None,
+        }
+    }
+
+    fn user(username: &str, email: Option<&str>) -> user::Model {
+        user::Model {
+            id: Uuid::new_v4(),
+            username: username.to_owned(),
+            name: format!("Name {username}"),
+            email: email.map(str::to_owned),
+            role: UserRole::Student,
+            deactivated_at: None,
+        }
+    }
+
+    fn grade<'a>(user: &'a user::Model, final_grade: Option<f64>) -> StudentGrade<'a> {
+        StudentGrade {
+            user,
+            group_name: "Gruppe 1",
+            group_grade: Some(2.0),
+            peer_factor: final_grade.map(|_| 0.8333),
+            individual_grade: None,
+            justification: None,
+            computed_grade: final_grade,
+        }
+    }
+
+    #[test]
+    fn csv_contains_all_students() {
+        let max = user("max", Some("max@example.org"));
+        let erika = user("erika", None);
+        let export = grades(
+            &project(Language::De),
+            &Scale::default(),
+            &[grade(&max, Some(1.6666)), grade(&erika, None)],
+            ExportFormat::Csv,
+        )
+        .unwrap();
+
+        assert_eq!(export.filename, "lf-5-uebung-noten.csv");
+        let body = String::from_utf8(export.body).unwrap();
+        let rows = csv::parse(&body);
+        assert_eq!(
+            rows[0],
+            vec![
+                "Name",
+                "Benutzername",
+                "E-Mail-Adresse",
+                "Gruppe",
+                "Gruppennote",
+                "Peer-Faktor",
+                "Berechnete Note",
+                "Festgelegte Note",
+                "Endnote",
+                "Begründung"
+            ]
+        );
+        assert_eq!(
+            rows[1],
+            vec![
+                "Name max",
+                "max",
+                "max@example.org",
+                "Gruppe 1",
+                "2.0",
+                "0.83",
+                "1.7",
+                "",
+                "1.7",
+                ""
+            ]
+        );
+        assert_eq!(rows[2][8], "");
+    }
+
+    #[test]
+    fn overridden_grades_keep_the_computed_grade() {
+        let max = user("max", Some("max@example.org"));
+        let overridden = StudentGrade {
+            individual_grade: Some(1.0),
+            justification: Some("Hat die Gruppe nach dem Ausfall getragen"),
+            ..grade(&max, Some(1.6666))
+        };
+
+        let export = grades(
+            &project(Language::De),
+            &Scale::default(),
+            std::slice::from_ref(&overridden),
+            ExportFormat::Csv,
+        )
+        .unwrap();
+        let rows = csv::parse(&String::from_utf8(export.body).unwrap());
+        assert_eq!(
+            rows[1][6..],
+            [
+                "1.7",
+                "1.0",
+                "1.0",
+                "Hat die Gruppe nach dem Ausfall getragen"
+            ]
+        );
+
+        // Moodle only gets the effective grade
+        let export = grades(
+            &project(Language::De),
+            &Scale::default(),
+            &[overridden],
+            ExportFormat::Moodle,
+        )
+        .unwrap();
+        assert!(
+            String::from_utf8(export.body)
+                .unwrap()
+                .ends_with("max@example.org,1.0\r\n")
+        );
+    }
+
+    #[test]
+    fn moodle_skips_rows_it_cant_import() {
+        let max = user("max", Some("max@example.org"));
+        let erika = user("erika", None);
+        let ungraded = user("ungraded", Some("ungraded@example.org"));
+        let export = grades(
+            &project(Language::En),
+            &Scale::default(),
+            &[
+                grade(&max, Some(2.25)),
+                grade(&erika, Some(3.0)),
+                grade(&ungraded, None),
+            ],
+            ExportFormat::Moodle,
+        )
+        .unwrap();
+
+        let body = String::from_utf8(export.body).unwrap();
+        assert_eq!(body, "Email address,LF 5: Übung\r\nmax@example.org,2.3\r\n");
+    }
+
+    #[test]
+    fn grades_follow_the_rounding_of_the_class() {
+        let max = user("max", Some("max@example.org"));
+        let scale = Scale {
+            kind: GradeScale::German,
+            rounding: GradeRounding::Tendency,
+        };
+
+        let export = grades(
+            &project(Language::De),
+            &scale,
+            &[grade(&max, Some(1.6666))],
+            ExportFormat::Csv,
+        )
+        .unwrap();
+        let rows = csv::parse(&String::from_utf8(export.body).unwrap());
+        assert_eq!(rows[1][8], "2+");
+
+        // Moodle needs numbers
+        let export = grades(
+            &project(Language::De),
+            &scale,
+            &[grade(&max, Some(1.6666))],
+            ExportFormat::Moodle,
+        )
+        .unwrap();
+        assert!(
+            String::from_utf8(export.body)
+                .unwrap()
+                .ends_with(",1.7\r\n")
+        );
+    }
+
+    #[test]
+    fn xlsx_is_a_zip_file() {
+        let max = user("max", Some("max@example.org"));
+        let export = grades(
+            &project(Language::En),
+            &Scale::default(),
+            &[grade(&max, Some(2.0))],
+            ExportFormat::Xlsx,
+        )
+        .unwrap();
+
+        assert_eq!(export.filename, "lf-5-uebung-grades.xlsx");
+        assert!(export.body.starts_with(b"PK"));
+    }
+}
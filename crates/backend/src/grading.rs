@@ -0,0 +1,453 @@
+//! Individual grades computed from the group grade and the peer feedback.
+//!
+//! The peer factor of a student compares the ratings they received with the
+//! average rating in their group: `1.0` is the group average, below `1.0`
+//! means their group mates rated them better (German grades, lower is better).
+//! The final grade weights the group grade with [`GROUP_WEIGHT`] and the group
+//! grade scaled by the peer factor with the rest. It is only computed once the
+//! group grade is set and every member submitted their feedback.
+//!
+//! Self-assessments are left out unless the project opts in, then they count
+//! like a rating from a group mate.
+//!
+//! Only ratings given within a group between its current members count. When a
+//! student moves to another group, the ratings they gave and received in the
+//! old group drop out, forms marked stale keep counting with the ratings that
+//! remain. Until someone rates a newcomer, they get no computed grade.
+//!
+//! How the received ratings are aggregated into the peer factor is chosen per
+//! project, see [`Aggregation`] for the built-in strategies. Projects can also
+//! bound how far the computed grade may move away from the group grade.
+//!
+//! Grades in other scales than German grades, e.g. Oberstufen points, are
+//! converted to German grades for the computation and back, see [`Scale`].
+
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        entity::{project, sea_orm_active_enums::PeerAggregation, user},
+        grading::GroupGrading,
+    },
+    scale::Scale,
+};
+
+/// Share of the group grade that is the same for every member
+pub const GROUP_WEIGHT: f64 = 0.5;
+
+/// Grade of one student as shown to teachers and in exports, in the scale of
+/// the project
+pub struct StudentGrade<'a> {
+    pub user: &'a user::Model,
+    pub group_name: &'a str,
+    pub group_grade: Option<f64>,
+    pub peer_factor: Option<f64>,
+    /// Grade set by the teacher, takes precedence over the computed grade
+    pub individual_grade: Option<f64>,
+    /// Why the teacher overrode the computed grade
+    pub justification: Option<&'a str>,
+    pub computed_grade: Option<f64>,
+}
+
+impl StudentGrade<'_> {
+    /// Effective grade, the only one students get to see
+    pub fn final_grade(&self) -> Option<f64> {
+        self.individual_grade.or(self.computed_grade)
+    }
+}
+
+/// Rating a student gave a group mate
+pub struct Rating {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub rating: i16,
+}
+
+/// Turns the ratings within a group into peer factors. Like the ratings, the
+/// factors are on the German grade scale: a factor of `1.0` is the group
+/// average, below `1.0` is better.
+pub trait Aggregation {
+    /// Peer factor of every member who received ratings, only ratings between
+    /// current members are passed
+    fn peer_factors(&self, members: &[Uuid], ratings: &[&Rating]) -> HashMap<Uuid, f64>;
+}
+
+/// Average rating received divided by the average of all ratings in the group
+pub struct Mean;
+
+/// Average rating received without the best and the worst one, relative to
+/// the group. Students with less than three ratings keep all of them.
+pub struct TrimmedMean;
+
+/// Median rating received relative to the group
+pub struct Median;
+
+/// Normalised contribution factor as in WebPA: every rating is divided by the
+/// average rating its rater gave, so each rater hands out the same total and
+/// lenient or harsh raters don't shift the factors. The factor is the average
+/// of the normalised ratings a student received.
+pub struct WebPa;
+
+impl Aggregation for Mean {
+    fn peer_factors(&self, members: &[Uuid], ratings: &[&Rating]) -> HashMap<Uuid, f64> {
+        let Some(group_mean) = mean(ratings.iter().map(|r| f64::from(r.rating))) else {
+            return HashMap::new();
+        };
+
+        members
+            .iter()
+            .filter_map(|member| {
+                let received = received(ratings, *member).map(|r| f64::from(r.rating));
+                Some((*member, mean(received)? / group_mean))
+            })
+            .collect()
+    }
+}
+
+impl Aggregation for TrimmedMean {
+    fn peer_factors(&self, members: &[Uuid], ratings: &[&Rating]) -> HashMap<Uuid, f64> {
+        relative_to_group(members, ratings, |mut received| {
+            if received.len() >= 3 {
+                received.sort_unstable();
+                received.pop();
+                received.remove(0);
+            }
+            mean(received.into_iter().map(f64::from))
+        })
+    }
+}
+
+impl Aggregation for Median {
+    fn peer_factors(&self, members: &[Uuid], ratings: &[&Rating]) -> HashMap<Uuid, f64> {
+        relative_to_group(members, ratings, |mut received| {
+            received.sort_unstable();
+            let middle = received.len() / 2;
+            match received.len() {
+                0 => None,
+                n if n % 2 == 0 => {
+                    Some((f64::from(received[middle - 1]) + f64::from(received[middle])) / 2.0)
+                }
+                _ => Some(f64::from(received[middle])),
+            }
+        })
+    }
+}
+
+impl Aggregation for WebPa {
+    fn peer_factors(&self, members: &[Uuid], ratings: &[&Rating]) -> HashMap<Uuid, f64> {
+        let rater_means: HashMap<Uuid, f64> = members
+            .iter()
+            .filter_map(|rater| {
+                let given = ratings.iter().filter(|r| r.from == *rater);
+                Some((*rater, mean(given.map(|r| f64::from(r.rating)))?))
+            })
+            .collect();
+
+        members
+            .iter()
+            .filter_map(|member| {
+                let normalised =
+                    received(ratings, *member).map(|r| f64::from(r.rating) / rater_means[&r.from]);
+                Some((*member, mean(normalised)?))
+            })
+            .collect()
+    }
+}
+
+/// Strategy implementing the aggregation chosen for a project
+pub fn strategy(aggregation: PeerAggregation) -> &'static dyn Aggregation {
+    match aggregation {
+        PeerAggregation::Mean => &Mean,
+        PeerAggregation::TrimmedMean => &TrimmedMean,
+        PeerAggregation::Median => &Median,
+        PeerAggregation::WebPa => &WebPa,
+    }
+}
+
+/// Peer factor of every member who received ratings, a rating with `from` and
+/// `to` being the same student is a self-assessment
+pub fn peer_factors(
+    aggregation: &dyn Aggregation,
+    members: &[Uuid],
+    ratings: &[Rating],
+) -> HashMap<Uuid, f64> {
+    // Ratings of students who left the group don't count
+    let ratings: Vec<&Rating> = ratings
+        .iter()
+        .filter(|r| members.contains(&r.from) && members.contains(&r.to))
+        .collect();
+
+    aggregation.peer_factors(members, &ratings)
+}
+
+/// German group grade scaled by the peer factor according to [`GROUP_WEIGHT`],
+/// before any bounds are applied
+pub fn weighted_grade(group_grade: f64, peer_factor: f64) -> f64 {
+    group_grade * (GROUP_WEIGHT + (1.0 - GROUP_WEIGHT) * peer_factor)
+}
+
+/// Final grade of a student in the scale of the group grade. It is at most
+/// `max_deviation` German grade steps away from the group grade and stays in
+/// the range of the scale.
+pub fn final_grade(
+    scale: &Scale,
+    group_grade: f64,
+    peer_factor: f64,
+    max_deviation: Option<f64>,
+) -> f64 {
+    let group_grade = scale.german_grade(group_grade);
+    let mut grade = weighted_grade(group_grade, peer_factor);
+    if let Some(max_deviation) = max_deviation {
+        grade = grade.clamp(group_grade - max_deviation, group_grade + max_deviation);
+    }
+    scale.scale_value(grade)
+}
+
+/// Grades of all students of the groups, ordered by group and name, computed
+/// with the grading settings of the project
+pub fn student_grades<'a>(
+    project: &project::Model,
+    scale: &Scale,
+    groups: &'a [GroupGrading],
+) -> Vec<StudentGrade<'a>> {
+    let aggregation = strategy(project.peer_aggregation);
+    let mut grades = Vec::new();
+
+    for grading in groups {
+        let members: Vec<Uuid> = grading.members.iter().map(|(m, _)| m.user_id).collect();
+        let mut ratings: Vec<Rating> = grading
+            .responses
+            .iter()
+            .map(|r| Rating {
+                from: r.from_user_id,
+                to: r.to_user_id,
+                rating: r.rating,
+            })
+            .collect();
+        if project.self_assessment_in_grade {
+            ratings.extend(grading.self_assessments.iter().map(|a| Rating {
+                from: a.user_id,
+                to: a.user_id,
+                rating: a.rating,
+            }));
+        }
+
+        let mut factors = peer_factors(aggregation, &members, &ratings);
+        // Nobody can rate the only member of a group
+        if let [member] = members.as_slice() {
+            factors.insert(*member, 1.0);
+        }
+        let complete = grading.members.iter().all(|(m, _)| m.feedback_completed);
+
+        for (member, user) in &grading.members {
+            let peer_factor = complete
+                .then(|| factors.get(&member.user_id).copied())
+                .flatten();
+            grades.push(StudentGrade {
+                user,
+                group_name: &grading.group.name,
+                group_grade: grading.group.grade,
+                peer_factor,
+                individual_grade: member.grade,
+                justification: member.grade_justification.as_deref(),
+                computed_grade: grading.group.grade.zip(peer_factor).map(
+                    |(group_grade, factor)| {
+                        final_grade(scale, group_grade, factor, project.max_grade_deviation)
+                    },
+                ),
+            });
+        }
+    }
+
+    grades
+}
+
+/// Peer factors as a statistic of the ratings each member received divided by
+/// the average of that statistic in the group, so the factors average to `1.0`
+fn relative_to_group(
+    members: &[Uuid],
+    ratings: &[&Rating],
+    statistic: impl Fn(Vec<i16>) -> Option<f64>,
+) -> HashMap<Uuid, f64> {
+    let values: Vec<(Uuid, f64)> = members
+        .iter()
+        .filter_map(|member| {
+            let received = received(ratings, *member).map(|r| r.rating).collect();
+            Some((*member, statistic(received)?))
+        })
+        .collect();
+
+    let Some(group_value) = mean(values.iter().map(|(_, value)| *value)) else {
+        return HashMap::new();
+    };
+
+    values
+        .into_iter()
+        .map(|(member, value)| (member, value / group_value))
+        .collect()
+}
+
+fn received<'a>(ratings: &'a [&'a Rating], member: Uuid) -> impl Iterator<Item = &'a Rating> {
+    ratings.iter().copied().filter(move |r| r.to == member)
+}
+
+pub(crate) fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / f64::from(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::entity::sea_orm_active_enums::{GradeRounding, GradeScale};
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    fn rating(from: u128, to: u128, rating: i16) -> Rating {
+        Rating {
+            from: id(from),
+            to: id(to),
+            rating,
+        }
+    }
+
+    #[test]
+    fn peer_factor_is_relative_to_the_group() {
+        let members = [id(1), id(2), id(3)];
+        let ratings = [
+            rating(1, 2, 2),
+            rating(1, 3, 4),
+            rating(2, 1, 3),
+            rating(2, 3, 4),
+            rating(3, 1, 3),
+            rating(3, 2, 2),
+        ];
+
+        let factors = peer_factors(&Mean, &members, &ratings);
+        assert_eq!(factors[&id(1)], 1.0);
+        assert_eq!(factors[&id(2)], 2.0 / 3.0);
+        assert_eq!(factors[&id(3)], 4.0 / 3.0);
+    }
+
+    #[test]
+    fn ratings_of_former_members_are_ignored() {
+        let factors = peer_factors(&Mean, &[id(1), id(2)], &[rating(1, 2, 2), rating(9, 2, 6)]);
+        assert_eq!(factors[&id(2)], 1.0);
+        assert!(!factors.contains_key(&id(1)));
+    }
+
+    #[test]
+    fn self_assessments_count_like_peer_ratings() {
+        let members = [id(1), id(2)];
+        let ratings = [rating(1, 2, 2), rating(2, 1, 4), rating(1, 1, 2)];
+
+        let factors = peer_factors(&Mean, &members, &ratings);
+        assert_eq!(factors[&id(1)], 3.0 / (8.0 / 3.0));
+        assert_eq!(factors[&id(2)], 2.0 / (8.0 / 3.0));
+    }
+
+    #[test]
+    fn trimmed_mean_drops_the_extremes() {
+        let members = [id(1), id(2), id(3), id(4)];
+        let ratings = [
+            rating(2, 1, 1),
+            rating(3, 1, 2),
+            rating(4, 1, 6),
+            rating(1, 2, 2),
+            rating(3, 2, 2),
+            rating(4, 2, 2),
+        ];
+
+        let factors = peer_factors(&TrimmedMean, &members, &ratings);
+        assert_eq!(factors[&id(1)], 1.0);
+        assert_eq!(factors[&id(2)], 1.0);
+        assert!(!factors.contains_key(&id(3)));
+    }
+
+    #[test]
+    fn median_of_the_ratings_received() {
+        let members = [id(1), id(2), id(3)];
+        let ratings = [
+            rating(2, 1, 1),
+            rating(3, 1, 6),
+            rating(1, 2, 3),
+            rating(3, 2, 3),
+            rating(1, 3, 2),
+        ];
+
+        let factors = peer_factors(&Median, &members, &ratings);
+        assert_eq!(factors[&id(1)], 3.5 / (8.5 / 3.0));
+        assert_eq!(factors[&id(2)], 3.0 / (8.5 / 3.0));
+        assert_eq!(factors[&id(3)], 2.0 / (8.5 / 3.0));
+    }
+
+    #[test]
+    fn webpa_normalises_per_rater() {
+        let members = [id(1), id(2), id(3)];
+        // The first rater is harsh, the second lenient, both rank 2 over 3
+        let ratings = [
+            rating(1, 2, 4),
+            rating(1, 3, 6),
+            rating(2, 1, 1),
+            rating(2, 3, 1),
+            rating(3, 1, 2),
+            rating(3, 2, 2),
+        ];
+
+        let factors = peer_factors(&WebPa, &members, &ratings);
+        assert_eq!(factors[&id(1)], 1.0);
+        assert!((factors[&id(2)] - 0.9).abs() < 1e-9);
+        assert!((factors[&id(3)] - 1.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn strategies_agree_on_uniform_ratings() {
+        let members = [id(1), id(2)];
+        let ratings = [rating(1, 2, 3), rating(2, 1, 3)];
+
+        for aggregation in [
+            PeerAggregation::Mean,
+            PeerAggregation::TrimmedMean,
+            PeerAggregation::Median,
+            PeerAggregation::WebPa,
+        ] {
+            let factors = peer_factors(strategy(aggregation), &members, &ratings);
+            assert_eq!(factors[&id(1)], 1.0);
+            assert_eq!(factors[&id(2)], 1.0);
+        }
+    }
+
+    #[test]
+    fn final_grade_weights_the_group_grade() {
+        assert_eq!(final_grade(&Scale::default(), 2.0, 1.0, None), 2.0);
+        assert!((final_grade(&Scale::default(), 3.0, 2.0 / 3.0, None) - 2.5).abs() < 1e-9);
+        assert_eq!(final_grade(&Scale::default(), 4.0, 1.5, None), 5.0);
+    }
+
+    #[test]
+    fn final_grade_stays_in_range() {
+        assert_eq!(final_grade(&Scale::default(), 1.0, 0.5, None), 1.0);
+        assert_eq!(final_grade(&Scale::default(), 5.0, 2.0, None), 6.0);
+    }
+
+    #[test]
+    fn final_grade_in_points() {
+        let points = Scale {
+            kind: GradeScale::Points,
+            rounding: GradeRounding::Whole,
+        };
+        // 11 points are a 2, scaled to 2.5 that is 9.5 points
+        assert!((final_grade(&points, 11.0, 1.5, None) - 9.5).abs() < 1e-9);
+        assert_eq!(final_grade(&points, 15.0, 0.5, None), 15.0);
+    }
+
+    #[test]
+    fn final_grade_is_bounded_around_the_group_grade() {
+        assert_eq!(final_grade(&Scale::default(), 4.0, 1.5, Some(0.5)), 4.5);
+        assert_eq!(final_grade(&Scale::default(), 3.0, 0.5, Some(0.5)), 2.5);
+        assert!((final_grade(&Scale::default(), 2.0, 1.1, Some(0.5)) - 2.1).abs() < 1e-9);
+    }
+}
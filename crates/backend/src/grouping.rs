@@ -0,0 +1,229 @@
+//! Automatic division of students into groups.
+//!
+//! Groups are as even as possible, their sizes differ by at most one. Random
+//! grouping shuffles the students and deals them out. Balanced grouping sorts
+//! them by a score, e.g. their previous grades, and deals them out in a snake
+//! order (1, 2, 3, 3, 2, 1, ...), so every group gets strong and weak students
+//! and the group averages end up close. Students without a score are shuffled
+//! and dealt out last.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    db::entity::{sea_orm_active_enums::Language, user},
+    error::ApiError,
+    grading,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupingMethod {
+    #[default]
+    Random,
+    /// Spread the students evenly by [`BalanceBy`]
+    Balanced,
+}
+
+/// Score of a student in earlier projects to balance the groups by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceBy {
+    /// Average final grade, as German grade so projects in other scales compare
+    #[default]
+    PreviousGrade,
+    /// Average peer factor, how the group mates rated the student
+    PeerFactor,
+}
+
+/// How many groups to create
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupSizing {
+    /// Groups with at most this many members
+    Size(usize),
+    Count(usize),
+}
+
+/// A generated group that isn't stored yet
+#[derive(Serialize, ToSchema)]
+pub struct ProposedGroup {
+    pub name: String,
+    pub members: Vec<ProposedMember>,
+    /// Average score of the members who have one
+    pub average_score: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProposedMember {
+    pub user_id: Uuid,
+    pub name: String,
+    pub username: String,
+    /// Score the groups were balanced by, `null` without earlier results
+    pub score: Option<f64>,
+}
+
+/// Divides the students into `count` groups, named after the language of the
+/// project. `scores` are only used by the balanced method.
+pub fn propose(
+    students: &[user::Model],
+    scores: &HashMap<Uuid, f64>,
+    method: GroupingMethod,
+    count: usize,
+    language: Language,
+    rng: &mut impl Rng,
+) -> Vec<ProposedGroup> {
+    let groups = match method {
+        GroupingMethod::Random => random(students.iter().map(|s| s.id).collect(), count, rng),
+        GroupingMethod::Balanced => balanced(
+            students
+                .iter()
+                .map(|s| (s.id, scores.get(&s.id).copied()))
+                .collect(),
+            count,
+            rng,
+        ),
+    };
+
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, ids)| {
+            let members: Vec<ProposedMember> = ids
+                .into_iter()
+                .filter_map(|id| students.iter().find(|s| s.id == id))
+                .map(|student| ProposedMember {
+                    user_id: student.id,
+                    name: student.name.clone(),
+                    username: student.username.clone(),
+                    score: scores.get(&student.id).copied(),
+                })
+                .collect();
+            ProposedGroup {
+                name: group_name(language, i + 1),
+                average_score: grading::mean(members.iter().filter_map(|m| m.score)),
+                members,
+            }
+        })
+        .collect()
+}
+
+fn group_name(language: Language, number: usize) -> String {
+    match language {
+        Language::De => format!("Gruppe {}", number),
+        Language::En => format!("Group {}", number),
+    }
+}
+
+/// Number of groups for the students, fails if a group would end up empty
+pub fn group_count(students: usize, sizing: GroupSizing) -> Result<usize, ApiError> {
+    let count = match sizing {
+        GroupSizing::Size(0) | GroupSizing::Count(0) => {
+            return Err(ApiError::BadRequest(
+                "Group size and count must be at least 1".to_owned(),
+            ));
+        }
+        GroupSizing::Size(size) => students.div_ceil(size),
+        GroupSizing::Count(count) => count,
+    };
+
+    if count == 0 || count > students {
+        return Err(ApiError::BadRequest(format!(
+            "{} students can't be divided into {} groups",
+            students, count
+        )));
+    }
+    Ok(count)
+}
+
+/// Shuffles the students into `count` groups
+pub fn random(mut students: Vec<Uuid>, count: usize, rng: &mut impl Rng) -> Vec<Vec<Uuid>> {
+    students.shuffle(rng);
+    deal(students, count, false)
+}
+
+/// Deals the students into `count` groups so the scores are spread evenly.
+/// Students with the same score are shuffled, so repeated runs can differ.
+pub fn balanced(
+    students: Vec<(Uuid, Option<f64>)>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<Vec<Uuid>> {
+    let (mut scored, mut unscored): (Vec<_>, Vec<_>) =
+        students.into_iter().partition(|(_, score)| score.is_some());
+    scored.shuffle(rng);
+    scored.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    unscored.shuffle(rng);
+
+    let students = scored
+        .into_iter()
+        .chain(unscored)
+        .map(|(id, _)| id)
+        .collect();
+    deal(students, count, true)
+}
+
+/// Hands the students out one by one, in a snake order if `snake` is set
+fn deal(students: Vec<Uuid>, count: usize, snake: bool) -> Vec<Vec<Uuid>> {
+    let mut groups = vec![Vec::new(); count];
+    for (i, student) in students.into_iter().enumerate() {
+        let round = i / count;
+        let position = i % count;
+        let group = if snake && round % 2 == 1 {
+            count - 1 - position
+        } else {
+            position
+        };
+        groups[group].push(student);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn id(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    #[test]
+    fn group_count_from_size_or_count() {
+        assert_eq!(group_count(10, GroupSizing::Size(4)).unwrap(), 3);
+        assert_eq!(group_count(12, GroupSizing::Size(4)).unwrap(), 3);
+        assert_eq!(group_count(10, GroupSizing::Count(5)).unwrap(), 5);
+        assert!(group_count(3, GroupSizing::Count(4)).is_err());
+        assert!(group_count(3, GroupSizing::Size(0)).is_err());
+        assert!(group_count(0, GroupSizing::Size(3)).is_err());
+    }
+
+    #[test]
+    fn random_groups_are_even() {
+        let students: Vec<Uuid> = (0..10).map(id).collect();
+        let groups = random(students.clone(), 3, &mut StdRng::seed_from_u64(7));
+
+        let sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [4, 3, 3]);
+        let mut members: Vec<Uuid> = groups.into_iter().flatten().collect();
+        members.sort();
+        assert_eq!(members, students);
+    }
+
+    #[test]
+    fn balanced_groups_spread_the_scores() {
+        let students: Vec<(Uuid, Option<f64>)> = (1..=6)
+            .map(|n| (id(n), Some(n as f64)))
+            .chain([(id(7), None)])
+            .collect();
+        let groups = balanced(students, 2, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(groups[0][..3], [id(1), id(4), id(5)]);
+        assert_eq!(groups[1][..3], [id(2), id(3), id(6)]);
+        assert_eq!(groups[0].len() + groups[1].len(), 7);
+        assert!(groups.iter().any(|group| group.contains(&id(7))));
+    }
+}
@@ -0,0 +1,352 @@
+//! Durable background jobs.
+//!
+//! Work that may outlast an HTTP request is stored in the `job` table and
+//! picked up by workers running inside the backend process. Workers claim jobs
+//! with `SELECT ... FOR UPDATE SKIP LOCKED`, so several workers, also across
+//! multiple backend instances, never run the same job twice. Failed jobs are
+//! retried with exponential backoff until `max_attempts` is reached.
+//!
+//! Next to the workers a scheduler queues recurring work, like reminder mails
+//! and the daily run of the retention policy.
+
+use actix_web::ResponseError;
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::entity::{job, sea_orm_active_enums::JobKind},
+    error::ApiError,
+    mail::{self, DeliverySummary, Mailer},
+    reminder,
+    retention::{self, RetentionPolicy},
+    utils::get_env_var,
+};
+
+/// Attempts of a new job, including the first one
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+const BACKOFF_BASE: Duration = Duration::from_secs(30);
+const BACKOFF_MAX: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error("Invalid job payload: {0}")]
+    Payload(#[from] serde_json::Error),
+    /// Part of the work failed and may succeed on a later attempt
+    #[error("{0}")]
+    Incomplete(String),
+}
+
+impl JobError {
+    /// Client errors like a deleted project will fail again, so they are not retried
+    fn is_retryable(&self) -> bool {
+        match self {
+            JobError::Api(e) => e.status_code().is_server_error(),
+            JobError::Payload(..) => false,
+            JobError::Incomplete(..) => true,
+        }
+    }
+
+    /// Message stored with the job, visible to its creator
+    fn public_message(&self) -> String {
+        match self {
+            JobError::Api(e) if !e.status_code().is_server_error() => {
+                e.public_detail().unwrap_or_else(|| e.to_string())
+            }
+            JobError::Api(..) => "Internal error, see the server log".to_owned(),
+            JobError::Payload(..) | JobError::Incomplete(..) => self.to_string(),
+        }
+    }
+}
+
+/// Puts a job into the queue, workers pick it up within their poll interval
+pub async fn enqueue(
+    db: &Database,
+    kind: JobKind,
+    payload: &impl Serialize,
+    created_by: Option<Uuid>,
+) -> Result<job::Model, ApiError> {
+    let payload = serde_json::to_value(payload)
+        .map_err(|e| ApiError::InternalServerError(format!("serializing job payload: {e}")))?;
+
+    db.enqueue_job(kind, payload, created_by, DEFAULT_MAX_ATTEMPTS)
+        .await
+}
+
+/// Handle passed to running jobs to report their progress
+pub struct JobContext<'a> {
+    db: &'a Database,
+    id: Uuid,
+}
+
+impl JobContext<'_> {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub async fn progress(&self, done: usize, total: usize) -> Result<(), ApiError> {
+        self.db
+            .set_job_progress(self.id, clamp(done), clamp(total))
+            .await
+    }
+}
+
+fn clamp(value: usize) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+/// Delay before the next attempt after `attempts` failed ones
+fn backoff(attempts: i32) -> Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(16);
+    BACKOFF_BASE.saturating_mul(1 << exponent).min(BACKOFF_MAX)
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Number of jobs run concurrently by this process
+    pub workers: usize,
+    /// Pause between polls while the queue is empty
+    pub poll_interval: Duration,
+    /// Running jobs without heartbeat for this long are taken over by another worker
+    pub stale_after: Duration,
+    /// Pause between looking for due reminders
+    pub schedule_interval: Duration,
+    /// Deletion of the data of past school years
+    pub retention: RetentionPolicy,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        WorkerConfig {
+            workers: 2,
+            poll_interval: Duration::from_secs(2),
+            stale_after: Duration::from_secs(10 * 60),
+            schedule_interval: Duration::from_secs(5 * 60),
+            retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+impl WorkerConfig {
+    pub fn from_env() -> Self {
+        let default = WorkerConfig::default();
+
+        WorkerConfig {
+            workers: get_env_var("JOB_WORKERS")
+                .map(|x| x.parse().expect("JOB_WORKERS is not a number"))
+                .unwrap_or(default.workers),
+            poll_interval: get_env_var("JOB_POLL_INTERVAL_MS")
+                .map(|x| {
+                    Duration::from_millis(x.parse().expect("JOB_POLL_INTERVAL_MS is not a number"))
+                })
+                .unwrap_or(default.poll_interval),
+            stale_after: get_env_var("JOB_STALE_AFTER_SECS")
+                .map(|x| {
+                    Duration::from_secs(x.parse().expect("JOB_STALE_AFTER_SECS is not a number"))
+                })
+                .unwrap_or(default.stale_after),
+            schedule_interval: get_env_var("JOB_SCHEDULE_INTERVAL_SECS")
+                .map(|x| {
+                    Duration::from_secs(
+                        x.parse()
+                            .expect("JOB_SCHEDULE_INTERVAL_SECS is not a number"),
+                    )
+                })
+                .unwrap_or(default.schedule_interval),
+            retention: RetentionPolicy::from_env(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Worker {
+    db: Database,
+    mailer: Mailer,
+    config: WorkerConfig,
+}
+
+impl Worker {
+    pub fn new(db: Database, mailer: Mailer, config: WorkerConfig) -> Self {
+        Worker { db, mailer, config }
+    }
+
+    /// Starts the configured number of workers on the current runtime
+    pub fn spawn(self) {
+        info!("Starting {} job worker(s)", self.config.workers);
+
+        for _ in 0..self.config.workers {
+            let worker = self.clone();
+            actix_web::rt::spawn(async move { worker.run().await });
+        }
+        actix_web::rt::spawn(async move { self.schedule().await });
+    }
+
+    async fn run(self) {
+        loop {
+            match self.run_next().await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => error!("Job worker failed to talk to the database: {}", e),
+            }
+            actix_web::rt::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Runs the next due job, returns `false` if the queue is empty
+    pub async fn run_next(&self) -> Result<bool, ApiError> {
+        self.claim_and_run(None).await
+    }
+
+    /// Runs one specific job if it is due, e.g. to process it without waiting for a worker
+    #[allow(dead_code)] // Only used outside of the worker loop, e.g. by the integration tests
+    pub async fn run_job(&self, id: Uuid) -> Result<bool, ApiError> {
+        self.claim_and_run(Some(id)).await
+    }
+
+    async fn claim_and_run(&self, only: Option<Uuid>) -> Result<bool, ApiError> {
+        let Some(job) = self.db.claim_job(self.config.stale_after, only).await? else {
+            return Ok(false);
+        };
+
+        // The worker of the last attempt stopped without recording the outcome
+        if job.attempts > job.max_attempts {
+            warn!("Job {} exceeded its {} attempts", job.id, job.max_attempts);
+            self.db
+                .fail_job(job.id, "The job was interrupted too often".to_owned(), None)
+                .await?;
+            return Ok(true);
+        }
+
+        info!(
+            "Running {:?} job {}, attempt {} of {}",
+            job.kind, job.id, job.attempts, job.max_attempts
+        );
+
+        match self.execute(&job).await {
+            Ok(result) => {
+                info!("Job {} succeeded", job.id);
+                self.db.complete_job(job.id, result).await?;
+            }
+            Err(e) => {
+                let retry_at = (e.is_retryable() && job.attempts < job.max_attempts)
+                    .then(|| Utc::now() + backoff(job.attempts));
+
+                match retry_at {
+                    Some(retry_at) => {
+                        warn!("Job {} failed, retrying at {}: {}", job.id, retry_at, e)
+                    }
+                    None => error!("Job {} failed: {:?}", job.id, e),
+                }
+                self.db
+                    .fail_job(job.id, e.public_message(), retry_at)
+                    .await?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn execute(&self, job: &job::Model) -> Result<serde_json::Value, JobError> {
+        let ctx = JobContext {
+            db: &self.db,
+            id: job.id,
+        };
+
+        match job.kind {
+            JobKind::SendFeedbackLinks => {
+                let request = serde_json::from_value(job.payload.clone())?;
+                let summary =
+                    mail::send_feedback_links(&self.db, &self.mailer, &ctx, &request).await?;
+                delivery_result(summary)
+            }
+            JobKind::SendReminders => {
+                let request = serde_json::from_value(job.payload.clone())?;
+                let summary =
+                    reminder::send_reminders(&self.db, &self.mailer, &ctx, &request).await?;
+                delivery_result(summary)
+            }
+            JobKind::ApplyRetention => {
+                let request: retention::RetentionJob = serde_json::from_value(job.payload.clone())?;
+                let summary =
+                    retention::apply(&self.db, &self.config.retention, &ctx, request.as_of).await?;
+                Ok(serde_json::to_value(summary)?)
+            }
+        }
+    }
+
+    /// Queues the reminders and the retention run that are due every `schedule_interval`
+    async fn schedule(self) {
+        loop {
+            let now = Utc::now();
+            if let Err(e) = reminder::schedule_due_reminders(&self.db, now).await {
+                error!("Scheduling reminders failed: {}", e);
+            }
+            if let Err(e) =
+                retention::schedule(&self.db, &self.config.retention, now.date_naive()).await
+            {
+                error!("Scheduling the retention policy failed: {}", e);
+            }
+            actix_web::rt::time::sleep(self.config.schedule_interval).await;
+        }
+    }
+}
+
+/// Mails that could not be sent are retried with the next attempt
+fn delivery_result(summary: DeliverySummary) -> Result<serde_json::Value, JobError> {
+    if summary.failed > 0 {
+        return Err(JobError::Incomplete(format!(
+            "{} of {} mails could not be sent",
+            summary.failed,
+            summary.total()
+        )));
+    }
+    Ok(serde_json::to_value(summary)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        assert_eq!(backoff(1), Duration::from_secs(30));
+        assert_eq!(backoff(2), Duration::from_secs(60));
+        assert_eq!(backoff(4), Duration::from_secs(240));
+        assert_eq!(backoff(20), BACKOFF_MAX);
+        assert_eq!(backoff(i32::MAX), BACKOFF_MAX);
+    }
+
+    #[test]
+    fn only_server_errors_are_retried() {
+        assert!(JobError::Api(ApiError::InternalServerError("smtp".into())).is_retryable());
+        assert!(JobError::Incomplete("1 of 2 mails could not be sent".into()).is_retryable());
+        assert!(!JobError::Api(ApiError::NotFound).is_retryable());
+
+        let payload = serde_json::from_str::<mail::FeedbackLinksJob>("{}").unwrap_err();
+        assert!(!JobError::Payload(payload).is_retryable());
+    }
+
+    #[test]
+    fn public_message_hides_internal_errors() {
+        let error = JobError::Api(ApiError::Database(sea_orm::DbErr::Custom(
+            "relation secret".to_owned(),
+        )));
+        assert!(!error.public_message().contains("secret"));
+
+        assert_eq!(
+            JobError::Api(ApiError::NotFound).public_message(),
+            "Not Found"
+        );
+
+        let error = JobError::Incomplete("1 of 2 mails could not be sent".to_owned());
+        assert_eq!(error.public_message(), "1 of 2 mails could not be sent");
+    }
+}
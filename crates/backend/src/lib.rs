@@ -1,8 +1,26 @@
+pub mod analytics;
+pub mod auth;
 pub mod controller;
+pub mod csv;
 pub mod db;
 pub mod error;
+pub mod export;
+pub mod grading;
+pub mod grouping;
+pub mod jobs;
+pub mod mail;
+pub mod oidc;
+pub mod pagination;
+pub mod pdf;
+pub mod privacy;
+pub mod reminder;
+pub mod report;
+pub mod retention;
+pub mod scale;
+pub mod survey;
 pub mod utils;
 pub mod utoipa;
+pub mod xlsx;
 
 pub use db::Database;
 pub use db::entity;
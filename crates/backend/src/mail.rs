@@ -0,0 +1,431 @@
+//! Outgoing email.
+//!
+//! Mails are delivered over SMTP with STARTTLS in production. For development
+//! and tests they can be written to a directory or only logged instead.
+
+mod smtp;
+pub mod templates;
+
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::{
+        entity::sea_orm_active_enums::{Language, MailKind, MailStatus},
+        mail::{FeedbackRecipient, NewMailDelivery},
+    },
+    error::ApiError,
+    jobs::JobContext,
+    utils::get_env_var,
+};
+
+pub use smtp::SmtpConfig;
+
+/// Upper bound for a whole SMTP conversation, a stuck server must not block a request forever
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("SMTP server rejected {command}: {reply}")]
+    Rejected { command: String, reply: String },
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("Invalid email address: {0}")]
+    InvalidAddress(String),
+    #[error("Timed out talking to the mail server")]
+    Timeout,
+}
+
+/// A plain text message to a single recipient
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Transport {
+    Smtp(SmtpConfig),
+    /// Writes every message as `.eml` file into the directory
+    File(PathBuf),
+    /// Only logs the messages, the default if nothing is configured
+    Log,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    from: String,
+    public_url: String,
+    transport: Transport,
+}
+
+impl Mailer {
+    pub fn new(from: String, public_url: String, transport: Transport) -> Self {
+        Mailer {
+            from,
+            public_url: public_url.trim_end_matches('/').to_owned(),
+            transport,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let transport = match get_env_var("MAIL_TRANSPORT").as_deref() {
+            Ok("smtp") => Transport::Smtp(SmtpConfig::from_env()),
+            Ok("file") => Transport::File(
+                get_env_var("MAIL_FILE_DIR")
+                    .expect("MAIL_FILE_DIR must be set in .env")
+                    .into(),
+            ),
+            Ok("log") | Err(_) => Transport::Log,
+            Ok(other) => panic!("MAIL_TRANSPORT '{other}' is not one of smtp, file or log"),
+        };
+
+        Mailer::new(
+            get_env_var("MAIL_FROM").unwrap_or_else(|_| "PGG <pgg@localhost>".to_owned()),
+            get_env_var("PUBLIC_URL").unwrap_or_else(|_| "http://localhost:8080".to_owned()),
+            transport,
+        )
+    }
+
+    /// Link to the personal feedback form of a student
+    pub fn feedback_url(&self, feedback_id: Uuid) -> String {
+        format!("{}/feedback/{}", self.public_url, feedback_id)
+    }
+
+    pub async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let to = validate_address(&mail.to)?;
+        let message = format_message(&self.from, mail, &self.message_id());
+
+        match &self.transport {
+            Transport::Smtp(config) => {
+                let from = validate_address(envelope_address(&self.from))?;
+                tokio::time::timeout(SEND_TIMEOUT, smtp::send(config, from, to, &message))
+                    .await
+                    .map_err(|_| MailError::Timeout)?
+            }
+            Transport::File(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                let path = dir.join(format!(
+                    "{}-{}.eml",
+                    Utc::now().format("%Y%m%dT%H%M%S"),
+                    Uuid::new_v4()
+                ));
+                debug!("Writing mail to {} into {}", to, path.display());
+                tokio::fs::write(path, message).await?;
+                Ok(())
+            }
+            Transport::Log => {
+                info!("Mail to {}: {}\n{}", to, mail.subject, mail.body);
+                Ok(())
+            }
+        }
+    }
+
+    fn message_id(&self) -> String {
+        let domain = envelope_address(&self.from)
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain);
+        format!("<{}@{}>", Uuid::new_v4(), domain)
+    }
+}
+
+/// Payload of a [`JobKind::SendFeedbackLinks`](crate::db::entity::sea_orm_active_enums::JobKind) job
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedbackLinksJob {
+    pub project_id: Uuid,
+    /// Only the members of this group, all groups of the project if `None`
+    pub group_id: Option<Uuid>,
+    pub language: Language,
+}
+
+/// Number of recipients per outcome, stored as result of the job
+#[derive(Debug, Default, Serialize)]
+pub struct DeliverySummary {
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl DeliverySummary {
+    pub fn total(&self) -> usize {
+        self.sent + self.failed + self.skipped
+    }
+
+    fn count(&mut self, status: MailStatus) {
+        match status {
+            MailStatus::Sent => self.sent += 1,
+            MailStatus::Skipped => self.skipped += 1,
+            MailStatus::Failed | MailStatus::Pending => self.failed += 1,
+        }
+    }
+}
+
+/// Sends the personal feedback link to every student of the project or group
+/// and records the outcome per recipient
+pub async fn send_feedback_links(
+    db: &Database,
+    mailer: &Mailer,
+    job: &JobContext<'_>,
+    request: &FeedbackLinksJob,
+) -> Result<DeliverySummary, ApiError> {
+    let project = db.get_project(&request.project_id).await?.unwrap();
+    let recipients = db
+        .get_feedback_recipients(request.project_id, request.group_id)
+        .await?;
+
+    let batch = MailBatch {
+        project_id: project.id,
+        kind: MailKind::FeedbackLink,
+        language: request.language,
+    };
+
+    deliver(db, mailer, job, &batch, &recipients, |recipient| {
+        templates::feedback_link(
+            request.language,
+            &templates::FeedbackLink {
+                student_name: &recipient.user.name,
+                project_name: &project.name,
+                group_name: &recipient.group.name,
+                url: &mailer.feedback_url(recipient.feedback_id),
+            },
+        )
+    })
+    .await
+}
+
+/// What a job sends, shared by all of its mails
+pub(crate) struct MailBatch {
+    pub project_id: Uuid,
+    pub kind: MailKind,
+    pub language: Language,
+}
+
+/// Sends every recipient the mail rendered by `render` and records the outcome
+/// per recipient. When the job is retried, recipients that already got their
+/// mail or were skipped are not sent another one.
+pub(crate) async fn deliver(
+    db: &Database,
+    mailer: &Mailer,
+    job: &JobContext<'_>,
+    batch: &MailBatch,
+    recipients: &[FeedbackRecipient],
+    render: impl Fn(&FeedbackRecipient) -> (String, String),
+) -> Result<DeliverySummary, ApiError> {
+    let previous = db.get_job_mail_deliveries(job.id()).await?;
+
+    let mut summary = DeliverySummary::default();
+    job.progress(0, recipients.len()).await?;
+
+    for (done, recipient) in recipients.iter().enumerate() {
+        let previous = previous
+            .iter()
+            .find(|delivery| delivery.user_id == recipient.user.id);
+
+        let delivery = match previous {
+            Some(delivery) if matches!(delivery.status, MailStatus::Sent | MailStatus::Skipped) => {
+                summary.count(delivery.status);
+                job.progress(done + 1, recipients.len()).await?;
+                continue;
+            }
+            Some(delivery) => delivery.id,
+            None => {
+                db.create_mail_delivery(NewMailDelivery {
+                    user_id: recipient.user.id,
+                    project_id: batch.project_id,
+                    group_id: Some(recipient.group.id),
+                    job_id: Some(job.id()),
+                    kind: batch.kind,
+                    recipient: recipient.user.email.clone(),
+                    language: batch.language,
+                })
+                .await?
+                .id
+            }
+        };
+
+        let (status, error) = match (&recipient.user.email, recipient.user.deactivated_at) {
+            (_, Some(_)) => (MailStatus::Skipped, Some("User is deactivated".to_owned())),
+            (None, _) => (MailStatus::Skipped, Some("No email address".to_owned())),
+            (Some(email), None) => {
+                let (subject, body) = render(recipient);
+                let mail = Mail {
+                    to: email.clone(),
+                    subject,
+                    body,
+                };
+
+                match mailer.send(&mail).await {
+                    Ok(()) => (MailStatus::Sent, None),
+                    Err(e) => {
+                        warn!(
+                            "Sending {:?} mail to user {} failed: {}",
+                            batch.kind, recipient.user.id, e
+                        );
+                        (MailStatus::Failed, Some(e.to_string()))
+                    }
+                }
+            }
+        };
+
+        db.set_mail_delivery_status(delivery, status, error).await?;
+        summary.count(status);
+        job.progress(done + 1, recipients.len()).await?;
+    }
+
+    Ok(summary)
+}
+
+/// Extracts `user@example.org` from `Name <user@example.org>`
+fn envelope_address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// Rejects anything that could inject headers or SMTP commands
+fn validate_address(address: &str) -> Result<&str, MailError> {
+    let address = address.trim();
+    let valid = address
+        .split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && !domain.is_empty())
+        && !address
+            .chars()
+            .any(|c| c.is_control() || c.is_whitespace() || matches!(c, '<' | '>' | ','));
+
+    if valid {
+        Ok(address)
+    } else {
+        Err(MailError::InvalidAddress(address.to_owned()))
+    }
+}
+
+/// RFC 2047 encoded word for header values that are not plain ASCII
+fn encode_header(value: &str) -> String {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+
+    if value.is_ascii() && !value.contains(['\r', '\n']) {
+        value.to_owned()
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+/// Builds the RFC 5322 message, the body is base64 encoded so any UTF-8 passes every relay
+fn format_message(from: &str, mail: &Mail, message_id: &str) -> String {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+
+    let from = match from.rfind('<') {
+        Some(start) => format!("{} {}", encode_header(from[..start].trim()), &from[start..]),
+        None => from.to_owned(),
+    };
+
+    let body = STANDARD.encode(mail.body.replace('\n', "\r\n"));
+    let body: Vec<&str> = body
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default())
+        .collect();
+
+    format!(
+        "From: {from}\r\n\
+         To: {to}\r\n\
+         Subject: {subject}\r\n\
+         Date: {date}\r\n\
+         Message-ID: {message_id}\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\
+         \r\n\
+         {body}\r\n",
+        to = mail.to.trim(),
+        subject = encode_header(&mail.subject),
+        date = Utc::now().to_rfc2822(),
+        body = body.join("\r\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{Engine, engine::general_purpose::STANDARD};
+
+    fn mail() -> Mail {
+        Mail {
+            to: "max@example.org".to_owned(),
+            subject: "Peer-Feedback für Projekt".to_owned(),
+            body: "Hallo Max,\nhier ist dein Link.".to_owned(),
+        }
+    }
+
+    #[test]
+    fn envelope_address_strips_display_name() {
+        assert_eq!(envelope_address("PGG <pgg@example.org>"), "pgg@example.org");
+        assert_eq!(envelope_address("pgg@example.org"), "pgg@example.org");
+    }
+
+    #[test]
+    fn validate_address_rejects_injection() {
+        assert!(validate_address("max@example.org").is_ok());
+        assert!(validate_address("max@example.org\r\nBcc: eve@example.org").is_err());
+        assert!(validate_address("max").is_err());
+        assert!(validate_address("@example.org").is_err());
+    }
+
+    #[test]
+    fn format_message_encodes_utf8() {
+        let message = format_message("Schule Süd <pgg@example.org>", &mail(), "<id@example.org>");
+        let (headers, body) = message.split_once("\r\n\r\n").unwrap();
+
+        assert!(headers.contains("From: =?UTF-8?B?"));
+        assert!(headers.contains(" <pgg@example.org>\r\n"));
+        assert!(headers.contains(&format!(
+            "Subject: =?UTF-8?B?{}?=",
+            STANDARD.encode("Peer-Feedback für Projekt")
+        )));
+        assert!(headers.contains("To: max@example.org"));
+
+        let decoded = STANDARD.decode(body.replace("\r\n", "")).unwrap();
+        assert_eq!(
+            String::from_utf8(decoded).unwrap(),
+            "Hallo Max,\r\nhier ist dein Link."
+        );
+    }
+
+    #[test]
+    fn feedback_url_has_no_double_slash() {
+        let mailer = Mailer::new(
+            "pgg@example.org".to_owned(),
+            "https://pgg.example.org/".to_owned(),
+            Transport::Log,
+        );
+        assert_eq!(
+            mailer.feedback_url(Uuid::nil()),
+            "https://pgg.example.org/feedback/00000000-0000-0000-0000-000000000000"
+        );
+    }
+
+    #[actix_web::test]
+    async fn file_transport_writes_eml() {
+        let dir = tempfile::tempdir().unwrap();
+        let mailer = Mailer::new(
+            "pgg@example.org".to_owned(),
+            "http://localhost".to_owned(),
+            Transport::File(dir.path().to_owned()),
+        );
+
+        mailer.send(&mail()).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let content = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(content.starts_with("From: pgg@example.org\r\nTo: max@example.org\r\n"));
+    }
+}
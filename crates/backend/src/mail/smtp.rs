@@ -0,0 +1,316 @@
+//! Minimal SMTP client (RFC 5321) with STARTTLS (RFC 3207) and `AUTH PLAIN`.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::{debug, warn};
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{self, ClientConfig, RootCertStore, pki_types::ServerName},
+};
+
+use super::MailError;
+use crate::utils::get_env_var;
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Upgrade the connection with STARTTLS, only disable for local test servers
+    pub starttls: bool,
+    /// Name sent with `EHLO`
+    pub helo_name: String,
+}
+
+impl SmtpConfig {
+    pub fn from_env() -> Self {
+        SmtpConfig {
+            host: get_env_var("SMTP_HOST").expect("SMTP_HOST must be set in .env"),
+            port: get_env_var("SMTP_PORT")
+                .map(|x| x.parse::<u16>().expect("SMTP_PORT is not a valid port"))
+                .unwrap_or(587),
+            username: get_env_var("SMTP_USERNAME").ok(),
+            password: get_env_var("SMTP_PASSWORD").ok(),
+            starttls: get_env_var("SMTP_STARTTLS")
+                .map(|x| x != "false")
+                .unwrap_or(true),
+            helo_name: get_env_var("SMTP_HELO_NAME").unwrap_or_else(|_| "localhost".to_owned()),
+        }
+    }
+}
+
+pub(super) async fn send(
+    config: &SmtpConfig,
+    from: &str,
+    to: &str,
+    message: &str,
+) -> Result<(), MailError> {
+    debug!("Connecting to SMTP server {}:{}", config.host, config.port);
+
+    let stream = TcpStream::connect((config.host.as_str(), config.port)).await?;
+    let mut connection = Connection::new(stream);
+    connection.expect("greeting", 220).await?;
+    connection
+        .command(&format!("EHLO {}", config.helo_name), 250)
+        .await?;
+
+    if !config.starttls {
+        return transaction(&mut connection, config, from, to, message).await;
+    }
+
+    connection.command("STARTTLS", 220).await?;
+
+    let server_name =
+        ServerName::try_from(config.host.clone()).map_err(|e| MailError::Tls(e.to_string()))?;
+    let stream = tls_connector()?
+        .connect(server_name, connection.into_inner())
+        .await?;
+
+    let mut connection = Connection::new(stream);
+    connection
+        .command(&format!("EHLO {}", config.helo_name), 250)
+        .await?;
+
+    transaction(&mut connection, config, from, to, message).await
+}
+
+async fn transaction<S: AsyncRead + AsyncWrite + Unpin>(
+    connection: &mut Connection<S>,
+    config: &SmtpConfig,
+    from: &str,
+    to: &str,
+    message: &str,
+) -> Result<(), MailError> {
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        if !config.starttls {
+            warn!("Sending SMTP credentials without TLS");
+        }
+
+        let credentials = STANDARD.encode(format!("\0{username}\0{password}"));
+        connection
+            .write_line(&format!("AUTH PLAIN {credentials}"))
+            .await?;
+        connection.expect("AUTH PLAIN", 235).await?;
+    }
+
+    connection
+        .command(&format!("MAIL FROM:<{from}>"), 250)
+        .await?;
+    connection.command(&format!("RCPT TO:<{to}>"), 250).await?;
+    connection.command("DATA", 354).await?;
+
+    connection.write_line(&dot_stuff(message)).await?;
+    connection.command(".", 250).await?;
+
+    // The message is accepted at this point, a failing QUIT doesn't matter
+    let _ = connection.command("QUIT", 221).await;
+    Ok(())
+}
+
+/// Escapes lines starting with `.` so they don't end the DATA section early
+fn dot_stuff(message: &str) -> String {
+    let message = message.trim_end_matches("\r\n");
+    let stuffed = message.replace("\r\n.", "\r\n..");
+
+    match stuffed.strip_prefix('.') {
+        Some(_) => format!(".{stuffed}"),
+        None => stuffed,
+    }
+}
+
+fn tls_connector() -> Result<TlsConnector, MailError> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| MailError::Tls(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+struct Connection<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    fn new(stream: S) -> Self {
+        Connection {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<(), MailError> {
+        self.stream.write_all(line.as_bytes()).await?;
+        self.stream.write_all(b"\r\n").await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> Result<String, MailError> {
+        self.write_line(command).await?;
+        self.expect(command, expected).await
+    }
+
+    /// Reads a possibly multi-line reply and fails unless it has the expected code
+    async fn expect(&mut self, command: &str, expected: u16) -> Result<String, MailError> {
+        let mut reply = String::new();
+
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(MailError::Rejected {
+                    command: command.to_owned(),
+                    reply: "connection closed".to_owned(),
+                });
+            }
+
+            let line = line.trim_end();
+            reply.push_str(line);
+            reply.push('\n');
+
+            // `250-` continues a multi-line reply, `250 ` ends it
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        let code = reply.get(..3).and_then(|code| code.parse::<u16>().ok());
+        if code == Some(expected) {
+            Ok(reply)
+        } else {
+            Err(MailError::Rejected {
+                command: command.to_owned(),
+                reply: reply.trim_end().to_owned(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    /// Plays the server side of a conversation and returns everything the client sent
+    async fn fake_server(listener: TcpListener, replies: &'static [&'static str]) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut received = String::new();
+
+        stream.write_all(b"220 test ESMTP\r\n").await.unwrap();
+        for reply in replies {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            received.push_str(&line);
+
+            if line == "DATA\r\n" {
+                stream.write_all(b"354 go ahead\r\n").await.unwrap();
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                    received.push_str(&line);
+                    if line == ".\r\n" {
+                        break;
+                    }
+                }
+            }
+
+            stream.write_all(reply.as_bytes()).await.unwrap();
+        }
+
+        let mut rest = String::new();
+        let _ = stream.read_to_string(&mut rest).await;
+        received + &rest
+    }
+
+    fn config(port: u16) -> SmtpConfig {
+        SmtpConfig {
+            host: "127.0.0.1".to_owned(),
+            port,
+            username: Some("pgg".to_owned()),
+            password: Some("secret".to_owned()),
+            starttls: false,
+            helo_name: "pgg.test".to_owned(),
+        }
+    }
+
+    #[test]
+    fn dot_stuff_escapes_leading_dots() {
+        assert_eq!(dot_stuff(".a\r\n.b\r\nc\r\n"), "..a\r\n..b\r\nc");
+    }
+
+    #[actix_web::test]
+    async fn send_runs_the_smtp_conversation() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_server(
+            listener,
+            &[
+                "250-test greets pgg.test\r\n250 AUTH PLAIN\r\n",
+                "235 authenticated\r\n",
+                "250 ok\r\n",
+                "250 ok\r\n",
+                "250 queued\r\n",
+                "221 bye\r\n",
+            ],
+        ));
+
+        send(
+            &config(port),
+            "pgg@example.org",
+            "max@example.org",
+            "Subject: Test\r\n\r\n.hidden\r\n",
+        )
+        .await
+        .unwrap();
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("EHLO pgg.test\r\n"));
+        assert!(received.contains(&format!(
+            "AUTH PLAIN {}\r\n",
+            STANDARD.encode("\0pgg\0secret")
+        )));
+        assert!(received.contains("MAIL FROM:<pgg@example.org>\r\n"));
+        assert!(received.contains("RCPT TO:<max@example.org>\r\n"));
+        assert!(received.contains("\r\n..hidden\r\n.\r\n"));
+        assert!(received.ends_with("QUIT\r\n"));
+    }
+
+    #[actix_web::test]
+    async fn send_reports_rejected_recipient() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(fake_server(
+            listener,
+            &[
+                "250 test\r\n",
+                "235 authenticated\r\n",
+                "250 ok\r\n",
+                "550 no such user\r\n",
+            ],
+        ));
+
+        let result = send(&config(port), "pgg@example.org", "nobody@example.org", "").await;
+
+        match result {
+            Err(MailError::Rejected { command, reply }) => {
+                assert_eq!(command, "RCPT TO:<nobody@example.org>");
+                assert_eq!(reply, "550 no such user");
+            }
+            other => panic!("expected rejection, got {other:?}"),
+        }
+    }
+}
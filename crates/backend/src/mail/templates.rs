@@ -0,0 +1,153 @@
+//! Texts of the mails sent to students, in German and English.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::entity::sea_orm_active_enums::Language;
+
+pub struct FeedbackLink<'a> {
+    pub student_name: &'a str,
+    pub project_name: &'a str,
+    pub group_name: &'a str,
+    pub url: &'a str,
+}
+
+/// Subject and body of the invitation to fill in the peer feedback form
+pub fn feedback_link(language: Language, link: &FeedbackLink) -> (String, String) {
+    let FeedbackLink {
+        student_name,
+        project_name,
+        group_name,
+        url,
+    } = link;
+
+    match language {
+        Language::De => (
+            format!("Peer-Feedback für {project_name}"),
+            format!(
+                "Hallo {student_name},\n\
+                 \n\
+                 für das Projekt „{project_name}“ kannst du jetzt Feedback zu den Mitgliedern \
+                 deiner Gruppe {group_name} geben. Dein persönlicher Link zum Feedbackbogen:\n\
+                 \n\
+                 {url}\n\
+                 \n\
+                 Bitte gib den Link nicht weiter, er ist nur für dich bestimmt.\n\
+                 \n\
+                 Diese Nachricht wurde automatisch versendet.\n"
+            ),
+        ),
+        Language::En => (
+            format!("Peer feedback for {project_name}"),
+            format!(
+                "Hello {student_name},\n\
+                 \n\
+                 you can now give feedback on the members of your group {group_name} \
+                 in the project \"{project_name}\". Your personal link to the feedback form:\n\
+                 \n\
+                 {url}\n\
+                 \n\
+                 Please do not share this link, it is meant for you only.\n\
+                 \n\
+                 This message was sent automatically.\n"
+            ),
+        ),
+    }
+}
+
+pub struct Reminder<'a> {
+    pub student_name: &'a str,
+    pub project_name: &'a str,
+    pub deadline: DateTime<Utc>,
+    pub url: &'a str,
+}
+
+/// Subject and body of the reminder for students who haven't submitted their feedback
+pub fn reminder(language: Language, reminder: &Reminder) -> (String, String) {
+    let Reminder {
+        student_name,
+        project_name,
+        deadline,
+        url,
+    } = reminder;
+
+    match language {
+        Language::De => (
+            format!("Erinnerung: Peer-Feedback für {project_name}"),
+            format!(
+                "Hallo {student_name},\n\
+                 \n\
+                 du hast für das Projekt „{project_name}“ noch kein Feedback abgegeben. \
+                 Bitte fülle den Feedbackbogen bis {deadline} aus:\n\
+                 \n\
+                 {url}\n\
+                 \n\
+                 Diese Nachricht wurde automatisch versendet.\n",
+                deadline = deadline.format("%d.%m.%Y %H:%M UTC"),
+            ),
+        ),
+        Language::En => (
+            format!("Reminder: peer feedback for {project_name}"),
+            format!(
+                "Hello {student_name},\n\
+                 \n\
+                 you have not given your feedback for the project \"{project_name}\" yet. \
+                 Please fill in the feedback form by {deadline}:\n\
+                 \n\
+                 {url}\n\
+                 \n\
+                 This message was sent automatically.\n",
+                deadline = deadline.format("%Y-%m-%d %H:%M UTC"),
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: FeedbackLink = FeedbackLink {
+        student_name: "Max Mustermann",
+        project_name: "Webshop",
+        group_name: "Gruppe 1",
+        url: "https://pgg.example.org/feedback/abc",
+    };
+
+    #[test]
+    fn feedback_link_german() {
+        let (subject, body) = feedback_link(Language::De, &LINK);
+        assert_eq!(subject, "Peer-Feedback für Webshop");
+        assert!(body.starts_with("Hallo Max Mustermann,\n"));
+        assert!(body.contains("\nhttps://pgg.example.org/feedback/abc\n"));
+        assert!(body.contains("Gruppe 1"));
+    }
+
+    #[test]
+    fn feedback_link_english() {
+        let (subject, body) = feedback_link(Language::En, &LINK);
+        assert_eq!(subject, "Peer feedback for Webshop");
+        assert!(body.starts_with("Hello Max Mustermann,\n"));
+        assert!(body.contains("\nhttps://pgg.example.org/feedback/abc\n"));
+    }
+
+    #[test]
+    fn reminder_formats_deadline_per_language() {
+        let reminder = Reminder {
+            student_name: "Max Mustermann",
+            project_name: "Webshop",
+            deadline: DateTime::parse_from_rfc3339("2026-11-02T16:00:00Z")
+                .unwrap()
+                .to_utc(),
+            url: "https://pgg.example.org/feedback/abc",
+        };
+
+        let (subject, body) = super::reminder(Language::De, &reminder);
+        assert_eq!(subject, "Erinnerung: Peer-Feedback für Webshop");
+        assert!(body.contains("bis 02.11.2026 16:00 UTC"));
+
+        let (subject, body) = super::reminder(Language::En, &reminder);
+        assert_eq!(subject, "Reminder: peer feedback for Webshop");
+        assert!(body.contains("by 2026-11-02 16:00 UTC"));
+        assert!(body.contains("\nhttps://pgg.example.org/feedback/abc\n"));
+    }
+}
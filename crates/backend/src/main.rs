@@ -4,18 +4,41 @@ use actix_web::{App, HttpServer, cookie::Key, middleware::Logger, web};
 use log::debug;
 use utoipa_swagger_ui::SwaggerUi;
 
+mod analytics;
+mod auth;
 mod controller;
+mod csv;
 mod db;
 mod error;
+mod export;
+mod grading;
+mod grouping;
+mod jobs;
+mod mail;
+mod oidc;
+mod pagination;
+mod pdf;
+mod privacy;
+mod reminder;
+mod report;
+mod retention;
+mod scale;
+mod survey;
 mod utils;
 mod utoipa;
+mod xlsx;
 
 use db::Database;
+use jobs::{Worker, WorkerConfig};
 use log::info;
+use mail::Mailer;
 use migration::Migrator;
 use migration::MigratorTrait;
+use oidc::{OidcClient, OidcConfig};
 use utils::{build_database_url, get_env_var};
 
+// TODO: Read `ldap_auth` once LDAP login is implemented
+#[allow(dead_code)]
 #[derive(Clone)]
 struct AppConfig {
     ldap_auth: bool,
@@ -38,6 +61,24 @@ async fn main() -> std::io::Result<()> {
 
     let app_config = AppConfig { ldap_auth: false };
 
+    let oidc_client = match OidcConfig::from_env() {
+        Some(config) => Some(
+            OidcClient::discover(config)
+                .await
+                .expect("OIDC provider discovery failed"),
+        ),
+        None => {
+            info!("OIDC_ISSUER_URL not set, OpenID Connect login disabled");
+            None
+        }
+    };
+
+    let mailer = Mailer::from_env();
+
+    let worker_config = WorkerConfig::from_env();
+    let retention_policy = worker_config.retention.clone();
+    Worker::new(database.clone(), mailer.clone(), worker_config).spawn();
+
     // use dotenvy here to get SECRET_KEY
     let secret_key = Key::generate();
     debug!("Secret Key {:?}", secret_key.master());
@@ -55,9 +96,17 @@ async fn main() -> std::io::Result<()> {
 
         let session_middleware = session_middleware.build();
 
-        let app = App::new()
+        let mut app = App::new()
             .app_data(web::Data::new(database.clone()))
             .app_data(web::Data::new(app_config.clone()))
+            .app_data(web::Data::new(mailer.clone()))
+            .app_data(web::Data::new(retention_policy.clone()));
+
+        if let Some(oidc_client) = &oidc_client {
+            app = app.app_data(web::Data::new(oidc_client.clone()));
+        }
+
+        let app = app
             .wrap(Logger::default())
             .wrap(session_middleware)
             .service(web::scope("/api/v1").configure(controller::register_controllers))
@@ -0,0 +1,520 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, body::Bytes, header};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use log::{debug, info};
+use rand::RngCore;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+
+use crate::{db::entity::sea_orm_active_enums::UserRole, error::ApiError, utils::get_env_var};
+
+type HttpClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// Deployment specific OpenID Connect settings, read from the environment
+#[derive(Clone, Debug)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    pub scopes: String,
+    /// Where the browser is sent after a successful login
+    pub post_login_redirect: String,
+    pub username_claim: String,
+    /// Claim holding the roles, nested claims are separated by dots (e.g. `realm_access.roles`)
+    pub role_claim: String,
+    pub admin_roles: Vec<String>,
+    pub teacher_roles: Vec<String>,
+    pub default_role: UserRole,
+}
+
+impl OidcConfig {
+    /// Returns `None` if `OIDC_ISSUER_URL` is not set, which disables OIDC login
+    pub fn from_env() -> Option<Self> {
+        let issuer_url = get_env_var("OIDC_ISSUER_URL").ok()?;
+        require_https(&issuer_url).expect("OIDC_ISSUER_URL must use https");
+        let client_id = get_env_var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID must be set in .env");
+        let redirect_url =
+            get_env_var("OIDC_REDIRECT_URL").expect("OIDC_REDIRECT_URL must be set in .env");
+
+        let default_role = match get_env_var("OIDC_DEFAULT_ROLE").as_deref() {
+            Ok("admin") => UserRole::Admin,
+            Ok("teacher") => UserRole::Teacher,
+            Ok("student") | Err(_) => UserRole::Student,
+            Ok(other) => panic!("OIDC_DEFAULT_ROLE '{other}' is not a valid role"),
+        };
+
+        Some(Self {
+            issuer_url: issuer_url.trim_end_matches('/').to_owned(),
+            client_id,
+            client_secret: get_env_var("OIDC_CLIENT_SECRET").ok(),
+            redirect_url,
+            scopes: get_env_var("OIDC_SCOPES").unwrap_or_else(|_| "openid profile".to_owned()),
+            post_login_redirect: get_env_var("OIDC_POST_LOGIN_REDIRECT")
+                .unwrap_or_else(|_| "/".to_owned()),
+            username_claim: get_env_var("OIDC_USERNAME_CLAIM")
+                .unwrap_or_else(|_| "preferred_username".to_owned()),
+            role_claim: get_env_var("OIDC_ROLE_CLAIM").unwrap_or_else(|_| "roles".to_owned()),
+            admin_roles: split_list(get_env_var("OIDC_ADMIN_ROLES").unwrap_or_default()),
+            teacher_roles: split_list(get_env_var("OIDC_TEACHER_ROLES").unwrap_or_default()),
+            default_role,
+        })
+    }
+}
+
+fn split_list(value: String) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+impl ProviderMetadata {
+    /// The ID token is trusted because it comes straight from the token
+    /// endpoint, so every endpoint has to be reached over TLS
+    fn validate(&self, issuer_url: &str) -> Result<(), ApiError> {
+        if self.issuer.trim_end_matches('/') != issuer_url {
+            return Err(ApiError::OidcError(format!(
+                "Issuer mismatch: expected {}, got {}",
+                issuer_url, self.issuer
+            )));
+        }
+
+        require_https(&self.authorization_endpoint)?;
+        require_https(&self.token_endpoint)
+    }
+}
+
+fn require_https(url: &str) -> Result<(), ApiError> {
+    if url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(ApiError::OidcError(format!("{url} does not use https")))
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// Values that have to survive the round trip to the identity provider
+#[derive(Serialize, Deserialize)]
+pub struct OidcLoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub login_state: OidcLoginState,
+}
+
+/// Identity of a user after a successful login
+#[derive(Debug)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub username: String,
+    pub name: String,
+    pub role: UserRole,
+}
+
+#[derive(Clone)]
+pub struct OidcClient {
+    config: Arc<OidcConfig>,
+    metadata: Arc<ProviderMetadata>,
+    http: HttpClient,
+}
+
+impl OidcClient {
+    /// Fetches the provider metadata from `/.well-known/openid-configuration`
+    pub async fn discover(config: OidcConfig) -> Result<Self, ApiError> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        Self::discover_with_roots(config, roots).await
+    }
+
+    /// Like [`Self::discover`], but only trusts the certificate authorities in `roots`
+    pub async fn discover_with_roots(
+        config: OidcConfig,
+        roots: RootCertStore,
+    ) -> Result<Self, ApiError> {
+        let tls =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(|e| ApiError::OidcError(e.to_string()))?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+        let connector = HttpsConnectorBuilder::new()
+            .with_tls_config(tls)
+            .https_only()
+            .enable_http1()
+            .build();
+        let http = Client::builder(TokioExecutor::new()).build(connector);
+
+        let discovery_url = format!("{}/.well-known/openid-configuration", config.issuer_url);
+        info!("Discovering OIDC provider at {}", discovery_url);
+
+        let request = Request::get(&discovery_url)
+            .body(Full::default())
+            .map_err(|e| ApiError::OidcError(e.to_string()))?;
+        let metadata: ProviderMetadata = send(&http, request).await?;
+        metadata.validate(&config.issuer_url)?;
+
+        Ok(Self {
+            config: Arc::new(config),
+            metadata: Arc::new(metadata),
+            http,
+        })
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Builds the authorization code + PKCE redirect to the identity provider
+    pub fn authorization_request(&self) -> AuthorizationRequest {
+        let login_state = OidcLoginState {
+            state: random_token(),
+            nonce: random_token(),
+            code_verifier: random_token(),
+        };
+
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", &self.config.client_id),
+            ("redirect_uri", &self.config.redirect_url),
+            ("scope", &self.config.scopes),
+            ("state", &login_state.state),
+            ("nonce", &login_state.nonce),
+            (
+                "code_challenge",
+                &pkce_challenge(&login_state.code_verifier),
+            ),
+            ("code_challenge_method", "S256"),
+        ])
+        .expect("query parameters are always serializable");
+
+        let separator = if self.metadata.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+
+        AuthorizationRequest {
+            url: format!(
+                "{}{}{}",
+                self.metadata.authorization_endpoint, separator, query
+            ),
+            login_state,
+        }
+    }
+
+    /// Redeems the authorization code and validates the returned ID token.
+    ///
+    /// The ID token is received directly from the token endpoint, which is
+    /// only ever contacted over https, so its signature is not checked again
+    /// (OpenID Connect Core 1.0, section 3.1.3.7).
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        login_state: &OidcLoginState,
+    ) -> Result<OidcIdentity, ApiError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_url),
+            ("code_verifier", &login_state.code_verifier),
+        ];
+
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(&self.metadata.token_endpoint)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(header::ACCEPT, "application/json");
+
+        match &self.config.client_secret {
+            Some(secret) => {
+                let credentials = format!(
+                    "{}:{}",
+                    form_encode(&self.config.client_id),
+                    form_encode(secret)
+                );
+                let credentials = base64::engine::general_purpose::STANDARD.encode(credentials);
+                request = request.header(header::AUTHORIZATION, format!("Basic {credentials}"));
+            }
+            None => form.push(("client_id", &self.config.client_id)),
+        }
+
+        let body =
+            serde_urlencoded::to_string(&form).map_err(|e| ApiError::OidcError(e.to_string()))?;
+        let request = request
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| ApiError::OidcError(e.to_string()))?;
+
+        let token: TokenResponse = send(&self.http, request).await?;
+        let claims = decode_claims(&token.id_token)?;
+
+        self.validate_claims(&claims, &login_state.nonce)?;
+        self.identity_from_claims(&claims)
+    }
+
+    fn validate_claims(&self, claims: &Value, nonce: &str) -> Result<(), ApiError> {
+        if claims["iss"].as_str() != Some(self.metadata.issuer.as_str()) {
+            debug!("ID token issuer mismatch: {:?}", claims["iss"]);
+            return Err(ApiError::Unauthorized);
+        }
+
+        let audience_matches = match &claims["aud"] {
+            Value::String(aud) => *aud == self.config.client_id,
+            Value::Array(aud) => aud
+                .iter()
+                .any(|a| a.as_str() == Some(&self.config.client_id)),
+            _ => false,
+        };
+        if !audience_matches {
+            debug!("ID token audience mismatch: {:?}", claims["aud"]);
+            return Err(ApiError::Unauthorized);
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        if claims["exp"].as_i64().is_none_or(|exp| exp <= now) {
+            debug!("ID token expired");
+            return Err(ApiError::Unauthorized);
+        }
+
+        if claims["nonce"].as_str() != Some(nonce) {
+            debug!("ID token nonce mismatch");
+            return Err(ApiError::Unauthorized);
+        }
+
+        Ok(())
+    }
+
+    fn identity_from_claims(&self, claims: &Value) -> Result<OidcIdentity, ApiError> {
+        let subject = claims["sub"]
+            .as_str()
+            .ok_or(ApiError::Unauthorized)?
+            .to_owned();
+        let username = claims[self.config.username_claim.as_str()]
+            .as_str()
+            .unwrap_or(&subject)
+            .to_owned();
+        let name = claims["name"].as_str().unwrap_or(&username).to_owned();
+
+        Ok(OidcIdentity {
+            issuer: self.metadata.issuer.clone(),
+            role: map_role(&self.config, claims),
+            subject,
+            username,
+            name,
+        })
+    }
+}
+
+/// Maps the configured role claim to a role, the most privileged match wins
+fn map_role(config: &OidcConfig, claims: &Value) -> UserRole {
+    let claim = config
+        .role_claim
+        .split('.')
+        .fold(claims, |value, key| &value[key]);
+
+    let roles: Vec<&str> = match claim {
+        Value::String(role) => vec![role.as_str()],
+        Value::Array(roles) => roles.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+
+    let has_any = |configured: &[String]| roles.iter().any(|r| configured.iter().any(|c| c == r));
+
+    if has_any(&config.admin_roles) {
+        UserRole::Admin
+    } else if has_any(&config.teacher_roles) {
+        UserRole::Teacher
+    } else {
+        config.default_role
+    }
+}
+
+async fn send<T: DeserializeOwned>(
+    http: &HttpClient,
+    request: Request<Full<Bytes>>,
+) -> Result<T, ApiError> {
+    let uri = request.uri().clone();
+    let response = http
+        .request(request)
+        .await
+        .map_err(|e| ApiError::OidcError(format!("{uri}: {e}")))?;
+
+    let status = response.status();
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| ApiError::OidcError(format!("{uri}: {e}")))?
+        .to_bytes();
+
+    if !status.is_success() {
+        return Err(ApiError::OidcError(format!(
+            "{uri} returned {status}: {}",
+            String::from_utf8_lossy(&body)
+        )));
+    }
+
+    serde_json::from_slice(&body).map_err(|e| ApiError::OidcError(format!("{uri}: {e}")))
+}
+
+fn decode_claims(id_token: &str) -> Result<Value, ApiError> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| ApiError::OidcError("ID token is not a JWT".to_owned()))?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|e| ApiError::OidcError(e.to_string()))?;
+
+    serde_json::from_slice(&payload).map_err(|e| ApiError::OidcError(e.to_string()))
+}
+
+fn form_encode(value: &str) -> String {
+    serde_urlencoded::to_string([("", value)]).expect("strings are always serializable")[1..]
+        .to_owned()
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> OidcConfig {
+        OidcConfig {
+            issuer_url: "https://login.example.org".to_owned(),
+            client_id: "pgg".to_owned(),
+            client_secret: None,
+            redirect_url: "http://localhost:8080/api/v1/auth/oidc/callback".to_owned(),
+            scopes: "openid profile".to_owned(),
+            post_login_redirect: "/".to_owned(),
+            username_claim: "preferred_username".to_owned(),
+            role_claim: "realm_access.roles".to_owned(),
+            admin_roles: vec!["pgg-admin".to_owned()],
+            teacher_roles: vec!["lehrer".to_owned(), "teacher".to_owned()],
+            default_role: UserRole::Student,
+        }
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn random_tokens_are_unique() {
+        assert_ne!(random_token(), random_token());
+        assert_eq!(random_token().len(), 43);
+    }
+
+    #[test]
+    fn map_role_prefers_admin() {
+        let claims = json!({ "realm_access": { "roles": ["teacher", "pgg-admin"] } });
+        assert_eq!(map_role(&config(), &claims), UserRole::Admin);
+    }
+
+    #[test]
+    fn map_role_teacher_from_single_string() {
+        let mut config = config();
+        config.role_claim = "role".to_owned();
+        let claims = json!({ "role": "lehrer" });
+        assert_eq!(map_role(&config, &claims), UserRole::Teacher);
+    }
+
+    #[test]
+    fn map_role_falls_back_to_default() {
+        let claims = json!({ "realm_access": { "roles": ["offline_access"] } });
+        assert_eq!(map_role(&config(), &claims), UserRole::Student);
+        assert_eq!(map_role(&config(), &json!({})), UserRole::Student);
+    }
+
+    #[test]
+    fn decode_claims_reads_payload() {
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"sub":"1234","nonce":"abc"}"#);
+        let token = format!("eyJhbGciOiJub25lIn0.{payload}.");
+        let claims = decode_claims(&token).unwrap();
+        assert_eq!(claims["sub"], "1234");
+        assert_eq!(claims["nonce"], "abc");
+    }
+
+    #[test]
+    fn decode_claims_rejects_garbage() {
+        assert!(decode_claims("not-a-jwt").is_err());
+    }
+
+    fn metadata(token_endpoint: &str) -> ProviderMetadata {
+        ProviderMetadata {
+            issuer: "https://login.example.org/".to_owned(),
+            authorization_endpoint: "https://login.example.org/auth".to_owned(),
+            token_endpoint: token_endpoint.to_owned(),
+        }
+    }
+
+    #[test]
+    fn metadata_accepts_https_endpoints() {
+        let metadata = metadata("https://login.example.org/token");
+        assert!(metadata.validate(&config().issuer_url).is_ok());
+    }
+
+    #[test]
+    fn metadata_rejects_plain_http_token_endpoint() {
+        let metadata = metadata("http://login.example.org/token");
+        assert!(metadata.validate(&config().issuer_url).is_err());
+    }
+
+    #[test]
+    fn metadata_rejects_issuer_mismatch() {
+        let metadata = metadata("https://login.example.org/token");
+        assert!(metadata.validate("https://other.example.org").is_err());
+    }
+
+    #[test]
+    fn require_https_rejects_plain_http() {
+        assert!(require_https("http://localhost:8081").is_err());
+        assert!(require_https("https://localhost:8081").is_ok());
+    }
+
+    #[test]
+    fn form_encode_escapes_reserved_characters() {
+        assert_eq!(form_encode("a b:c"), "a+b%3Ac");
+    }
+}
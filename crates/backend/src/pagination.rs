@@ -0,0 +1,277 @@
+//! Shared paging and sorting for list endpoints.
+//!
+//! Handlers take a [`Pagination`] extractor next to their resource specific
+//! filter query, pass it to the database layer and wrap the result with
+//! [`Pagination::page`].
+
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
+use sea_orm::{ColumnTrait, ConnectionTrait, Order, PaginatorTrait, QueryOrder, SelectorTrait};
+use serde::{Deserialize, Serialize};
+use std::future::{Ready, ready};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::error::ApiError;
+
+const DEFAULT_PER_PAGE: u64 = 25;
+
+#[derive(Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Page to return, starting at 1
+    #[validate(range(min = 1))]
+    #[param(minimum = 1, default = 1)]
+    page: Option<u64>,
+    /// Number of items per page
+    #[validate(range(min = 1, max = 100))]
+    #[param(minimum = 1, maximum = 100, default = 25)]
+    per_page: Option<u64>,
+    /// Field to sort by, prefix with `-` for descending order
+    #[param(example = "-name")]
+    sort: Option<String>,
+}
+
+/// Requested page and sort order of a list endpoint
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    pub page: u64,
+    pub per_page: u64,
+    sort: Option<String>,
+    path: String,
+    query: Vec<(String, String)>,
+}
+
+impl Pagination {
+    /// Pagination without a request, links in the resulting page are relative to `/`
+    #[allow(dead_code)] // Only used outside of request handlers, e.g. by the integration tests
+    pub fn new(page: u64, per_page: u64) -> Self {
+        Pagination {
+            page: page.max(1),
+            per_page: per_page.max(1),
+            sort: None,
+            path: "/".to_owned(),
+            query: Vec::new(),
+        }
+    }
+
+    /// Sorts the query by the requested field, `fields` maps the public field names to columns.
+    /// Unknown fields are rejected so typos don't silently fall back to the default order.
+    pub fn sort<S, C>(&self, select: S, fields: &[(&str, C)], default: C) -> Result<S, ApiError>
+    where
+        S: QueryOrder,
+        C: ColumnTrait,
+    {
+        let Some(sort) = self.sort.as_deref().filter(|sort| !sort.is_empty()) else {
+            return Ok(select.order_by(default, Order::Asc));
+        };
+
+        let (field, order) = match sort.strip_prefix('-') {
+            Some(field) => (field, Order::Desc),
+            None => (sort, Order::Asc),
+        };
+
+        let column = fields
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, column)| *column)
+            .ok_or_else(|| {
+                let names: Vec<_> = fields.iter().map(|(name, _)| *name).collect();
+                ApiError::BadRequest(format!(
+                    "Cannot sort by '{}', expected one of: {}",
+                    field,
+                    names.join(", ")
+                ))
+            })?;
+
+        // Tie-break on the default column to keep pages stable
+        let select = select.order_by(column, order);
+        Ok(if column.as_str() == default.as_str() {
+            select
+        } else {
+            select.order_by(default, Order::Asc)
+        })
+    }
+
+    /// Fetches the requested page and the total number of matching rows
+    pub async fn fetch<'db, C, S>(
+        &self,
+        conn: &'db C,
+        select: S,
+    ) -> Result<(Vec<<S::Selector as SelectorTrait>::Item>, u64), ApiError>
+    where
+        C: ConnectionTrait,
+        S: PaginatorTrait<'db, C>,
+    {
+        let paginator = select.paginate(conn, self.per_page);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(self.page - 1).await?;
+
+        Ok((items, total))
+    }
+
+    /// Wraps the items of this page together with the total and navigation links
+    pub fn page<T>(&self, items: Vec<T>, total: u64) -> Page<T> {
+        let total_pages = total.div_ceil(self.per_page).max(1);
+
+        Page {
+            items,
+            total,
+            page: self.page,
+            per_page: self.per_page,
+            total_pages,
+            links: PageLinks {
+                current: self.link(self.page),
+                first: self.link(1),
+                prev: (self.page > 1).then(|| self.link((self.page - 1).min(total_pages))),
+                next: (self.page < total_pages).then(|| self.link(self.page + 1)),
+                last: self.link(total_pages),
+            },
+        }
+    }
+
+    fn link(&self, page: u64) -> String {
+        let mut params = self.query.clone();
+        params.push(("page".to_owned(), page.to_string()));
+        params.push(("per_page".to_owned(), self.per_page.to_string()));
+
+        let query = serde_urlencoded::to_string(&params).unwrap_or_default();
+        format!("{}?{}", self.path, query)
+    }
+}
+
+impl FromRequest for Pagination {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::from_http_request(req))
+    }
+}
+
+impl Pagination {
+    fn from_http_request(req: &HttpRequest) -> Result<Self, ApiError> {
+        let list = web::Query::<ListQuery>::from_query(req.query_string())
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?
+            .into_inner();
+        list.validate()?;
+
+        // Keep filters in the links, paging parameters are added per link
+        let query: Vec<(String, String)> = serde_urlencoded::from_str(req.query_string())
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        let query = query
+            .into_iter()
+            .filter(|(key, _)| key != "page" && key != "per_page")
+            .collect();
+
+        Ok(Pagination {
+            page: list.page.unwrap_or(1),
+            per_page: list.per_page.unwrap_or(DEFAULT_PER_PAGE),
+            sort: list.sort,
+            path: req.path().to_owned(),
+            query,
+        })
+    }
+}
+
+/// One page of a list endpoint
+#[derive(Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of items across all pages
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+    pub links: PageLinks,
+}
+
+/// Links to other pages, keeping filters and sort order
+#[derive(Serialize, ToSchema)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub first: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub last: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use sea_orm::EntityTrait;
+
+    fn pagination(uri: &str) -> Result<Pagination, ApiError> {
+        Pagination::from_http_request(&TestRequest::get().uri(uri).to_http_request())
+    }
+
+    #[test]
+    fn defaults_to_first_page() {
+        let pagination = pagination("/api/v1/user").unwrap();
+        assert_eq!(pagination.page, 1);
+        assert_eq!(pagination.per_page, DEFAULT_PER_PAGE);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        assert!(pagination("/api/v1/user?page=0").is_err());
+        assert!(pagination("/api/v1/user?per_page=101").is_err());
+        assert!(pagination("/api/v1/user?page=abc").is_err());
+    }
+
+    #[test]
+    fn links_keep_filters() {
+        let pagination =
+            pagination("/api/v1/user?search=max&page=2&per_page=10&sort=-name").unwrap();
+        let page = pagination.page(vec![1, 2, 3], 35);
+
+        assert_eq!(page.total_pages, 4);
+        assert_eq!(
+            page.links.next.as_deref(),
+            Some("/api/v1/user?search=max&sort=-name&page=3&per_page=10")
+        );
+        assert_eq!(
+            page.links.prev.as_deref(),
+            Some("/api/v1/user?search=max&sort=-name&page=1&per_page=10")
+        );
+        assert_eq!(
+            page.links.last,
+            "/api/v1/user?search=max&sort=-name&page=4&per_page=10"
+        );
+    }
+
+    #[test]
+    fn sort_validates_fields() {
+        use crate::db::entity::user;
+        use sea_orm::{DbBackend, QueryTrait};
+
+        let fields = [
+            ("name", user::Column::Name),
+            ("username", user::Column::Username),
+        ];
+        let sql = pagination("/api/v1/user?sort=-name")
+            .unwrap()
+            .sort(user::Entity::find(), &fields, user::Column::Username)
+            .unwrap()
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.ends_with(r#"ORDER BY "user"."name" DESC, "user"."username" ASC"#));
+
+        let result = pagination("/api/v1/user?sort=password").unwrap().sort(
+            user::Entity::find(),
+            &fields,
+            user::Column::Username,
+        );
+        assert!(matches!(result, Err(ApiError::BadRequest(..))));
+    }
+
+    #[test]
+    fn single_page_has_no_neighbours() {
+        let page = Pagination::new(1, 25).page(Vec::<u8>::new(), 0);
+
+        assert_eq!(page.total_pages, 1);
+        assert!(page.links.prev.is_none());
+        assert!(page.links.next.is_none());
+    }
+}
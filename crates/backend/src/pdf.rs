@@ -0,0 +1,347 @@
+//! Minimal PDF writing.
+//!
+//! Lays out text from top to bottom on A4 pages and starts a new page when one
+//! is full, which is all the evaluation reports need. Only the standard
+//! Helvetica fonts are used, so no font has to be embedded. They are limited
+//! to the Windows-1252 character set, other characters are printed as `?`.
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+/// Space kept free above the bottom margin for the page number
+const FOOTER_HEIGHT: f32 = 20.0;
+pub const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const TEXT_SIZE: f32 = 10.0;
+const LINE_SPACING: f32 = 1.35;
+
+#[derive(Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"F1"),
+            Font::Bold => Name(b"F2"),
+        }
+    }
+
+    /// Width of the text in points
+    fn width(self, text: &str, size: f32) -> f32 {
+        let units: u32 = text.chars().map(|c| u32::from(self.advance(c))).sum();
+        units as f32 * size / 1000.0
+    }
+
+    /// Advance width of a character in thousandths of the font size, taken from
+    /// the Adobe font metrics. Other characters are estimated from their case.
+    fn advance(self, c: char) -> u16 {
+        const HELVETICA: [u16; 95] = [
+            278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556,
+            556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667,
+            667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722,
+            667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500,
+            556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278,
+            556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+        ];
+        const HELVETICA_BOLD: [u16; 95] = [
+            278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556,
+            556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722,
+            722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722,
+            667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556,
+            611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333,
+            611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+        ];
+
+        let table = match self {
+            Font::Regular => &HELVETICA,
+            Font::Bold => &HELVETICA_BOLD,
+        };
+        match c {
+            ' '..='~' => table[c as usize - 32],
+            c if c.is_uppercase() => 722,
+            _ => 611,
+        }
+    }
+}
+
+/// A PDF document that is filled from top to bottom
+pub struct Document {
+    title: String,
+    pages: Vec<Content>,
+    page: Content,
+    /// Top of the free space on the current page
+    y: f32,
+}
+
+impl Document {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_owned(),
+            pages: Vec::new(),
+            page: Content::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        self.lines(text, Font::Bold, 16.0);
+        self.space(6.0);
+    }
+
+    pub fn subheading(&mut self, text: &str) {
+        self.space(10.0);
+        self.lines(text, Font::Bold, 12.0);
+        self.space(2.0);
+    }
+
+    /// Text wrapped to the page width, line breaks in the text are kept
+    pub fn paragraph(&mut self, text: &str) {
+        self.lines(text, Font::Regular, TEXT_SIZE);
+    }
+
+    /// Label and value next to each other
+    pub fn field(&mut self, label: &str, value: &str) {
+        self.columns(
+            &[
+                (label, 160.0, Font::Bold),
+                (value, CONTENT_WIDTH - 160.0, Font::Regular),
+            ],
+            TEXT_SIZE,
+        );
+    }
+
+    /// Table row with cells of the given widths in points, header rows are
+    /// printed bold and underlined
+    pub fn row(&mut self, cells: &[(&str, f32)], header: bool) {
+        let font = if header { Font::Bold } else { Font::Regular };
+        let cells: Vec<_> = cells
+            .iter()
+            .map(|(text, width)| (*text, *width, font))
+            .collect();
+        self.columns(&cells, TEXT_SIZE);
+
+        if header {
+            self.page
+                .set_line_width(0.5)
+                .move_to(MARGIN, self.y + 2.0)
+                .line_to(PAGE_WIDTH - MARGIN, self.y + 2.0)
+                .stroke();
+            self.space(3.0);
+        }
+    }
+
+    pub fn space(&mut self, points: f32) {
+        self.y -= points;
+    }
+
+    pub fn page_break(&mut self) {
+        let page = std::mem::replace(&mut self.page, Content::new());
+        self.pages.push(page);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.pages.push(self.page);
+
+        let catalog_id = Ref::new(1);
+        let tree_id = Ref::new(2);
+        let regular_id = Ref::new(3);
+        let bold_id = Ref::new(4);
+        let info_id = Ref::new(5);
+        let page_ids: Vec<Ref> = (0..self.pages.len())
+            .map(|i| Ref::new(6 + 2 * i as i32))
+            .collect();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(tree_id);
+        pdf.pages(tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+
+        let count = self.pages.len();
+        for (index, (mut content, page_id)) in self.pages.into_iter().zip(&page_ids).enumerate() {
+            let footer = format!("{} – {}/{}", self.title, index + 1, count);
+            show(&mut content, MARGIN, MARGIN, Font::Regular, 8.0, &footer);
+
+            let content_id = Ref::new(page_id.get() + 1);
+            let mut page = pdf.page(*page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(tree_id)
+                .contents(content_id);
+            page.resources()
+                .fonts()
+                .pair(Font::Regular.resource(), regular_id)
+                .pair(Font::Bold.resource(), bold_id);
+            page.finish();
+            pdf.stream(content_id, &content.finish());
+        }
+
+        for (id, base_font) in [(regular_id, "Helvetica"), (bold_id, "Helvetica-Bold")] {
+            pdf.type1_font(id)
+                .base_font(Name(base_font.as_bytes()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        pdf.document_info(info_id).title(TextStr(&self.title));
+
+        pdf.finish()
+    }
+
+    fn lines(&mut self, text: &str, font: Font, size: f32) {
+        self.columns(&[(text, CONTENT_WIDTH, font)], size);
+    }
+
+    /// Cells next to each other, each wrapped to its width
+    fn columns(&mut self, cells: &[(&str, f32, Font)], size: f32) {
+        let line_height = size * LINE_SPACING;
+        let wrapped: Vec<Vec<String>> = cells
+            .iter()
+            .map(|(text, width, font)| wrap(text, *font, size, *width - 6.0))
+            .collect();
+        let mut remaining = wrapped.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut line = 0;
+        while remaining > 0 {
+            // Rows longer than a page continue on the next one
+            let fitting = ((self.y - MARGIN - FOOTER_HEIGHT) / line_height).floor() as usize;
+            if fitting == 0 {
+                self.page_break();
+                continue;
+            }
+
+            for offset in 0..fitting.min(remaining) {
+                let baseline = self.y - size - offset as f32 * line_height;
+                let mut x = MARGIN;
+                for ((_, width, font), lines) in cells.iter().zip(&wrapped) {
+                    if let Some(text) = lines.get(line + offset) {
+                        show(&mut self.page, x, baseline, *font, size, text);
+                    }
+                    x += width;
+                }
+            }
+
+            let printed = fitting.min(remaining);
+            line += printed;
+            remaining -= printed;
+            self.y -= printed as f32 * line_height;
+        }
+    }
+}
+
+fn show(content: &mut Content, x: f32, baseline: f32, font: Font, size: f32, text: &str) {
+    content
+        .begin_text()
+        .set_font(font.resource(), size)
+        .next_line(x, baseline)
+        .show(Str(&encode(text)))
+        .end_text();
+}
+
+/// Breaks the text into lines no wider than `width`
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_owned()
+            } else {
+                format!("{line} {word}")
+            };
+            if font.width(&candidate, size) <= width {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Words wider than a line are split anywhere
+            for c in word.chars() {
+                if !line.is_empty() && font.width(&format!("{line}{c}"), size) > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Encodes the text as Windows-1252, the `WinAnsiEncoding` of the fonts
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '\t' => b' ',
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_uses_windows_1252() {
+        assert_eq!(encode("Übung „A“ – 5 €"), b"\xdcbung \x84A\x93 \x96 5 \x80");
+        assert_eq!(encode("Łódź"), b"?\xf3d?");
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        let width = Font::Regular.width("Peer feedback", TEXT_SIZE);
+        assert_eq!(
+            wrap(
+                "Peer feedback of the group\n\nDone",
+                Font::Regular,
+                TEXT_SIZE,
+                width
+            ),
+            vec!["Peer feedback", "of the group", "", "Done"]
+        );
+        assert_eq!(
+            wrap(
+                "abcdef",
+                Font::Regular,
+                TEXT_SIZE,
+                Font::Regular.width("abc", TEXT_SIZE)
+            ),
+            vec!["abc", "def"]
+        );
+    }
+
+    #[test]
+    fn long_text_continues_on_new_pages() {
+        let mut document = Document::new("Bewertungsbogen");
+        document.heading("Bewertungsbogen");
+        for _ in 0..120 {
+            document.field("Gruppe", "Gruppe 1");
+        }
+        let pdf = document.finish();
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.contains("/Count 3"));
+        assert!(pdf.contains("/BaseFont /Helvetica-Bold"));
+    }
+}
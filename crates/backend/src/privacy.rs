@@ -0,0 +1,363 @@
+//! Access requests under the GDPR.
+//!
+//! Admins can download everything stored about a user, either as one JSON
+//! document or as a ZIP with one JSON file per section. Ratings the user
+//! received are exported without the group mate who gave them, as on the
+//! evaluation sheet. Password hashes, token secrets and feedback links are
+//! credentials, not personal data, and are left out.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    db::{
+        entity::{
+            api_token, feedback_response, grade_override, mail_delivery, self_assessment, user,
+        },
+        privacy::UserData,
+    },
+    error::ApiError,
+    export::{Export, slug},
+    grading,
+};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DataExportFormat {
+    #[default]
+    Json,
+    /// One JSON file per section
+    Zip,
+}
+
+/// Everything stored about a user
+#[derive(Serialize, ToSchema)]
+pub struct UserDataExport {
+    #[schema(value_type = String, format = DateTime)]
+    pub exported_at: DateTime<Utc>,
+    pub profile: Profile,
+    pub memberships: Vec<Membership>,
+    /// Ratings and comments the user gave their group mates
+    pub feedback_given: Vec<feedback_response::Model>,
+    pub feedback_received: Vec<ReceivedRating>,
+    pub self_assessments: Vec<self_assessment::Model>,
+    /// Overrides of the user's computed grades
+    pub grade_history: Vec<grade_override::Model>,
+    pub mails: Vec<mail_delivery::Model>,
+    pub api_tokens: Vec<api_token::Model>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Profile {
+    pub user: user::Model,
+    /// The user can log in with a password
+    pub password_login: bool,
+    /// Identity provider and subject of the single sign-on login
+    pub oidc_issuer: Option<String>,
+    pub oidc_subject: Option<String>,
+}
+
+/// Group of the user in a project with their grade, in the scale of the project
+#[derive(Serialize, ToSchema)]
+pub struct Membership {
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub group_id: Uuid,
+    pub group_name: String,
+    pub feedback_completed: bool,
+    pub group_grade: Option<f64>,
+    pub peer_factor: Option<f64>,
+    pub computed_grade: Option<f64>,
+    pub individual_grade: Option<f64>,
+    pub justification: Option<String>,
+    pub final_grade: Option<f64>,
+}
+
+/// Rating a group mate gave the user, without the group mate
+#[derive(Serialize, ToSchema)]
+pub struct ReceivedRating {
+    pub project_id: Uuid,
+    pub group_id: Uuid,
+    pub rating: i16,
+    pub comment: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    pub submitted_at: DateTime<Utc>,
+}
+
+impl UserDataExport {
+    pub fn new(data: UserData) -> Self {
+        let user_id = data.user.id;
+        let memberships = data
+            .projects
+            .iter()
+            .filter_map(|project| {
+                let grades = grading::student_grades(
+                    &project.project,
+                    &project.scale,
+                    std::slice::from_ref(&project.grading),
+                );
+                let grade = grades.iter().find(|grade| grade.user.id == user_id)?;
+                let (member, _) = project
+                    .grading
+                    .members
+                    .iter()
+                    .find(|(member, _)| member.user_id == user_id)?;
+
+                Some(Membership {
+                    project_id: project.project.id,
+                    project_name: project.project.name.clone(),
+                    group_id: project.grading.group.id,
+                    group_name: grade.group_name.to_owned(),
+                    feedback_completed: member.feedback_completed,
+                    group_grade: grade.group_grade,
+                    peer_factor: grade.peer_factor,
+                    computed_grade: grade.computed_grade.map(|g| project.scale.round(g)),
+                    individual_grade: grade.individual_grade,
+                    justification: grade.justification.map(str::to_owned),
+                    final_grade: grade.final_grade().map(|g| project.scale.round(g)),
+                })
+            })
+            .collect();
+
+        UserDataExport {
+            exported_at: Utc::now(),
+            profile: Profile {
+                password_login: data.password_login,
+                oidc_issuer: data.oidc.as_ref().map(|oidc| oidc.issuer.clone()),
+                oidc_subject: data.oidc.map(|oidc| oidc.subject),
+                user: data.user,
+            },
+            memberships,
+            feedback_given: data.feedback_given,
+            feedback_received: data
+                .feedback_received
+                .into_iter()
+                .map(|response| ReceivedRating {
+                    project_id: response.project_id,
+                    group_id: response.group_id,
+                    rating: response.rating,
+                    comment: response.comment,
+                    submitted_at: response.submitted_at.to_utc(),
+                })
+                .collect(),
+            self_assessments: data.self_assessments,
+            grade_history: data.grade_history,
+            mails: data.mails,
+            api_tokens: data.api_tokens,
+        }
+    }
+
+    /// Renders the export in the requested format
+    pub fn render(&self, format: DataExportFormat) -> Result<Export, ApiError> {
+        let filename = format!("{}-data", slug(&self.profile.user.username));
+        let write_error = |e: &dyn std::fmt::Display| {
+            ApiError::InternalServerError(format!("writing data export: {e}"))
+        };
+
+        match format {
+            DataExportFormat::Json => Ok(Export {
+                content_type: "application/json",
+                filename: format!("{filename}.json"),
+                body: serde_json::to_vec_pretty(self).map_err(|e| write_error(&e))?,
+            }),
+            DataExportFormat::Zip => {
+                let sections = [
+                    ("profile.json", serde_json::to_vec_pretty(&self.profile)),
+                    (
+                        "memberships.json",
+                        serde_json::to_vec_pretty(&self.memberships),
+                    ),
+                    (
+                        "feedback_given.json",
+                        serde_json::to_vec_pretty(&self.feedback_given),
+                    ),
+                    (
+                        "feedback_received.json",
+                        serde_json::to_vec_pretty(&self.feedback_received),
+                    ),
+                    (
+                        "self_assessments.json",
+                        serde_json::to_vec_pretty(&self.self_assessments),
+                    ),
+                    (
+                        "grade_history.json",
+                        serde_json::to_vec_pretty(&self.grade_history),
+                    ),
+                    ("mails.json", serde_json::to_vec_pretty(&self.mails)),
+                    (
+                        "api_tokens.json",
+                        serde_json::to_vec_pretty(&self.api_tokens),
+                    ),
+                ];
+
+                let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+                let options = SimpleFileOptions::default();
+                for (name, content) in sections {
+                    let content = content.map_err(|e| write_error(&e))?;
+                    zip.start_file(name, options).map_err(|e| write_error(&e))?;
+                    zip.write_all(&content).map_err(|e| write_error(&e))?;
+                }
+                let body = zip.finish().map_err(|e| write_error(&e))?.into_inner();
+
+                Ok(Export {
+                    content_type: "application/zip",
+                    filename: format!("{filename}.zip"),
+                    body,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        entity::{
+            group, project,
+            sea_orm_active_enums::{Language, PeerAggregation, SurveyStatus, UserRole},
+            user_group_project,
+        },
+        grading::GroupGrading,
+        privacy::ProjectData,
+    };
+    use crate::scale::Scale;
+
+    fn user(name: &str) -> user::Model {
+        user::Model {
+            id: Uuid::new_v4(),
+            username: name.to_lowercase(),
+            name: name.to_owned(),
+            email: Some(format!("{}@example.org", name.to_lowercase())),
+            role: UserRole::Student,
+            deactivated_at: None,
+        }
+    }
+
+    fn member(user: &user::Model, group: &group::Model) -> user_group_project::Model {
+        user_group_project::Model {
+            user_id: user.id,
+            group_id: group.id,
+            project_id: group.project_id,
+            feedback_id: Some(Uuid::new_v4()),
+            feedback_completed: true,
+            feedback_completed_at: None,
+            feedback_stale: false,
+            grade: None,
+            grade_justification: None,
+        }
+    }
+
+    fn rating(
+        from: &user::Model,
+        to: &user::Model,
+        group: &group::Model,
+        rating: i16,
+    ) -> feedback_response::Model {
+        feedback_response::Model {
+            id: Uuid::new_v4(),
+            project_id: group.project_id,
+            group_id: group.id,
+            from_user_id: from.id,
+            to_user_id: to.id,
+            rating,
+            comment: Some(format!("Kommentar von {}", from.name)),
+            submitted_at: Utc::now().into(),
+        }
+    }
+
+    fn data() -> UserData {
+        let max = user("Max");
+        let erika = user("Erika");
+        let project = project::Model {
+            id: Uuid::new_v4(),
+            name: "LF 5".to_owned(),
+            feedback_deadline: None,
+            reminder_days: Vec::new(),
+            reminders_enabled: false,
+            language: Language::De,
+            survey_status: SurveyStatus::Finalised,
+            feedback_opens_at: None,
+            self_assessment: false,
+            self_assessment_in_grade: false,
+            peer_aggregation: PeerAggregation::Mean,
+            max_grade_deviation: None,
+            class_id: None,
+            archived_at: None,
+        };
+        let group = group::Model {
+            id: Uuid::new_v4(),
+            project_id: project.id,
+            name: "Gruppe 1".to_owned(),
+            grade: Some(2.0),
+        };
+        let given = rating(&max, &erika, &group, 2);
+        let received = rating(&erika, &max, &group, 2);
+
+        UserData {
+            password_login: true,
+            oidc: None,
+            projects: vec![ProjectData {
+                project,
+                scale: Scale::default(),
+                grading: GroupGrading {
+                    members: vec![
+                        (member(&erika, &group), erika.clone()),
+                        (member(&max, &group), max.clone()),
+                    ],
+                    responses: vec![given.clone(), received.clone()],
+                    self_assessments: Vec::new(),
+                    group,
+                },
+            }],
+            user: max,
+            feedback_given: vec![given],
+            feedback_received: vec![received],
+            self_assessments: Vec::new(),
+            grade_history: Vec::new(),
+            mails: Vec::new(),
+            api_tokens: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn export_contains_the_grades_but_not_the_raters() {
+        let data = data();
+        let erika = data.projects[0].grading.members[0].1.id;
+        let export = UserDataExport::new(data);
+
+        assert_eq!(export.memberships.len(), 1);
+        assert_eq!(export.memberships[0].group_name, "Gruppe 1");
+        assert_eq!(export.memberships[0].final_grade, Some(2.0));
+
+        let json = String::from_utf8(export.render(DataExportFormat::Json).unwrap().body).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["profile"]["user"]["email"], "max@example.org");
+        assert_eq!(
+            value["feedback_received"][0]["comment"],
+            "Kommentar von Erika"
+        );
+        assert!(value["feedback_received"][0].get("from_user_id").is_none());
+        // Feedback links are credentials
+        assert!(!json.contains("feedback_id"));
+        // The rater only shows up as recipient of the user's own ratings
+        assert_eq!(json.matches(&erika.to_string()).count(), 1);
+    }
+
+    #[test]
+    fn zip_has_a_file_per_section() {
+        let export = UserDataExport::new(data());
+        let zip = export.render(DataExportFormat::Zip).unwrap();
+        assert_eq!(zip.filename, "max-data.zip");
+
+        let archive = zip::ZipArchive::new(Cursor::new(zip.body)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert!(names.contains(&"profile.json"));
+        assert!(names.contains(&"feedback_received.json"));
+        assert_eq!(names.len(), 8);
+    }
+}
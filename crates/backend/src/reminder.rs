@@ -0,0 +1,180 @@
+//! Reminder mails for students who haven't submitted their feedback yet.
+//!
+//! Teachers give a project a feedback deadline and the days before it at which
+//! reminders go out. Every backend process periodically runs
+//! [`schedule_due_reminders`], which queues a job per due reminder. Reminders
+//! that were missed, e.g. because the deadline was set late, are merged into a
+//! single mail instead of sending several at once. Reminders only go out while
+//! the survey of the project is open.
+
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::entity::sea_orm_active_enums::{MailKind, SurveyStatus},
+    error::ApiError,
+    jobs::{DEFAULT_MAX_ATTEMPTS, JobContext},
+    mail::{self, DeliverySummary, MailBatch, Mailer, templates},
+};
+
+/// Payload of a [`JobKind::SendReminders`](crate::db::entity::sea_orm_active_enums::JobKind) job
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemindersJob {
+    pub project_id: Uuid,
+    /// Deadline the reminder was scheduled for
+    pub deadline: DateTime<Utc>,
+    pub days_before: i32,
+}
+
+/// Queues the reminders that are due, returns the number of queued jobs
+pub async fn schedule_due_reminders(db: &Database, now: DateTime<Utc>) -> Result<usize, ApiError> {
+    let mut queued = 0;
+
+    for project in db.get_projects_awaiting_reminders(now).await? {
+        let Some(deadline) = project.feedback_deadline.map(|d| d.to_utc()) else {
+            continue;
+        };
+
+        let scheduled = db.get_scheduled_reminder_days(project.id, deadline).await?;
+        let due = due_reminders(deadline, &project.reminder_days, &scheduled, now);
+        let Some(&days_before) = due.iter().min() else {
+            continue;
+        };
+
+        let payload = serde_json::to_value(RemindersJob {
+            project_id: project.id,
+            deadline,
+            days_before,
+        })
+        .map_err(|e| ApiError::InternalServerError(format!("serializing job payload: {e}")))?;
+
+        if let Some(job) = db
+            .schedule_reminder(project.id, deadline, &due, payload, DEFAULT_MAX_ATTEMPTS)
+            .await?
+        {
+            info!(
+                "Queued reminder job {} for project {}, {} day(s) before the deadline",
+                job.id, project.id, days_before
+            );
+            queued += 1;
+        }
+    }
+
+    Ok(queued)
+}
+
+/// Offsets whose reminder time has come but that were not scheduled yet
+fn due_reminders(
+    deadline: DateTime<Utc>,
+    reminder_days: &[i32],
+    scheduled: &[i32],
+    now: DateTime<Utc>,
+) -> Vec<i32> {
+    if now >= deadline {
+        return Vec::new();
+    }
+
+    let mut due: Vec<i32> = reminder_days
+        .iter()
+        .copied()
+        .filter(|days| !scheduled.contains(days))
+        .filter(|days| now >= deadline - Duration::days(i64::from(*days)))
+        .collect();
+    due.sort_unstable();
+    due.dedup();
+    due
+}
+
+/// Reminds the students of the project who haven't completed their feedback
+pub async fn send_reminders(
+    db: &Database,
+    mailer: &Mailer,
+    job: &JobContext<'_>,
+    request: &RemindersJob,
+) -> Result<DeliverySummary, ApiError> {
+    let project = db.get_project(&request.project_id).await?.unwrap();
+
+    // The teacher opted out, closed the survey or moved the deadline after the job was queued
+    let deadline = project.feedback_deadline.map(|d| d.to_utc());
+    if !project.reminders_enabled
+        || project.survey_status != SurveyStatus::Open
+        || deadline != Some(request.deadline)
+    {
+        info!(
+            "Skipping outdated reminder job {} for project {}",
+            job.id(),
+            project.id
+        );
+        return Ok(DeliverySummary::default());
+    }
+
+    let recipients: Vec<_> = db
+        .get_feedback_recipients(project.id, None)
+        .await?
+        .into_iter()
+        .filter(|recipient| !recipient.feedback_completed)
+        .collect();
+
+    let batch = MailBatch {
+        project_id: project.id,
+        kind: MailKind::Reminder,
+        language: project.language,
+    };
+
+    mail::deliver(db, mailer, job, &batch, &recipients, |recipient| {
+        templates::reminder(
+            project.language,
+            &templates::Reminder {
+                student_name: &recipient.user.name,
+                project_name: &project.name,
+                deadline: request.deadline,
+                url: &mailer.feedback_url(recipient.feedback_id),
+            },
+        )
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+    }
+
+    #[test]
+    fn due_reminders_waits_for_the_offset() {
+        let deadline = at("2026-11-10T12:00:00Z");
+
+        assert!(due_reminders(deadline, &[3, 1], &[], at("2026-11-07T11:59:00Z")).is_empty());
+        assert_eq!(
+            due_reminders(deadline, &[3, 1], &[], at("2026-11-07T12:00:00Z")),
+            vec![3]
+        );
+        assert_eq!(
+            due_reminders(deadline, &[3, 1], &[3], at("2026-11-09T12:30:00Z")),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn due_reminders_merges_missed_offsets() {
+        let deadline = at("2026-11-10T12:00:00Z");
+
+        assert_eq!(
+            due_reminders(deadline, &[7, 1, 3, 3], &[], at("2026-11-09T18:00:00Z")),
+            vec![1, 3, 7]
+        );
+    }
+
+    #[test]
+    fn due_reminders_stops_at_the_deadline() {
+        let deadline = at("2026-11-10T12:00:00Z");
+
+        assert!(due_reminders(deadline, &[1], &[], deadline).is_empty());
+    }
+}
@@ -0,0 +1,670 @@
+//! Evaluation reports as PDF.
+//!
+//! The evaluation sheet of a student shows the ratings and comments they
+//! received without the names of their group mates, and how their grade was
+//! calculated. The group report is meant for the teacher: it lists who rated
+//! whom, followed by the sheets of all members so they can be printed and
+//! handed out at once. Texts follow the language of the project.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        entity::{
+            feedback_response, project,
+            sea_orm_active_enums::{GradeScale, Language, PeerAggregation},
+        },
+        grading::GroupGrading,
+    },
+    error::ApiError,
+    export::{round, slug},
+    grading::{self, GROUP_WEIGHT, StudentGrade},
+    pdf::{CONTENT_WIDTH, Document},
+    scale::Scale,
+};
+
+/// A rendered PDF report
+pub struct Report {
+    pub filename: String,
+    pub body: Vec<u8>,
+}
+
+struct Texts {
+    sheet: &'static str,
+    group_report: &'static str,
+    project: &'static str,
+    name: &'static str,
+    group: &'static str,
+    created: &'static str,
+    date_format: &'static str,
+    peer_feedback: &'static str,
+    question: &'static str,
+    received: &'static str,
+    average: &'static str,
+    distribution: &'static str,
+    self_assessment: &'static str,
+    comments: &'static str,
+    no_comments: &'static str,
+    calculation: &'static str,
+    group_grade: &'static str,
+    group_weight: &'static str,
+    aggregation: &'static str,
+    aggregations: [&'static str; 4],
+    peer_factor: &'static str,
+    max_deviation: &'static str,
+    bounded: &'static str,
+    grade_scale: &'static str,
+    scales: [&'static str; 4],
+    in_scale: &'static str,
+    computed_grade: &'static str,
+    individual_grade: &'static str,
+    justification: &'static str,
+    final_grade: &'static str,
+    pending: &'static str,
+    members: &'static str,
+    submitted: &'static str,
+    yes: &'static str,
+    no: &'static str,
+    ratings: &'static str,
+    from: &'static str,
+    to: &'static str,
+    rating: &'static str,
+    comment: &'static str,
+    no_ratings: &'static str,
+}
+
+fn texts(language: Language) -> Texts {
+    match language {
+        Language::De => Texts {
+            sheet: "Bewertungsbogen",
+            group_report: "Gruppenbericht",
+            project: "Projekt",
+            name: "Name",
+            group: "Gruppe",
+            created: "Erstellt am",
+            date_format: "%d.%m.%Y",
+            peer_feedback: "Peer-Feedback",
+            question: "Wie bewertest du die Mitarbeit deiner Gruppenmitglieder? \
+                (1 = sehr gut, 6 = ungenügend)",
+            received: "Erhaltene Bewertungen",
+            average: "Durchschnitt",
+            distribution: "Verteilung",
+            self_assessment: "Selbsteinschätzung",
+            comments: "Kommentare",
+            no_comments: "Keine Kommentare erhalten.",
+            calculation: "Notenberechnung",
+            group_grade: "Gruppennote",
+            group_weight: "Gewichtung der Gruppennote",
+            aggregation: "Berechnung des Peer-Faktors",
+            aggregations: [
+                "Mittelwert",
+                "Mittelwert ohne beste und schlechteste Bewertung",
+                "Median",
+                "Normalisiert je Bewertendem (WebPA)",
+            ],
+            peer_factor: "Peer-Faktor",
+            max_deviation: "Maximale Abweichung von der Gruppennote",
+            bounded: "Begrenzt auf",
+            grade_scale: "Notenskala",
+            scales: [
+                "Noten 1 bis 6",
+                "Punkte 15 bis 0",
+                "Prozent",
+                "IHK-Punkte 100 bis 0",
+            ],
+            in_scale: "In der Notenskala",
+            computed_grade: "Berechnete Note",
+            individual_grade: "Von der Lehrkraft festgelegt",
+            justification: "Begründung",
+            final_grade: "Endnote",
+            pending: "Die Note steht noch nicht fest, da die Gruppennote oder Feedbackbögen fehlen.",
+            members: "Mitglieder",
+            submitted: "Feedback abgegeben",
+            yes: "ja",
+            no: "nein",
+            ratings: "Bewertungen",
+            from: "Von",
+            to: "Für",
+            rating: "Note",
+            comment: "Kommentar",
+            no_ratings: "Noch keine Bewertungen abgegeben.",
+        },
+        Language::En => Texts {
+            sheet: "Evaluation sheet",
+            group_report: "Group report",
+            project: "Project",
+            name: "Name",
+            group: "Group",
+            created: "Created on",
+            date_format: "%Y-%m-%d",
+            peer_feedback: "Peer feedback",
+            question: "How do you rate the contribution of your group mates? \
+                (1 = very good, 6 = insufficient)",
+            received: "Ratings received",
+            average: "Average",
+            distribution: "Distribution",
+            self_assessment: "Self-assessment",
+            comments: "Comments",
+            no_comments: "No comments received.",
+            calculation: "Grade calculation",
+            group_grade: "Group grade",
+            group_weight: "Weight of the group grade",
+            aggregation: "Peer factor calculation",
+            aggregations: [
+                "Mean",
+                "Mean without best and worst rating",
+                "Median",
+                "Normalised per rater (WebPA)",
+            ],
+            peer_factor: "Peer factor",
+            max_deviation: "Maximum deviation from the group grade",
+            bounded: "Bounded to",
+            grade_scale: "Grade scale",
+            scales: [
+                "Grades 1 to 6",
+                "Points 15 to 0",
+                "Percent",
+                "IHK points 100 to 0",
+            ],
+            in_scale: "In the grade scale",
+            computed_grade: "Calculated grade",
+            individual_grade: "Set by the teacher",
+            justification: "Justification",
+            final_grade: "Final grade",
+            pending: "The grade is not final yet, the group grade or feedback forms are missing.",
+            members: "Members",
+            submitted: "Feedback submitted",
+            yes: "yes",
+            no: "no",
+            ratings: "Ratings",
+            from: "From",
+            to: "For",
+            rating: "Rating",
+            comment: "Comment",
+            no_ratings: "No ratings submitted yet.",
+        },
+    }
+}
+
+/// Evaluation sheet of one member of the group
+pub fn student_sheet(
+    project: &project::Model,
+    scale: &Scale,
+    grading: &GroupGrading,
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Report, ApiError> {
+    let texts = texts(project.language);
+    let grades = grading::student_grades(project, scale, std::slice::from_ref(grading));
+    let grade = grades
+        .iter()
+        .find(|grade| grade.user.id == user_id)
+        .ok_or(ApiError::NotFound)?;
+
+    let mut document = Document::new(&format!("{} – {}", texts.sheet, grade.user.name));
+    write_sheet(&mut document, &texts, project, scale, grading, grade, now);
+
+    Ok(Report {
+        filename: format!(
+            "{}-{}-{}.pdf",
+            slug(&project.name),
+            slug(&grade.user.username),
+            slug(texts.sheet)
+        ),
+        body: document.finish(),
+    })
+}
+
+/// Overview of the ratings in the group followed by the sheets of all members
+pub fn group_report(
+    project: &project::Model,
+    scale: &Scale,
+    grading: &GroupGrading,
+    now: DateTime<Utc>,
+) -> Report {
+    let texts = texts(project.language);
+    let language = project.language;
+    let grades = grading::student_grades(project, scale, std::slice::from_ref(grading));
+
+    let mut document = Document::new(&format!("{} – {}", texts.group_report, grading.group.name));
+    document.heading(&format!("{}: {}", texts.group_report, grading.group.name));
+    document.field(texts.project, &project.name);
+    document.field(
+        texts.group_grade,
+        &grade_text(grading.group.grade, scale, language),
+    );
+    document.field(texts.created, &now.format(texts.date_format).to_string());
+
+    document.subheading(texts.members);
+    let widths = [170.0, 100.0, 70.0, 70.0, CONTENT_WIDTH - 410.0];
+    let header = [
+        texts.name,
+        texts.submitted,
+        texts.average,
+        texts.peer_factor,
+        texts.final_grade,
+    ];
+    document.row(&cells(&header, &widths), true);
+    for (grade, (member, _)) in grades.iter().zip(&grading.members) {
+        let received = ratings(&received(grading, grade.user.id));
+        let row = [
+            grade.user.name.as_str(),
+            if member.feedback_completed {
+                texts.yes
+            } else {
+                texts.no
+            },
+            &number(mean(&received), 2, language),
+            &number(grade.peer_factor, 2, language),
+            &grade_text(grade.final_grade(), scale, language),
+        ];
+        document.row(&cells(&row, &widths), false);
+    }
+
+    document.subheading(texts.ratings);
+    if grading.responses.is_empty() {
+        document.paragraph(texts.no_ratings);
+    } else {
+        let widths = [130.0, 130.0, 50.0, CONTENT_WIDTH - 310.0];
+        let header = [texts.from, texts.to, texts.rating, texts.comment];
+        document.row(&cells(&header, &widths), true);
+
+        let name = |id: Uuid| {
+            grading
+                .members
+                .iter()
+                .find(|(_, user)| user.id == id)
+                .map_or("–", |(_, user)| user.name.as_str())
+        };
+        let mut responses: Vec<_> = grading.responses.iter().collect();
+        responses.sort_by_key(|r| (name(r.from_user_id), name(r.to_user_id)));
+        for response in responses {
+            let row = [
+                name(response.from_user_id),
+                name(response.to_user_id),
+                &response.rating.to_string(),
+                response.comment.as_deref().unwrap_or_default(),
+            ];
+            document.row(&cells(&row, &widths), false);
+        }
+    }
+
+    for grade in &grades {
+        document.page_break();
+        write_sheet(&mut document, &texts, project, scale, grading, grade, now);
+    }
+
+    Report {
+        filename: format!(
+            "{}-{}-{}.pdf",
+            slug(&project.name),
+            slug(&grading.group.name),
+            slug(texts.group_report)
+        ),
+        body: document.finish(),
+    }
+}
+
+fn write_sheet(
+    document: &mut Document,
+    texts: &Texts,
+    project: &project::Model,
+    scale: &Scale,
+    grading: &GroupGrading,
+    grade: &StudentGrade,
+    now: DateTime<Utc>,
+) {
+    let language = project.language;
+
+    document.heading(texts.sheet);
+    document.field(texts.project, &project.name);
+    document.field(texts.name, &grade.user.name);
+    document.field(texts.group, grade.group_name);
+    document.field(texts.created, &now.format(texts.date_format).to_string());
+
+    document.subheading(texts.peer_feedback);
+    document.paragraph(texts.question);
+    document.space(4.0);
+    let received = received(grading, grade.user.id);
+    let ratings = ratings(&received);
+    document.field(texts.received, &ratings.len().to_string());
+    document.field(texts.average, &number(mean(&ratings), 2, language));
+    let distribution = (1..=6)
+        .map(|rating| {
+            let count = ratings.iter().filter(|r| **r == rating).count();
+            format!("{rating}: {count}")
+        })
+        .collect::<Vec<_>>()
+        .join("   ");
+    document.field(texts.distribution, &distribution);
+    if let Some(assessment) = grading
+        .self_assessments
+        .iter()
+        .find(|a| a.user_id == grade.user.id)
+    {
+        document.field(texts.self_assessment, &assessment.rating.to_string());
+    }
+
+    // Sorted so the order doesn't reveal who wrote which comment
+    let mut comments: Vec<&str> = received
+        .iter()
+        .filter_map(|r| r.comment.as_deref())
+        .map(str::trim)
+        .filter(|comment| !comment.is_empty())
+        .collect();
+    comments.sort_unstable();
+
+    document.subheading(texts.comments);
+    if comments.is_empty() {
+        document.paragraph(texts.no_comments);
+    }
+    for comment in comments {
+        document.paragraph(&format!("• {comment}"));
+        document.space(3.0);
+    }
+
+    document.subheading(texts.calculation);
+    if scale.kind != GradeScale::German {
+        let name = match scale.kind {
+            GradeScale::German => texts.scales[0],
+            GradeScale::Points => texts.scales[1],
+            GradeScale::Percent => texts.scales[2],
+            GradeScale::Ihk => texts.scales[3],
+        };
+        document.field(texts.grade_scale, name);
+    }
+    document.field(
+        texts.group_grade,
+        &grade_text(grade.group_grade, scale, language),
+    );
+    document.field(texts.group_weight, &number(Some(GROUP_WEIGHT), 2, language));
+    let aggregation = match project.peer_aggregation {
+        PeerAggregation::Mean => texts.aggregations[0],
+        PeerAggregation::TrimmedMean => texts.aggregations[1],
+        PeerAggregation::Median => texts.aggregations[2],
+        PeerAggregation::WebPa => texts.aggregations[3],
+    };
+    document.field(texts.aggregation, aggregation);
+    document.field(texts.peer_factor, &number(grade.peer_factor, 2, language));
+    if let Some(max_deviation) = project.max_grade_deviation {
+        document.field(
+            texts.max_deviation,
+            &format!("± {}", number(Some(max_deviation), 1, language)),
+        );
+    }
+    if let (Some(group_grade), Some(factor), Some(computed)) =
+        (grade.group_grade, grade.peer_factor, grade.computed_grade)
+    {
+        // The peer factor applies to German grades
+        let group_grade = scale.german_grade(group_grade);
+        let weighted = grading::weighted_grade(group_grade, factor);
+        let formula = format!(
+            "{} × ({} + {} × {}) = {}",
+            number(Some(group_grade), 1, language),
+            number(Some(GROUP_WEIGHT), 2, language),
+            number(Some(1.0 - GROUP_WEIGHT), 2, language),
+            number(Some(factor), 2, language),
+            number(Some(weighted), 1, language),
+        );
+        document.field(texts.computed_grade, &formula);
+        let converted = scale.scale_value(weighted);
+        if scale.kind != GradeScale::German {
+            document.field(
+                texts.in_scale,
+                &grade_text(Some(converted), scale, language),
+            );
+        }
+        // Outside the grade range or the allowed deviation
+        if scale.round(converted) != scale.round(computed) {
+            document.field(texts.bounded, &grade_text(Some(computed), scale, language));
+        }
+    }
+    if let Some(individual) = grade.individual_grade {
+        document.field(
+            texts.individual_grade,
+            &grade_text(Some(individual), scale, language),
+        );
+        if let Some(justification) = grade.justification {
+            document.field(texts.justification, justification);
+        }
+    }
+
+    match grade.final_grade() {
+        Some(final_grade) => {
+            document.space(4.0);
+            document.field(
+                texts.final_grade,
+                &grade_text(Some(final_grade), scale, language),
+            );
+        }
+        None => {
+            document.space(4.0);
+            document.paragraph(texts.pending);
+        }
+    }
+}
+
+/// Feedback the student received from current members of the group
+fn received(grading: &GroupGrading, user_id: Uuid) -> Vec<&feedback_response::Model> {
+    grading
+        .responses
+        .iter()
+        .filter(|r| r.to_user_id == user_id)
+        .filter(|r| {
+            grading
+                .members
+                .iter()
+                .any(|(m, _)| m.user_id == r.from_user_id)
+        })
+        .collect()
+}
+
+fn ratings(responses: &[&feedback_response::Model]) -> Vec<i16> {
+    responses.iter().map(|r| r.rating).collect()
+}
+
+fn mean(ratings: &[i16]) -> Option<f64> {
+    (!ratings.is_empty())
+        .then(|| ratings.iter().map(|r| f64::from(*r)).sum::<f64>() / ratings.len() as f64)
+}
+
+/// Grade rounded as configured for the class, `–` if missing
+fn grade_text(value: Option<f64>, scale: &Scale, language: Language) -> String {
+    value.map_or_else(|| "–".to_owned(), |value| scale.display(value, language))
+}
+
+/// Number with the decimal separator of the language, `–` if missing
+fn number(value: Option<f64>, digits: usize, language: Language) -> String {
+    let Some(value) = value else {
+        return "–".to_owned();
+    };
+    let number = format!("{:.*}", digits, round(value, digits as i32));
+    match language {
+        Language::De => number.replace('.', ","),
+        Language::En => number,
+    }
+}
+
+fn cells<'a>(texts: &[&'a str], widths: &[f32]) -> Vec<(&'a str, f32)> {
+    texts.iter().copied().zip(widths.iter().copied()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::entity::{
+        group,
+        sea_orm_active_enums::{GradeRounding, SurveyStatus, UserRole},
+        user, user_group_project,
+    };
+
+    fn project() -> project::Model {
+        project::Model {
+            id: Uuid::nil(),
+            name: "LF 5: Übung".to_owned(),
+            feedback_deadline: None,
+            reminder_days: Vec::new(),
+            reminders_enabled: true,
+            language: Language::De,
+            survey_status: SurveyStatus::Finalised,
+            feedback_opens_at: None,
+            self_assessment: false,
+            self_assessment_in_grade: false,
+            peer_aggregation: PeerAggregation::Mean,
+            max_grade_deviation: None,
+            class_id: None,
+            archived_at: None,
+        }
+    }
+
+    fn member(username: &str) -> (user_group_project::Model, user::Model) {
+        let user = user::Model {
+            id: Uuid::new_v4(),
+            username: username.to_owned(),
+            name: format!("Name {username}"),
+            email: None,
+            role: UserRole::Student,
+            deactivated_at: None,
+        };
+        let member = user_group_project::Model {
+            user_id: user.id,
+            group_id: Uuid::nil(),
+            project_id: Uuid::nil(),
+            feedback_id: Some(Uuid::new_v4()),
+            feedback_completed: true,
+            feedback_completed_at: None,
+            feedback_stale: false,
+            grade: None,
+            grade_justification: None,
+        };
+        (member, user)
+    }
+
+    fn response(from: &user::Model, to: &user::Model, rating: i16) -> feedback_response::Model {
+        feedback_response::Model {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            group_id: Uuid::nil(),
+            from_user_id: from.id,
+            to_user_id: to.id,
+            rating,
+            comment: Some(format!("Kommentar von {}", from.username)),
+            submitted_at: Utc::now().into(),
+        }
+    }
+
+    fn grading() -> GroupGrading {
+        let max = member("max");
+        let erika = member("erika");
+        GroupGrading {
+            group: group::Model {
+                id: Uuid::nil(),
+                project_id: Uuid::nil(),
+                name: "Gruppe 1".to_owned(),
+                grade: Some(2.0),
+            },
+            responses: vec![response(&max.1, &erika.1, 2), response(&erika.1, &max.1, 3)],
+            members: vec![erika, max],
+            self_assessments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn numbers_use_the_decimal_separator_of_the_language() {
+        assert_eq!(number(Some(2.25), 1, Language::De), "2,3");
+        assert_eq!(number(Some(1.2), 2, Language::En), "1.20");
+        assert_eq!(number(None, 1, Language::En), "–");
+    }
+
+    #[test]
+    fn student_sheet_of_a_member() {
+        let grading = grading();
+        let max = &grading.members[1].1;
+        let report =
+            student_sheet(&project(), &Scale::default(), &grading, max.id, Utc::now()).unwrap();
+
+        assert_eq!(report.filename, "lf-5-uebung-max-bewertungsbogen.pdf");
+        assert!(report.body.starts_with(b"%PDF-"));
+        let body = String::from_utf8_lossy(&report.body);
+        // Content streams aren't compressed, strings with non-ASCII characters
+        // are written in hex though
+        assert!(body.contains("(Berechnete Note)"));
+        assert!(body.contains("(2,2)"));
+        assert!(!body.contains("(Keine Kommentare erhalten.)"));
+        assert!(!body.contains("Name erika"));
+    }
+
+    #[test]
+    fn student_sheet_shows_bounded_grade() {
+        let project = project::Model {
+            max_grade_deviation: Some(0.1),
+            ..project()
+        };
+        let grading = grading();
+        let max = &grading.members[1].1;
+        let report =
+            student_sheet(&project, &Scale::default(), &grading, max.id, Utc::now()).unwrap();
+
+        let body = String::from_utf8_lossy(&report.body);
+        assert!(body.contains("(Begrenzt auf)"));
+        assert!(body.contains("(2,1)"));
+    }
+
+    #[test]
+    fn student_sheet_in_points() {
+        let scale = Scale {
+            kind: GradeScale::Points,
+            rounding: GradeRounding::Whole,
+        };
+        let mut grading = grading();
+        grading.group.grade = Some(11.0);
+        let max = &grading.members[1].1;
+        let report = student_sheet(&project(), &scale, &grading, max.id, Utc::now()).unwrap();
+
+        let body = String::from_utf8_lossy(&report.body);
+        assert!(body.contains("(Punkte 15 bis 0)"));
+        // 11 points are a 2, 2.2 after the peer factor are 10.4 points
+        assert!(body.contains("(In der Notenskala)"));
+        assert!(body.contains("(10)"));
+    }
+
+    #[test]
+    fn student_sheet_shows_override_with_justification() {
+        let mut grading = grading();
+        grading.members[1].0.grade = Some(1.0);
+        grading.members[1].0.grade_justification = Some("Projektleitung uebernommen".to_owned());
+        let max = &grading.members[1].1;
+        let report =
+            student_sheet(&project(), &Scale::default(), &grading, max.id, Utc::now()).unwrap();
+
+        let body = String::from_utf8_lossy(&report.body);
+        // The computed grade stays visible next to the override
+        assert!(body.contains("(Berechnete Note)"));
+        assert!(body.contains("(Von der Lehrkraft festgelegt)"));
+        assert!(body.contains("(Projektleitung uebernommen)"));
+        assert!(body.contains("(1,0)"));
+    }
+
+    #[test]
+    fn student_sheet_of_a_stranger() {
+        let result = student_sheet(
+            &project(),
+            &Scale::default(),
+            &grading(),
+            Uuid::new_v4(),
+            Utc::now(),
+        );
+        assert!(matches!(result, Err(ApiError::NotFound)));
+    }
+
+    #[test]
+    fn group_report_names_raters() {
+        let report = group_report(&project(), &Scale::default(), &grading(), Utc::now());
+
+        assert_eq!(report.filename, "lf-5-uebung-gruppe-1-gruppenbericht.pdf");
+        let body = String::from_utf8_lossy(&report.body);
+        assert!(body.contains("Name erika"));
+        assert!(body.contains("/Count 3"));
+    }
+}
@@ -0,0 +1,319 @@
+//! Retention policy for the data of past school years.
+//!
+//! Classes can be assigned to a school year. Once it is over, the projects of
+//! its classes are archived after [`RetentionPolicy::archive_after_days`] and
+//! deleted after [`RetentionPolicy::purge_after_days`], with their groups,
+//! feedback, grades and audit log. Students who aren't in any other project
+//! are deleted with them. Projects of classes without school year are kept.
+//!
+//! The policy is off by default. While it is enabled, the scheduler queues a
+//! job applying it once a day. Admins can look at what the job would do at a
+//! given date with the [`report`], which changes nothing.
+
+use chrono::{Days, NaiveDate};
+use log::info;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    Database,
+    db::{entity::sea_orm_active_enums::JobKind, retention::ExpiredProject},
+    error::ApiError,
+    jobs::{DEFAULT_MAX_ATTEMPTS, JobContext},
+    utils::get_env_var,
+};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RetentionPolicy {
+    /// Whether the policy is applied automatically once a day
+    pub enabled: bool,
+    /// Days after the end of the school year until its projects are archived
+    pub archive_after_days: u32,
+    /// Days after the end of the school year until its projects are deleted
+    pub purge_after_days: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            enabled: false,
+            archive_after_days: 30,
+            purge_after_days: 365,
+        }
+    }
+}
+
+/// What happens to a project under the retention policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionStage {
+    Archive,
+    Purge,
+}
+
+impl RetentionPolicy {
+    pub fn from_env() -> Self {
+        let default = RetentionPolicy::default();
+
+        let policy = RetentionPolicy {
+            enabled: get_env_var("RETENTION_ENABLED")
+                .map(|x| x.parse().expect("RETENTION_ENABLED is not a boolean"))
+                .unwrap_or(default.enabled),
+            archive_after_days: get_env_var("RETENTION_ARCHIVE_AFTER_DAYS")
+                .map(|x| {
+                    x.parse()
+                        .expect("RETENTION_ARCHIVE_AFTER_DAYS is not a number")
+                })
+                .unwrap_or(default.archive_after_days),
+            purge_after_days: get_env_var("RETENTION_PURGE_AFTER_DAYS")
+                .map(|x| {
+                    x.parse()
+                        .expect("RETENTION_PURGE_AFTER_DAYS is not a number")
+                })
+                .unwrap_or(default.purge_after_days),
+        };
+
+        assert!(
+            policy.purge_after_days >= policy.archive_after_days,
+            "RETENTION_PURGE_AFTER_DAYS must not be less than RETENTION_ARCHIVE_AFTER_DAYS"
+        );
+        policy
+    }
+
+    pub fn archive_on(&self, ends_on: NaiveDate) -> NaiveDate {
+        ends_on + Days::new(self.archive_after_days.into())
+    }
+
+    pub fn purge_on(&self, ends_on: NaiveDate) -> NaiveDate {
+        ends_on + Days::new(self.purge_after_days.into())
+    }
+
+    /// Stage due on `today` for a project of a school year ending on `ends_on`
+    pub fn stage(
+        &self,
+        ends_on: NaiveDate,
+        archived: bool,
+        today: NaiveDate,
+    ) -> Option<RetentionStage> {
+        if today >= self.purge_on(ends_on) {
+            Some(RetentionStage::Purge)
+        } else if !archived && today >= self.archive_on(ends_on) {
+            Some(RetentionStage::Archive)
+        } else {
+            None
+        }
+    }
+}
+
+/// Payload of a [`JobKind::ApplyRetention`] job
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionJob {
+    /// Day the policy is applied for
+    pub as_of: NaiveDate,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RetentionReport {
+    #[schema(value_type = String, format = Date)]
+    pub as_of: NaiveDate,
+    pub policy: RetentionPolicy,
+    pub archive: Vec<RetainedProject>,
+    pub purge: Vec<RetainedProject>,
+    /// Students deleted with the purged projects because they aren't in any other project
+    pub students: Vec<PurgedStudent>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RetainedProject {
+    pub id: Uuid,
+    pub name: String,
+    pub class_id: Uuid,
+    pub class_name: String,
+    pub school_year: String,
+    #[schema(value_type = String, format = Date)]
+    pub archive_on: NaiveDate,
+    #[schema(value_type = String, format = Date)]
+    pub purge_on: NaiveDate,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PurgedStudent {
+    pub id: Uuid,
+    pub username: String,
+    pub name: String,
+}
+
+/// Outcome of a [`JobKind::ApplyRetention`] job
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionSummary {
+    pub archived: usize,
+    pub purged: usize,
+    pub deleted_students: usize,
+}
+
+/// Expired projects by the stage due on `as_of`
+async fn due_projects(
+    db: &Database,
+    policy: &RetentionPolicy,
+    as_of: NaiveDate,
+) -> Result<(Vec<ExpiredProject>, Vec<ExpiredProject>), ApiError> {
+    let ended_by = as_of - Days::new(policy.archive_after_days.into());
+    let mut archive = Vec::new();
+    let mut purge = Vec::new();
+
+    for expired in db.get_expired_projects(ended_by).await? {
+        match policy.stage(
+            expired.school_year.ends_on,
+            expired.project.archived_at.is_some(),
+            as_of,
+        ) {
+            Some(RetentionStage::Archive) => archive.push(expired),
+            Some(RetentionStage::Purge) => purge.push(expired),
+            None => {}
+        }
+    }
+
+    Ok((archive, purge))
+}
+
+/// What applying the policy on `as_of` would do
+pub async fn report(
+    db: &Database,
+    policy: &RetentionPolicy,
+    as_of: NaiveDate,
+) -> Result<RetentionReport, ApiError> {
+    let (archive, purge) = due_projects(db, policy, as_of).await?;
+    let purge_ids: Vec<Uuid> = purge.iter().map(|expired| expired.project.id).collect();
+    let students = db
+        .get_students_only_in(&purge_ids)
+        .await?
+        .into_iter()
+        .map(|student| PurgedStudent {
+            id: student.id,
+            username: student.username,
+            name: student.name,
+        })
+        .collect();
+
+    let retained = |expired: ExpiredProject| RetainedProject {
+        id: expired.project.id,
+        name: expired.project.name,
+        class_id: expired.class.id,
+        class_name: expired.class.name,
+        school_year: expired.school_year.name,
+        archive_on: policy.archive_on(expired.school_year.ends_on),
+        purge_on: policy.purge_on(expired.school_year.ends_on),
+    };
+
+    Ok(RetentionReport {
+        as_of,
+        policy: policy.clone(),
+        archive: archive.into_iter().map(retained).collect(),
+        purge: purge.into_iter().map(retained).collect(),
+        students,
+    })
+}
+
+/// Archives and deletes the projects that are due on `as_of`. Each project is
+/// purged in its own transaction, so an interrupted run continues where it
+/// stopped when retried.
+pub async fn apply(
+    db: &Database,
+    policy: &RetentionPolicy,
+    ctx: &JobContext<'_>,
+    as_of: NaiveDate,
+) -> Result<RetentionSummary, ApiError> {
+    let (archive, purge) = due_projects(db, policy, as_of).await?;
+    let total = purge.len() + 1;
+    let mut summary = RetentionSummary::default();
+
+    let archive: Vec<_> = archive.into_iter().map(|expired| expired.project).collect();
+    summary.archived = db.archive_projects(&archive).await?;
+    ctx.progress(1, total).await?;
+
+    for (done, expired) in purge.iter().enumerate() {
+        summary.deleted_students += db.purge_project(&expired.project).await?;
+        summary.purged += 1;
+        ctx.progress(done + 2, total).await?;
+    }
+
+    info!(
+        "Retention policy archived {} and deleted {} project(s) and {} student(s)",
+        summary.archived, summary.purged, summary.deleted_students
+    );
+    Ok(summary)
+}
+
+/// Queues the job applying the policy for `today` once, while it is enabled
+pub async fn schedule(
+    db: &Database,
+    policy: &RetentionPolicy,
+    today: NaiveDate,
+) -> Result<bool, ApiError> {
+    if !policy.enabled {
+        return Ok(false);
+    }
+
+    let payload = serde_json::to_value(RetentionJob { as_of: today })
+        .map_err(|e| ApiError::InternalServerError(format!("serializing job payload: {e}")))?;
+    let job = db
+        .enqueue_job_once(JobKind::ApplyRetention, payload, DEFAULT_MAX_ATTEMPTS)
+        .await?;
+
+    if let Some(job) = &job {
+        info!("Queued retention job {} for {}", job.id, today);
+    }
+    Ok(job.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn projects_are_archived_then_purged() {
+        let policy = RetentionPolicy {
+            enabled: true,
+            archive_after_days: 30,
+            purge_after_days: 365,
+        };
+        let ends_on = date("2025-07-31");
+
+        assert_eq!(policy.stage(ends_on, false, date("2025-07-31")), None);
+        assert_eq!(policy.stage(ends_on, false, date("2025-08-29")), None);
+        assert_eq!(
+            policy.stage(ends_on, false, date("2025-08-30")),
+            Some(RetentionStage::Archive)
+        );
+        assert_eq!(policy.stage(ends_on, true, date("2025-08-30")), None);
+        assert_eq!(
+            policy.stage(ends_on, true, date("2026-07-31")),
+            Some(RetentionStage::Purge)
+        );
+        assert_eq!(
+            policy.stage(ends_on, false, date("2027-01-01")),
+            Some(RetentionStage::Purge)
+        );
+    }
+
+    #[test]
+    fn same_day_archive_and_purge_purges() {
+        let policy = RetentionPolicy {
+            enabled: true,
+            archive_after_days: 0,
+            purge_after_days: 0,
+        };
+        let ends_on = date("2025-07-31");
+
+        assert_eq!(policy.stage(ends_on, false, date("2025-07-30")), None);
+        assert_eq!(
+            policy.stage(ends_on, false, ends_on),
+            Some(RetentionStage::Purge)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Grade scales and rounding rules.
+//!
+//! Grades are stored in the scale of the project's class, German grades if the
+//! project has no class. The peer factor is defined on German grades, so every
+//! scale converts its values to a continuous German grade and back: Oberstufe
+//! points with the usual `(17 - points) / 3`, percentages and IHK points along
+//! their grade keys, interpolated linearly between the grade boundaries.
+//! Rounding only applies when grades are shown or exported.
+
+use crate::{
+    db::entity::{
+        class,
+        sea_orm_active_enums::{GradeRounding, GradeScale, Language},
+    },
+    error::ApiError,
+    export::round,
+};
+
+/// Lowest percentage of every German grade from 1 to 5, the rest is a 6
+const PERCENT_KEY: [f64; 5] = [85.0, 70.0, 55.0, 40.0, 20.0];
+/// Lowest IHK points of every German grade from 1 to 5, the rest is a 6
+const IHK_KEY: [f64; 5] = [92.0, 81.0, 67.0, 50.0, 30.0];
+
+const BEST_GRADE: f64 = 1.0;
+const WORST_GRADE: f64 = 6.0;
+
+/// Scale and rounding rule grades of a project are given in
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Scale {
+    pub kind: GradeScale,
+    pub rounding: GradeRounding,
+}
+
+impl Scale {
+    /// Scale of the class, German grades without class
+    pub fn of(class: Option<&class::Model>) -> Self {
+        class.map_or_else(Self::default, |class| Self {
+            kind: class.grade_scale,
+            rounding: class.grade_rounding,
+        })
+    }
+
+    /// Tendencies only exist for German grades
+    pub fn validate(self) -> Result<Self, ApiError> {
+        match (self.kind, self.rounding) {
+            (GradeScale::German, _) => Ok(self),
+            (_, GradeRounding::Tendency) => Err(ApiError::BadRequest(
+                "Tendencies can only be used with German grades".to_owned(),
+            )),
+            _ => Ok(self),
+        }
+    }
+
+    /// Best and worst value of the scale
+    pub fn bounds(&self) -> (f64, f64) {
+        match self.kind {
+            GradeScale::German => (BEST_GRADE, WORST_GRADE),
+            GradeScale::Points => (15.0, 0.0),
+            GradeScale::Percent | GradeScale::Ihk => (100.0, 0.0),
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        let (best, worst) = self.bounds();
+        (best.min(worst)..=best.max(worst)).contains(&value)
+    }
+
+    /// Continuous German grade of a value of the scale
+    pub fn german_grade(&self, value: f64) -> f64 {
+        match self.kind {
+            GradeScale::German => value,
+            GradeScale::Points => (17.0 - value) / 3.0,
+            GradeScale::Percent => from_key(&PERCENT_KEY, value),
+            GradeScale::Ihk => from_key(&IHK_KEY, value),
+        }
+    }
+
+    /// Value of the scale closest to a continuous German grade
+    pub fn scale_value(&self, grade: f64) -> f64 {
+        let value = match self.kind {
+            GradeScale::German => grade,
+            GradeScale::Points => 17.0 - 3.0 * grade,
+            GradeScale::Percent => to_key(&PERCENT_KEY, grade),
+            GradeScale::Ihk => to_key(&IHK_KEY, grade),
+        };
+        let (best, worst) = self.bounds();
+        value.clamp(best.min(worst), best.max(worst))
+    }
+
+    /// Converts a value of this scale into `other`
+    pub fn convert(&self, value: f64, other: &Scale) -> f64 {
+        if self.kind == other.kind {
+            value
+        } else {
+            other.scale_value(self.german_grade(value))
+        }
+    }
+
+    pub fn round(&self, value: f64) -> f64 {
+        match self.rounding {
+            GradeRounding::Tenth => round(value, 1),
+            GradeRounding::Half => (value * 2.0).round() / 2.0,
+            GradeRounding::Whole => value.round(),
+            // 1.3 and 1.7 as usual for 1- and 2+
+            GradeRounding::Tendency => round((value * 3.0).round() / 3.0, 1),
+        }
+    }
+
+    /// Decimal places of a rounded value, tendencies are written as `1.3` and `1.7`
+    pub fn decimals(&self) -> usize {
+        match self.rounding {
+            GradeRounding::Whole => 0,
+            GradeRounding::Tenth | GradeRounding::Half | GradeRounding::Tendency => 1,
+        }
+    }
+
+    /// Rounded value, German grades with tendency are written like `2+`
+    pub fn format(&self, value: f64, decimal_separator: char) -> String {
+        let value = self.round(value);
+        if self.rounding == GradeRounding::Tendency {
+            let whole = value.round();
+            let tendency = match value - whole {
+                d if d < -0.1 => "+",
+                d if d > 0.1 => "-",
+                _ => "",
+            };
+            return format!("{whole}{tendency}");
+        }
+
+        format!("{:.*}", self.decimals(), value).replace('.', &decimal_separator.to_string())
+    }
+
+    /// Formatted with the decimal separator of the language
+    pub fn display(&self, value: f64, language: Language) -> String {
+        let separator = match language {
+            Language::De => ',',
+            Language::En => '.',
+        };
+        self.format(value, separator)
+    }
+}
+
+/// Grade boundaries of a key as pairs of value and German grade, best first.
+/// The value where one grade ends and the next starts maps to the `.5` between
+/// them, so the lowest value of a grade still rounds to that grade.
+fn boundaries(key: &[f64; 5]) -> Vec<(f64, f64)> {
+    let mut boundaries = vec![(100.0, BEST_GRADE)];
+    boundaries.extend(
+        key.iter()
+            .enumerate()
+            .map(|(i, lowest)| (lowest - 0.5, i as f64 + 1.5)),
+    );
+    boundaries.push((0.0, WORST_GRADE));
+    boundaries
+}
+
+fn from_key(key: &[f64; 5], value: f64) -> f64 {
+    let boundaries = boundaries(key);
+    for pair in boundaries.windows(2) {
+        let ((high, better), (low, worse)) = (pair[0], pair[1]);
+        if value >= low {
+            return worse - (value - low) / (high - low) * (worse - better);
+        }
+    }
+    WORST_GRADE
+}
+
+fn to_key(key: &[f64; 5], grade: f64) -> f64 {
+    let boundaries = boundaries(key);
+    for pair in boundaries.windows(2) {
+        let ((high, better), (low, worse)) = (pair[0], pair[1]);
+        if grade <= worse {
+            return low + (worse - grade) / (worse - better) * (high - low);
+        }
+    }
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scale(kind: GradeScale, rounding: GradeRounding) -> Scale {
+        Scale { kind, rounding }
+    }
+
+    #[test]
+    fn points_convert_to_german_grades() {
+        let points = scale(GradeScale::Points, GradeRounding::Whole);
+        assert_eq!(points.german_grade(11.0), 2.0);
+        assert_eq!(points.scale_value(2.0), 11.0);
+        assert_eq!(points.scale_value(6.0), 0.0);
+        assert_eq!(points.round(points.scale_value(1.3)), 13.0);
+    }
+
+    #[test]
+    fn grade_keys_keep_the_grade_boundaries() {
+        let ihk = scale(GradeScale::Ihk, GradeRounding::Whole);
+        assert_eq!(ihk.german_grade(100.0), 1.0);
+        assert_eq!(ihk.german_grade(92.0).round(), 1.0);
+        assert_eq!(ihk.german_grade(91.0).round(), 2.0);
+        assert_eq!(ihk.german_grade(50.0).round(), 4.0);
+        assert_eq!(ihk.german_grade(49.0).round(), 5.0);
+        assert_eq!(ihk.german_grade(0.0), 6.0);
+
+        let percent = scale(GradeScale::Percent, GradeRounding::Whole);
+        assert_eq!(percent.german_grade(85.0).round(), 1.0);
+        assert_eq!(percent.german_grade(84.0).round(), 2.0);
+        for value in [0.0, 19.5, 42.0, 77.7, 100.0] {
+            assert!((percent.scale_value(percent.german_grade(value)) - value).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn conversion_between_scales() {
+        let german = Scale::default();
+        let points = scale(GradeScale::Points, GradeRounding::Whole);
+        assert_eq!(german.convert(2.0, &points), 11.0);
+        assert_eq!(points.convert(15.0, &german), 1.0);
+        assert_eq!(german.convert(2.5, &german), 2.5);
+    }
+
+    #[test]
+    fn tendencies() {
+        let tendency = scale(GradeScale::German, GradeRounding::Tendency);
+        assert_eq!(tendency.round(1.8), 1.7);
+        assert_eq!(tendency.format(1.8, ','), "2+");
+        assert_eq!(tendency.format(2.2, ','), "2-");
+        assert_eq!(tendency.format(2.1, ','), "2");
+        assert!(
+            scale(GradeScale::Points, GradeRounding::Tendency)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn format_follows_the_rounding() {
+        assert_eq!(Scale::default().format(2.25, ','), "2,3");
+        assert_eq!(
+            scale(GradeScale::German, GradeRounding::Half).format(2.3, '.'),
+            "2.5"
+        );
+        assert_eq!(
+            scale(GradeScale::Percent, GradeRounding::Whole).format(86.5, '.'),
+            "87"
+        );
+    }
+}
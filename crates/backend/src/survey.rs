@@ -0,0 +1,209 @@
+//! Lifecycle of the feedback survey of a project.
+//!
+//! A survey starts as a draft, is opened for the students and closed again,
+//! possibly several times, and is finalised once grading is done. Students can
+//! only submit while the survey is open and, if set, between
+//! `feedback_opens_at` and `feedback_deadline`. Finalising locks grades and
+//! responses, a finalised survey can only be reopened to closed with a reason.
+//! Every status change is recorded as a `survey_event`.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    db::entity::{project, sea_orm_active_enums::SurveyStatus},
+    error::ApiError,
+};
+
+/// Checks whether the survey may move from `from` to `to`
+pub fn check_transition(
+    from: SurveyStatus,
+    to: SurveyStatus,
+    reason: Option<&str>,
+) -> Result<(), ApiError> {
+    use SurveyStatus::*;
+
+    match (from, to) {
+        (Draft, Open) | (Open, Closed) | (Closed, Open) | (Closed, Finalised) => Ok(()),
+        (Finalised, Closed) if reason.is_some_and(|r| !r.trim().is_empty()) => Ok(()),
+        (Finalised, Closed) => Err(ApiError::BadRequest(
+            "Reopening a finalised survey requires a reason".to_owned(),
+        )),
+        (from, to) => Err(ApiError::BadRequest(format!(
+            "The survey can't change from {} to {}",
+            name(from),
+            name(to)
+        ))),
+    }
+}
+
+/// Checks that students can submit feedback for the project at `now`
+pub fn check_submission_window(
+    project: &project::Model,
+    now: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    match project.survey_status {
+        SurveyStatus::Open => {}
+        SurveyStatus::Draft => {
+            return Err(ApiError::SurveyNotOpen(
+                "The feedback survey has not been opened yet".to_owned(),
+            ));
+        }
+        SurveyStatus::Closed | SurveyStatus::Finalised => {
+            return Err(ApiError::SurveyNotOpen(
+                "The feedback survey is closed".to_owned(),
+            ));
+        }
+    }
+
+    if let Some(opens_at) = project.feedback_opens_at.map(|d| d.to_utc())
+        && now < opens_at
+    {
+        return Err(ApiError::SurveyNotOpen(format!(
+            "The feedback period starts at {}",
+            format_time(opens_at)
+        )));
+    }
+    if let Some(deadline) = project.feedback_deadline.map(|d| d.to_utc())
+        && now >= deadline
+    {
+        return Err(ApiError::SurveyNotOpen(format!(
+            "The feedback period ended at {}",
+            format_time(deadline)
+        )));
+    }
+
+    Ok(())
+}
+
+/// Grades and responses of a finalised survey can't be changed
+pub fn ensure_not_finalised(project: &project::Model) -> Result<(), ApiError> {
+    match project.survey_status {
+        SurveyStatus::Finalised => Err(ApiError::SurveyLocked),
+        _ => Ok(()),
+    }
+}
+
+fn name(status: SurveyStatus) -> &'static str {
+    match status {
+        SurveyStatus::Draft => "draft",
+        SurveyStatus::Open => "open",
+        SurveyStatus::Closed => "closed",
+        SurveyStatus::Finalised => "finalised",
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::entity::sea_orm_active_enums::{Language, PeerAggregation};
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+    }
+
+    fn project(
+        status: SurveyStatus,
+        opens_at: Option<&str>,
+        deadline: Option<&str>,
+    ) -> project::Model {
+        project::Model {
+            id: uuid::Uuid::nil(),
+            name: "Lernfeld 5".to_owned(),
+            feedback_deadline: deadline.map(|d| at(d).into()),
+            reminder_days: Vec::new(),
+            reminders_enabled: true,
+            language: Language::De,
+            survey_status: status,
+            feedback_opens_at: opens_at.map(|d| at(d).into()),
+            self_assessment: false,
+            self_assessment_in_grade: false,
+            peer_aggregation: PeerAggregation::Mean,
+            max_grade_deviation: None,
+            class_id: None,
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn transitions_follow_the_lifecycle() {
+        use SurveyStatus::*;
+
+        assert!(check_transition(Draft, Open, None).is_ok());
+        assert!(check_transition(Open, Closed, None).is_ok());
+        assert!(check_transition(Closed, Open, None).is_ok());
+        assert!(check_transition(Closed, Finalised, None).is_ok());
+
+        assert!(check_transition(Draft, Finalised, None).is_err());
+        assert!(check_transition(Open, Finalised, None).is_err());
+        assert!(check_transition(Finalised, Open, Some("typo")).is_err());
+        assert!(check_transition(Open, Open, None).is_err());
+    }
+
+    #[test]
+    fn reopening_requires_a_reason() {
+        use SurveyStatus::*;
+
+        assert!(check_transition(Finalised, Closed, None).is_err());
+        assert!(check_transition(Finalised, Closed, Some("  ")).is_err());
+        assert!(check_transition(Finalised, Closed, Some("Wrong group grade")).is_ok());
+    }
+
+    #[test]
+    fn submissions_need_an_open_survey() {
+        let now = at("2026-11-01T10:00:00Z");
+
+        assert!(check_submission_window(&project(SurveyStatus::Open, None, None), now).is_ok());
+        for status in [
+            SurveyStatus::Draft,
+            SurveyStatus::Closed,
+            SurveyStatus::Finalised,
+        ] {
+            assert!(matches!(
+                check_submission_window(&project(status, None, None), now),
+                Err(ApiError::SurveyNotOpen(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn submissions_respect_the_window() {
+        let project = project(
+            SurveyStatus::Open,
+            Some("2026-11-01T08:00:00Z"),
+            Some("2026-11-10T12:00:00Z"),
+        );
+
+        assert!(check_submission_window(&project, at("2026-11-01T08:00:00Z")).is_ok());
+        assert!(check_submission_window(&project, at("2026-11-10T11:59:59Z")).is_ok());
+
+        let Err(ApiError::SurveyNotOpen(message)) =
+            check_submission_window(&project, at("2026-11-01T07:59:00Z"))
+        else {
+            panic!("submission before the window was accepted");
+        };
+        assert_eq!(
+            message,
+            "The feedback period starts at 2026-11-01 08:00 UTC"
+        );
+
+        let Err(ApiError::SurveyNotOpen(message)) =
+            check_submission_window(&project, at("2026-11-10T12:00:00Z"))
+        else {
+            panic!("submission after the deadline was accepted");
+        };
+        assert_eq!(message, "The feedback period ended at 2026-11-10 12:00 UTC");
+    }
+
+    #[test]
+    fn only_finalised_surveys_are_locked() {
+        assert!(ensure_not_finalised(&project(SurveyStatus::Closed, None, None)).is_ok());
+        assert!(matches!(
+            ensure_not_finalised(&project(SurveyStatus::Finalised, None, None)),
+            Err(ApiError::SurveyLocked)
+        ));
+    }
+}
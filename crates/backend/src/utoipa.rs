@@ -1,6 +1,8 @@
 use utoipa::OpenApi;
 
-use crate::{controller, db, db::entity, error};
+use crate::{
+    analytics, controller, db, db::entity, error, export, grouping, pagination, privacy, retention,
+};
 
 #[derive(OpenApi)]
 #[openapi(
@@ -12,47 +14,194 @@ use crate::{controller, db, db::entity, error};
     paths(
         controller::auth::login,
         controller::auth::logout,
+        controller::auth::oidc_login,
+        controller::auth::oidc_callback,
         controller::project::get_projects,
         controller::project::get_project,
         controller::project::create_project,
         controller::project::update_project,
         controller::project::delete_project,
+        controller::project::clone_project,
+        controller::project::archive_project,
+        controller::project::unarchive_project,
+        controller::project::update_feedback_settings,
+        controller::project::update_grading_settings,
+        controller::project::send_feedback_links,
+        controller::project::get_mail_deliveries,
+        controller::project::set_survey_status,
+        controller::project::get_survey_events,
+        controller::project::get_grades,
+        controller::project::get_grade_history,
+        controller::project::export_grades,
+        controller::project::get_student_report,
+        controller::project::get_group_report,
+        controller::project::get_project_analytics,
+        controller::project::generate_groups,
+        controller::project::create_groups,
+        controller::job::get_jobs,
+        controller::job::get_job,
+        controller::user::get_me,
+        controller::user::get_tokens,
+        controller::user::create_token,
+        controller::user::delete_token,
         controller::user::get_users,
         controller::user::get_user,
         controller::user::create_user,
+        controller::user::bulk_create_users,
         controller::user::update_user,
+        controller::user::deactivate_user,
+        controller::user::reactivate_user,
+        controller::user::export_user_data,
+        controller::user::erase_user,
         controller::user::delete_user,
         controller::group::get_groups,
         controller::group::get_groups_for_project,
         controller::group::create_group,
         controller::group::update_group,
         controller::group::delete_group,
+        controller::group::generate_group_feedback_tokens,
+        controller::group::set_group_grade,
+        controller::group::set_individual_grades,
+        controller::group::move_group_member,
+        controller::group::remove_group_member,
+        controller::feedback::get_feedback_form,
+        controller::feedback::submit_feedback,
+        controller::feedback::get_feedback_status,
+        controller::feedback::reset_feedback,
+        controller::feedback::open_feedback_session,
+        controller::feedback::get_session_feedback_form,
+        controller::feedback::submit_session_feedback,
+        controller::feedback::get_session_feedback_status,
         controller::class::get_classes,
         controller::class::get_class,
         controller::class::create_class,
         controller::class::update_class,
         controller::class::delete_class,
+        controller::class::get_roster,
+        controller::class::enrol_student,
+        controller::class::import_roster,
+        controller::class::update_enrolment,
+        controller::class::delete_enrolment,
         controller::template::get_templates,
         controller::template::get_template,
         controller::template::create_template,
         controller::template::update_template,
         controller::template::delete_template,
+        controller::audit::get_audit_events,
+        controller::school_year::get_school_years,
+        controller::school_year::get_school_year,
+        controller::school_year::create_school_year,
+        controller::school_year::update_school_year,
+        controller::school_year::delete_school_year,
+        controller::retention::get_retention_report,
+        controller::retention::apply_retention,
     ),
     components(schemas(
         controller::auth::LoginRequest,
         error::MessageResponse,
+        error::ProblemDetails,
+        error::FieldError,
         db::project::CreateProject,
+        controller::project::FeedbackSettings,
+        controller::project::GradingSettings,
+        controller::project::SendFeedbackLinks,
+        controller::project::CloneProject,
+        controller::project::SurveyTransition,
+        controller::project::ProjectGrade,
+        controller::project::GenerateGroups,
+        controller::project::CreateGroups,
+        controller::project::CreateGroup,
+        controller::project::CreatedGroup,
+        grouping::GroupingMethod,
+        grouping::BalanceBy,
+        grouping::ProposedGroup,
+        grouping::ProposedMember,
+        controller::class::ClassSettings,
+        controller::class::RosterEntry,
+        controller::class::Enrolment,
+        controller::class::EnrolmentPeriod,
+        controller::class::RosterRowStatus,
+        controller::class::RosterRowResult,
+        controller::class::RosterImportResult,
+        export::ExportFormat,
+        analytics::ProjectAnalytics,
+        analytics::QuestionAnalytics,
+        analytics::StudentAnalytics,
+        analytics::OutlierRater,
+        analytics::OutlierReason,
+        analytics::GroupAnalytics,
+        controller::group::GroupGrade,
+        controller::group::IndividualGrades,
+        controller::group::IndividualGrade,
+        controller::group::MoveMember,
+        db::group::MembershipChange,
+        controller::feedback::FeedbackForm,
+        controller::feedback::FeedbackFormMember,
+        controller::feedback::StudentSession,
+        controller::feedback::SubmitFeedback,
+        controller::feedback::PeerRating,
+        controller::feedback::SelfAssessment,
+        controller::feedback::FeedbackStatus,
+        controller::feedback::ReceivedFeedback,
         controller::user::CreateUser,
+        controller::user::UpdateUser,
+        controller::user::BulkRowStatus,
+        controller::user::BulkUserResult,
+        controller::user::BulkCreateResult,
+        controller::user::CreateApiToken,
+        controller::user::CreatedApiToken,
+        privacy::DataExportFormat,
+        privacy::UserDataExport,
+        privacy::Profile,
+        privacy::Membership,
+        privacy::ReceivedRating,
+        controller::school_year::SchoolYearSettings,
+        retention::RetentionPolicy,
+        retention::RetentionStage,
+        retention::RetentionReport,
+        retention::RetainedProject,
+        retention::PurgedStudent,
+        pagination::PageLinks,
         entity::project::Model,
+        entity::class::Model,
+        entity::class_enrolment::Model,
         entity::user::Model,
+        entity::api_token::Model,
+        entity::sea_orm_active_enums::UserRole,
+        entity::sea_orm_active_enums::TokenAccess,
+        entity::sea_orm_active_enums::Language,
+        entity::sea_orm_active_enums::MailKind,
+        entity::sea_orm_active_enums::MailStatus,
+        entity::sea_orm_active_enums::JobKind,
+        entity::sea_orm_active_enums::JobStatus,
+        entity::sea_orm_active_enums::SurveyStatus,
+        entity::sea_orm_active_enums::PeerAggregation,
+        entity::sea_orm_active_enums::GradeScale,
+        entity::sea_orm_active_enums::GradeRounding,
+        entity::sea_orm_active_enums::AuditAction,
+        entity::mail_delivery::Model,
+        entity::job::Model,
+        entity::group::Model,
+        entity::user_group_project::Model,
+        entity::survey_event::Model,
+        entity::grade_override::Model,
+        entity::audit_event::Model,
+        entity::school_year::Model,
+        entity::feedback_response::Model,
+        entity::self_assessment::Model,
     )),
     tags(
         (name = "auth", description = "Authentication endpoints"),
         (name = "users", description = "User management endpoints"),
         (name = "projects", description = "Project management endpoints"),
+        (name = "feedback", description = "Feedback form endpoints for students"),
+        (name = "jobs", description = "Background job status endpoints"),
         (name = "groups", description = "Group management endpoints (Not Implemented)"),
-        (name = "classes", description = "Class management endpoints (Not Implemented)"),
+        (name = "classes", description = "Class management endpoints"),
         (name = "templates", description = "Template management endpoints (Not Implemented)"),
+        (name = "audit", description = "Audit log of the changes made by teachers and admins"),
+        (name = "school years", description = "School years and terms of the classes"),
+        (name = "retention", description = "Archiving and deletion of the data of past school years"),
     )
 )]
 pub struct ApiDoc;
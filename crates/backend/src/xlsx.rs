@@ -0,0 +1,190 @@
+//! Minimal XLSX writing.
+//!
+//! Writes a workbook with a single sheet of text and number cells, which is
+//! all the grade exports need. Strings are stored inline, so no shared string
+//! table or styles are required.
+
+use std::io::{Cursor, Write};
+use zip::{ZipWriter, result::ZipResult, write::SimpleFileOptions};
+
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::Text(value.to_owned())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Cell::Text(value)
+    }
+}
+
+impl From<Option<f64>> for Cell {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(Cell::Empty, Cell::Number)
+    }
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+/// Writes `rows` as the only sheet of a workbook
+pub fn write(sheet_name: &str, rows: &[Vec<Cell>]) -> ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+
+    zip.start_file("[Content_Types].xml", options)?;
+    zip.write_all(CONTENT_TYPES.as_bytes())?;
+    zip.start_file("_rels/.rels", options)?;
+    zip.write_all(ROOT_RELS.as_bytes())?;
+    zip.start_file("xl/_rels/workbook.xml.rels", options)?;
+    zip.write_all(WORKBOOK_RELS.as_bytes())?;
+    zip.start_file("xl/workbook.xml", options)?;
+    zip.write_all(workbook(sheet_name).as_bytes())?;
+    zip.start_file("xl/worksheets/sheet1.xml", options)?;
+    zip.write_all(worksheet(rows).as_bytes())?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+fn workbook(sheet_name: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        escape(&sheet_title(sheet_name))
+    )
+}
+
+fn worksheet(rows: &[Vec<Cell>]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    );
+
+    for (row_index, row) in rows.iter().enumerate() {
+        let row_number = row_index + 1;
+        xml.push_str(&format!(r#"<row r="{row_number}">"#));
+
+        for (column, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(column), row_number);
+            match cell {
+                Cell::Text(text) => xml.push_str(&format!(
+                    r#"<c r="{reference}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    escape(text)
+                )),
+                Cell::Number(number) if number.is_finite() => {
+                    xml.push_str(&format!(r#"<c r="{reference}"><v>{number}</v></c>"#))
+                }
+                Cell::Number(..) | Cell::Empty => {}
+            }
+        }
+
+        xml.push_str("</row>");
+    }
+
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Spreadsheet column name of a zero based index, e.g. `A`, `Z`, `AA`
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Sheet names are limited to 31 characters and may not contain `[]:*?/\`
+fn sheet_title(name: &str) -> String {
+    let title: String = name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect();
+
+    if title.trim().is_empty() {
+        "Sheet1".to_owned()
+    } else {
+        title
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        // Control characters other than tab and newlines are invalid in XML 1.0
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .fold(String::with_capacity(text.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                c => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn read_entry(xlsx: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(xlsx)).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn column_names() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(27), "AB");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn write_contains_cells() {
+        let rows = vec![
+            vec![Cell::from("Name"), Cell::from("Note")],
+            vec![Cell::from("Müller & Söhne <AG>"), Cell::Number(2.3)],
+            vec![Cell::from("Erika"), Cell::Empty],
+        ];
+        let xlsx = write("Noten: LF 5", &rows).unwrap();
+
+        let sheet = read_entry(&xlsx, "xl/worksheets/sheet1.xml");
+        assert!(sheet.contains(r#"<c r="A1" t="inlineStr"><is><t xml:space="preserve">Name</t>"#));
+        assert!(sheet.contains("Müller &amp; Söhne &lt;AG&gt;"));
+        assert!(sheet.contains(r#"<c r="B2"><v>2.3</v></c>"#));
+        assert!(!sheet.contains(r#"r="B3""#));
+
+        let workbook = read_entry(&xlsx, "xl/workbook.xml");
+        assert!(workbook.contains(r#"<sheet name="Noten LF 5""#));
+    }
+}
@@ -1,3 +1,3 @@
-pub mod user_helpers;
-pub mod project_helpers;
 pub mod auth_helpers;
+pub mod project_helpers;
+pub mod user_helpers;
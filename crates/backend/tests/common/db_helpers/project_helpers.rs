@@ -2,6 +2,7 @@ use crate::common::test_helpers::TestContext;
 use backend::{
     Database,
     db::{entity, project::CreateProject},
+    pagination::Pagination,
 };
 use uuid::Uuid;
 
@@ -12,9 +13,12 @@ impl TestContext {
         name: Option<String>,
     ) -> Result<entity::project::Model, backend::error::ApiError> {
         let name = name.unwrap_or_else(|| format!("Test Project {}", self.test_id));
-        let create_project = CreateProject { name };
+        let create_project = CreateProject {
+            name,
+            class_id: None,
+        };
 
-        let project = db.create_project(create_project).await?;
+        let project = db.create_project(create_project, None).await?;
 
         if let Ok(mut projects) = self.created_projects.lock() {
             projects.push(project.id);
@@ -23,6 +27,91 @@ impl TestContext {
         Ok(project)
     }
 
+    /// Creates a group in the project with the given users as members, without
+    /// the role, enrolment and survey checks of `Database::create_groups`
+    pub async fn create_group_with_members(
+        &self,
+        db: &Database,
+        project: &entity::project::Model,
+        name: &str,
+        members: &[&entity::user::Model],
+    ) -> Result<entity::group::Model, sea_orm::DbErr> {
+        use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+
+        let group = entity::group::ActiveModel {
+            project_id: Set(project.id),
+            name: Set(name.to_owned()),
+            ..Default::default()
+        }
+        .insert(db.connection())
+        .await?;
+
+        for member in members {
+            entity::user_group_project::ActiveModel {
+                user_id: Set(member.id),
+                group_id: Set(group.id),
+                project_id: Set(project.id),
+                ..Default::default()
+            }
+            .insert(db.connection())
+            .await?;
+        }
+
+        Ok(group)
+    }
+
+    /// Marks the feedback of a group member as submitted
+    pub async fn complete_feedback(
+        &self,
+        db: &Database,
+        group: &entity::group::Model,
+        user: &entity::user::Model,
+    ) -> Result<(), sea_orm::DbErr> {
+        use sea_orm::{ActiveModelTrait, ActiveValue::Set, ActiveValue::Unchanged};
+
+        entity::user_group_project::ActiveModel {
+            user_id: Unchanged(user.id),
+            group_id: Unchanged(group.id),
+            project_id: Unchanged(group.project_id),
+            feedback_completed: Set(true),
+            ..Default::default()
+        }
+        .update(db.connection())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Submits the ratings a group member gives their group mates, the survey has to be open
+    pub async fn submit_ratings(
+        &self,
+        db: &Database,
+        group: &entity::group::Model,
+        from: &entity::user::Model,
+        ratings: &[(&entity::user::Model, i16)],
+    ) -> Result<(), backend::error::ApiError> {
+        use backend::db::feedback::NewFeedbackResponse;
+
+        let (member, _) = db
+            .get_group_members(group.project_id, group.id)
+            .await?
+            .into_iter()
+            .find(|(member, _)| member.user_id == from.id)
+            .ok_or(backend::error::ApiError::NotFound)?;
+
+        let responses = ratings
+            .iter()
+            .map(|(to, rating)| NewFeedbackResponse {
+                to_user_id: to.id,
+                rating: *rating,
+                comment: None,
+            })
+            .collect();
+
+        db.submit_feedback(&member, responses, None, chrono::Utc::now())
+            .await
+    }
+
     pub async fn create_project_with_name(
         &self,
         db: &Database,
@@ -59,7 +148,9 @@ impl TestContext {
         &self,
         db: &Database,
     ) -> Result<Vec<entity::project::Model>, backend::error::ApiError> {
-        db.get_projects().await
+        db.get_projects(None, true, &Pagination::new(1, u64::MAX))
+            .await
+            .map(|(projects, _)| projects)
     }
 
     pub async fn update_project(
@@ -68,15 +159,15 @@ impl TestContext {
         id: &Uuid,
         name: String,
     ) -> Result<entity::project::Model, backend::error::ApiError> {
-        let update_data = CreateProject { name };
-        db.update_project(id, update_data).await
+        let update_data = CreateProject {
+            name,
+            class_id: None,
+        };
+        db.update_project(id, update_data, None).await
     }
 
     pub async fn assert_project_exists(&self, db: &Database, id: &Uuid) -> bool {
-        match self.get_project_by_id(db, id).await {
-            Ok(Some(_)) => true,
-            _ => false,
-        }
+        matches!(self.get_project_by_id(db, id).await, Ok(Some(_)))
     }
 
     pub async fn assert_project_count(&self, db: &Database, expected: usize) -> bool {
@@ -102,7 +193,7 @@ impl TestContext {
         db: &Database,
         id: &Uuid,
     ) -> Result<(), backend::error::ApiError> {
-        db.delete_project(id).await?;
+        db.delete_project(id, None).await?;
 
         if let Ok(mut projects) = self.created_projects.lock() {
             projects.retain(|&project_id| project_id != *id);
@@ -112,10 +203,14 @@ impl TestContext {
     }
 
     pub async fn cleanup_projects(&self, db: &Database) {
-        if let Ok(projects) = self.created_projects.lock() {
-            for project_id in projects.iter() {
-                let _ = db.delete_project(project_id).await;
-            }
+        let projects = self
+            .created_projects
+            .lock()
+            .map(|projects| projects.clone())
+            .unwrap_or_default();
+
+        for project_id in projects {
+            let _ = db.delete_project(&project_id, None).await;
         }
 
         if let Ok(mut projects) = self.created_projects.lock() {
@@ -1,5 +1,13 @@
 use crate::common::test_helpers::TestContext;
-use backend::{Database, db::entity};
+use backend::{
+    Database,
+    db::entity::{
+        self,
+        sea_orm_active_enums::{TokenAccess, UserRole},
+    },
+    pagination::Pagination,
+};
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 impl TestContext {
@@ -14,7 +22,56 @@ impl TestContext {
         let name = name.unwrap_or_else(|| format!("name_{}", test_id));
         let password = "password123".to_string();
 
-        let user = db.create_user(name, username, password).await?;
+        let user = db
+            .create_user(
+                name,
+                username,
+                None,
+                Some(password),
+                UserRole::Teacher,
+                None,
+            )
+            .await?;
+
+        if let Ok(mut users) = self.created_users.lock() {
+            users.push(user.id);
+        }
+
+        Ok(user)
+    }
+
+    /// Creates a user with the given role and an email address, the username is prefixed with the role
+    pub async fn create_user_with_role(
+        &self,
+        db: &Database,
+        role: UserRole,
+    ) -> Result<entity::user::Model, backend::error::ApiError> {
+        let role_name = serde_json::to_value(role).unwrap();
+        self.create_named_user_with_role(db, role_name.as_str().unwrap(), role)
+            .await
+    }
+
+    /// Creates a further user with the given role, the username is prefixed with `prefix`
+    pub async fn create_named_user_with_role(
+        &self,
+        db: &Database,
+        prefix: &str,
+        role: UserRole,
+    ) -> Result<entity::user::Model, backend::error::ApiError> {
+        let username = format!("{}_{}", prefix, self.test_id);
+        let name = format!("name_{}", username);
+        let email = format!("{}@example.org", username);
+
+        let user = db
+            .create_user(
+                name,
+                username,
+                Some(email),
+                Some("password123".to_string()),
+                role,
+                None,
+            )
+            .await?;
 
         if let Ok(mut users) = self.created_users.lock() {
             users.push(user.id);
@@ -40,6 +97,27 @@ impl TestContext {
         Ok(users)
     }
 
+    /// Creates a personal access token for the user and returns the plaintext token
+    pub async fn create_api_token(
+        &self,
+        db: &Database,
+        user: &entity::user::Model,
+        access: TokenAccess,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<String, backend::error::ApiError> {
+        let (_, token) = db
+            .create_api_token(
+                user.id,
+                format!("token_{}", self.test_id),
+                user.role,
+                access,
+                expires_at,
+            )
+            .await?;
+
+        Ok(token)
+    }
+
     pub async fn get_user_by_id(
         &self,
         db: &Database,
@@ -52,7 +130,9 @@ impl TestContext {
         &self,
         db: &Database,
     ) -> Result<Vec<entity::user::Model>, backend::error::ApiError> {
-        db.get_users().await
+        db.get_users(None, true, &Pagination::new(1, u64::MAX))
+            .await
+            .map(|(users, _)| users)
     }
 
     pub async fn assert_user_exists(&self, db: &Database, id: Uuid) -> bool {
@@ -76,7 +156,7 @@ impl TestContext {
         db: &Database,
         id: Uuid,
     ) -> Result<(), backend::error::ApiError> {
-        db.delete_user(id).await?;
+        db.delete_user(id, None).await?;
 
         if let Ok(mut users) = self.created_users.lock() {
             users.retain(|&user_id| user_id != id);
@@ -86,10 +166,14 @@ impl TestContext {
     }
 
     pub async fn cleanup_users(&self, db: &Database) {
-        if let Ok(users) = self.created_users.lock() {
-            for user_id in users.iter() {
-                let _ = db.delete_user(*user_id).await;
-            }
+        let users = self
+            .created_users
+            .lock()
+            .map(|users| users.clone())
+            .unwrap_or_default();
+
+        for user_id in users {
+            let _ = db.delete_user(user_id, None).await;
         }
 
         if let Ok(mut users) = self.created_users.lock() {
@@ -1,3 +1,6 @@
+// Not every integration test uses every helper
+#![allow(dead_code)]
+
 pub mod db_helpers;
 pub mod setup;
 pub mod test_helpers;
@@ -33,7 +33,7 @@ pub async fn get_database() -> Database {
         .min_connections(1)
         .connect_timeout(std::time::Duration::from_secs(10))
         .acquire_timeout(std::time::Duration::from_secs(10));
-    
+
     Database::new(opts).await.unwrap()
 }
 
@@ -73,11 +73,19 @@ impl TestContext {
 #[macro_export]
 macro_rules! create_test_app {
     () => {{
+        $crate::create_test_app!(backend::mail::Mailer::new(
+            "pgg@example.org".to_owned(),
+            "http://localhost:8080".to_owned(),
+            backend::mail::Transport::Log,
+        ))
+    }};
+    ($mailer:expr) => {{
         let db = $crate::common::test_helpers::get_database().await;
 
         actix_web::test::init_service(
             actix_web::App::new()
                 .app_data(actix_web::web::Data::new(db))
+                .app_data(actix_web::web::Data::new($mailer))
                 .service(
                     actix_web::web::scope("/api/v1")
                         .configure(backend::controller::register_controllers),
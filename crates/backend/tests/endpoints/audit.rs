@@ -0,0 +1,86 @@
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::db::entity::sea_orm_active_enums::{TokenAccess, UserRole};
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_audit_log_records_grade_changes() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let teacher_token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let admin_token = ctx
+            .create_api_token(db, &admin, TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student])
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/group/{}/grade", group.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", teacher_token)))
+            .set_json(serde_json::json!({ "grade": 2.0 }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let events = |token: &str| {
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/v1/audit?project_id={}&action=group_grade_set",
+                    project.id
+                ))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+
+        let resp = events(&teacher_token).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = events(&admin_token).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["total"], 1);
+        let event = &body["items"][0];
+        assert_eq!(event["actor_id"], teacher.id.to_string());
+        assert_eq!(event["entity_id"], group.id.to_string());
+        assert_eq!(event["before"]["grade"], serde_json::Value::Null);
+        assert_eq!(event["after"]["grade"], 2.0);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/audit?action=unknown")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", admin_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        ctx.cleanup_all(db).await;
+    }
+}
@@ -1,7 +1,280 @@
-use crate::create_test_app;
+use actix_session::{SessionMiddleware, storage::CookieSessionStore};
+use actix_web::{
+    App, HttpResponse, HttpServer,
+    cookie::{Cookie, Key},
+    http::{StatusCode, header},
+    test, web,
+};
+use backend::{
+    controller,
+    db::entity::sea_orm_active_enums::UserRole,
+    oidc::{OidcClient, OidcConfig},
+    pagination::Pagination,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio_rustls::rustls::{
+    self, RootCertStore, ServerConfig,
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+};
+
+use crate::common::test_helpers::{TestContext, get_database};
+
+#[derive(Default)]
+struct MockIdpState {
+    issuer: String,
+    nonce: String,
+    code_challenge: String,
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    code: String,
+    code_verifier: String,
+    client_id: String,
+}
+
+/// Starts a minimal identity provider that issues one ID token for the code `mock-code`,
+/// served over https with a self-signed certificate the returned client trusts
+async fn start_mock_idp() -> (Arc<Mutex<MockIdpState>>, OidcClient) {
+    let certificate = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_owned()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+        certificate.key_pair.serialize_der(),
+    ));
+    let tls =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate.cert.der().clone()], key)
+            .unwrap();
+
+    let state = Arc::new(Mutex::new(MockIdpState::default()));
+    let server_state = state.clone();
+
+    let server = HttpServer::new(move || {
+        let state = server_state.clone();
+        App::new()
+            .app_data(web::Data::new(state))
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(|state: web::Data<Arc<Mutex<MockIdpState>>>| async move {
+                    let issuer = state.lock().unwrap().issuer.clone();
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "issuer": issuer,
+                        "authorization_endpoint": format!("{issuer}/authorize"),
+                        "token_endpoint": format!("{issuer}/token"),
+                    }))
+                }),
+            )
+            .route(
+                "/token",
+                web::post().to(
+                    |state: web::Data<Arc<Mutex<MockIdpState>>>,
+                     form: web::Form<TokenRequest>| async move {
+                        let state = state.lock().unwrap();
+                        let challenge =
+                            URL_SAFE_NO_PAD.encode(Sha256::digest(form.code_verifier.as_bytes()));
+
+                        if form.code != "mock-code" || challenge != state.code_challenge {
+                            return HttpResponse::BadRequest()
+                                .json(serde_json::json!({ "error": "invalid_grant" }));
+                        }
+
+                        let claims = serde_json::json!({
+                            "iss": state.issuer,
+                            "aud": form.client_id,
+                            "sub": format!("sub-{}", state.username),
+                            "exp": chrono::Utc::now().timestamp() + 300,
+                            "nonce": state.nonce,
+                            "preferred_username": state.username,
+                            "name": "Mock Teacher",
+                            "groups": ["lehrkraefte"],
+                        });
+                        let id_token = format!(
+                            "{}.{}.",
+                            URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+                            URL_SAFE_NO_PAD.encode(claims.to_string())
+                        );
+
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "access_token": "mock-access-token",
+                            "token_type": "Bearer",
+                            "id_token": id_token,
+                        }))
+                    },
+                ),
+            )
+    })
+    .bind_rustls_0_23(("127.0.0.1", 0), tls)
+    .unwrap();
+
+    let port = server.addrs()[0].port();
+    let issuer = format!("https://127.0.0.1:{port}");
+    state.lock().unwrap().issuer = issuer.clone();
+    actix_web::rt::spawn(server.run());
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate.cert.der().clone()).unwrap();
+    let oidc = OidcClient::discover_with_roots(oidc_config(issuer), roots)
+        .await
+        .unwrap();
+
+    (state, oidc)
+}
+
+fn oidc_config(issuer_url: String) -> OidcConfig {
+    OidcConfig {
+        issuer_url,
+        client_id: "pgg".to_owned(),
+        client_secret: None,
+        redirect_url: "http://localhost:8080/api/v1/auth/oidc/callback".to_owned(),
+        scopes: "openid profile".to_owned(),
+        post_login_redirect: "/dashboard".to_owned(),
+        username_claim: "preferred_username".to_owned(),
+        role_claim: "groups".to_owned(),
+        admin_roles: vec![],
+        teacher_roles: vec!["lehrkraefte".to_owned()],
+        default_role: UserRole::Student,
+    }
+}
+
+fn query_params(location: &str) -> HashMap<String, String> {
+    let query = location.split_once('?').map(|(_, q)| q).unwrap_or_default();
+    serde_urlencoded::from_str(query).unwrap()
+}
 
 #[cfg(test)]
 mod tests {
-
     use super::*;
+
+    macro_rules! create_oidc_test_app {
+        ($db:expr, $oidc:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($db.clone()))
+                    .app_data(web::Data::new($oidc))
+                    .wrap(
+                        SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                            .cookie_secure(false)
+                            .build(),
+                    )
+                    .service(web::scope("/api/v1").configure(controller::register_controllers)),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn test_oidc_login_creates_user() {
+        let ctx = TestContext::new();
+        let db = get_database().await;
+        let (idp, oidc) = start_mock_idp().await;
+        let app = create_oidc_test_app!(db, oidc);
+
+        let username = format!("oidc_{}", ctx.test_id);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/auth/oidc/login")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let session_cookie: Cookie = resp.response().cookies().next().unwrap().into_owned();
+        let params = query_params(&location);
+
+        assert!(location.contains("/authorize?"));
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["client_id"], "pgg");
+
+        {
+            let mut idp = idp.lock().unwrap();
+            idp.nonce = params["nonce"].clone();
+            idp.code_challenge = params["code_challenge"].clone();
+            idp.username = username.clone();
+        }
+
+        let resp = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/auth/oidc/callback?code=mock-code&state={}",
+                params["state"]
+            ))
+            .cookie(session_cookie)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "/dashboard");
+
+        let user = db
+            .get_users(Some(&username), true, &Pagination::new(1, 25))
+            .await
+            .unwrap()
+            .0
+            .into_iter()
+            .find(|user| user.username == username)
+            .expect("OIDC login should create the user");
+        ctx.created_users.lock().unwrap().push(user.id);
+
+        assert_eq!(user.name, "Mock Teacher");
+        assert_eq!(user.role, UserRole::Teacher);
+
+        ctx.cleanup_all(&db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_oidc_callback_rejects_state_mismatch() {
+        let db = get_database().await;
+        let (_idp, oidc) = start_mock_idp().await;
+        let app = create_oidc_test_app!(db, oidc);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/auth/oidc/login")
+            .send_request(&app)
+            .await;
+        let session_cookie: Cookie = resp.response().cookies().next().unwrap().into_owned();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/auth/oidc/callback?code=mock-code&state=forged")
+            .cookie(session_cookie)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_oidc_callback_without_login() {
+        let db = get_database().await;
+        let (_idp, oidc) = start_mock_idp().await;
+        let app = create_oidc_test_app!(db, oidc);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/auth/oidc/callback?code=mock-code&state=anything")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_oidc_login_not_configured() {
+        let app = crate::create_test_app!();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/auth/oidc/login")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,193 @@
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::{
+    db::{
+        entity::sea_orm_active_enums::{TokenAccess, UserRole},
+        project::CreateProject,
+    },
+    scale::Scale,
+};
+use chrono::{Days, Utc};
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_class_roster() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let anna = ctx
+            .create_named_user_with_role(db, "anna", UserRole::Student)
+            .await
+            .unwrap();
+        let class = db
+            .create_class(
+                format!("Klasse {}", ctx.test_id),
+                Scale::default(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let auth = (header::AUTHORIZATION, format!("Bearer {}", token));
+
+        // Anna exists, Ben is created, Clara has no name and "abc" is too short
+        let ben = format!("ben_{}", ctx.test_id);
+        let csv = format!(
+            "username,name,email,starts_on\n\
+             {},,,01.08.2025\n\
+             {},Ben Becker,,2025-08-01\n\
+             clara_{},,,\n\
+             abc,Abc,,\n",
+            anna.username, ben, ctx.test_id
+        );
+        let import = |body: String| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/class/{}/students/import", class.id))
+                .insert_header(auth.clone())
+                .insert_header((header::CONTENT_TYPE, "text/csv"))
+                .set_payload(body)
+        };
+        let resp = import(csv.clone()).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(result["enrolled"], 1);
+        assert_eq!(result["created"], 1);
+        assert_eq!(result["failed"], 2);
+        assert_eq!(result["rows"][0]["user_id"], anna.id.to_string());
+        assert_eq!(result["rows"][2]["row"], 4);
+        let ben_id: uuid::Uuid = result["rows"][1]["user_id"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        ctx.created_users.lock().unwrap().push(ben_id);
+
+        // Enrolling the same students again overlaps
+        let resp = import(csv).send_request(&app).await;
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(result["failed"], 4);
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/class/{}/students", class.id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({ "user_id": teacher.id }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let roster = |query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/v1/class/{}/students{}", class.id, query))
+                .insert_header(auth.clone())
+        };
+        let resp = roster("").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let entries: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(entries["total"], 2);
+        let entries = entries["items"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        let ben_enrolment = entries
+            .iter()
+            .find(|entry| entry["student"]["id"] == ben_id.to_string())
+            .unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        let resp = roster("?on=2025-07-31").send_request(&app).await;
+        let entries: serde_json::Value = test::read_body_json(resp).await;
+        assert!(entries["items"].as_array().unwrap().is_empty());
+        let resp = roster("?per_page=1&sort=username").send_request(&app).await;
+        let entries: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(entries["items"].as_array().unwrap().len(), 1);
+        assert_eq!(entries["total"], 2);
+
+        // Ben left the class yesterday
+        let yesterday = Utc::now().date_naive() - Days::new(1);
+        let resp = test::TestRequest::put()
+            .uri(&format!(
+                "/api/v1/class/{}/students/{}",
+                class.id, ben_enrolment
+            ))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({
+                "starts_on": "2025-08-01",
+                "ends_on": yesterday
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = roster("").send_request(&app).await;
+        let entries: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(entries["items"].as_array().unwrap().len(), 1);
+        let resp = roster("?all=true").send_request(&app).await;
+        let entries: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(entries["items"].as_array().unwrap().len(), 2);
+
+        // Groups of a project of the class are formed from the roster
+        let project = db
+            .create_project(
+                CreateProject {
+                    name: format!("Projekt {}", ctx.test_id),
+                    class_id: Some(class.id),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.created_projects.lock().unwrap().push(project.id);
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/project/{}/groups/generate", project.id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({ "group_size": 3 }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let groups: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(groups.as_array().unwrap().len(), 1);
+        assert_eq!(groups[0]["members"][0]["user_id"], anna.id.to_string());
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/project/{}/groups", project.id))
+            .insert_header(auth.clone())
+            .set_json(serde_json::json!({
+                "groups": [{ "name": "Gruppe 1", "user_ids": [anna.id, ben_id] }]
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = test::TestRequest::delete()
+            .uri(&format!(
+                "/api/v1/class/{}/students/{}",
+                class.id, ben_enrolment
+            ))
+            .insert_header(auth.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = roster("?all=true").send_request(&app).await;
+        let entries: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(entries["items"].as_array().unwrap().len(), 1);
+
+        ctx.cleanup_all(db).await;
+        db.delete_class(class.id, None).await.unwrap();
+    }
+}
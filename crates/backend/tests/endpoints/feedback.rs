@@ -0,0 +1,630 @@
+use actix_session::{SessionMiddleware, storage::CookieSessionStore};
+use actix_web::{
+    App,
+    cookie::{Cookie, Key},
+    http::{StatusCode, header},
+    test, web,
+};
+use backend::controller;
+use backend::db::entity::sea_orm_active_enums::{SurveyStatus, TokenAccess, UserRole};
+use backend::db::feedback::{NewFeedbackResponse, NewSelfAssessment};
+use backend::db::project::SurveySettings;
+use uuid::Uuid;
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_submit_feedback_only_while_open() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_user(db, Some(format!("mate_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        ctx.create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        let feedback_id = db
+            .get_feedback_recipients(project.id, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|recipient| recipient.user.id == student.id)
+            .unwrap()
+            .feedback_id;
+
+        let ratings = serde_json::json!({
+            "ratings": [{ "user_id": mate.id, "rating": 2, "comment": "Hat viel beigetragen" }]
+        });
+
+        // Still a draft
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}", feedback_id))
+            .set_json(&ratings)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "survey_not_open");
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/project/{}/survey/status", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "status": "open" }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Every group mate has to be rated
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}", feedback_id))
+            .set_json(serde_json::json!({ "ratings": [] }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}", feedback_id))
+            .set_json(&ratings)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/feedback/{}", feedback_id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let form: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(form["completed"], true);
+        assert_eq!(form["submission_open"], true);
+        assert_eq!(form["members"][0]["user_id"], mate.id.to_string());
+        assert_eq!(form["members"][0]["rating"], 2);
+
+        // Submitting twice needs a reset by the teacher
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}", feedback_id))
+            .set_json(&ratings)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "feedback_already_submitted");
+
+        let resp = test::TestRequest::delete()
+            .uri(&format!("/api/v1/feedback/{}/reset", feedback_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // After the deadline
+        let deadline = chrono::Utc::now() - chrono::Duration::minutes(1);
+        let settings = SurveySettings {
+            deadline: Some(deadline),
+            reminders_enabled: true,
+            ..Default::default()
+        };
+        db.update_feedback_settings(project.id, settings, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}", feedback_id))
+            .set_json(&ratings)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(
+            body["detail"]
+                .as_str()
+                .unwrap()
+                .starts_with("The feedback period ended at")
+        );
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_feedback_token() {
+        let app = create_test_app!();
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/feedback/{}", Uuid::new_v4()))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_deactivated_student_loses_feedback_link() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .wrap(
+                    SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                        .cookie_secure(false)
+                        .build(),
+                )
+                .service(web::scope("/api/v1").configure(controller::register_controllers)),
+        )
+        .await;
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_user(db, Some(format!("mate_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        ctx.create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        let feedback_id = db
+            .get_feedback_recipients(project.id, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|recipient| recipient.user.id == student.id)
+            .unwrap()
+            .feedback_id;
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}/session", feedback_id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let session_cookie: Cookie = resp.response().cookies().next().unwrap().into_owned();
+
+        db.set_user_deactivated(student.id, true, Some(teacher.id))
+            .await
+            .unwrap();
+
+        let ratings = serde_json::json!({
+            "ratings": [{ "user_id": mate.id, "rating": 2 }]
+        });
+        let requests = [
+            test::TestRequest::get().uri(&format!("/api/v1/feedback/{}", feedback_id)),
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/feedback/{}", feedback_id))
+                .set_json(&ratings),
+            test::TestRequest::get().uri(&format!("/api/v1/feedback/{}/status", feedback_id)),
+            test::TestRequest::delete()
+                .uri(&format!("/api/v1/feedback/{}/reset", feedback_id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token))),
+            test::TestRequest::post().uri(&format!("/api/v1/feedback/{}/session", feedback_id)),
+            test::TestRequest::get()
+                .uri("/api/v1/feedback")
+                .cookie(session_cookie),
+        ];
+        for request in requests {
+            let resp = request.send_request(&app).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_finalised_survey_locks_grades_until_reopened() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student])
+            .await
+            .unwrap();
+        let feedback_id =
+            db.get_feedback_recipients(project.id, None).await.unwrap()[0].feedback_id;
+
+        let transition = |status: &str, reason: Option<&str>| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/project/{}/survey/status", project.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "status": status, "reason": reason }))
+        };
+        let set_grade = || {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/group/{}/grade", group.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "grade": 2.3 }))
+        };
+
+        // Finalising is only possible once the survey was closed
+        let resp = transition("finalised", None).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        for status in ["open", "closed"] {
+            let resp = transition(status, None).send_request(&app).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let resp = set_grade().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["grade"], 2.3);
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/group/{}/individual-grades", group.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "grades": [{
+                    "user_id": student.id,
+                    "grade": 1.7,
+                    "justification": "Mündliche Prüfung"
+                }]
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = transition("finalised", None).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = set_grade().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "survey_locked");
+
+        let resp = test::TestRequest::delete()
+            .uri(&format!("/api/v1/feedback/{}/reset", feedback_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        // Reopening needs a reason
+        let resp = transition("closed", None).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = transition("closed", Some("Gruppennote korrigiert"))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = set_grade().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/project/{}/survey/events", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let events: serde_json::Value = test::read_body_json(resp).await;
+        let events = events.as_array().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[3]["from_status"], "finalised");
+        assert_eq!(events[3]["to_status"], "closed");
+        assert_eq!(events[3]["reason"], "Gruppennote korrigiert");
+        assert_eq!(events[3]["actor_id"], teacher.id.to_string());
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_self_assessment() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_user(db, Some(format!("mate_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        let settings = SurveySettings {
+            self_assessment: true,
+            ..Default::default()
+        };
+        db.update_feedback_settings(project.id, settings, None)
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        let feedback_id = db
+            .get_feedback_recipients(project.id, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|recipient| recipient.user.id == student.id)
+            .unwrap()
+            .feedback_id;
+
+        let submit = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/feedback/{}", feedback_id))
+                .set_json(body)
+        };
+
+        let resp = submit(serde_json::json!({
+            "ratings": [{ "user_id": mate.id, "rating": 2 }]
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = submit(serde_json::json!({
+            "ratings": [{ "user_id": mate.id, "rating": 2 }],
+            "self_assessment": { "rating": 3, "comment": "Zu spät angefangen" }
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let status =
+            || test::TestRequest::get().uri(&format!("/api/v1/feedback/{}/status", feedback_id));
+
+        let resp = status().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["completed"], true);
+        assert_eq!(body["self_assessment"]["rating"], 3);
+        assert_eq!(body["received"], serde_json::json!([]));
+        assert!(body["grade"].is_null());
+
+        // The mate has to assess themselves as well
+        let (member, _) = db
+            .get_group_members(project.id, group.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(member, _)| member.user_id == mate.id)
+            .unwrap();
+        db.submit_feedback(
+            &member,
+            vec![NewFeedbackResponse {
+                to_user_id: student.id,
+                rating: 2,
+                comment: None,
+            }],
+            Some(NewSelfAssessment {
+                rating: 2,
+                comment: None,
+            }),
+            chrono::Utc::now(),
+        )
+        .await
+        .unwrap();
+        db.set_group_grade(group.id, 2.0, None).await.unwrap();
+        for next in [SurveyStatus::Closed, SurveyStatus::Finalised] {
+            db.set_survey_status(project.id, next, teacher.id, None)
+                .await
+                .unwrap();
+        }
+
+        let resp = status().send_request(&app).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["group_completed"], true);
+        assert_eq!(body["grade"], 2.0);
+        assert_eq!(body["received"][0]["rating"], 2);
+        // Rated themselves a grade worse than their mate did
+        assert_eq!(body["self_peer_gap"], 1.0);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_student_session_from_feedback_link() {
+        let ctx = TestContext::new();
+        let db = crate::common::test_helpers::get_database().await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .wrap(
+                    SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                        .cookie_secure(false)
+                        .build(),
+                )
+                .service(web::scope("/api/v1").configure(controller::register_controllers)),
+        )
+        .await;
+
+        let teacher = ctx
+            .create_user_with_role(&db, UserRole::Teacher)
+            .await
+            .unwrap();
+        // Students imported with the roster have no password
+        let username = format!("student_{}", ctx.test_id);
+        let student = db
+            .create_user(
+                "Max Mustermann".to_owned(),
+                username.clone(),
+                None,
+                None,
+                UserRole::Student,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.created_users.lock().unwrap().push(student.id);
+        let mate = ctx
+            .create_user(&db, Some(format!("mate_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+        assert!(db.verify_local_user(&username, "").await.is_err());
+
+        let project = ctx.create_project(&db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(&db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        let feedback_id = |db: backend::Database| async move {
+            db.get_feedback_recipients(project.id, None)
+                .await
+                .unwrap()
+                .into_iter()
+                .find(|recipient| recipient.user.id == student.id)
+                .unwrap()
+                .feedback_id
+        };
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/feedback")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/feedback/{}/session", Uuid::new_v4()))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/feedback/{}/session",
+                feedback_id(db.clone()).await
+            ))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let session_cookie: Cookie = resp.response().cookies().next().unwrap().into_owned();
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["project_id"], project.id.to_string());
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/feedback")
+            .cookie(session_cookie.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let form: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(form["members"][0]["user_id"], mate.id.to_string());
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/feedback")
+            .cookie(session_cookie.clone())
+            .set_json(serde_json::json!({
+                "ratings": [{ "user_id": mate.id, "rating": 2 }]
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/feedback/status")
+            .cookie(session_cookie.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let status: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(status["completed"], true);
+
+        // The session only reaches the feedback form
+        for uri in [
+            "/api/v1/user/me/tokens".to_owned(),
+            format!("/api/v1/project/{}", project.id),
+        ] {
+            let resp = test::TestRequest::get()
+                .uri(&uri)
+                .cookie(session_cookie.clone())
+                .send_request(&app)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", uri);
+        }
+
+        // New links end the session
+        db.regenerate_feedback_tokens(group.id, None).await.unwrap();
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/feedback")
+            .cookie(session_cookie)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // A teacher who is logged in doesn't lose their session to a link
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/auth/login")
+            .set_json(serde_json::json!({
+                "username": teacher.username,
+                "password": "password123"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let teacher_cookie: Cookie = resp.response().cookies().next().unwrap().into_owned();
+        let resp = test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/feedback/{}/session",
+                feedback_id(db.clone()).await
+            ))
+            .cookie(teacher_cookie)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        ctx.cleanup_all(&db).await;
+    }
+}
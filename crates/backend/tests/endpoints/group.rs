@@ -0,0 +1,344 @@
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::db::{
+    entity::sea_orm_active_enums::{SurveyStatus, TokenAccess, UserRole},
+    group::NewGradeOverride,
+};
+use std::collections::HashSet;
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_ids(value: &serde_json::Value) -> HashSet<String> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_move_and_remove_group_members() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let anna = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let ben = ctx
+            .create_named_user_with_role(db, "ben", UserRole::Student)
+            .await
+            .unwrap();
+        let clara = ctx
+            .create_named_user_with_role(db, "clara", UserRole::Student)
+            .await
+            .unwrap();
+        let dana = ctx
+            .create_named_user_with_role(db, "dana", UserRole::Student)
+            .await
+            .unwrap();
+        let emil = ctx
+            .create_named_user_with_role(db, "emil", UserRole::Student)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let first = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&anna, &ben, &clara])
+            .await
+            .unwrap();
+        let second = ctx
+            .create_group_with_members(db, &project, "Gruppe 2", &[&dana, &emil])
+            .await
+            .unwrap();
+        let other_project = ctx.create_project(db, None).await.unwrap();
+        let elsewhere = ctx
+            .create_group_with_members(db, &other_project, "Gruppe 1", &[])
+            .await
+            .unwrap();
+
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &first, &anna, &[(&ben, 2), (&clara, 2)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &first, &ben, &[(&anna, 2), (&clara, 3)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &first, &clara, &[(&anna, 1), (&ben, 2)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &second, &dana, &[(&emil, 2)])
+            .await
+            .unwrap();
+
+        let move_clara = |group_id: uuid::Uuid| {
+            test::TestRequest::post()
+                .uri(&format!(
+                    "/api/v1/group/{}/members/{}/move",
+                    first.id, clara.id
+                ))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "group_id": group_id }))
+        };
+
+        let resp = move_clara(elsewhere.id).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = move_clara(first.id).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = move_clara(second.id).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let change: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(change["member"]["group_id"], second.id.to_string());
+        assert_eq!(change["member"]["feedback_stale"], true);
+        // Emil hasn't submitted yet, so his form is still fine
+        assert_eq!(
+            user_ids(&change["stale"]),
+            [&anna, &ben, &clara, &dana]
+                .iter()
+                .map(|user| user.id.to_string())
+                .collect()
+        );
+        assert!(user_ids(&change["reset"]).is_empty());
+
+        let members = db.get_group_members(project.id, second.id).await.unwrap();
+        assert_eq!(members.len(), 3);
+        assert!(
+            members
+                .iter()
+                .all(|(member, _)| member.feedback_stale == member.feedback_completed)
+        );
+
+        let resp = test::TestRequest::delete()
+            .uri(&format!(
+                "/api/v1/group/{}/members/{}?reset_feedback=true",
+                first.id, ben.id
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let change: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(change["member"], serde_json::Value::Null);
+        assert_eq!(
+            user_ids(&change["reset"]),
+            HashSet::from([anna.id.to_string()])
+        );
+
+        let members = db.get_group_members(project.id, first.id).await.unwrap();
+        assert_eq!(members.len(), 1);
+        let (member, _) = &members[0];
+        assert!(!member.feedback_completed);
+        assert!(!member.feedback_stale);
+        assert!(
+            db.get_given_feedback(project.id, anna.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            db.get_given_feedback(project.id, ben.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        db.set_survey_status(project.id, SurveyStatus::Closed, teacher.id, None)
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Finalised, teacher.id, None)
+            .await
+            .unwrap();
+        let resp = test::TestRequest::delete()
+            .uri(&format!("/api/v1/group/{}/members/{}", second.id, clara.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_generate_and_create_groups() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let mut students = Vec::new();
+        for name in ["anna", "ben", "clara", "dana"] {
+            students.push(
+                ctx.create_named_user_with_role(db, name, UserRole::Student)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // Anna did best in the last project, Dana worst
+        let previous = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(
+                db,
+                &previous,
+                "Gruppe 1",
+                &students.iter().collect::<Vec<_>>(),
+            )
+            .await
+            .unwrap();
+        let overrides = students
+            .iter()
+            .zip([1.0, 2.0, 3.0, 4.0])
+            .map(|(student, grade)| NewGradeOverride {
+                user_id: student.id,
+                grade: Some(grade),
+                justification: "Mündliche Prüfung".to_owned(),
+            })
+            .collect();
+        db.set_individual_grades(group.id, overrides, teacher.id)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let user_ids: Vec<String> = students.iter().map(|s| s.id.to_string()).collect();
+        let generate = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/project/{}/groups/generate", project.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(body)
+        };
+
+        let resp = generate(serde_json::json!({ "user_ids": user_ids }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = generate(serde_json::json!({ "user_ids": user_ids, "group_count": 5 }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = generate(serde_json::json!({
+            "user_ids": [user_ids[0], teacher.id.to_string()],
+            "group_size": 2
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = generate(serde_json::json!({ "user_ids": user_ids, "group_size": 3 }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let groups: serde_json::Value = test::read_body_json(resp).await;
+        let sizes: Vec<usize> = groups
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|group| group["members"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(sizes, [2, 2]);
+
+        let resp = generate(serde_json::json!({
+            "user_ids": user_ids,
+            "group_count": 2,
+            "method": "balanced",
+            "balance_by": "previous_grade"
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let groups: serde_json::Value = test::read_body_json(resp).await;
+        let groups = groups.as_array().unwrap();
+        assert_eq!(groups[0]["name"], "Gruppe 1");
+        assert_eq!(groups[0]["members"][0]["user_id"], user_ids[0]);
+        assert_eq!(groups[0]["members"][1]["user_id"], user_ids[3]);
+        assert_eq!(groups[1]["members"][0]["user_id"], user_ids[1]);
+        assert_eq!(groups[1]["members"][1]["user_id"], user_ids[2]);
+        assert_eq!(groups[0]["average_score"], 2.5);
+        assert_eq!(groups[1]["average_score"], 2.5);
+
+        // Nothing is stored until the preview is confirmed
+        assert!(db.get_project_grading(project.id).await.unwrap().is_empty());
+
+        let create = || {
+            let groups: Vec<serde_json::Value> = groups
+                .iter()
+                .map(|group| {
+                    serde_json::json!({
+                        "name": group["name"],
+                        "user_ids": group["members"]
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|member| member["user_id"].clone())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/project/{}/groups", project.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "groups": groups }))
+        };
+
+        let resp = create().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(created.as_array().unwrap().len(), 2);
+        assert_eq!(created[1]["name"], "Gruppe 2");
+        assert_eq!(created[1]["members"].as_array().unwrap().len(), 2);
+
+        let grading = db.get_project_grading(project.id).await.unwrap();
+        assert_eq!(grading.len(), 2);
+        assert_eq!(grading[0].members.len(), 2);
+
+        // Every student can only be in one group of the project
+        let resp = create().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/group/{}?per_page=1", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["items"][0]["name"], "Gruppe 1");
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/group/{}", uuid::Uuid::new_v4()))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        ctx.cleanup_all(db).await;
+    }
+}
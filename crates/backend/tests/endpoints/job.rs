@@ -0,0 +1,223 @@
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::{
+    db::entity::sea_orm_active_enums::{
+        JobKind, JobStatus, Language, MailStatus, TokenAccess, UserRole,
+    },
+    jobs::{self, Worker, WorkerConfig},
+    mail::{FeedbackLinksJob, Mailer, Transport},
+};
+use uuid::Uuid;
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_mailer() -> Mailer {
+        Mailer::new(
+            "pgg@example.org".to_owned(),
+            "http://localhost:8080".to_owned(),
+            Transport::Log,
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_job_visible_to_creator_and_admin() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let other = ctx
+            .create_user(db, Some(format!("other_teacher_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+        db.update_user(other.id, None, None, None, Some(UserRole::Teacher), None)
+            .await
+            .unwrap();
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+
+        let job = jobs::enqueue(
+            db,
+            JobKind::SendFeedbackLinks,
+            &FeedbackLinksJob {
+                project_id: Uuid::new_v4(),
+                group_id: None,
+                language: Language::De,
+            },
+            Some(teacher.id),
+        )
+        .await
+        .unwrap();
+
+        for (user, expected) in [
+            (&teacher, StatusCode::OK),
+            (&other, StatusCode::NOT_FOUND),
+            (&admin, StatusCode::OK),
+        ] {
+            let token = ctx
+                .create_api_token(db, user, TokenAccess::Read, None)
+                .await
+                .unwrap();
+            let resp = test::TestRequest::get()
+                .uri(&format!("/api/v1/job/{}", job.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .send_request(&app)
+                .await;
+            assert_eq!(resp.status(), expected, "user {}", user.username);
+        }
+
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/job?status=queued")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let page: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["id"], job.id.to_string());
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_job_for_missing_project_fails_without_retry() {
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let job = jobs::enqueue(
+            db,
+            JobKind::SendFeedbackLinks,
+            &FeedbackLinksJob {
+                project_id: Uuid::new_v4(),
+                group_id: None,
+                language: Language::De,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+        let worker = Worker::new(db.clone(), log_mailer(), WorkerConfig::default());
+        assert!(worker.run_job(job.id).await.unwrap());
+
+        let job = db.get_job(job.id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.attempts, 1);
+        assert_eq!(job.error.as_deref(), Some("Not Found"));
+        assert!(job.finished_at.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_job_queued_once_by_concurrent_schedulers() {
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let payload = serde_json::to_value(FeedbackLinksJob {
+            project_id: Uuid::new_v4(),
+            group_id: None,
+            language: Language::De,
+        })
+        .unwrap();
+        let enqueue_once = || {
+            let (db, payload) = (db.clone(), payload.clone());
+            actix_web::rt::spawn(async move {
+                db.enqueue_job_once(JobKind::SendFeedbackLinks, payload, 1)
+                    .await
+                    .unwrap()
+            })
+        };
+
+        let schedulers: Vec<_> = (0..5).map(|_| enqueue_once()).collect();
+        let mut queued = Vec::new();
+        for scheduler in schedulers {
+            queued.extend(scheduler.await.unwrap());
+        }
+        assert_eq!(queued.len(), 1);
+
+        // Finished jobs keep the work from being queued again
+        let worker = Worker::new(db.clone(), log_mailer(), WorkerConfig::default());
+        assert!(worker.run_job(queued[0].id).await.unwrap());
+        assert!(enqueue_once().await.unwrap().is_none());
+
+        // Jobs queued without a key are never held back, not even by each other
+        for _ in 0..2 {
+            let job = db
+                .enqueue_job(JobKind::SendFeedbackLinks, payload.clone(), None, 1)
+                .await
+                .unwrap();
+            assert!(worker.run_job(job.id).await.unwrap());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_failed_mail_is_retried_later() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let project = ctx.create_project(db, None).await.unwrap();
+        ctx.create_group_with_members(db, &project, "Gruppe 1", &[&student])
+            .await
+            .unwrap();
+
+        // A regular file in place of the mail directory makes every delivery fail
+        let not_a_dir = tempfile::NamedTempFile::new().unwrap();
+        let mailer = Mailer::new(
+            "pgg@example.org".to_owned(),
+            "http://localhost:8080".to_owned(),
+            Transport::File(not_a_dir.path().to_owned()),
+        );
+
+        let job = jobs::enqueue(
+            db,
+            JobKind::SendFeedbackLinks,
+            &FeedbackLinksJob {
+                project_id: project.id,
+                group_id: None,
+                language: Language::De,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+        let worker = Worker::new(db.clone(), mailer, WorkerConfig::default());
+        assert!(worker.run_job(job.id).await.unwrap());
+
+        let retried = db.get_job(job.id).await.unwrap();
+        assert_eq!(retried.status, JobStatus::Queued);
+        assert_eq!(retried.attempts, 1);
+        assert_eq!(
+            retried.error.as_deref(),
+            Some("1 of 1 mails could not be sent")
+        );
+        assert!(retried.run_at > job.run_at);
+
+        // The retry waits for its backoff
+        assert!(!worker.run_job(job.id).await.unwrap());
+
+        let deliveries = db.get_job_mail_deliveries(job.id).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, MailStatus::Failed);
+
+        ctx.cleanup_all(db).await;
+    }
+}
@@ -1,6 +1,10 @@
+pub mod audit;
 pub mod auth;
-// pub mod class;
-// pub mod group;
-// pub mod project;
+pub mod class;
+pub mod feedback;
+pub mod group;
+pub mod job;
+pub mod project;
+pub mod retention;
 // pub mod template;
 pub mod user;
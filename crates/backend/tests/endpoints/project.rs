@@ -0,0 +1,1081 @@
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::{
+    db::entity::sea_orm_active_enums::{Language, MailStatus, SurveyStatus, TokenAccess, UserRole},
+    db::project::{CreateProject, SurveySettings},
+    jobs::{Worker, WorkerConfig},
+    mail::{Mailer, Transport},
+    reminder,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use uuid::Uuid;
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes the base64 bodies of all mails written by the file transport
+    fn read_mails(dir: &std::path::Path) -> Vec<String> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+                let (headers, body) = content.split_once("\r\n\r\n").unwrap();
+                let body = STANDARD.decode(body.replace("\r\n", "")).unwrap();
+                format!("{}\r\n\r\n{}", headers, String::from_utf8(body).unwrap())
+            })
+            .collect()
+    }
+
+    async fn worker(mailer: &Mailer) -> Worker {
+        Worker::new(
+            crate::common::test_helpers::get_database().await,
+            mailer.clone(),
+            WorkerConfig::default(),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_send_feedback_links() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mailer = Mailer::new(
+            "pgg@example.org".to_owned(),
+            "https://pgg.example.org".to_owned(),
+            Transport::File(dir.path().to_owned()),
+        );
+        let app = create_test_app!(mailer.clone());
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let no_email = ctx
+            .create_user(db, Some(format!("no_email_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &no_email])
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/project/{}/feedback-links/send",
+                project.id
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "group_id": group.id, "language": "en" }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let job: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(job["status"], "queued");
+        let job_id: Uuid = serde_json::from_value(job["id"].clone()).unwrap();
+
+        // Nothing is sent until a worker picks up the job
+        assert_eq!(std::fs::read_dir(dir.path()).map_or(0, |d| d.count()), 0);
+        assert!(worker(&mailer).await.run_job(job_id).await.unwrap());
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/job/{}", job_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let job: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(job["status"], "succeeded");
+        assert_eq!(job["progress_done"], 2);
+        assert_eq!(job["progress_total"], 2);
+        assert_eq!(job["result"]["sent"], 1);
+        assert_eq!(job["result"]["skipped"], 1);
+
+        let deliveries = db.get_job_mail_deliveries(job_id).await.unwrap();
+        assert_eq!(deliveries.len(), 2);
+
+        let sent = deliveries.iter().find(|d| d.user_id == student.id).unwrap();
+        assert_eq!(sent.status, MailStatus::Sent);
+        assert_eq!(sent.recipient, student.email.clone());
+        assert!(sent.sent_at.is_some());
+
+        let skipped = deliveries
+            .iter()
+            .find(|d| d.user_id == no_email.id)
+            .unwrap();
+        assert_eq!(skipped.status, MailStatus::Skipped);
+
+        let mails = read_mails(dir.path());
+        assert_eq!(mails.len(), 1);
+        assert!(mails[0].contains(&format!("To: {}", student.email.unwrap())));
+        assert!(mails[0].contains("Subject: Peer feedback for"));
+        assert!(mails[0].contains("https://pgg.example.org/feedback/"));
+
+        // The status stays available to the teacher
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/project/{}/mail-deliveries", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let page: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], 2);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_send_feedback_links_reuses_feedback_ids() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let project = ctx.create_project(db, None).await.unwrap();
+        ctx.create_group_with_members(db, &project, "Gruppe 1", &[&student])
+            .await
+            .unwrap();
+
+        let first = db.get_feedback_recipients(project.id, None).await.unwrap();
+        let second = db.get_feedback_recipients(project.id, None).await.unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].feedback_id, second[0].feedback_id);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_send_feedback_links_unknown_group() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let project = ctx.create_project(db, None).await.unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/project/{}/feedback-links/send",
+                project.id
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "group_id": Uuid::new_v4() }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_update_feedback_settings() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let project = ctx.create_project(db, None).await.unwrap();
+
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/project/{}/feedback-settings", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "deadline": "2030-01-31T16:00:00Z",
+                "reminder_days": [1, 7, 7],
+                "language": "en"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["reminder_days"], serde_json::json!([7, 1]));
+        assert_eq!(body["reminders_enabled"], true);
+        assert_eq!(body["language"], "en");
+
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/project/{}/feedback-settings", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "reminder_days": [0] }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_update_grading_settings() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_user(db, Some(format!("mate_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &student, &[(&mate, 2)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &mate, &[(&student, 3)])
+            .await
+            .unwrap();
+        db.set_group_grade(group.id, 2.0, None).await.unwrap();
+
+        let update = |settings: serde_json::Value| {
+            test::TestRequest::put()
+                .uri(&format!("/api/v1/project/{}/grading-settings", project.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(settings)
+        };
+
+        let resp = update(serde_json::json!({ "max_grade_deviation": -1.0 }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = update(serde_json::json!({
+            "peer_aggregation": "median",
+            "max_grade_deviation": 0.1
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["peer_aggregation"], "median");
+        assert_eq!(body["max_grade_deviation"], 0.1);
+
+        // 2.2 and 1.8 without the bound
+        let resp = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/project/{}/grades/export?format=csv",
+                project.id
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let rows = backend::csv::parse(&body);
+        let grade = |user: &backend::db::entity::user::Model| {
+            rows.iter().find(|row| row[1] == user.username).unwrap()[8].clone()
+        };
+        assert_eq!(grade(&student), "2.1");
+        assert_eq!(grade(&mate), "1.9");
+
+        for next in [SurveyStatus::Closed, SurveyStatus::Finalised] {
+            db.set_survey_status(project.id, next, teacher.id, None)
+                .await
+                .unwrap();
+        }
+        let resp = update(serde_json::json!({ "peer_aggregation": "webpa" }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_reminders_only_go_to_students_without_feedback() {
+        use backend::db::entity::reminder_run;
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let done = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let missing = ctx
+            .create_user(db, Some(format!("missing_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+        db.update_user(
+            missing.id,
+            None,
+            None,
+            Some(format!("missing_{}@example.org", ctx.test_id)),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&done, &missing])
+            .await
+            .unwrap();
+        ctx.complete_feedback(db, &group, &done).await.unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, done.id, None)
+            .await
+            .unwrap();
+
+        let now = chrono::Utc::now();
+        let deadline = now + chrono::Duration::hours(12);
+        let settings = SurveySettings {
+            deadline: Some(deadline),
+            reminder_days: vec![3, 1],
+            reminders_enabled: true,
+            language: Language::De,
+            ..Default::default()
+        };
+        db.update_feedback_settings(project.id, settings, None)
+            .await
+            .unwrap();
+        let deadline = db
+            .get_project(&project.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .feedback_deadline
+            .unwrap()
+            .to_utc();
+
+        reminder::schedule_due_reminders(db, now).await.unwrap();
+        reminder::schedule_due_reminders(db, now).await.unwrap();
+
+        // Both missed offsets are merged into a single job
+        let runs = reminder_run::Entity::find()
+            .filter(reminder_run::Column::ProjectId.eq(project.id))
+            .all(db.connection())
+            .await
+            .unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(runs.iter().all(|run| run.job_id == runs[0].job_id));
+        assert_eq!(
+            db.get_scheduled_reminder_days(project.id, deadline)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mailer = Mailer::new(
+            "pgg@example.org".to_owned(),
+            "https://pgg.example.org".to_owned(),
+            Transport::File(dir.path().to_owned()),
+        );
+        let job_id = runs[0].job_id.unwrap();
+        assert!(worker(&mailer).await.run_job(job_id).await.unwrap());
+
+        let deliveries = db.get_job_mail_deliveries(job_id).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].user_id, missing.id);
+        assert_eq!(deliveries[0].status, MailStatus::Sent);
+
+        let mails = read_mails(dir.path());
+        assert_eq!(mails.len(), 1);
+        assert!(mails[0].contains("noch kein Feedback abgegeben"));
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_export_grades() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let no_email = ctx
+            .create_user(db, Some(format!("no_email_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &no_email])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &student, &[(&no_email, 2)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &no_email, &[(&student, 3)])
+            .await
+            .unwrap();
+        db.set_group_grade(group.id, 2.0, None).await.unwrap();
+
+        let export = |format: &str| {
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/v1/project/{}/grades/export?format={}",
+                    project.id, format
+                ))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+
+        let resp = export("csv").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert!(
+            resp.headers()
+                .get(header::CONTENT_DISPOSITION)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .starts_with("attachment")
+        );
+
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let rows = backend::csv::parse(&body);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][8], "Endnote");
+        let row = |user: &backend::db::entity::user::Model| {
+            rows.iter()
+                .find(|row| row[1] == user.username)
+                .unwrap()
+                .clone()
+        };
+        // Rated 3 against a group average of 2.5
+        assert_eq!(row(&student)[5..], ["1.20", "2.2", "", "2.2", ""]);
+        assert_eq!(row(&no_email)[5..], ["0.80", "1.8", "", "1.8", ""]);
+
+        // Students without email address can't be imported into Moodle
+        let resp = export("moodle").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "E-Mail-Adresse,{}\r\n{},2.2\r\n",
+                project.name,
+                student.email.unwrap()
+            )
+        );
+
+        let resp = export("xlsx").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(test::read_body(resp).await.starts_with(b"PK"));
+
+        let resp = export("pdf").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_override_grades() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_named_user_with_role(db, "mate", UserRole::Student)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &student, &[(&mate, 2)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &mate, &[(&student, 3)])
+            .await
+            .unwrap();
+        db.set_group_grade(group.id, 2.0, None).await.unwrap();
+
+        let set_override = |grade: Option<f64>, justification: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/group/{}/individual-grades", group.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({
+                    "grades": [{
+                        "user_id": student.id,
+                        "grade": grade,
+                        "justification": justification
+                    }]
+                }))
+        };
+
+        let resp = set_override(Some(1.0), "").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = set_override(Some(1.0), "   ").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = set_override(Some(1.3), "Referat nachgereicht")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = set_override(Some(1.0), "Projektleitung übernommen")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/project/{}/grades", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let grades: serde_json::Value = test::read_body_json(resp).await;
+        let grade = grades
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|grade| grade["user_id"] == student.id.to_string())
+            .unwrap();
+        assert_eq!(grade["computed_grade"], 2.2);
+        assert_eq!(grade["individual_grade"], 1.0);
+        assert_eq!(grade["final_grade"], 1.0);
+        assert_eq!(grade["justification"], "Projektleitung übernommen");
+
+        let resp = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/project/{}/grades/history?user_id={}",
+                project.id, student.id
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let history: serde_json::Value = test::read_body_json(resp).await;
+        let history = history.as_array().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0]["previous_grade"], serde_json::Value::Null);
+        assert_eq!(history[0]["grade"], 1.3);
+        assert_eq!(history[1]["previous_grade"], 1.3);
+        assert_eq!(history[1]["grade"], 1.0);
+        assert_eq!(history[1]["justification"], "Projektleitung übernommen");
+        assert_eq!(history[1]["actor_id"], teacher.id.to_string());
+
+        // Students only see the effective grade
+        let feedback_id = db
+            .get_feedback_recipients(project.id, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|recipient| recipient.user.id == student.id)
+            .unwrap()
+            .feedback_id;
+        for next in [SurveyStatus::Closed, SurveyStatus::Finalised] {
+            db.set_survey_status(project.id, next, teacher.id, None)
+                .await
+                .unwrap();
+        }
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/feedback/{}/status", feedback_id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let status: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(status["grade"], 1.0);
+        assert!(!body.contains("Projektleitung"));
+        assert!(!body.contains("2.2"));
+
+        let resp = set_override(None, "Doch die berechnete Note")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_class_grade_scale() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_named_user_with_role(db, "mate", UserRole::Student)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/class")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": format!("Oberstufe {}", ctx.test_id),
+                "grade_scale": "points",
+                "grade_rounding": "tendency"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/class")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": format!("Oberstufe {}", ctx.test_id),
+                "grade_scale": "points",
+                "grade_rounding": "whole"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let class: serde_json::Value = test::read_body_json(resp).await;
+        let class_id: Uuid = class["id"].as_str().unwrap().parse().unwrap();
+
+        let project = db
+            .create_project(
+                CreateProject {
+                    name: format!("Test Project {}", ctx.test_id),
+                    class_id: Some(class_id),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.created_projects.lock().unwrap().push(project.id);
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &student, &[(&mate, 2)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &mate, &[(&student, 3)])
+            .await
+            .unwrap();
+
+        let grade = |grade: f64| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/group/{}/grade", group.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "grade": grade }))
+        };
+        let resp = grade(20.0).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = grade(11.0).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let moodle = || {
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/v1/project/{}/grades/export?format=moodle",
+                    project.id
+                ))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+        // 11 points are a 2.0, the final 2.2 and 1.8 are 10.4 and 11.6 points
+        let resp = moodle().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains(&format!("{},10\r\n", student.email.clone().unwrap())));
+        assert!(body.contains(&format!("{},12\r\n", mate.email.clone().unwrap())));
+
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/class/{}", class_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": format!("Oberstufe {}", ctx.test_id),
+                "grade_scale": "german"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let group = db.get_group(project.id, group.id).await.unwrap();
+        assert_eq!(group.grade, Some(2.0));
+
+        let resp = moodle().send_request(&app).await;
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains(&format!("{},2.2\r\n", student.email.unwrap())));
+
+        let resp = test::TestRequest::delete()
+            .uri(&format!("/api/v1/class/{}", class_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_evaluation_reports() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_user(db, Some(format!("mate_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &mate, &[(&student, 2)])
+            .await
+            .unwrap();
+
+        let report = |path: String| {
+            test::TestRequest::get()
+                .uri(&format!("/api/v1/project/{}/reports/{}", project.id, path))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+
+        let resp = report(format!("students/{}", student.id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/pdf"
+        );
+        assert!(test::read_body(resp).await.starts_with(b"%PDF-"));
+
+        let resp = report(format!("groups/{}", group.id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(test::read_body(resp).await.starts_with(b"%PDF-"));
+
+        // The teacher isn't a member of any group
+        let resp = report(format!("students/{}", teacher.id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = report(format!("groups/{}", Uuid::new_v4()))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_project_analytics() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let anna = ctx
+            .create_user(db, Some(format!("anna_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+        let ben = ctx
+            .create_user(db, Some(format!("ben_{}", ctx.test_id)), None)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &anna, &ben])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, teacher.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &student, &[(&anna, 1), (&ben, 1)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &anna, &[(&student, 3), (&ben, 4)])
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &ben, &[(&student, 3), (&anna, 4)])
+            .await
+            .unwrap();
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/project/{}/analytics", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let question = &body["questions"][0];
+        assert_eq!(question["responses"], 6);
+        assert_eq!(
+            question["distribution"],
+            serde_json::json!([2, 0, 2, 2, 0, 0])
+        );
+
+        let outliers = body["outlier_raters"].as_array().unwrap();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0]["user_id"], student.id.to_string());
+        assert_eq!(outliers[0]["reason"], "all_best");
+
+        let group = &body["groups"][0];
+        assert_eq!(group["members"], 3);
+        assert_eq!(group["completed"], 3);
+        assert!(group["cohesion"].as_f64().unwrap() < 1.0);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_clone_and_archive_project() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+
+        let mut class_ids = Vec::new();
+        for name in ["10a", "11a"] {
+            let resp = test::TestRequest::post()
+                .uri("/api/v1/class")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(serde_json::json!({ "name": format!("{} {}", name, ctx.test_id) }))
+                .send_request(&app)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let class: serde_json::Value = test::read_body_json(resp).await;
+            class_ids.push(class["id"].as_str().unwrap().to_owned());
+        }
+
+        let original = db
+            .create_project(
+                CreateProject {
+                    name: format!("LF 5 {}", ctx.test_id),
+                    class_id: Some(class_ids[0].parse().unwrap()),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.created_projects.lock().unwrap().push(original.id);
+        ctx.create_group_with_members(db, &original, "Gruppe 1", &[&student])
+            .await
+            .unwrap();
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/project/{}/grading-settings", original.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "peer_aggregation": "webpa",
+                "max_grade_deviation": 1.0
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let clone = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/project/{}/clone", original.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .set_json(body)
+        };
+        let resp = clone(serde_json::json!({
+            "name": format!("LF 5 next year {}", ctx.test_id),
+            "class_id": Uuid::new_v4()
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = clone(serde_json::json!({
+            "name": format!("LF 5 next year {}", ctx.test_id),
+            "class_id": class_ids[1]
+        }))
+        .send_request(&app)
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let copy: serde_json::Value = test::read_body_json(resp).await;
+        let copy_id: Uuid = copy["id"].as_str().unwrap().parse().unwrap();
+        ctx.created_projects.lock().unwrap().push(copy_id);
+        assert_eq!(copy["class_id"], class_ids[1]);
+        assert_eq!(copy["peer_aggregation"], "webpa");
+        assert_eq!(copy["max_grade_deviation"], 1.0);
+        assert_eq!(copy["survey_status"], "draft");
+        assert!(db.get_project_grading(copy_id).await.unwrap().is_empty());
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/project/{}/archive", original.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let archived: serde_json::Value = test::read_body_json(resp).await;
+        assert!(archived["archived_at"].is_string());
+
+        let listed = |include_archived: bool| {
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/v1/project?search={}&include_archived={}",
+                    ctx.test_id, include_archived
+                ))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+        let resp = listed(false).send_request(&app).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["items"][0]["id"], copy_id.to_string());
+        let resp = listed(true).send_request(&app).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["total"], 2);
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/project/{}/unarchive", original.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = listed(false).send_request(&app).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["total"], 2);
+
+        for class_id in class_ids {
+            let resp = test::TestRequest::delete()
+                .uri(&format!("/api/v1/class/{}", class_id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .send_request(&app)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        ctx.cleanup_all(db).await;
+    }
+}
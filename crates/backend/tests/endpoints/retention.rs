@@ -0,0 +1,226 @@
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::{
+    db::{
+        entity::sea_orm_active_enums::{JobStatus, TokenAccess, UserRole},
+        project::CreateProject,
+    },
+    jobs::{Worker, WorkerConfig},
+    mail::{Mailer, Transport},
+    scale::Scale,
+};
+use chrono::{Days, NaiveDate, Utc};
+use uuid::Uuid;
+
+use crate::{common::test_helpers::TestContext, create_test_app};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_retention_purges_projects_of_past_school_years() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &admin, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let leaver = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let stayer = ctx
+            .create_named_user_with_role(db, "stayer", UserRole::Student)
+            .await
+            .unwrap();
+        let newcomer = ctx
+            .create_named_user_with_role(db, "newcomer", UserRole::Student)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/school-year")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": format!("2019/20 {}", ctx.test_id),
+                "starts_on": "2020-08-01",
+                "ends_on": "2020-07-31"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/school-year")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": format!("2019/20 {}", ctx.test_id),
+                "starts_on": "2019-08-01",
+                "ends_on": "2020-07-31"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let school_year: serde_json::Value = test::read_body_json(resp).await;
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/class")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": format!("10a {}", ctx.test_id),
+                "school_year_id": school_year["id"]
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let class: serde_json::Value = test::read_body_json(resp).await;
+        let class_id: Uuid = class["id"].as_str().unwrap().parse().unwrap();
+
+        let expired = db
+            .create_project(
+                CreateProject {
+                    name: format!("Expired Project {}", ctx.test_id),
+                    class_id: Some(class_id),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.created_projects.lock().unwrap().push(expired.id);
+        ctx.create_group_with_members(db, &expired, "Gruppe 1", &[&leaver, &stayer, &newcomer])
+            .await
+            .unwrap();
+
+        // The leaver's enrolment is over, the newcomer joins a class next month
+        let next_class = db
+            .create_class(format!("11a {}", ctx.test_id), Scale::default(), None, None)
+            .await
+            .unwrap();
+        db.enrol_student(
+            class_id,
+            leaver.id,
+            NaiveDate::from_ymd_opt(2019, 8, 1),
+            NaiveDate::from_ymd_opt(2020, 7, 31),
+            None,
+        )
+        .await
+        .unwrap();
+        db.enrol_student(
+            next_class.id,
+            newcomer.id,
+            Some(Utc::now().date_naive() + Days::new(30)),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let current = ctx.create_project(db, None).await.unwrap();
+        ctx.create_group_with_members(db, &current, "Gruppe 1", &[&stayer])
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/retention/report?as_of=2020-09-15")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report: serde_json::Value = test::read_body_json(resp).await;
+        assert!(report["purge"].as_array().unwrap().is_empty());
+        assert!(
+            report["archive"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|project| project["id"] == expired.id.to_string())
+        );
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/retention/report")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report: serde_json::Value = test::read_body_json(resp).await;
+        let purged = report["purge"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|project| project["id"] == expired.id.to_string())
+            .unwrap();
+        assert_eq!(purged["purge_on"], "2021-07-31");
+        let students = report["students"].as_array().unwrap();
+        assert!(students.iter().any(|s| s["id"] == leaver.id.to_string()));
+        assert!(!students.iter().any(|s| s["id"] == stayer.id.to_string()));
+        assert!(!students.iter().any(|s| s["id"] == newcomer.id.to_string()));
+        // The report changes nothing
+        assert!(db.get_project(&expired.id).await.is_ok());
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/retention/apply")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let job: serde_json::Value = test::read_body_json(resp).await;
+        let job_id: Uuid = job["id"].as_str().unwrap().parse().unwrap();
+
+        let mailer = Mailer::new(
+            "pgg@example.org".to_owned(),
+            "http://localhost:8080".to_owned(),
+            Transport::Log,
+        );
+        let worker = Worker::new(db.clone(), mailer, WorkerConfig::default());
+        assert!(worker.run_job(job_id).await.unwrap());
+        assert_eq!(
+            db.get_job(job_id).await.unwrap().status,
+            JobStatus::Succeeded
+        );
+
+        assert!(db.get_project(&expired.id).await.is_err());
+        assert!(db.get_user(leaver.id).await.is_err());
+        assert!(db.get_user(stayer.id).await.is_ok());
+        assert!(db.get_user(newcomer.id).await.is_ok());
+        assert!(db.get_project(&current.id).await.is_ok());
+
+        let resp = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/audit?entity_id={}&action=project_purged",
+                expired.id
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["total"], 1);
+
+        let resp = test::TestRequest::delete()
+            .uri(&format!("/api/v1/class/{}", class_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::TestRequest::delete()
+            .uri(&format!(
+                "/api/v1/school-year/{}",
+                school_year["id"].as_str().unwrap()
+            ))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        db.delete_class(next_class.id, None).await.unwrap();
+        ctx.cleanup_all(db).await;
+    }
+}
@@ -1,4 +1,16 @@
-use actix_web::{http::header, test};
+use actix_web::{
+    http::{StatusCode, header},
+    test,
+};
+use backend::{
+    db::{
+        audit::AuditFilter,
+        entity::sea_orm_active_enums::{AuditAction, SurveyStatus, TokenAccess, UserRole},
+        feedback::NewFeedbackResponse,
+        group::NewGradeOverride,
+    },
+    pagination::Pagination,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{common::test_helpers::TestContext, create_test_app};
@@ -7,6 +19,14 @@ use crate::{common::test_helpers::TestContext, create_test_app};
 mod tests {
     use super::*;
 
+    #[derive(Deserialize, Debug)]
+    struct RespPage<T> {
+        items: Vec<T>,
+        total: u64,
+        total_pages: u64,
+        links: serde_json::Value,
+    }
+
     #[derive(Deserialize, Serialize, Debug, Clone)]
     struct RespCreateUser {
         id: String,
@@ -28,10 +48,40 @@ mod tests {
             "password": "password123"
         });
 
-        let resp = test::TestRequest::post()
-            .uri("/api/v1/user")
-            .insert_header(header::ContentType::json())
-            .set_payload(user_data.to_string())
+        let create = || {
+            test::TestRequest::post()
+                .uri("/api/v1/user")
+                .insert_header(header::ContentType::json())
+                .set_payload(user_data.to_string())
+        };
+
+        // Only teachers create students
+        let resp = create().send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let student_token = ctx
+            .create_api_token(db, &student, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let resp = create()
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", student_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let resp = create()
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
             .send_request(&app)
             .await;
 
@@ -53,7 +103,10 @@ mod tests {
         assert!(status.is_success());
 
         let user_id = uuid::Uuid::parse_str(&user.id).unwrap();
+        ctx.created_users.lock().unwrap().push(user_id);
         assert!(ctx.assert_user_exists(db, user_id).await);
+        let created = db.get_user(user_id).await.unwrap().unwrap();
+        assert_eq!(created.role, UserRole::Student);
 
         ctx.cleanup_all(db).await;
     }
@@ -66,13 +119,41 @@ mod tests {
         let app = create_test_app!();
 
         let user = ctx.create_user(db, None, None).await.unwrap();
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &admin, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let teacher_token = ctx
+            .create_api_token(db, &user, TokenAccess::Write, None)
+            .await
+            .unwrap();
 
         // Check if user exists before deletion
         assert!(ctx.assert_user_exists(db, user.id).await);
 
+        // Only admins delete users, and not themselves
+        let delete =
+            |id: uuid::Uuid| test::TestRequest::delete().uri(&format!("/api/v1/user/{}", id));
+        let resp = delete(user.id).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = delete(admin.id)
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", teacher_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = delete(admin.id)
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
         // Delete the user via API
-        let delete_resp = test::TestRequest::delete()
-            .uri(&format!("/api/v1/user/{}", user.id))
+        let delete_resp = delete(user.id)
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
             .send_request(&app)
             .await;
         let delete_status = delete_resp.status();
@@ -90,6 +171,26 @@ mod tests {
         // Verify user no longer exists in database
         assert!(ctx.assert_user_not_exists(db, user.id).await);
 
+        // The audit log keeps no personal data of the deleted user
+        let filter = AuditFilter {
+            entity_id: Some(user.id),
+            ..Default::default()
+        };
+        let (events, _) = db
+            .get_audit_events(&filter, &Pagination::new(1, 25))
+            .await
+            .unwrap();
+        assert!(
+            events
+                .iter()
+                .any(|event| event.action == AuditAction::UserDeleted)
+        );
+        assert!(
+            events
+                .iter()
+                .all(|event| event.before.is_none() && event.after.is_none())
+        );
+
         // Cleanup
         ctx.cleanup_all(db).await;
     }
@@ -105,17 +206,51 @@ mod tests {
         let users = ctx.create_multiple_users(db, 3).await.unwrap();
         assert_eq!(users.len(), 3);
 
-        // Test the API endpoint
+        let token = ctx
+            .create_api_token(db, &users[0], TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let auth = (header::AUTHORIZATION, format!("Bearer {}", token));
+
+        // Emails are only shown to teachers
         let resp = test::TestRequest::get()
-            .uri("/api/v1/user")
+            .uri(&format!("/api/v1/user?search={}", ctx.test_id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/user/{}", users[1].id))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/user/{}", users[1].id))
+            .insert_header(auth.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/user/{}", uuid::Uuid::new_v4()))
+            .insert_header(auth.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Test the API endpoint, other tests may have created users on earlier pages
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/user?search={}", ctx.test_id))
+            .insert_header(auth)
             .send_request(&app)
             .await;
 
         let status = resp.status();
-        let api_users: Vec<RespCreateUser> = test::read_body_json(resp).await;
+        let page: RespPage<RespCreateUser> = test::read_body_json(resp).await;
+        let api_users = page.items;
 
         assert!(status.is_success());
-        assert!(api_users.len() >= 3); // At least our 3 users (could be more from other tests)
+        assert_eq!(page.total, 3);
+        assert_eq!(api_users.len(), 3);
 
         // Verify our users are in the response
         for user in &users {
@@ -148,9 +283,19 @@ mod tests {
         let fake_uuid = uuid::Uuid::parse_str(fake_id).unwrap();
         assert!(ctx.assert_user_not_exists(db, fake_uuid).await);
 
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &admin, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
         // Try to delete non-existent user
         let resp = test::TestRequest::delete()
             .uri(&format!("/api/v1/user/{}", fake_id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
             .send_request(&app)
             .await;
 
@@ -167,4 +312,772 @@ mod tests {
         // Cleanup
         ctx.cleanup_all(db).await;
     }
+
+    #[actix_web::test]
+    async fn test_get_me_with_api_token() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let user = ctx.create_user(db, None, None).await.unwrap();
+        let token = ctx
+            .create_api_token(db, &user, TokenAccess::Read, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/user/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let me: RespCreateUser = test::read_body_json(resp).await;
+        assert_eq!(me.id, user.id.to_string());
+
+        let tokens = db.get_api_tokens(user.id).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens[0].last_used_at.is_some());
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_get_me_without_authentication() {
+        let app = create_test_app!();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/user/me")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/user/me")
+            .insert_header((header::AUTHORIZATION, "Bearer pgg_invalid"))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_expired_api_token_is_rejected() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let user = ctx.create_user(db, None, None).await.unwrap();
+        let expired = chrono::Utc::now() - chrono::Duration::hours(1);
+        let token = ctx
+            .create_api_token(db, &user, TokenAccess::Write, Some(expired))
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/user/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_read_only_api_token_cannot_write() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let user = ctx.create_user(db, None, None).await.unwrap();
+        let read_token = ctx
+            .create_api_token(db, &user, TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let write_token = ctx
+            .create_api_token(db, &user, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let project = serde_json::json!({ "name": format!("Project {}", ctx.test_id) });
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/project")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", read_token)))
+            .set_json(&project)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/project")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", read_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/project")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", write_token)))
+            .set_json(&project)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        let project_id = uuid::Uuid::parse_str(created["id"].as_str().unwrap()).unwrap();
+        ctx.created_projects.lock().unwrap().push(project_id);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_api_token_cannot_manage_tokens() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let user = ctx.create_user(db, None, None).await.unwrap();
+        let token = ctx
+            .create_api_token(db, &user, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/user/me/tokens")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "name": "escalation", "access": "write" }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_projects_require_authentication() {
+        let app = create_test_app!();
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/project")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_create_duplicate_user_conflicts() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let user = ctx.create_user(db, None, None).await.unwrap();
+        let token = ctx
+            .create_api_token(db, &user, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/user")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "username": user.username,
+                "name": "Duplicate User",
+                "password": "password123"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let problem: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(problem["code"], "user_already_exists");
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_create_user_validation_reports_fields() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/user")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "username": "usr",
+                "name": "Test User",
+                "password": "short"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let problem: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(problem["code"], "validation_error");
+        assert!(problem["errors"]["username"].is_array());
+        assert!(problem["errors"]["password"].is_array());
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_update_user() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &admin, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/user/{}", teacher.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({
+                "name": "Renamed Teacher",
+                "role": "admin"
+            }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let updated: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(updated["name"], "Renamed Teacher");
+        assert_eq!(updated["role"], "admin");
+        assert_eq!(updated["username"], teacher.username);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_teacher_cannot_change_roles() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/user/{}", student.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "role": "teacher" }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = test::TestRequest::put()
+            .uri(&format!("/api/v1/user/{}", student.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(serde_json::json!({ "name": "Renamed Student" }))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_deactivated_user_is_locked_out() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let teacher_token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student_token = ctx
+            .create_api_token(db, &student, TokenAccess::Read, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/user/{}/deactivate", student.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", teacher_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/user/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", student_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(
+            db.verify_local_user(&student.username, "password123")
+                .await
+                .is_err()
+        );
+
+        // Deactivated users are kept but hidden from the default listing
+        assert!(ctx.assert_user_exists(db, student.id).await);
+        let (listed, _) = db
+            .get_users(Some(&student.username), false, &Pagination::new(1, 25))
+            .await
+            .unwrap();
+        assert!(listed.is_empty());
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/user/{}/reactivate", student.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", teacher_token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            db.verify_local_user(&student.username, "password123")
+                .await
+                .is_ok()
+        );
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_cannot_deactivate_self() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &admin, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri(&format!("/api/v1/user/{}/deactivate", admin.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_search_users() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let users = ctx.create_multiple_users(db, 3).await.unwrap();
+        let token = ctx
+            .create_api_token(db, &users[0], TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let auth = (header::AUTHORIZATION, format!("Bearer {}", token));
+
+        let resp = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/user?search={}",
+                ctx.test_id.to_uppercase()
+            ))
+            .insert_header(auth.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let found: RespPage<RespCreateUser> = test::read_body_json(resp).await;
+        assert_eq!(found.items.len(), users.len());
+
+        // Wildcards in the search term match literally
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/user?search={}_%25", ctx.test_id))
+            .insert_header(auth)
+            .send_request(&app)
+            .await;
+        let found: RespPage<RespCreateUser> = test::read_body_json(resp).await;
+        assert!(found.items.is_empty());
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_bulk_create_students() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let csv = format!(
+            "username;name;password\n\
+             bulk_a_{id};Student A;password123\n\
+             bulk_b_{id};Student B;\n\
+             abc;Too Short;password123\n\
+             {teacher};Duplicate;password123\n",
+            id = ctx.test_id,
+            teacher = teacher.username,
+        );
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/user/bulk")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .insert_header((header::CONTENT_TYPE, "text/csv"))
+            .set_payload(csv)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        let rows = result["rows"].as_array().unwrap();
+        for row in rows.iter().filter(|row| row["status"] == "created") {
+            let id = uuid::Uuid::parse_str(row["user_id"].as_str().unwrap()).unwrap();
+            ctx.created_users.lock().unwrap().push(id);
+        }
+
+        assert_eq!(result["created"], 2);
+        assert_eq!(result["failed"], 2);
+        assert_eq!(rows[0]["row"], 2);
+        assert_eq!(rows[2]["code"], "validation_error");
+        assert_eq!(rows[3]["code"], "user_already_exists");
+
+        let (student, _) = db
+            .get_users(
+                Some(&format!("bulk_b_{}", ctx.test_id)),
+                false,
+                &Pagination::new(1, 25),
+            )
+            .await
+            .unwrap();
+        assert_eq!(student[0].role, UserRole::Student);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_get_users_paginates_and_sorts() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let users = ctx.create_multiple_users(db, 3).await.unwrap();
+        let token = ctx
+            .create_api_token(db, &users[0], TokenAccess::Read, None)
+            .await
+            .unwrap();
+        let auth = (header::AUTHORIZATION, format!("Bearer {}", token));
+
+        let resp = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/user?search={}&per_page=2&sort=-username",
+                ctx.test_id
+            ))
+            .insert_header(auth.clone())
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let page: RespPage<RespCreateUser> = test::read_body_json(resp).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(page.total_pages, 2);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].username, users[2].username);
+        assert!(page.links["prev"].is_null());
+
+        let next = page.links["next"].as_str().unwrap();
+        let resp = test::TestRequest::get()
+            .uri(next)
+            .insert_header(auth.clone())
+            .send_request(&app)
+            .await;
+        let page: RespPage<RespCreateUser> = test::read_body_json(resp).await;
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].username, users[0].username);
+
+        let resp = test::TestRequest::get()
+            .uri("/api/v1/user?sort=password")
+            .insert_header(auth)
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_bulk_create_requires_columns() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let teacher = ctx
+            .create_user_with_role(db, UserRole::Teacher)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &teacher, TokenAccess::Write, None)
+            .await
+            .unwrap();
+
+        let resp = test::TestRequest::post()
+            .uri("/api/v1/user/bulk")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_payload("vorname,nachname\nMax,Mustermann\n")
+            .send_request(&app)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        ctx.cleanup_all(db).await;
+    }
+
+    #[actix_web::test]
+    async fn test_export_and_erase_user_data() {
+        let ctx = TestContext::new();
+        let db = &crate::common::test_helpers::get_database().await;
+
+        let app = create_test_app!();
+
+        let admin = ctx
+            .create_user_with_role(db, UserRole::Admin)
+            .await
+            .unwrap();
+        let token = ctx
+            .create_api_token(db, &admin, TokenAccess::Write, None)
+            .await
+            .unwrap();
+        let student = ctx
+            .create_user_with_role(db, UserRole::Student)
+            .await
+            .unwrap();
+        let mate = ctx
+            .create_named_user_with_role(db, "mate", UserRole::Student)
+            .await
+            .unwrap();
+
+        let project = ctx.create_project(db, None).await.unwrap();
+        let group = ctx
+            .create_group_with_members(db, &project, "Gruppe 1", &[&student, &mate])
+            .await
+            .unwrap();
+        db.set_survey_status(project.id, SurveyStatus::Open, admin.id, None)
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &student, &[(&mate, 2)])
+            .await
+            .unwrap();
+        // The reset keeps the mate's first comment about the student in the audit log
+        let (mate_member, _) = db
+            .get_group_members(project.id, group.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(member, _)| member.user_id == mate.id)
+            .unwrap();
+        db.submit_feedback(
+            &mate_member,
+            vec![NewFeedbackResponse {
+                to_user_id: student.id,
+                rating: 4,
+                comment: Some("War bei keinem Treffen".to_owned()),
+            }],
+            None,
+            chrono::Utc::now(),
+        )
+        .await
+        .unwrap();
+        let feedback_id = db
+            .get_feedback_recipients(project.id, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|recipient| recipient.user.id == mate.id)
+            .unwrap()
+            .feedback_id;
+        db.reset_feedback(feedback_id, Some(admin.id))
+            .await
+            .unwrap();
+        ctx.submit_ratings(db, &group, &mate, &[(&student, 3)])
+            .await
+            .unwrap();
+        db.set_group_grade(group.id, 2.0, None).await.unwrap();
+        db.set_individual_grades(
+            group.id,
+            vec![NewGradeOverride {
+                user_id: student.id,
+                grade: Some(1.7),
+                justification: "Krankheitsbedingt nur die Präsentation gehalten".to_owned(),
+            }],
+            admin.id,
+        )
+        .await
+        .unwrap();
+
+        let grades = || async {
+            let resp = test::TestRequest::get()
+                .uri(&format!("/api/v1/project/{}/grades", project.id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .send_request(&app)
+                .await;
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            body.as_array().unwrap().clone()
+        };
+        let grade_of = |grades: &[serde_json::Value], id: uuid::Uuid| {
+            grades
+                .iter()
+                .find(|grade| grade["user_id"] == id.to_string())
+                .unwrap()
+                .clone()
+        };
+        let before = grades().await;
+        assert_eq!(
+            grade_of(&before, student.id)["justification"],
+            "Krankheitsbedingt nur die Präsentation gehalten"
+        );
+
+        let export = |format: &str| {
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/v1/user/{}/data-export?format={}",
+                    student.id, format
+                ))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+
+        let resp = export("json").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["profile"]["user"]["email"],
+            student.email.clone().unwrap()
+        );
+        assert_eq!(body["memberships"][0]["group_name"], "Gruppe 1");
+        assert_eq!(body["feedback_given"].as_array().unwrap().len(), 1);
+        assert_eq!(body["feedback_received"][0]["rating"], 3);
+        assert!(body["feedback_received"][0].get("from_user_id").is_none());
+
+        let resp = export("zip").send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/zip"
+        );
+        let body = test::read_body(resp).await;
+        assert!(body.starts_with(b"PK"));
+
+        let erase = |id: uuid::Uuid| {
+            test::TestRequest::post()
+                .uri(&format!("/api/v1/user/{}/erase", id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        };
+
+        let resp = erase(admin.id).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = erase(student.id).send_request(&app).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["username"].as_str().unwrap().starts_with("erased-"));
+        assert_eq!(body["email"], serde_json::Value::Null);
+        assert_ne!(body["deactivated_at"], serde_json::Value::Null);
+
+        // The grades within the group don't change
+        let after = grades().await;
+        assert_eq!(
+            grade_of(&after, mate.id)["final_grade"],
+            grade_of(&before, mate.id)["final_grade"]
+        );
+        assert_eq!(
+            grade_of(&after, student.id)["final_grade"],
+            grade_of(&before, student.id)["final_grade"]
+        );
+        assert!(
+            grade_of(&after, student.id)["name"]
+                .as_str()
+                .unwrap()
+                .starts_with("Pseudonym")
+        );
+
+        // Justifications and comments about the student are gone, in the audit log as well
+        assert_eq!(
+            grade_of(&after, student.id)["justification"],
+            serde_json::Value::Null
+        );
+        let resp = test::TestRequest::get()
+            .uri(&format!("/api/v1/project/{}/grades/history", project.id))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .send_request(&app)
+            .await;
+        let history: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(history[0]["grade"], 1.7);
+        assert_eq!(history[0]["justification"], serde_json::Value::Null);
+
+        let filter = AuditFilter {
+            action: Some(AuditAction::FeedbackReset),
+            entity_id: Some(mate.id),
+            ..Default::default()
+        };
+        let (events, _) = db
+            .get_audit_events(&filter, &Pagination::new(1, 25))
+            .await
+            .unwrap();
+        let responses = &events[0].before.as_ref().unwrap()["responses"];
+        assert_eq!(responses[0]["rating"], 4);
+        assert_eq!(responses[0]["comment"], serde_json::Value::Null);
+
+        ctx.cleanup_all(db).await;
+    }
 }
@@ -1,12 +1,46 @@
 pub use sea_orm_migration::prelude::*;
 
 mod baseline;
+mod m20261019_000001_user_role_oidc;
+mod m20261019_000002_api_token;
+mod m20261019_000003_user_deactivation;
+mod m20261019_000004_mail_delivery;
+mod m20261019_000005_job;
+mod m20261019_000006_feedback_reminders;
+mod m20261019_000007_survey_lifecycle;
+mod m20261019_000008_self_assessment;
+mod m20261019_000009_peer_aggregation;
+mod m20261019_000010_class_grade_scale;
+mod m20261019_000011_grade_override;
+mod m20261019_000012_audit_event;
+mod m20261019_000013_school_year_retention;
+mod m20261019_000014_feedback_stale;
+mod m20261019_000015_class_enrolment;
+mod m20261019_000016_job_dedup_key;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(baseline::Migration)]
+        vec![
+            Box::new(baseline::Migration),
+            Box::new(m20261019_000001_user_role_oidc::Migration),
+            Box::new(m20261019_000002_api_token::Migration),
+            Box::new(m20261019_000003_user_deactivation::Migration),
+            Box::new(m20261019_000004_mail_delivery::Migration),
+            Box::new(m20261019_000005_job::Migration),
+            Box::new(m20261019_000006_feedback_reminders::Migration),
+            Box::new(m20261019_000007_survey_lifecycle::Migration),
+            Box::new(m20261019_000008_self_assessment::Migration),
+            Box::new(m20261019_000009_peer_aggregation::Migration),
+            Box::new(m20261019_000010_class_grade_scale::Migration),
+            Box::new(m20261019_000011_grade_override::Migration),
+            Box::new(m20261019_000012_audit_event::Migration),
+            Box::new(m20261019_000013_school_year_retention::Migration),
+            Box::new(m20261019_000014_feedback_stale::Migration),
+            Box::new(m20261019_000015_class_enrolment::Migration),
+            Box::new(m20261019_000016_job_dedup_key::Migration),
+        ]
     }
 }
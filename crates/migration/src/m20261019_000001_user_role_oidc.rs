@@ -0,0 +1,85 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string(User::Role).default("student"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing accounts were created by an admin for teachers
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(User::Role, "teacher")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OidcAuth::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(OidcAuth::Id))
+                    .col(string(OidcAuth::Issuer))
+                    .col(string(OidcAuth::Subject))
+                    .index(
+                        Index::create()
+                            .name("oidc_auth_issuer_subject_key")
+                            .col(OidcAuth::Issuer)
+                            .col(OidcAuth::Subject)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-oidcauth-user")
+                            .from(OidcAuth::Table, OidcAuth::Id)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OidcAuth::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    Role,
+}
+
+#[derive(DeriveIden)]
+enum OidcAuth {
+    Table,
+    Id,
+    Issuer,
+    Subject,
+}
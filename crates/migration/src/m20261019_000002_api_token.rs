@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiToken::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(ApiToken::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(ApiToken::UserId))
+                    .col(string(ApiToken::Name))
+                    .col(string_uniq(ApiToken::TokenHash))
+                    .col(string(ApiToken::Role))
+                    .col(string(ApiToken::Access))
+                    .col(timestamp_with_time_zone_null(ApiToken::ExpiresAt))
+                    .col(timestamp_with_time_zone_null(ApiToken::LastUsedAt))
+                    .col(
+                        timestamp_with_time_zone(ApiToken::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-apitoken-user")
+                            .from(ApiToken::Table, ApiToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ApiToken {
+    Table,
+    Id,
+    UserId,
+    Name,
+    TokenHash,
+    Role,
+    Access,
+    ExpiresAt,
+    LastUsedAt,
+    CreatedAt,
+}
@@ -0,0 +1,111 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string_null(User::Email))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MailDelivery::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(MailDelivery::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(MailDelivery::UserId))
+                    .col(uuid(MailDelivery::ProjectId))
+                    .col(uuid_null(MailDelivery::GroupId))
+                    .col(string(MailDelivery::Kind))
+                    .col(string_null(MailDelivery::Recipient))
+                    .col(string(MailDelivery::Language))
+                    .col(string(MailDelivery::Status))
+                    .col(text_null(MailDelivery::Error))
+                    .col(
+                        timestamp_with_time_zone(MailDelivery::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(timestamp_with_time_zone_null(MailDelivery::SentAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-maildelivery-user")
+                            .from(MailDelivery::Table, MailDelivery::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-maildelivery-project")
+                            .from(MailDelivery::Table, MailDelivery::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-maildelivery-project")
+                    .table(MailDelivery::Table)
+                    .col(MailDelivery::ProjectId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MailDelivery::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Email)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    Email,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum MailDelivery {
+    Table,
+    Id,
+    UserId,
+    ProjectId,
+    GroupId,
+    Kind,
+    Recipient,
+    Language,
+    Status,
+    Error,
+    CreatedAt,
+    SentAt,
+}
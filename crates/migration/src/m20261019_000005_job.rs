@@ -0,0 +1,121 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Job::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(Job::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(string(Job::Kind))
+                    .col(json_binary(Job::Payload))
+                    .col(string(Job::Status))
+                    .col(integer(Job::Attempts).default(0))
+                    .col(integer(Job::MaxAttempts))
+                    .col(integer(Job::ProgressDone).default(0))
+                    .col(integer_null(Job::ProgressTotal))
+                    .col(json_binary_null(Job::Result))
+                    .col(text_null(Job::Error))
+                    .col(uuid_null(Job::CreatedBy))
+                    .col(timestamp_with_time_zone(Job::RunAt).default(Expr::current_timestamp()))
+                    .col(timestamp_with_time_zone_null(Job::LockedAt))
+                    .col(
+                        timestamp_with_time_zone(Job::CreatedAt).default(Expr::current_timestamp()),
+                    )
+                    .col(timestamp_with_time_zone_null(Job::FinishedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-job-created-by")
+                            .from(Job::Table, Job::CreatedBy)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Workers look for due jobs by status and time
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-status-run-at")
+                    .table(Job::Table)
+                    .col(Job::Status)
+                    .col(Job::RunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MailDelivery::Table)
+                    .add_column(uuid_null(MailDelivery::JobId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-maildelivery-job")
+                            .from_tbl(MailDelivery::Table)
+                            .from_col(MailDelivery::JobId)
+                            .to_tbl(Job::Table)
+                            .to_col(Job::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MailDelivery::Table)
+                    .drop_column(MailDelivery::JobId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Job::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum MailDelivery {
+    Table,
+    JobId,
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    Id,
+    Kind,
+    Payload,
+    Status,
+    Attempts,
+    MaxAttempts,
+    ProgressDone,
+    ProgressTotal,
+    Result,
+    Error,
+    CreatedBy,
+    RunAt,
+    LockedAt,
+    CreatedAt,
+    FinishedAt,
+}
@@ -0,0 +1,117 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column(timestamp_with_time_zone_null(Project::FeedbackDeadline))
+                    .add_column(
+                        array(Project::ReminderDays, ColumnType::Integer)
+                            .default(Expr::cust("'{}'")),
+                    )
+                    .add_column(boolean(Project::RemindersEnabled).default(true))
+                    .add_column(string(Project::Language).default("de"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReminderRun::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(ReminderRun::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(ReminderRun::ProjectId))
+                    .col(timestamp_with_time_zone(ReminderRun::Deadline))
+                    .col(integer(ReminderRun::DaysBefore))
+                    .col(uuid_null(ReminderRun::JobId))
+                    .col(
+                        timestamp_with_time_zone(ReminderRun::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reminderrun-project")
+                            .from(ReminderRun::Table, ReminderRun::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reminderrun-job")
+                            .from(ReminderRun::Table, ReminderRun::JobId)
+                            .to(Job::Table, Job::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Keeps concurrent schedulers from sending the same reminder twice
+        manager
+            .create_index(
+                Index::create()
+                    .name("reminder_run_project_deadline_days_key")
+                    .table(ReminderRun::Table)
+                    .col(ReminderRun::ProjectId)
+                    .col(ReminderRun::Deadline)
+                    .col(ReminderRun::DaysBefore)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReminderRun::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .drop_column(Project::FeedbackDeadline)
+                    .drop_column(Project::ReminderDays)
+                    .drop_column(Project::RemindersEnabled)
+                    .drop_column(Project::Language)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+    FeedbackDeadline,
+    ReminderDays,
+    RemindersEnabled,
+    Language,
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ReminderRun {
+    Table,
+    Id,
+    ProjectId,
+    Deadline,
+    DaysBefore,
+    JobId,
+    CreatedAt,
+}
@@ -0,0 +1,230 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column(string(Project::SurveyStatus).default("draft"))
+                    .add_column(timestamp_with_time_zone_null(Project::FeedbackOpensAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Group::Table)
+                    .add_column(double_null(Group::Grade))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserGroupProject::Table)
+                    .add_column(double_null(UserGroupProject::Grade))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeedbackResponse::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(FeedbackResponse::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(FeedbackResponse::ProjectId))
+                    .col(uuid(FeedbackResponse::GroupId))
+                    .col(uuid(FeedbackResponse::FromUserId))
+                    .col(uuid(FeedbackResponse::ToUserId))
+                    .col(small_integer(FeedbackResponse::Rating))
+                    .col(text_null(FeedbackResponse::Comment))
+                    .col(
+                        timestamp_with_time_zone(FeedbackResponse::SubmittedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .check(
+                        Expr::col(FeedbackResponse::Rating).between(1, 6).and(
+                            Expr::col(FeedbackResponse::FromUserId)
+                                .ne(Expr::col(FeedbackResponse::ToUserId)),
+                        ),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-feedbackresponse-group")
+                            .from(
+                                FeedbackResponse::Table,
+                                (FeedbackResponse::GroupId, FeedbackResponse::ProjectId),
+                            )
+                            .to(Group::Table, (Group::Id, Group::ProjectId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-feedbackresponse-from-user")
+                            .from(FeedbackResponse::Table, FeedbackResponse::FromUserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-feedbackresponse-to-user")
+                            .from(FeedbackResponse::Table, FeedbackResponse::ToUserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One rating per pair of students and project
+        manager
+            .create_index(
+                Index::create()
+                    .name("feedback_response_project_from_to_key")
+                    .table(FeedbackResponse::Table)
+                    .col(FeedbackResponse::ProjectId)
+                    .col(FeedbackResponse::FromUserId)
+                    .col(FeedbackResponse::ToUserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SurveyEvent::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(SurveyEvent::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(SurveyEvent::ProjectId))
+                    .col(uuid_null(SurveyEvent::ActorId))
+                    .col(string(SurveyEvent::FromStatus))
+                    .col(string(SurveyEvent::ToStatus))
+                    .col(text_null(SurveyEvent::Reason))
+                    .col(
+                        timestamp_with_time_zone(SurveyEvent::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-surveyevent-project")
+                            .from(SurveyEvent::Table, SurveyEvent::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-surveyevent-actor")
+                            .from(SurveyEvent::Table, SurveyEvent::ActorId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SurveyEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(FeedbackResponse::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserGroupProject::Table)
+                    .drop_column(UserGroupProject::Grade)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Group::Table)
+                    .drop_column(Group::Grade)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .drop_column(Project::SurveyStatus)
+                    .drop_column(Project::FeedbackOpensAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+    SurveyStatus,
+    FeedbackOpensAt,
+}
+
+#[derive(DeriveIden)]
+enum Group {
+    Table,
+    Id,
+    ProjectId,
+    Grade,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum UserGroupProject {
+    Table,
+    Grade,
+}
+
+#[derive(DeriveIden)]
+enum FeedbackResponse {
+    Table,
+    Id,
+    ProjectId,
+    GroupId,
+    FromUserId,
+    ToUserId,
+    Rating,
+    Comment,
+    SubmittedAt,
+}
+
+#[derive(DeriveIden)]
+enum SurveyEvent {
+    Table,
+    Id,
+    ProjectId,
+    ActorId,
+    FromStatus,
+    ToStatus,
+    Reason,
+    CreatedAt,
+}
@@ -0,0 +1,119 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column(boolean(Project::SelfAssessment).default(false))
+                    .add_column(boolean(Project::SelfAssessmentInGrade).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SelfAssessment::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(SelfAssessment::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(SelfAssessment::ProjectId))
+                    .col(uuid(SelfAssessment::GroupId))
+                    .col(uuid(SelfAssessment::UserId))
+                    .col(small_integer(SelfAssessment::Rating))
+                    .col(text_null(SelfAssessment::Comment))
+                    .col(
+                        timestamp_with_time_zone(SelfAssessment::SubmittedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .check(Expr::col(SelfAssessment::Rating).between(1, 6))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-selfassessment-group")
+                            .from(
+                                SelfAssessment::Table,
+                                (SelfAssessment::GroupId, SelfAssessment::ProjectId),
+                            )
+                            .to(Group::Table, (Group::Id, Group::ProjectId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-selfassessment-user")
+                            .from(SelfAssessment::Table, SelfAssessment::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One self-assessment per student and project
+        manager
+            .create_index(
+                Index::create()
+                    .name("self_assessment_project_user_key")
+                    .table(SelfAssessment::Table)
+                    .col(SelfAssessment::ProjectId)
+                    .col(SelfAssessment::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SelfAssessment::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .drop_column(Project::SelfAssessment)
+                    .drop_column(Project::SelfAssessmentInGrade)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    SelfAssessment,
+    SelfAssessmentInGrade,
+}
+
+#[derive(DeriveIden)]
+enum Group {
+    Table,
+    Id,
+    ProjectId,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum SelfAssessment {
+    Table,
+    Id,
+    ProjectId,
+    GroupId,
+    UserId,
+    Rating,
+    Comment,
+    SubmittedAt,
+}
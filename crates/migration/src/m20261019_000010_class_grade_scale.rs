@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Class::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(Class::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(string(Class::Name))
+                    .col(string(Class::GradeScale).default("german"))
+                    .col(string(Class::GradeRounding).default("tenth"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column(uuid_null(Project::ClassId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-project-class")
+                            .from_tbl(Project::Table)
+                            .from_col(Project::ClassId)
+                            .to_tbl(Class::Table)
+                            .to_col(Class::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .drop_column(Project::ClassId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Class::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    ClassId,
+}
+
+#[derive(DeriveIden)]
+enum Class {
+    Table,
+    Id,
+    Name,
+    GradeScale,
+    GradeRounding,
+}
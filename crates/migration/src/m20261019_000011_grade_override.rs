@@ -0,0 +1,112 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserGroupProject::Table)
+                    .add_column(text_null(UserGroupProject::GradeJustification))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(GradeOverride::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(GradeOverride::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(GradeOverride::ProjectId))
+                    .col(uuid(GradeOverride::GroupId))
+                    .col(uuid(GradeOverride::UserId))
+                    .col(double_null(GradeOverride::ComputedGrade))
+                    .col(double_null(GradeOverride::PreviousGrade))
+                    .col(double_null(GradeOverride::Grade))
+                    .col(text_null(GradeOverride::Justification))
+                    .col(uuid_null(GradeOverride::ActorId))
+                    .col(
+                        timestamp_with_time_zone(GradeOverride::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-gradeoverride-project")
+                            .from(GradeOverride::Table, GradeOverride::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-gradeoverride-user")
+                            .from(GradeOverride::Table, GradeOverride::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-gradeoverride-actor")
+                            .from(GradeOverride::Table, GradeOverride::ActorId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GradeOverride::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserGroupProject::Table)
+                    .drop_column(UserGroupProject::GradeJustification)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum UserGroupProject {
+    Table,
+    GradeJustification,
+}
+
+#[derive(DeriveIden)]
+enum GradeOverride {
+    Table,
+    Id,
+    ProjectId,
+    GroupId,
+    UserId,
+    ComputedGrade,
+    PreviousGrade,
+    Grade,
+    Justification,
+    ActorId,
+    CreatedAt,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign keys, events have to outlive the users and projects they name
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditEvent::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(AuditEvent::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid_null(AuditEvent::ActorId))
+                    .col(string(AuditEvent::Action))
+                    .col(uuid_null(AuditEvent::EntityId))
+                    .col(uuid_null(AuditEvent::ProjectId))
+                    .col(json_binary_null(AuditEvent::Before))
+                    .col(json_binary_null(AuditEvent::After))
+                    .col(
+                        timestamp_with_time_zone(AuditEvent::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-auditevent-created-at")
+                    .table(AuditEvent::Table)
+                    .col(AuditEvent::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-auditevent-project")
+                    .table(AuditEvent::Table)
+                    .col(AuditEvent::ProjectId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditEvent {
+    Table,
+    Id,
+    ActorId,
+    Action,
+    EntityId,
+    ProjectId,
+    Before,
+    After,
+    CreatedAt,
+}
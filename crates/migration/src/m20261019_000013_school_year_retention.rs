@@ -0,0 +1,95 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchoolYear::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(SchoolYear::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(string_uniq(SchoolYear::Name))
+                    .col(date(SchoolYear::StartsOn))
+                    .col(date(SchoolYear::EndsOn))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Class::Table)
+                    .add_column(uuid_null(Class::SchoolYearId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-class-school-year")
+                            .from_tbl(Class::Table)
+                            .from_col(Class::SchoolYearId)
+                            .to_tbl(SchoolYear::Table)
+                            .to_col(SchoolYear::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column(timestamp_with_time_zone_null(Project::ArchivedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .drop_column(Project::ArchivedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Class::Table)
+                    .drop_column(Class::SchoolYearId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SchoolYear::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    ArchivedAt,
+}
+
+#[derive(DeriveIden)]
+enum Class {
+    Table,
+    SchoolYearId,
+}
+
+#[derive(DeriveIden)]
+enum SchoolYear {
+    Table,
+    Id,
+    Name,
+    StartsOn,
+    EndsOn,
+}
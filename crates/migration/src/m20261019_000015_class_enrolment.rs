@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ClassEnrolment::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(ClassEnrolment::Id).extra("DEFAULT gen_random_uuid()"))
+                    .col(uuid(ClassEnrolment::ClassId))
+                    .col(uuid(ClassEnrolment::UserId))
+                    .col(date(ClassEnrolment::StartsOn))
+                    .col(date_null(ClassEnrolment::EndsOn))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-classenrolment-class")
+                            .from(ClassEnrolment::Table, ClassEnrolment::ClassId)
+                            .to(Class::Table, Class::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-classenrolment-user")
+                            .from(ClassEnrolment::Table, ClassEnrolment::UserId)
+                            .to(User::Table, User::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-classenrolment-class-user")
+                    .table(ClassEnrolment::Table)
+                    .col(ClassEnrolment::ClassId)
+                    .col(ClassEnrolment::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ClassEnrolment::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Class {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ClassEnrolment {
+    Table,
+    Id,
+    ClassId,
+    UserId,
+    StartsOn,
+    EndsOn,
+}
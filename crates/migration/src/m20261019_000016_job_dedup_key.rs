@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .add_column(text_null(Job::DedupKey))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Jobs without a key are never the same, Postgres treats NULLs as distinct
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-kind-dedup-key")
+                    .table(Job::Table)
+                    .col(Job::Kind)
+                    .col(Job::DedupKey)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-job-kind-dedup-key")
+                    .table(Job::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .drop_column(Job::DedupKey)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    Kind,
+    DedupKey,
+}